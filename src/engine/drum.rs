@@ -1,14 +1,17 @@
+use std::sync::Arc;
+
 use fundsp::prelude32::*;
 
+use super::drum_sample::{build_sample_drum_voice_unit, DrumShaper, SampleDrumVoiceShared};
 use super::effects::{wire_chorus, wire_delay, wire_reverb, EffectsConfig, EffectSlot};
-use super::filter::{Add2, Mul2, resonance_to_q};
+use super::filter::{Add2, Mul2, VoiceSleep, resonance_to_q};
 use super::voice::Voice;
 
 // ── Drum sound parameters ──
 
 /// Runtime parameters defining a single percussion sound.
 /// All values are set via Shared atomics — no graph rebuild needed.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct DrumParams {
     pub freq: f32,
     pub noise_level: f32,
@@ -18,7 +21,6 @@ pub struct DrumParams {
 }
 
 /// 16-instrument drum kit (top row = index 0, bottom row = index 15).
-#[allow(dead_code)]
 pub const DRUM_KIT: [DrumParams; 16] = [
     DrumParams { freq: 300.0,  noise_level: 0.9,  sine_level: 0.1,  cutoff: 15000.0, resonance: 0.05 }, // Crash
     DrumParams { freq: 500.0,  noise_level: 0.8,  sine_level: 0.2,  cutoff: 10000.0, resonance: 0.2  }, // Ride
@@ -39,7 +41,6 @@ pub const DRUM_KIT: [DrumParams; 16] = [
 ];
 
 /// Rock kit: punchy kick, snappy snare, bright cymbals, tight hats.
-#[allow(dead_code)]
 pub const DRUM_KIT_ROCK: [DrumParams; 16] = [
     DrumParams { freq: 280.0,  noise_level: 0.95, sine_level: 0.05, cutoff: 16000.0, resonance: 0.05 }, // Crash
     DrumParams { freq: 520.0,  noise_level: 0.85, sine_level: 0.15, cutoff: 11000.0, resonance: 0.15 }, // Ride
@@ -60,7 +61,6 @@ pub const DRUM_KIT_ROCK: [DrumParams; 16] = [
 ];
 
 /// Jazz kit: soft brush-like textures, warm tones, prominent ride.
-#[allow(dead_code)]
 pub const DRUM_KIT_JAZZ: [DrumParams; 16] = [
     DrumParams { freq: 320.0,  noise_level: 0.85, sine_level: 0.15, cutoff: 10000.0, resonance: 0.05 }, // Crash
     DrumParams { freq: 480.0,  noise_level: 0.7,  sine_level: 0.3,  cutoff: 12000.0, resonance: 0.25 }, // Ride
@@ -81,7 +81,6 @@ pub const DRUM_KIT_JAZZ: [DrumParams; 16] = [
 ];
 
 /// Dance/808 kit: deep sub kick, sharp electronic hats, clap-heavy.
-#[allow(dead_code)]
 pub const DRUM_KIT_DANCE: [DrumParams; 16] = [
     DrumParams { freq: 250.0,  noise_level: 0.9,  sine_level: 0.1,  cutoff: 14000.0, resonance: 0.05 }, // Crash
     DrumParams { freq: 550.0,  noise_level: 0.8,  sine_level: 0.2,  cutoff: 10000.0, resonance: 0.15 }, // Ride
@@ -102,7 +101,6 @@ pub const DRUM_KIT_DANCE: [DrumParams; 16] = [
 ];
 
 /// Electronic/909 kit: punchy, synthetic, bright hats, tight snare.
-#[allow(dead_code)]
 pub const DRUM_KIT_ELECTRONIC: [DrumParams; 16] = [
     DrumParams { freq: 350.0,  noise_level: 0.9,  sine_level: 0.1,  cutoff: 18000.0, resonance: 0.03 }, // Crash
     DrumParams { freq: 600.0,  noise_level: 0.75, sine_level: 0.25, cutoff: 14000.0, resonance: 0.1  }, // Ride
@@ -123,7 +121,6 @@ pub const DRUM_KIT_ELECTRONIC: [DrumParams; 16] = [
 ];
 
 /// Latin kit: prominent congas, timbales, warm tones, cowbell/claves forward.
-#[allow(dead_code)]
 pub const DRUM_KIT_LATIN: [DrumParams; 16] = [
     DrumParams { freq: 300.0,  noise_level: 0.85, sine_level: 0.15, cutoff: 12000.0, resonance: 0.05 }, // Crash
     DrumParams { freq: 500.0,  noise_level: 0.8,  sine_level: 0.2,  cutoff: 10000.0, resonance: 0.2  }, // Ride
@@ -154,6 +151,7 @@ pub const DRUM_LABELS: [&str; 16] = [
 
 /// Atomic shared parameters for a single drum voice.
 /// Updated per-hit to change the drum sound without graph rebuild.
+#[derive(Clone)]
 pub struct DrumVoiceShared {
     pub freq: Shared,
     pub noise_level: Shared,
@@ -161,10 +159,15 @@ pub struct DrumVoiceShared {
     pub cutoff: Shared,
     pub resonance: Shared,
     pub level: Shared,
+    /// Hit counter that restarts the Decay envelope (see `DrumShaper`).
+    pub trigger: Shared,
+    /// Seconds for a hit to fall by 60 dB; infinite leaves the ADSR alone.
+    pub decay: Shared,
+    /// Tone tilt, -1.0 (dark) ..= +1.0 (bright), 0.0 is neutral.
+    pub color: Shared,
 }
 
 impl DrumVoiceShared {
-    #[allow(dead_code)]
     pub fn new() -> Self {
         let default = &DRUM_KIT[14]; // Kick
         Self {
@@ -174,11 +177,13 @@ impl DrumVoiceShared {
             cutoff: Shared::new(default.cutoff),
             resonance: Shared::new(default.resonance),
             level: Shared::new(1.0),
+            trigger: Shared::new(0.0),
+            decay: Shared::new(f32::INFINITY),
+            color: Shared::new(0.0),
         }
     }
 
     /// Apply a drum sound's parameters to the shared atomics.
-    #[allow(dead_code)]
     pub fn apply_drum(&self, params: &DrumParams) {
         self.freq.set_value(params.freq);
         self.noise_level.set_value(params.noise_level);
@@ -196,6 +201,7 @@ impl DrumVoiceShared {
 ///   noise() × noise_level + sine(freq) × sine_level
 ///   → lowpass(cutoff, Q)
 ///   → × ADSR(0.001, 0.5, 0.0, 0.1)
+///   → DrumShaper (decay, color)
 ///   → × velocity → × level → × master_amp → stereo split
 pub fn build_drum_voice_unit(
     gate: &Shared,
@@ -244,10 +250,18 @@ pub fn build_drum_voice_unit(
     net.connect(filter_id, 0, env_mul_id, 0);
     net.connect(env_id, 0, env_mul_id, 1);
 
+    // Extra amplitude decay and tone tilt, restarted by the hit counter
+    let shaper_id = net.push(Box::new(An(DrumShaper::new(
+        &shared.trigger,
+        &shared.decay,
+        &shared.color,
+    ))));
+    net.connect(env_mul_id, 0, shaper_id, 0);
+
     // × velocity
     let vel_id = net.push(Box::new(var(velocity)));
     let vel_mul_id = net.push(Box::new(An(Mul2::new())));
-    net.connect(env_mul_id, 0, vel_mul_id, 0);
+    net.connect(shaper_id, 0, vel_mul_id, 0);
     net.connect(vel_id, 0, vel_mul_id, 1);
 
     // × per-voice level
@@ -271,6 +285,106 @@ pub fn build_drum_voice_unit(
     Box::new(net)
 }
 
+// ── Drum engine selection ──
+
+/// Half-life in seconds of the output level that decides when a drum voice
+/// has gone quiet.
+const DRUM_LEVEL_TIMESCALE: f64 = 0.01;
+
+/// Drum voices of the Matrix graph: WAV sample playback or a synthesized kit.
+#[derive(Clone)]
+pub enum DrumEngine {
+    /// One-shot samples; a hit selects the buffer and bumps the trigger counter.
+    Sample {
+        shared: Vec<SampleDrumVoiceShared>,
+        buffers: Arc<Vec<Vec<f32>>>,
+    },
+    /// Noise + sine synthesis; a hit loads the instrument's `DrumParams`.
+    Synth {
+        shared: Vec<DrumVoiceShared>,
+        kit: &'static [DrumParams; 16],
+    },
+}
+
+impl DrumEngine {
+    pub fn sample(voices: usize, buffers: &Arc<Vec<Vec<f32>>>) -> Self {
+        Self::Sample {
+            shared: (0..voices).map(|_| SampleDrumVoiceShared::new()).collect(),
+            buffers: Arc::clone(buffers),
+        }
+    }
+
+    pub fn synth(voices: usize, kit: &'static [DrumParams; 16]) -> Self {
+        Self::Synth {
+            shared: (0..voices).map(|_| DrumVoiceShared::new()).collect(),
+            kit,
+        }
+    }
+
+    /// Build the stereo unit for drum voice `i`. Samples ring past the gate,
+    /// so the voice sleeps once its output level has died away.
+    pub fn build_voice_unit(&self, i: usize, voice: &Voice, master_amp: &Shared) -> Box<dyn AudioUnit> {
+        let unit = match self {
+            Self::Sample { shared, buffers } => {
+                build_sample_drum_voice_unit(&voice.velocity, master_amp, &shared[i], buffers)
+            }
+            Self::Synth { shared, .. } => {
+                build_drum_voice_unit(&voice.gate, &voice.velocity, master_amp, &shared[i])
+            }
+        };
+        // Both channels carry the same signal
+        let metered = Net::wrap(unit)
+            >> (monitor(&voice.envelope, Meter::Peak(DRUM_LEVEL_TIMESCALE)) | pass());
+        Box::new(An(VoiceSleep::new(
+            Box::new(metered),
+            &voice.gate,
+            &voice.envelope,
+            &voice.sounding,
+        )))
+    }
+
+    /// Start drum voice `i` playing kit instrument `index`, pitched by `pitch_ratio`.
+    /// The voice's gate is handled by the allocator.
+    pub fn hit(&self, i: usize, index: usize, pitch_ratio: f32) {
+        let index = Ord::min(index, 15);
+        match self {
+            Self::Sample { shared, .. } => {
+                let shared = &shared[i];
+                shared.sample_index.set_value(index as f32);
+                shared.pitch_ratio.set_value(pitch_ratio);
+                shared.trigger.set_value(shared.trigger.value() + 1.0);
+            }
+            Self::Synth { shared, kit } => {
+                let shared = &shared[i];
+                let params = kit[index];
+                shared.apply_drum(&DrumParams {
+                    freq: params.freq * pitch_ratio,
+                    ..params
+                });
+                shared.trigger.set_value(shared.trigger.value() + 1.0);
+            }
+        }
+    }
+
+    /// Apply the Drum Kit panel's Decay and Color to every voice.
+    pub fn set_shape(&self, decay: f32, color: f32) {
+        match self {
+            Self::Sample { shared, .. } => {
+                for s in shared {
+                    s.decay.set_value(decay);
+                    s.color.set_value(color);
+                }
+            }
+            Self::Synth { shared, .. } => {
+                for s in shared {
+                    s.decay.set_value(decay);
+                    s.color.set_value(color);
+                }
+            }
+        }
+    }
+}
+
 // ── Polyphonic drum graph ──
 
 /// Build a polyphonic drum graph with 8 voices summed together, plus effects chain.
@@ -505,6 +619,36 @@ mod tests {
         assert!(hh_has_sound, "hi-hat should produce sound after param switch");
     }
 
+    #[test]
+    fn drum_engine_hit_and_shape_reach_synth_voice() {
+        let voice = Voice::new();
+        let master = Shared::new(1.0);
+        let tail_energy = |decay: f32| {
+            let drums = DrumEngine::synth(1, &DRUM_KIT);
+            let mut unit = drums.build_voice_unit(0, &voice, &master);
+            unit.set_sample_rate(SAMPLE_RATE);
+            unit.allocate();
+            drums.set_shape(decay, 0.0);
+            voice.gate.set_value(0.0);
+            voice.velocity.set_value(1.0);
+            for _ in 0..100 {
+                unit.get_stereo();
+            }
+            drums.hit(0, 6, 1.0); // Snare
+            voice.gate.set_value(1.0);
+            (0..8820)
+                .map(|s| {
+                    let (l, _) = unit.get_stereo();
+                    if s >= 4410 { l * l } else { 0.0 }
+                })
+                .sum::<f32>()
+        };
+        let full = tail_energy(f32::INFINITY);
+        let short = tail_energy(0.02);
+        assert!(full > 0.01, "unshaped snare should still ring, got {full}");
+        assert!(short < full * 1e-3, "short decay should cut the tail: {short} vs {full}");
+    }
+
     #[test]
     fn build_drum_poly_graph_returns_stereo() {
         let voices: Vec<Voice> = (0..8).map(|_| Voice::new()).collect();
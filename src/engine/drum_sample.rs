@@ -109,14 +109,136 @@ impl AudioNode for SamplePlayer {
     }
 }
 
+// ── DrumShaper AudioNode ──
+
+/// High-shelf corner used when Color tilts a sample brighter.
+const COLOR_SHELF_HZ: f32 = 1500.0;
+/// Lowpass cutoff at the darkest Color setting.
+const COLOR_MIN_CUTOFF_HZ: f32 = 400.0;
+/// Lowpass cutoff at the neutral Color setting (effectively open).
+const COLOR_MAX_CUTOFF_HZ: f32 = 20000.0;
+
+/// Shapes a one-shot sample with an amplitude-decay envelope and a tone tilt.
+///
+/// The envelope restarts whenever the trigger counter changes and falls by
+/// 60 dB over `decay` seconds; an infinite decay leaves the sample unshaped.
+/// `color` runs from -1.0 (lowpass, darker) through 0.0 (untouched) to +1.0
+/// (high-shelf boost, brighter).
+#[derive(Clone)]
+pub struct DrumShaper {
+    trigger: Shared,
+    decay: Shared,
+    color: Shared,
+    sample_rate: f32,
+    prev_trigger: f32,
+    env: f32,
+    env_coeff: f32,
+    cached_decay: f32,
+    lp: f32,
+    lp_coeff: f32,
+    cached_color: f32,
+}
+
+impl DrumShaper {
+    pub fn new(trigger: &Shared, decay: &Shared, color: &Shared) -> Self {
+        let mut shaper = Self {
+            trigger: trigger.clone(),
+            decay: decay.clone(),
+            color: color.clone(),
+            sample_rate: DEFAULT_SR as f32,
+            prev_trigger: 0.0,
+            env: 1.0,
+            env_coeff: 1.0,
+            cached_decay: f32::NAN,
+            lp: 0.0,
+            lp_coeff: 1.0,
+            cached_color: f32::NAN,
+        };
+        shaper.update_coefficients();
+        shaper
+    }
+
+    /// Recompute the per-sample coefficients when a parameter moved.
+    fn update_coefficients(&mut self) {
+        let decay = self.decay.value();
+        if decay != self.cached_decay {
+            self.cached_decay = decay;
+            self.env_coeff = if decay.is_finite() && decay > 0.0 {
+                10f32.powf(-3.0 / (decay * self.sample_rate))
+            } else {
+                1.0
+            };
+        }
+
+        let color = self.color.value().clamp(-1.0, 1.0);
+        if color != self.cached_color {
+            self.cached_color = color;
+            let cutoff = if color < 0.0 {
+                COLOR_MAX_CUTOFF_HZ * (COLOR_MIN_CUTOFF_HZ / COLOR_MAX_CUTOFF_HZ).powf(-color)
+            } else {
+                COLOR_SHELF_HZ
+            };
+            let cutoff = cutoff.min(self.sample_rate * 0.45);
+            self.lp_coeff = 1.0 - (-std::f32::consts::TAU * cutoff / self.sample_rate).exp();
+        }
+    }
+}
+
+impl AudioNode for DrumShaper {
+    const ID: u64 = 900_021;
+    type Inputs = U1;
+    type Outputs = U1;
+
+    fn reset(&mut self) {
+        self.prev_trigger = 0.0;
+        self.env = 1.0;
+        self.lp = 0.0;
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate as f32;
+        self.cached_decay = f32::NAN;
+        self.cached_color = f32::NAN;
+        self.update_coefficients();
+    }
+
+    #[inline]
+    fn tick(&mut self, input: &Frame<f32, U1>) -> Frame<f32, U1> {
+        self.update_coefficients();
+
+        let trig = self.trigger.value();
+        if trig != self.prev_trigger {
+            self.prev_trigger = trig;
+            self.env = 1.0;
+        }
+
+        let x = input[0] * self.env;
+        self.env *= self.env_coeff;
+
+        self.lp += self.lp_coeff * (x - self.lp);
+        let color = self.cached_color;
+        let out = if color < 0.0 {
+            self.lp
+        } else {
+            x + color * (x - self.lp)
+        };
+        [out].into()
+    }
+}
+
 // ── Per-voice shared parameters ──
 
 /// Atomic shared parameters for a single sample-based drum voice.
+#[derive(Clone)]
 pub struct SampleDrumVoiceShared {
     pub sample_index: Shared,
     pub pitch_ratio: Shared,
     pub level: Shared,
     pub trigger: Shared,
+    /// Seconds for a hit to fall by 60 dB; infinite plays the whole sample.
+    pub decay: Shared,
+    /// Tone tilt, -1.0 (dark) ..= +1.0 (bright), 0.0 is neutral.
+    pub color: Shared,
 }
 
 impl SampleDrumVoiceShared {
@@ -126,6 +248,8 @@ impl SampleDrumVoiceShared {
             pitch_ratio: Shared::new(1.0),
             level: Shared::new(1.0),
             trigger: Shared::new(0.0),
+            decay: Shared::new(f32::INFINITY),
+            color: Shared::new(0.0),
         }
     }
 }
@@ -135,7 +259,7 @@ impl SampleDrumVoiceShared {
 /// Build a single sample-based drum voice.
 ///
 /// Signal chain:
-///   SamplePlayer → × velocity → × level → × master_amp → stereo split
+///   SamplePlayer → DrumShaper (decay, color) → × velocity → × level → × master_amp → stereo split
 pub fn build_sample_drum_voice_unit(
     velocity: &Shared,
     master_amp: &Shared,
@@ -152,10 +276,18 @@ pub fn build_sample_drum_voice_unit(
         &shared.pitch_ratio,
     ))));
 
+    // Amplitude decay and tone tilt
+    let shaper_id = net.push(Box::new(An(DrumShaper::new(
+        &shared.trigger,
+        &shared.decay,
+        &shared.color,
+    ))));
+    net.connect(player_id, 0, shaper_id, 0);
+
     // × velocity
     let vel_id = net.push(Box::new(var(velocity)));
     let vel_mul_id = net.push(Box::new(An(Mul2::new())));
-    net.connect(player_id, 0, vel_mul_id, 0);
+    net.connect(shaper_id, 0, vel_mul_id, 0);
     net.connect(vel_id, 0, vel_mul_id, 1);
 
     // × per-voice level
@@ -215,11 +347,37 @@ fn samples_dir(dir_name: &str) -> std::path::PathBuf {
 }
 
 fn load_wav(path: &std::path::Path, output_sample_rate: f64) -> Vec<f32> {
-    let mut reader = match hound::WavReader::open(path) {
-        Ok(r) => r,
-        Err(_) => return Vec::new(),
+    let Ok((mono, file_sr)) = read_wav(path) else {
+        return Vec::new();
     };
 
+    // Resample if file rate differs from output rate
+    if (file_sr - output_sample_rate).abs() > 1.0 && !mono.is_empty() {
+        let ratio = output_sample_rate / file_sr;
+        let new_len = (mono.len() as f64 * ratio) as usize;
+        let mut resampled = Vec::with_capacity(new_len);
+        for i in 0..new_len {
+            let src = i as f64 / ratio;
+            let idx = src as usize;
+            let frac = src - idx as f64;
+            let s0 = mono.get(idx).copied().unwrap_or(0.0);
+            let s1 = mono.get(idx + 1).copied().unwrap_or(s0);
+            resampled.push(s0 + (s1 - s0) * frac as f32);
+        }
+        resampled
+    } else {
+        mono
+    }
+}
+
+/// Read a WAV file as mono f32 samples (channels averaged) at its own sample
+/// rate, which is returned alongside.
+pub fn read_wav(path: &std::path::Path) -> std::io::Result<(Vec<f32>, f64)> {
+    let mut reader = hound::WavReader::open(path).map_err(|err| match err {
+        hound::Error::IoError(err) => err,
+        err => std::io::Error::new(std::io::ErrorKind::InvalidData, err),
+    })?;
+
     let spec = reader.spec();
     let file_sr = spec.sample_rate as f64;
 
@@ -236,10 +394,6 @@ fn load_wav(path: &std::path::Path, output_sample_rate: f64) -> Vec<f32> {
         }
     };
 
-    if raw.is_empty() {
-        return Vec::new();
-    }
-
     // Mix to mono if multi-channel
     let mono: Vec<f32> = if spec.channels > 1 {
         let ch = spec.channels as usize;
@@ -250,23 +404,7 @@ fn load_wav(path: &std::path::Path, output_sample_rate: f64) -> Vec<f32> {
         raw
     };
 
-    // Resample if file rate differs from output rate
-    if (file_sr - output_sample_rate).abs() > 1.0 && !mono.is_empty() {
-        let ratio = output_sample_rate / file_sr;
-        let new_len = (mono.len() as f64 * ratio) as usize;
-        let mut resampled = Vec::with_capacity(new_len);
-        for i in 0..new_len {
-            let src = i as f64 / ratio;
-            let idx = src as usize;
-            let frac = src - idx as f64;
-            let s0 = mono.get(idx).copied().unwrap_or(0.0);
-            let s1 = mono.get(idx + 1).copied().unwrap_or(s0);
-            resampled.push(s0 + (s1 - s0) * frac as f32);
-        }
-        resampled
-    } else {
-        mono
-    }
+    Ok((mono, file_sr))
 }
 
 #[cfg(test)]
@@ -381,6 +519,73 @@ mod tests {
         );
     }
 
+    /// Peak output of a shaper fed a sine at `freq`, after the filter settles.
+    fn shaper_sine_peak(color: f32, freq: f32) -> f32 {
+        let trigger = Shared::new(0.0);
+        let decay = Shared::new(f32::INFINITY);
+        let color = Shared::new(color);
+        let mut shaper = DrumShaper::new(&trigger, &decay, &color);
+        shaper.set_sample_rate(SAMPLE_RATE);
+        let mut peak = 0.0f32;
+        for s in 0..8820 {
+            let x = (std::f32::consts::TAU * freq * s as f32 / SAMPLE_RATE as f32).sin();
+            let out = shaper.tick(&[x].into());
+            if s >= 4410 {
+                peak = peak.max(out[0].abs());
+            }
+        }
+        peak
+    }
+
+    #[test]
+    fn drum_shaper_neutral_is_passthrough() {
+        let trigger = Shared::new(0.0);
+        let decay = Shared::new(f32::INFINITY);
+        let color = Shared::new(0.0);
+        let mut shaper = DrumShaper::new(&trigger, &decay, &color);
+        shaper.set_sample_rate(SAMPLE_RATE);
+        for s in 0..1000 {
+            let x = (s as f32 * 0.37).sin();
+            assert_eq!(shaper.tick(&[x].into())[0], x);
+        }
+    }
+
+    #[test]
+    fn drum_shaper_decay_falls_60db_and_retriggers() {
+        let trigger = Shared::new(0.0);
+        let decay = Shared::new(0.1);
+        let color = Shared::new(0.0);
+        let mut shaper = DrumShaper::new(&trigger, &decay, &color);
+        shaper.set_sample_rate(SAMPLE_RATE);
+
+        trigger.set_value(1.0);
+        let first = shaper.tick(&[1.0].into())[0];
+        assert!((first - 1.0).abs() < 1e-6);
+        let mut last = first;
+        for _ in 1..4410 {
+            last = shaper.tick(&[1.0].into())[0];
+        }
+        assert!((last - 0.001).abs() < 1e-4, "expected -60 dB after decay time, got {last}");
+
+        // A new hit restarts the envelope
+        trigger.set_value(2.0);
+        let out = shaper.tick(&[1.0].into())[0];
+        assert!((out - 1.0).abs() < 1e-6, "retrigger should restore full level, got {out}");
+    }
+
+    #[test]
+    fn drum_shaper_color_tilts_high_frequencies() {
+        let neutral = shaper_sine_peak(0.0, 8000.0);
+        let dark = shaper_sine_peak(-1.0, 8000.0);
+        let bright = shaper_sine_peak(1.0, 8000.0);
+        assert!(dark < neutral * 0.2, "dark color should cut highs: {dark} vs {neutral}");
+        assert!(bright > neutral * 1.5, "bright color should lift highs: {bright} vs {neutral}");
+
+        // Lows pass through a bright tilt almost unchanged
+        let low = shaper_sine_peak(1.0, 60.0);
+        assert!((low - 1.0).abs() < 0.1, "bright tilt should leave lows alone, got {low}");
+    }
+
     #[test]
     fn build_sample_drum_voice_unit_returns_stereo() {
         let buffers = make_test_buffers();
@@ -437,4 +642,32 @@ mod tests {
             assert!(buf.is_empty());
         }
     }
+
+    #[test]
+    fn sample_drum_voice_decay_shortens_hit() {
+        let tail_energy = |decay: f32| {
+            let buffers = make_test_buffers();
+            let vel = Shared::new(1.0);
+            let master = Shared::new(1.0);
+            let shared = SampleDrumVoiceShared::new();
+            shared.decay.set_value(decay);
+            let mut unit = build_sample_drum_voice_unit(&vel, &master, &shared, &buffers);
+            unit.set_sample_rate(SAMPLE_RATE);
+            unit.allocate();
+            for _ in 0..100 {
+                unit.get_stereo();
+            }
+            shared.trigger.set_value(1.0);
+            (0..4410)
+                .map(|s| {
+                    let (l, _) = unit.get_stereo();
+                    if s >= 2205 { l * l } else { 0.0 }
+                })
+                .sum::<f32>()
+        };
+        let full = tail_energy(f32::INFINITY);
+        let short = tail_energy(0.02);
+        assert!(full > 1.0, "unshaped hit should ring through, got {full}");
+        assert!(short < full * 1e-3, "short decay should silence the tail: {short} vs {full}");
+    }
 }
@@ -1,6 +1,8 @@
 use fundsp::prelude32::*;
 use serde::{Deserialize, Serialize};
 
+use super::voice::SILENT_LEVEL;
+
 /// Filter type for the resonant filter.
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum FilterType {
@@ -39,6 +41,10 @@ pub enum LfoTarget {
     Frequency,
     Cutoff,
     Amplitude,
+    /// Width of `Waveform::Pulse` (PWM).
+    PulseWidth,
+    /// Frame position of `Waveform::Wavetable`.
+    WavetablePosition,
 }
 
 /// LFO configuration (waveform + target + enabled). Changes trigger a graph rebuild.
@@ -107,6 +113,189 @@ impl AudioNode for Add2 {
     }
 }
 
+/// Shortest glide: plain note changes still settle over 10 ms instead of jumping.
+const MIN_GLIDE: f32 = 0.01;
+
+/// Portamento node: 2 inputs (target frequency in Hz, glide time in seconds) → 1 output.
+/// Slides in pitch rather than Hz, so every interval takes the same time; the output
+/// is within 1% of the interval after the glide time.
+#[derive(Clone)]
+pub struct Glide {
+    sample_rate: f32,
+    /// Current pitch as log2 of the frequency; `None` until the first sample.
+    pitch: Option<f32>,
+    time: f32,
+    coeff: f32,
+}
+
+impl Glide {
+    pub fn new() -> Self {
+        Self {
+            sample_rate: DEFAULT_SR as f32,
+            pitch: None,
+            time: 0.0,
+            coeff: 0.0,
+        }
+    }
+}
+
+impl AudioNode for Glide {
+    const ID: u64 = 900_003;
+    type Inputs = U2;
+    type Outputs = U1;
+
+    fn reset(&mut self) {
+        self.pitch = None;
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate as f32;
+        self.time = 0.0;
+    }
+
+    #[inline]
+    fn tick(&mut self, input: &Frame<f32, Self::Inputs>) -> Frame<f32, Self::Outputs> {
+        let target = input[0].max(1.0e-3).log2();
+        let time = input[1].max(MIN_GLIDE);
+        if time != self.time {
+            self.time = time;
+            // e^-4.6 ≈ 1% of the interval left after `time`
+            self.coeff = (-4.6 / (time * self.sample_rate)).exp();
+        }
+        let pitch = match self.pitch {
+            Some(pitch) => target + (pitch - target) * self.coeff,
+            None => target,
+        };
+        self.pitch = Some(pitch);
+        [pitch.exp2()].into()
+    }
+}
+
+/// Gate low time forced by a retrigger, longer than the envelope's 2 ms control rate.
+const RETRIGGER_GAP: f32 = 0.004;
+
+/// Gate node: 2 inputs (gate, retrigger count) → 1 output. Passes the gate through,
+/// closing it briefly whenever the count changes so a held gate restarts its envelope.
+/// The first sample is always closed: the envelope only attacks after seeing the gate low,
+/// even for a note that was already down when the graph was built.
+#[derive(Clone)]
+pub struct Retrigger {
+    sample_rate: f32,
+    count: Option<f32>,
+    /// Samples left with the gate forced closed.
+    hold: u32,
+}
+
+impl Retrigger {
+    pub fn new() -> Self {
+        Self {
+            sample_rate: DEFAULT_SR as f32,
+            count: None,
+            hold: 0,
+        }
+    }
+}
+
+impl AudioNode for Retrigger {
+    const ID: u64 = 900_004;
+    type Inputs = U2;
+    type Outputs = U1;
+
+    fn reset(&mut self) {
+        self.count = None;
+        self.hold = 0;
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate as f32;
+    }
+
+    #[inline]
+    fn tick(&mut self, input: &Frame<f32, Self::Inputs>) -> Frame<f32, Self::Outputs> {
+        match self.count {
+            None => self.hold = 1,
+            Some(count) if count != input[1] => {
+                self.hold = (RETRIGGER_GAP * self.sample_rate) as u32;
+            }
+            Some(_) => {}
+        }
+        self.count = Some(input[1]);
+        if self.hold > 0 {
+            self.hold -= 1;
+            return [0.0].into();
+        }
+        [input[0]].into()
+    }
+}
+
+/// Stereo voice wrapper: 0 inputs → 2 outputs. Runs the wrapped voice while
+/// its gate is open and until its level has fallen to `SILENT_LEVEL`, then
+/// outputs silence without ticking it and clears `sounding` so the allocator
+/// can reuse the voice. An open gate wakes it up again. It starts awake so
+/// the envelope sees the gate low before the first note.
+#[derive(Clone)]
+pub struct VoiceSleep {
+    unit: Box<dyn AudioUnit>,
+    gate: Shared,
+    level: Shared,
+    sounding: Shared,
+    asleep: bool,
+    buffer: [f32; 2],
+}
+
+impl VoiceSleep {
+    /// `level` is written by the wrapped unit, e.g. through `monitor()`.
+    pub fn new(unit: Box<dyn AudioUnit>, gate: &Shared, level: &Shared, sounding: &Shared) -> Self {
+        Self {
+            unit,
+            gate: gate.clone(),
+            level: level.clone(),
+            sounding: sounding.clone(),
+            asleep: false,
+            buffer: [0.0; 2],
+        }
+    }
+}
+
+impl AudioNode for VoiceSleep {
+    const ID: u64 = 900_005;
+    type Inputs = U0;
+    type Outputs = U2;
+
+    fn reset(&mut self) {
+        self.unit.reset();
+        self.asleep = false;
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.unit.set_sample_rate(sample_rate);
+    }
+
+    fn allocate(&mut self) {
+        self.unit.allocate();
+    }
+
+    #[inline]
+    fn tick(&mut self, _: &Frame<f32, Self::Inputs>) -> Frame<f32, Self::Outputs> {
+        let gate_open = self.gate.value() > 0.0;
+        if self.asleep {
+            if !gate_open {
+                // A note may have started and stopped between two samples
+                self.sounding.set_value(0.0);
+                return [0.0, 0.0].into();
+            }
+            self.asleep = false;
+            self.sounding.set_value(1.0);
+        }
+        self.unit.tick(&[], &mut self.buffer);
+        if !gate_open && self.level.value() <= SILENT_LEVEL {
+            self.asleep = true;
+            self.sounding.set_value(0.0);
+        }
+        self.buffer.into()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -167,4 +356,83 @@ mod tests {
         assert_eq!(cfg.target, LfoTarget::Cutoff);
         assert!(!cfg.enabled);
     }
+
+    #[test]
+    fn glide_reaches_target_after_glide_time() {
+        let mut node = Glide::new();
+        node.set_sample_rate(1000.0);
+        assert_eq!(node.tick(&[220.0, 0.1].into())[0], 220.0, "starts on the first frequency");
+        let mut out = 0.0;
+        for _ in 0..10 {
+            out = node.tick(&[440.0, 0.1].into())[0];
+        }
+        assert!(out > 250.0 && out < 400.0, "on the way, got {out}");
+        for _ in 0..90 {
+            out = node.tick(&[440.0, 0.1].into())[0];
+        }
+        assert!((out - 440.0).abs() < 440.0 * 0.01, "glide ends near the target, got {out}");
+    }
+
+    #[test]
+    fn glide_is_symmetric_in_pitch() {
+        // An octave up and an octave down take the same time
+        let partway = |from: f32, to: f32| {
+            let mut node = Glide::new();
+            node.set_sample_rate(1000.0);
+            node.tick(&[from, 0.2].into());
+            (0..40).map(|_| node.tick(&[to, 0.2].into())[0]).last().unwrap()
+        };
+        let up = (partway(220.0, 440.0) / 220.0).log2();
+        let down = (440.0 / partway(440.0, 220.0)).log2();
+        assert!((up - down).abs() < 1e-3);
+    }
+
+    #[test]
+    fn retrigger_closes_gate_briefly() {
+        let mut node = Retrigger::new();
+        node.set_sample_rate(1000.0);
+        assert_eq!(node.tick(&[1.0, 0.0].into())[0], 0.0, "first sample closed");
+        assert_eq!(node.tick(&[1.0, 0.0].into())[0], 1.0);
+        assert_eq!(node.tick(&[1.0, 1.0].into())[0], 0.0, "count changed");
+        let gap = (0..10).take_while(|_| node.tick(&[1.0, 1.0].into())[0] == 0.0).count();
+        assert_eq!(gap, 3, "4 ms at 1 kHz");
+        assert_eq!(node.tick(&[0.0, 1.0].into())[0], 0.0, "gate passes through");
+    }
+
+    #[test]
+    fn voice_sleep_skips_finished_voices() {
+        let (gate, level, sounding) = (Shared::new(0.0), Shared::new(0.0), Shared::new(1.0));
+        let mut node = VoiceSleep::new(Box::new(dc((0.5, 0.5))), &gate, &level, &sounding);
+        node.tick(&Frame::default());
+        assert_eq!(node.tick(&Frame::default()).as_slice(), [0.0, 0.0], "asleep until gated");
+        sounding.set_value(1.0);
+        node.tick(&Frame::default());
+        assert_eq!(sounding.value(), 0.0, "a missed note is reported finished");
+
+        gate.set_value(1.0);
+        sounding.set_value(1.0);
+        assert_eq!(node.tick(&Frame::default()).as_slice(), [0.5, 0.5]);
+        gate.set_value(0.0);
+        level.set_value(0.3);
+        assert_eq!(node.tick(&Frame::default()).as_slice(), [0.5, 0.5], "release still playing");
+        assert_eq!(sounding.value(), 1.0);
+
+        level.set_value(0.0);
+        node.tick(&Frame::default());
+        assert_eq!(sounding.value(), 0.0);
+        assert_eq!(node.tick(&Frame::default()).as_slice(), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn voice_sleep_reports_sounding_on_wake() {
+        let (gate, level, sounding) = (Shared::new(0.0), Shared::new(0.0), Shared::new(0.0));
+        let mut node = VoiceSleep::new(Box::new(dc((0.5, 0.5))), &gate, &level, &sounding);
+        node.tick(&Frame::default());
+        assert_eq!(sounding.value(), 0.0);
+
+        // Nobody else raises the flag: the node must, as soon as the gate opens
+        gate.set_value(1.0);
+        assert_eq!(node.tick(&Frame::default()).as_slice(), [0.5, 0.5]);
+        assert_eq!(sounding.value(), 1.0);
+    }
 }
@@ -0,0 +1,157 @@
+use std::sync::Arc;
+
+use fundsp::prelude32::*;
+
+use super::oscillator::{PULSE_WIDTH_RANGE, Waveform};
+use super::wavetable::Wavetable;
+
+/// Oscillator hard-synced to a master: 3 inputs (master frequency, own frequency,
+/// shape) → 1 output. Its phase restarts whenever the master completes a cycle,
+/// so it repeats at the master's pitch while its own pitch sets the timbre.
+/// The shape is the pulse width for `Pulse` and the table position for `Wavetable`.
+#[derive(Clone)]
+pub struct HardSyncOsc {
+    waveform: Waveform,
+    table: Arc<Wavetable>,
+    sample_rate: f32,
+    master_phase: f32,
+    phase: f32,
+}
+
+impl HardSyncOsc {
+    /// `None` for waveforms without a cycle to restart (noise and supersaw).
+    /// `Wavetable` plays `wavetable`, or the built-in table.
+    pub fn new(waveform: Waveform, wavetable: Option<&Arc<Wavetable>>) -> Option<Self> {
+        match waveform {
+            Waveform::Noise | Waveform::PinkNoise | Waveform::Supersaw => None,
+            _ => Some(Self {
+                waveform,
+                table: wavetable.map_or_else(Wavetable::basic, Arc::clone),
+                sample_rate: DEFAULT_SR as f32,
+                master_phase: 0.0,
+                phase: 0.0,
+            }),
+        }
+    }
+
+    /// Current output for a pitch of `step` cycles per sample. Edges within a
+    /// cycle are smoothed with PolyBLEP; the sync reset itself is not.
+    fn shape(&self, step: f32, shape: f32) -> f32 {
+        let t = self.phase;
+        let dt = step.abs().max(1.0e-6);
+        match self.waveform {
+            Waveform::Sine => (t * std::f32::consts::TAU).sin(),
+            Waveform::Triangle => 2.0 * (2.0 * t - 1.0).abs() - 1.0,
+            Waveform::Saw => 2.0 * t - 1.0 - poly_blep(t, dt),
+            Waveform::Square => pulse(t, 0.5, dt),
+            Waveform::Pulse => {
+                let (min, max) = PULSE_WIDTH_RANGE;
+                pulse(t, shape.clamp(min, max), dt)
+            }
+            Waveform::Wavetable => self.table.sample(step, shape, t),
+            Waveform::Noise | Waveform::PinkNoise | Waveform::Supersaw => 0.0,
+        }
+    }
+}
+
+/// Band-limited correction for a unit step at phase 0, `dt` cycles per sample.
+fn poly_blep(t: f32, dt: f32) -> f32 {
+    if t < dt {
+        let x = t / dt;
+        2.0 * x - x * x - 1.0
+    } else if t > 1.0 - dt {
+        let x = (t - 1.0) / dt;
+        x * x + 2.0 * x + 1.0
+    } else {
+        0.0
+    }
+}
+
+/// Pulse that is high for the first `width` of each cycle.
+fn pulse(t: f32, width: f32, dt: f32) -> f32 {
+    let naive = if t < width { 1.0 } else { -1.0 };
+    naive + poly_blep(t, dt) - poly_blep((t - width).rem_euclid(1.0), dt)
+}
+
+impl AudioNode for HardSyncOsc {
+    const ID: u64 = 900_007;
+    type Inputs = U3;
+    type Outputs = U1;
+
+    fn reset(&mut self) {
+        self.master_phase = 0.0;
+        self.phase = 0.0;
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate as f32;
+    }
+
+    #[inline]
+    fn tick(&mut self, input: &Frame<f32, Self::Inputs>) -> Frame<f32, Self::Outputs> {
+        let master_step = input[0] / self.sample_rate;
+        let step = input[1] / self.sample_rate;
+        let out = self.shape(step, input[2]);
+
+        self.master_phase += master_step;
+        if self.master_phase >= 1.0 {
+            self.master_phase -= self.master_phase.floor();
+            // Restart from where the master wrapped between samples
+            self.phase = (self.master_phase * step / master_step.max(f32::EPSILON)).rem_euclid(1.0);
+        } else {
+            self.phase = (self.phase + step).rem_euclid(1.0);
+        }
+        [out].into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f64 = 44100.0;
+
+    fn render(waveform: Waveform, master: f32, freq: f32, n: usize) -> Vec<f32> {
+        let mut osc = HardSyncOsc::new(waveform, None).unwrap();
+        osc.set_sample_rate(SAMPLE_RATE);
+        (0..n).map(|_| osc.tick(&[master, freq, 0.5].into())[0]).collect()
+    }
+
+    #[test]
+    fn synced_oscillator_repeats_at_master_pitch() {
+        // 441 Hz is exactly 100 samples per cycle
+        let samples = render(Waveform::Saw, 441.0, 700.0, 2000);
+        for i in 1000..1100 {
+            assert!(
+                (samples[i] - samples[i + 100]).abs() < 0.05,
+                "sample {i} should repeat a master cycle later"
+            );
+        }
+        // Without a master in step the 700 Hz saw does not repeat every 100 samples
+        let free = render(Waveform::Saw, 700.0, 700.0, 2000);
+        assert!((1000..1100).any(|i| (free[i] - free[i + 100]).abs() > 0.1));
+    }
+
+    #[test]
+    fn synced_oscillator_follows_its_own_pitch_within_a_cycle() {
+        // Slave at 4× the master: four saw ramps per master cycle. The saw only
+        // falls through zero at a reset (PolyBLEP spreads the drop over two samples)
+        let samples = render(Waveform::Saw, 441.0, 1764.0, 1000);
+        let resets = samples[500..600].windows(2).filter(|w| w[0] > 0.0 && w[1] <= 0.0).count();
+        assert_eq!(resets, 4);
+    }
+
+    #[test]
+    fn every_periodic_waveform_syncs() {
+        for waveform in Waveform::ALL {
+            let osc = HardSyncOsc::new(waveform, None);
+            let periodic = !matches!(waveform, Waveform::Noise | Waveform::PinkNoise | Waveform::Supersaw);
+            assert_eq!(osc.is_some(), periodic, "{waveform}");
+            if periodic {
+                let samples = render(waveform, 441.0, 620.0, 2000);
+                let peak = samples.iter().fold(0.0f32, |p, s| p.max(s.abs()));
+                assert!(peak > 0.5 && peak < 1.5, "{waveform} peak {peak}");
+            }
+        }
+    }
+}
@@ -1,27 +1,27 @@
-use std::sync::Arc;
-
 use fundsp::prelude32::*;
 
-use super::drum_sample::{build_sample_drum_voice_unit, SampleDrumVoiceShared};
+use super::drum::DrumEngine;
 use super::effects::{wire_chorus, wire_delay, wire_reverb, EffectSlot, EffectsConfig};
 use super::filter::Add2;
 use super::oscillator::build_voice_unit;
+use super::sequencer::{SequencerNode, SequencerPort};
 use super::voice::{Voice, VoiceConfig, VoiceShared};
 
 /// Build a combined Matrix graph with 8 lead + 8 drum + 8 bass voices,
 /// summed together through a single shared effects chain.
 /// All voice sets play simultaneously; mode switching only affects the UI.
+/// The sequencer runs inside the graph and gates the voices sample-accurately.
 #[allow(clippy::too_many_arguments)]
 pub fn build_matrix_graph(
     lead_voices: &[Voice],
     lead_configs: &[VoiceConfig],
     lead_shared: &[VoiceShared],
     drum_voices: &[Voice],
-    drum_shared: &[SampleDrumVoiceShared],
-    drum_buffers: &Arc<Vec<Vec<f32>>>,
+    drums: &DrumEngine,
     bass_voices: &[Voice],
     bass_configs: &[VoiceConfig],
     bass_shared: &[VoiceShared],
+    sequencer: SequencerPort,
     master_amp: &Shared,
     effects_cfg: &EffectsConfig,
     delay_time: &Shared,
@@ -46,33 +46,13 @@ pub fn build_matrix_graph(
     for (i, voice) in lead_voices.iter().enumerate() {
         let cfg = &lead_configs[i];
         let shared = &lead_shared[i];
-        let unit = build_voice_unit(
-            cfg.waveform,
-            &voice.freq,
-            &voice.gate,
-            &voice.velocity,
-            master_amp,
-            &shared.level,
-            &cfg.adsr,
-            &cfg.filter_cfg,
-            &shared.cutoff,
-            &shared.resonance,
-            &cfg.lfo_cfg,
-            &shared.lfo_rate,
-            &shared.lfo_depth,
-        );
+        let unit = build_voice_unit(voice, cfg, shared, master_amp);
         all_voice_ids.push(net.push(unit));
     }
 
-    // ── Build 8 sample-based drum voice units ──
+    // ── Build 8 drum voice units (sampled or synthesized kit) ──
     for (i, voice) in drum_voices.iter().enumerate() {
-        let shared = &drum_shared[i];
-        let unit = build_sample_drum_voice_unit(
-            &voice.velocity,
-            master_amp,
-            shared,
-            drum_buffers,
-        );
+        let unit = drums.build_voice_unit(i, voice, master_amp);
         all_voice_ids.push(net.push(unit));
     }
 
@@ -80,21 +60,7 @@ pub fn build_matrix_graph(
     for (i, voice) in bass_voices.iter().enumerate() {
         let cfg = &bass_configs[i];
         let shared = &bass_shared[i];
-        let unit = build_voice_unit(
-            cfg.waveform,
-            &voice.freq,
-            &voice.gate,
-            &voice.velocity,
-            master_amp,
-            &shared.level,
-            &cfg.adsr,
-            &cfg.filter_cfg,
-            &shared.cutoff,
-            &shared.resonance,
-            &cfg.lfo_cfg,
-            &shared.lfo_rate,
-            &shared.lfo_depth,
-        );
+        let unit = build_voice_unit(voice, cfg, shared, master_amp);
         all_voice_ids.push(net.push(unit));
     }
 
@@ -153,12 +119,29 @@ pub fn build_matrix_graph(
     net.connect(chain_l, 0, snoop_l_id, 0);
     net.connect(chain_r, 0, snoop_r_id, 0);
 
+    // ── Sequencer ──
+    // Unconnected nodes still run; pushed last, it is ordered before the voices
+    // so note events reach the gates on the same sample.
+    net.push(Box::new(An(SequencerNode::new(
+        sequencer,
+        lead_voices,
+        drum_voices,
+        drums,
+        bass_voices,
+    ))));
+
     (Box::new(net), snoop_l, snoop_r)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Arc;
+
+    use crate::engine::drum::DRUM_KIT;
+    use crate::engine::sequencer::{
+        sequencer_channel, SeqCommand, SeqPattern, SeqStatus, Sequencer, StepNote,
+    };
     use crate::engine::voice::Voice;
 
     const SAMPLE_RATE: f64 = 44100.0;
@@ -176,6 +159,10 @@ mod tests {
         Arc::new(buffers)
     }
 
+    fn idle_sequencer() -> SequencerPort {
+        sequencer_channel(Sequencer::new(SeqPattern::default()), &SeqStatus::new()).1
+    }
+
     #[test]
     fn build_matrix_graph_returns_stereo() {
         let lead_voices: Vec<Voice> = (0..8).map(|_| Voice::new()).collect();
@@ -183,9 +170,7 @@ mod tests {
         let lead_shared: Vec<VoiceShared> =
             lead_configs.iter().map(VoiceShared::new).collect();
         let drum_voices: Vec<Voice> = (0..8).map(|_| Voice::new()).collect();
-        let drum_shared: Vec<SampleDrumVoiceShared> =
-            (0..8).map(|_| SampleDrumVoiceShared::new()).collect();
-        let drum_buffers = make_test_buffers();
+        let drums = DrumEngine::sample(8, &make_test_buffers());
         let bass_voices: Vec<Voice> = (0..8).map(|_| Voice::new()).collect();
         let bass_configs: Vec<VoiceConfig> = (0..8).map(|_| VoiceConfig::default()).collect();
         let bass_shared: Vec<VoiceShared> =
@@ -200,8 +185,9 @@ mod tests {
 
         let (graph, _, _) = build_matrix_graph(
             &lead_voices, &lead_configs, &lead_shared,
-            &drum_voices, &drum_shared, &drum_buffers,
+            &drum_voices, &drums,
             &bass_voices, &bass_configs, &bass_shared,
+            idle_sequencer(),
             &master, &ecfg, &dt, &fb, &dm, &rm, &cm,
         );
         assert_eq!(graph.inputs(), 0);
@@ -215,9 +201,10 @@ mod tests {
         let lead_shared: Vec<VoiceShared> =
             lead_configs.iter().map(VoiceShared::new).collect();
         let drum_voices: Vec<Voice> = (0..8).map(|_| Voice::new()).collect();
-        let drum_shared: Vec<SampleDrumVoiceShared> =
-            (0..8).map(|_| SampleDrumVoiceShared::new()).collect();
-        let drum_buffers = make_test_buffers();
+        let drums = DrumEngine::sample(8, &make_test_buffers());
+        let DrumEngine::Sample { shared: drum_shared, .. } = &drums else {
+            unreachable!()
+        };
         let bass_voices: Vec<Voice> = (0..8).map(|_| Voice::new()).collect();
         let bass_configs: Vec<VoiceConfig> = (0..8).map(|_| VoiceConfig::default()).collect();
         let bass_shared: Vec<VoiceShared> =
@@ -232,8 +219,9 @@ mod tests {
 
         let (mut graph, _, _) = build_matrix_graph(
             &lead_voices, &lead_configs, &lead_shared,
-            &drum_voices, &drum_shared, &drum_buffers,
+            &drum_voices, &drums,
             &bass_voices, &bass_configs, &bass_shared,
+            idle_sequencer(),
             &master, &ecfg, &dt, &fb, &dm, &rm, &cm,
         );
         graph.set_sample_rate(SAMPLE_RATE);
@@ -269,6 +257,8 @@ mod tests {
         drum_shared[0].sample_index.set_value(14.0);
         drum_shared[0].trigger.set_value(1.0);
         drum_voices[0].velocity.set_value(1.0);
+        // The gate wakes the sleeping voice, as the allocator does on a hit
+        drum_voices[0].gate.set_value(1.0);
 
         let mut drum_sound = false;
         for _ in 0..4096 {
@@ -280,4 +270,118 @@ mod tests {
         }
         assert!(drum_sound, "drum voice should produce sound");
     }
+
+    #[test]
+    fn build_matrix_graph_sequencer_plays_pattern() {
+        let lead_voices: Vec<Voice> = (0..8).map(|_| Voice::new()).collect();
+        let lead_configs: Vec<VoiceConfig> = (0..8).map(|_| VoiceConfig::default()).collect();
+        let lead_shared: Vec<VoiceShared> =
+            lead_configs.iter().map(VoiceShared::new).collect();
+        let drum_voices: Vec<Voice> = (0..8).map(|_| Voice::new()).collect();
+        let drums = DrumEngine::sample(8, &make_test_buffers());
+        let DrumEngine::Sample { shared: drum_shared, .. } = &drums else {
+            unreachable!()
+        };
+        let bass_voices: Vec<Voice> = (0..8).map(|_| Voice::new()).collect();
+        let bass_configs: Vec<VoiceConfig> = (0..8).map(|_| VoiceConfig::default()).collect();
+        let bass_shared: Vec<VoiceShared> =
+            bass_configs.iter().map(VoiceShared::new).collect();
+        let master = Shared::new(0.5);
+        let ecfg = EffectsConfig::default();
+        let dt = Shared::new(0.3);
+        let fb = Shared::new(0.3);
+        let dm = Shared::new(0.0);
+        let rm = Shared::new(0.0);
+        let cm = Shared::new(0.0);
+
+        // Kick on step 0 only
+        let mut drum_steps = vec![Vec::new(); 16];
+        drum_steps[0].push(StepNote::new(14, 127));
+        let pattern = SeqPattern {
+            steps: [vec![Vec::new(); 16], drum_steps, vec![Vec::new(); 16]],
+            ..SeqPattern::default()
+        };
+        let status = SeqStatus::new();
+        let step = &status.steps[0];
+        let (tx, port) = sequencer_channel(Sequencer::new(pattern), &status);
+
+        let (mut graph, _, _) = build_matrix_graph(
+            &lead_voices, &lead_configs, &lead_shared,
+            &drum_voices, &drums,
+            &bass_voices, &bass_configs, &bass_shared,
+            port,
+            &master, &ecfg, &dt, &fb, &dm, &rm, &cm,
+        );
+        graph.set_sample_rate(SAMPLE_RATE);
+        graph.allocate();
+
+        for _ in 0..256 {
+            let (l, r) = graph.get_stereo();
+            assert_eq!((l, r), (0.0, 0.0), "silent while stopped");
+        }
+        assert_eq!(step.value(), -1.0);
+
+        tx.send(SeqCommand::Start(0)).unwrap();
+        let (l, _) = graph.get_stereo();
+        assert_eq!(step.value(), 0.0);
+        assert_eq!(drum_shared[0].trigger.value(), 1.0);
+        // The kick starts on the very sample the step fires
+        let mut sound = l.abs() > 0.0;
+        for _ in 0..64 {
+            let (l, _) = graph.get_stereo();
+            sound |= l.abs() > 0.001;
+        }
+        assert!(sound, "sequenced kick should produce sound");
+    }
+
+    #[test]
+    fn build_matrix_graph_synth_kit_needs_no_samples() {
+        let lead_voices: Vec<Voice> = (0..8).map(|_| Voice::new()).collect();
+        let lead_configs: Vec<VoiceConfig> = (0..8).map(|_| VoiceConfig::default()).collect();
+        let lead_shared: Vec<VoiceShared> =
+            lead_configs.iter().map(VoiceShared::new).collect();
+        let drum_voices: Vec<Voice> = (0..8).map(|_| Voice::new()).collect();
+        let drums = DrumEngine::synth(8, &DRUM_KIT);
+        let bass_voices: Vec<Voice> = (0..8).map(|_| Voice::new()).collect();
+        let bass_configs: Vec<VoiceConfig> = (0..8).map(|_| VoiceConfig::default()).collect();
+        let bass_shared: Vec<VoiceShared> =
+            bass_configs.iter().map(VoiceShared::new).collect();
+        let master = Shared::new(0.5);
+        let ecfg = EffectsConfig::default();
+        let dt = Shared::new(0.3);
+        let fb = Shared::new(0.3);
+        let dm = Shared::new(0.0);
+        let rm = Shared::new(0.0);
+        let cm = Shared::new(0.0);
+
+        // Snare on step 0 only
+        let mut drum_steps = vec![Vec::new(); 16];
+        drum_steps[0].push(StepNote::new(6, 127));
+        let pattern = SeqPattern {
+            steps: [vec![Vec::new(); 16], drum_steps, vec![Vec::new(); 16]],
+            ..SeqPattern::default()
+        };
+        let (tx, port) = sequencer_channel(Sequencer::new(pattern), &SeqStatus::new());
+
+        let (mut graph, _, _) = build_matrix_graph(
+            &lead_voices, &lead_configs, &lead_shared,
+            &drum_voices, &drums,
+            &bass_voices, &bass_configs, &bass_shared,
+            port,
+            &master, &ecfg, &dt, &fb, &dm, &rm, &cm,
+        );
+        graph.set_sample_rate(SAMPLE_RATE);
+        graph.allocate();
+
+        for _ in 0..256 {
+            graph.get_stereo();
+        }
+        tx.send(SeqCommand::Start(0)).unwrap();
+        let mut peak = 0.0f32;
+        for _ in 0..4096 {
+            let (l, _) = graph.get_stereo();
+            peak = peak.max(l.abs());
+        }
+        assert!(peak > 0.01, "synthesized snare should produce sound, got {peak}");
+    }
 }
@@ -2,9 +2,13 @@ pub mod drum;
 pub mod drum_sample;
 pub mod effects;
 pub mod filter;
+pub mod hard_sync;
 pub mod oscillator;
+pub mod render;
+pub mod sequencer;
 pub mod matrix;
 pub mod voice;
+pub mod wavetable;
 
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{Device, SampleFormat, StreamConfig, SupportedStreamConfig};
@@ -1,11 +1,16 @@
+use std::sync::Arc;
+
 use fundsp::prelude32::*;
 use serde::{Deserialize, Serialize};
 
 use super::effects::{EffectsConfig, EffectSlot, wire_delay, wire_reverb, wire_chorus};
 use super::filter::{
-    Add2, FilterConfig, FilterType, LfoConfig, LfoTarget, LfoWaveform, Mul2, resonance_to_q,
+    Add2, FilterType, Glide, LfoConfig, LfoTarget, LfoWaveform, Mul2, Retrigger, VoiceSleep,
+    resonance_to_q,
 };
-use super::voice::{Voice, VoiceConfig, VoiceShared};
+use super::hard_sync::HardSyncOsc;
+use super::voice::{Voice, VoiceConfig, VoiceEngine, VoiceShared};
+use super::wavetable::{Wavetable, WavetableOsc};
 
 /// ADSR envelope parameters.
 #[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
@@ -27,12 +32,44 @@ impl Default for AdsrParams {
     }
 }
 
+/// Pulse width of a fresh voice (a square wave).
+pub const DEFAULT_PULSE_WIDTH: f32 = 0.5;
+/// Narrowest and widest pulse, so PWM never thins the pulse into silence.
+pub const PULSE_WIDTH_RANGE: (f32, f32) = (0.05, 0.95);
+/// Detune of each supersaw saw in cents, after the JP-8000's uneven spread.
+const SUPERSAW_CENTS: [f32; 7] = [-22.0, -12.6, -3.9, 0.0, 4.0, 12.4, 21.5];
+
 #[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
 pub enum Waveform {
     Sine,
     Saw,
     Square,
     Triangle,
+    /// Variable-width pulse; the width follows `VoiceConfig::pulse_width`
+    /// and the LFO when it targets the pulse width.
+    Pulse,
+    /// Seven detuned saws.
+    Supersaw,
+    /// White noise.
+    Noise,
+    PinkNoise,
+    /// Band-limited wavetable (`VoiceConfig::wavetable`, or the built-in
+    /// `Wavetable::basic()`) read at `VoiceConfig::wavetable_position`.
+    Wavetable,
+}
+
+impl Waveform {
+    pub const ALL: [Waveform; 9] = [
+        Self::Sine,
+        Self::Saw,
+        Self::Square,
+        Self::Triangle,
+        Self::Pulse,
+        Self::Supersaw,
+        Self::Noise,
+        Self::PinkNoise,
+        Self::Wavetable,
+    ];
 }
 
 impl std::fmt::Display for Waveform {
@@ -42,22 +79,57 @@ impl std::fmt::Display for Waveform {
             Waveform::Saw => write!(f, "saw"),
             Waveform::Square => write!(f, "square"),
             Waveform::Triangle => write!(f, "triangle"),
+            Waveform::Pulse => write!(f, "pulse"),
+            Waveform::Supersaw => write!(f, "supersaw"),
+            Waveform::Noise => write!(f, "noise"),
+            Waveform::PinkNoise => write!(f, "pink-noise"),
+            Waveform::Wavetable => write!(f, "wavetable"),
+        }
+    }
+}
+
+/// One oscillator of the given waveform. Inputs: frequency and shape — the pulse
+/// width for `Pulse` (clamped to `PULSE_WIDTH_RANGE`) or the table position for
+/// `Wavetable`, ignored otherwise; output: audio. Noise ignores both inputs.
+fn oscillator(waveform: Waveform, wavetable: Option<&Arc<Wavetable>>) -> Box<dyn AudioUnit> {
+    match waveform {
+        Waveform::Sine => Box::new((pass() | sink()) >> sine()),
+        Waveform::Saw => Box::new((pass() | sink()) >> saw()),
+        Waveform::Square => Box::new((pass() | sink()) >> square()),
+        Waveform::Triangle => Box::new((pass() | sink()) >> triangle()),
+        Waveform::Pulse => {
+            let (min, max) = PULSE_WIDTH_RANGE;
+            let width = map(move |i: &Frame<f32, U1>| i[0].clamp(min, max));
+            Box::new((pass() | width) >> pulse())
+        }
+        Waveform::Supersaw => {
+            let saw_at = |cents: f32| mul((cents / 1200.0).exp2()) >> saw();
+            let [a, b, c, d, e, f, g] = SUPERSAW_CENTS;
+            // Detuned saws are uncorrelated, so normalize by power
+            let norm = 1.0 / (SUPERSAW_CENTS.len() as f32).sqrt();
+            Box::new(
+                (pass() | sink())
+                    >> (saw_at(a) & saw_at(b) & saw_at(c) & saw_at(d) & saw_at(e) & saw_at(f) & saw_at(g))
+                    >> mul(norm),
+            )
+        }
+        Waveform::Noise => Box::new((sink() | sink()) >> noise()),
+        Waveform::PinkNoise => Box::new((sink() | sink()) >> pink()),
+        Waveform::Wavetable => {
+            let table = wavetable.map_or_else(Wavetable::basic, Arc::clone);
+            Box::new(An(WavetableOsc::new(table)))
         }
     }
 }
 
 /// Build a fundsp audio graph for the given waveform, frequency, and amplitude.
 /// Returns a boxed stereo `AudioUnit` (mono oscillator duplicated to both channels).
+/// Pulses are square (`DEFAULT_PULSE_WIDTH`) and wavetables use the middle of
+/// `Wavetable::basic()`.
 pub fn build_oscillator(waveform: Waveform, frequency: f32, amplitude: f32) -> Box<dyn AudioUnit> {
-    let freq = frequency;
-    let amp = amplitude;
-
-    match waveform {
-        Waveform::Sine => Box::new(dc(freq) >> (sine() * dc(amp)) >> split::<U2>()),
-        Waveform::Saw => Box::new(dc(freq) >> (saw() * dc(amp)) >> split::<U2>()),
-        Waveform::Square => Box::new(dc(freq) >> (square() * dc(amp)) >> split::<U2>()),
-        Waveform::Triangle => Box::new(dc(freq) >> (triangle() * dc(amp)) >> split::<U2>()),
-    }
+    let controls = Net::wrap(Box::new(dc((frequency, DEFAULT_PULSE_WIDTH))));
+    let output = Net::wrap(Box::new(mul(amplitude) >> split::<U2>()));
+    Box::new(controls >> Net::wrap(oscillator(waveform, None)) >> output)
 }
 
 /// Build a fundsp audio graph with shared (atomic) parameters and snoop outputs.
@@ -71,29 +143,14 @@ pub fn build_oscillator_shared(
     let (snoop_left, snoop_backend_left) = snoop(32768);
     let (snoop_right, snoop_backend_right) = snoop(32768);
 
-    let freq_control = var(freq_shared) >> follow(0.01);
-    let amp_control = var(amp_shared) >> follow(0.01);
-
-    let graph: Box<dyn AudioUnit> = match waveform {
-        Waveform::Sine => Box::new(
-            freq_control >> (sine() * amp_control) >> split::<U2>()
-                >> (snoop_backend_left | snoop_backend_right),
-        ),
-        Waveform::Saw => Box::new(
-            freq_control >> (saw() * amp_control) >> split::<U2>()
-                >> (snoop_backend_left | snoop_backend_right),
-        ),
-        Waveform::Square => Box::new(
-            freq_control >> (square() * amp_control) >> split::<U2>()
-                >> (snoop_backend_left | snoop_backend_right),
-        ),
-        Waveform::Triangle => Box::new(
-            freq_control >> (triangle() * amp_control) >> split::<U2>()
-                >> (snoop_backend_left | snoop_backend_right),
-        ),
-    };
+    let controls = Net::wrap(Box::new((var(freq_shared) >> follow(0.01)) | dc(DEFAULT_PULSE_WIDTH)));
+    let amp_control = Net::wrap(Box::new(var(amp_shared) >> follow(0.01)));
+    let output = Net::wrap(Box::new(
+        An(Mul2::new()) >> split::<U2>() >> (snoop_backend_left | snoop_backend_right),
+    ));
+    let graph = ((controls >> Net::wrap(oscillator(waveform, None))) | amp_control) >> output;
 
-    (graph, snoop_left, snoop_right)
+    (Box::new(graph), snoop_left, snoop_right)
 }
 
 /// Build an LFO modulation node inside a Net.
@@ -133,36 +190,239 @@ fn build_lfo_mod(
     add_id
 }
 
-/// Build a single polyphonic voice unit with ADSR envelope, optional filter and LFO.
-/// Uses an internal Net graph for dynamic node wiring.
-/// Returns a 0-input, 2-output (stereo) AudioUnit.
+
+/// Position of unison copy `k` of `n` across the stereo field, in [-1, 1].
+fn unison_position(k: usize, n: usize) -> f32 {
+    if n < 2 {
+        0.0
+    } else {
+        2.0 * k as f32 / (n - 1) as f32 - 1.0
+    }
+}
+
+/// Build the oscillator section: `cfg.unison` copies of the waveform, each offset by
+/// the coarse pitch plus its share of the detune. A single copy stays mono (one
+/// output); stacked copies are panned by `spread` and summed into two outputs (L, R).
+/// Every copy reads its shape (pulse width or table position) from `shape_id`, and
+/// gets its own oscillator 2 reading `osc2_shape_id` when `cfg.osc2` is enabled.
+/// With `fm_index_id` (FM voices) each copy's oscillator 1 is frequency-modulated.
 #[allow(clippy::too_many_arguments)]
-pub fn build_voice_unit(
+fn build_unison_osc(
+    net: &mut Net,
+    cfg: &VoiceConfig,
+    shared: &VoiceShared,
+    freq_id: NodeId,
+    shape_id: NodeId,
+    osc2_shape_id: Option<NodeId>,
+    fm_index_id: Option<NodeId>,
+) -> Vec<NodeId> {
+    let n = Ord::max(cfg.unison, 1);
+    let pitch_id = net.push(Box::new(var(&shared.pitch) >> follow(0.01)));
+    let detune_id = net.push(Box::new(var(&shared.detune) >> follow(0.01)));
+    let spread_id = net.push(Box::new(var(&shared.spread) >> follow(0.01)));
+    let osc2_controls = osc2_shape_id.map(|shape_id| {
+        let pitch_id = net.push(Box::new(var(&shared.osc2_pitch) >> follow(0.01)));
+        let level_id = net.push(Box::new(var(&shared.osc2_level) >> follow(0.01)));
+        (shape_id, pitch_id, level_id)
+    });
+    let fm_controls = fm_index_id.map(|index_id| {
+        let ratio_id = net.push(Box::new(var(&shared.fm_ratio) >> follow(0.01)));
+        (ratio_id, index_id)
+    });
+    // Detuned copies are uncorrelated, so normalize by power rather than amplitude
+    let norm = 1.0 / (n as f32).sqrt();
+
+    let mut sums: [Option<NodeId>; 2] = [None, None];
+    let mut mono = None;
+    for k in 0..n {
+        let pos = unison_position(k, n);
+
+        // freq × 2^((pitch·100 + pos·detune) / 1200)
+        let ratio_id = net.push(Box::new(map(move |i: &Frame<f32, U2>| {
+            ((i[0] * 100.0 + pos * i[1]) / 1200.0).exp2()
+        })));
+        net.connect(pitch_id, 0, ratio_id, 0);
+        net.connect(detune_id, 0, ratio_id, 1);
+        let copy_freq_id = net.push(Box::new(An(Mul2::new())));
+        net.connect(freq_id, 0, copy_freq_id, 0);
+        net.connect(ratio_id, 0, copy_freq_id, 1);
+
+        let carrier_freq_id = match fm_controls {
+            Some((ratio_id, index_id)) => build_fm_modulator(net, copy_freq_id, ratio_id, index_id),
+            None => copy_freq_id,
+        };
+        let osc_id = net.push(oscillator(cfg.waveform, cfg.wavetable.as_ref()));
+        net.connect(carrier_freq_id, 0, osc_id, 0);
+        net.connect(shape_id, 0, osc_id, 1);
+        let osc_id = match osc2_controls {
+            Some((shape_id, pitch_id, level_id)) => {
+                build_osc2(net, cfg, copy_freq_id, osc_id, shape_id, pitch_id, level_id)
+            }
+            None => osc_id,
+        };
+
+        if n == 1 {
+            mono = Some(osc_id);
+            break;
+        }
+
+        // Linear pan law: L = 1 - spread·pos, R = 1 + spread·pos
+        for (ch, side) in [(0, -1.0f32), (1, 1.0f32)] {
+            let gain_id = net.push(Box::new(map(move |i: &Frame<f32, U1>| {
+                (1.0 + side * i[0] * pos) * norm
+            })));
+            net.connect(spread_id, 0, gain_id, 0);
+            let pan_id = net.push(Box::new(An(Mul2::new())));
+            net.connect(osc_id, 0, pan_id, 0);
+            net.connect(gain_id, 0, pan_id, 1);
+            sums[ch] = Some(match sums[ch] {
+                None => pan_id,
+                Some(prev) => {
+                    let add = net.push(Box::new(An(Add2::new())));
+                    net.connect(prev, 0, add, 0);
+                    net.connect(pan_id, 0, add, 1);
+                    add
+                }
+            });
+        }
+    }
+
+    match mono {
+        Some(osc_id) => vec![osc_id],
+        None => sums.iter().flatten().copied().collect(),
+    }
+}
+
+/// FM modulator for a carrier at `freq_id`: a sine at `ratio_id` × the carrier
+/// frequency. Returns the modulated carrier frequency, which swings by index ×
+/// modulator frequency so that `index_id` is the carrier's peak phase deviation.
+fn build_fm_modulator(net: &mut Net, freq_id: NodeId, ratio_id: NodeId, index_id: NodeId) -> NodeId {
+    let mod_freq_id = net.push(Box::new(An(Mul2::new())));
+    net.connect(freq_id, 0, mod_freq_id, 0);
+    net.connect(ratio_id, 0, mod_freq_id, 1);
+    // Cosine, so the carrier phase deviation (its integral) is a sine starting at 0
+    let mod_id = net.push(Box::new(An(Sine::<f32>::with_phase(0.25))));
+    net.connect(mod_freq_id, 0, mod_id, 0);
+
+    // freq + index × mod_freq × modulator
+    let carrier_freq_id = net.push(Box::new(map(|i: &Frame<f32, U4>| i[0] + i[3] * i[1] * i[2])));
+    net.connect(freq_id, 0, carrier_freq_id, 0);
+    net.connect(mod_freq_id, 0, carrier_freq_id, 1);
+    net.connect(mod_id, 0, carrier_freq_id, 2);
+    net.connect(index_id, 0, carrier_freq_id, 3);
+    carrier_freq_id
+}
+
+/// Oscillator 2 of one unison copy, mixed into oscillator 1 (`osc1_id`). It plays
+/// `pitch_id` semitones from `freq_id`, hard-synced to oscillator 1 and/or
+/// ring-modulated by it as `cfg.osc2` asks. Returns the mix node.
+fn build_osc2(
+    net: &mut Net,
+    cfg: &VoiceConfig,
+    freq_id: NodeId,
+    osc1_id: NodeId,
+    shape_id: NodeId,
+    pitch_id: NodeId,
+    level_id: NodeId,
+) -> NodeId {
+    let osc2 = &cfg.osc2;
+    let freq2_id = net.push(Box::new(map(|i: &Frame<f32, U2>| i[0] * (i[1] / 12.0).exp2())));
+    net.connect(freq_id, 0, freq2_id, 0);
+    net.connect(pitch_id, 0, freq2_id, 1);
+
+    // Noise and supersaw have no cycle to restart, so they ignore sync
+    let synced = osc2.sync.then(|| HardSyncOsc::new(osc2.waveform, cfg.wavetable.as_ref())).flatten();
+    let osc2_id = match synced {
+        Some(osc) => {
+            let osc_id = net.push(Box::new(An(osc)));
+            net.connect(freq_id, 0, osc_id, 0);
+            net.connect(freq2_id, 0, osc_id, 1);
+            net.connect(shape_id, 0, osc_id, 2);
+            osc_id
+        }
+        None => {
+            let osc_id = net.push(oscillator(osc2.waveform, cfg.wavetable.as_ref()));
+            net.connect(freq2_id, 0, osc_id, 0);
+            net.connect(shape_id, 0, osc_id, 1);
+            osc_id
+        }
+    };
+
+    // osc1 + level × (osc2, or osc1 × osc2 when ring-modulated)
+    let mix_id = if osc2.ring {
+        net.push(Box::new(map(|i: &Frame<f32, U3>| i[0] + i[2] * i[0] * i[1])))
+    } else {
+        net.push(Box::new(map(|i: &Frame<f32, U3>| i[0] + i[2] * i[1])))
+    };
+    net.connect(osc1_id, 0, mix_id, 0);
+    net.connect(osc2_id, 0, mix_id, 1);
+    net.connect(level_id, 0, mix_id, 2);
+    mix_id
+}
+
+/// Oscillator shape input for `waveform`: the table position for wavetables, the
+/// pulse width otherwise, swept by the LFO if targeted.
+fn build_shape(
+    net: &mut Net,
     waveform: Waveform,
-    freq: &Shared,
-    gate: &Shared,
-    velocity: &Shared,
-    master_amp: &Shared,
-    voice_level: &Shared,
-    adsr: &AdsrParams,
-    filter_cfg: &FilterConfig,
-    cutoff: &Shared,
-    resonance: &Shared,
+    shared: &VoiceShared,
     lfo_cfg: &LfoConfig,
-    lfo_rate: &Shared,
-    lfo_depth: &Shared,
+    lfo_mod_id: Option<NodeId>,
+) -> NodeId {
+    if waveform == Waveform::Wavetable {
+        let position_id = net.push(Box::new(var(&shared.wavetable_position) >> follow(0.01)));
+        if let Some(lfo_id) = lfo_mod_id
+            && lfo_cfg.target == LfoTarget::WavetablePosition
+        {
+            // The mod is 1 + lfo·depth: full depth sweeps half the table either way
+            let sweep_id = net.push(Box::new(map(|i: &Frame<f32, U2>| i[0] + (i[1] - 1.0) * 0.5)));
+            net.connect(position_id, 0, sweep_id, 0);
+            net.connect(lfo_id, 0, sweep_id, 1);
+            sweep_id
+        } else {
+            position_id
+        }
+    } else {
+        let pulse_width_id = net.push(Box::new(var(&shared.pulse_width) >> follow(0.01)));
+        if let Some(lfo_id) = lfo_mod_id
+            && lfo_cfg.target == LfoTarget::PulseWidth
+        {
+            let mul_id = net.push(Box::new(An(Mul2::new())));
+            net.connect(pulse_width_id, 0, mul_id, 0);
+            net.connect(lfo_id, 0, mul_id, 1);
+            mul_id
+        } else {
+            pulse_width_id
+        }
+    }
+}
+
+/// Build a single polyphonic voice unit with ADSR envelope, optional filter and LFO.
+/// Uses an internal Net graph for dynamic node wiring. The envelope, filter, LFO and
+/// level stages run once per oscillator output (mono, or L/R for unison).
+/// Returns a 0-input, 2-output (stereo) AudioUnit that sleeps once the release
+/// has finished.
+pub fn build_voice_unit(
+    voice: &Voice,
+    cfg: &VoiceConfig,
+    shared: &VoiceShared,
+    master_amp: &Shared,
 ) -> Box<dyn AudioUnit> {
     let mut net = Net::new(0, 2);
+    let adsr = &cfg.adsr;
+    let filter_cfg = &cfg.filter_cfg;
+    let lfo_cfg = &cfg.lfo_cfg;
 
     // Build LFO mod node if enabled
     let lfo_mod_id = if lfo_cfg.enabled {
-        Some(build_lfo_mod(&mut net, lfo_cfg, lfo_rate, lfo_depth))
+        Some(build_lfo_mod(&mut net, lfo_cfg, &shared.lfo_rate, &shared.lfo_depth))
     } else {
         None
     };
 
-    // Frequency source
-    let freq_id = net.push(Box::new(var(freq) >> follow(0.01)));
+    // Frequency source, gliding only while the voice slides between notes
+    let glide_time = var(&shared.glide) * var(&voice.slide);
+    let freq_id = net.push(Box::new((var(&voice.freq) | glide_time) >> An(Glide::new())));
 
     // Apply LFO to frequency if targeted
     let osc_input_id = if lfo_cfg.enabled && lfo_cfg.target == LfoTarget::Frequency {
@@ -175,37 +435,66 @@ pub fn build_voice_unit(
         freq_id
     };
 
-    // Oscillator (1 input: frequency, 1 output: audio)
-    let osc_id = match waveform {
-        Waveform::Sine => net.push(Box::new(sine())),
-        Waveform::Saw => net.push(Box::new(saw())),
-        Waveform::Square => net.push(Box::new(square())),
-        Waveform::Triangle => net.push(Box::new(triangle())),
-    };
-    net.connect(osc_input_id, 0, osc_id, 0);
-
-    // ADSR envelope (0 input, 1 output)
+    // Oscillator shapes; oscillator 2 shares oscillator 1's when it reads the same one
+    let shape_id = build_shape(&mut net, cfg.waveform, shared, lfo_cfg, lfo_mod_id);
+    let osc2_shape_id = cfg.osc2.enabled.then(|| {
+        if (cfg.osc2.waveform == Waveform::Wavetable) == (cfg.waveform == Waveform::Wavetable) {
+            shape_id
+        } else {
+            build_shape(&mut net, cfg.osc2.waveform, shared, lfo_cfg, lfo_mod_id)
+        }
+    });
+
+    // FM index: the peak index shaped by its own envelope
+    let fm_index_id = (cfg.engine == VoiceEngine::Fm).then(|| {
+        let env = &cfg.fm.index_env;
+        let env_id = net.push(Box::new(
+            (var(&voice.gate) | var(&voice.retrigger))
+                >> An(Retrigger::new())
+                >> adsr_live(env.attack, env.decay, env.sustain, env.release),
+        ));
+        let index_id = net.push(Box::new(var(&shared.fm_index) >> follow(0.01)));
+        let scaled_id = net.push(Box::new(An(Mul2::new())));
+        net.connect(env_id, 0, scaled_id, 0);
+        net.connect(index_id, 0, scaled_id, 1);
+        scaled_id
+    });
+
+    // Oscillator section (mono, or stereo L/R for unison)
+    let osc_ids = build_unison_osc(
+        &mut net,
+        cfg,
+        shared,
+        osc_input_id,
+        shape_id,
+        osc2_shape_id,
+        fm_index_id,
+    );
+
+    // Control sources shared by every channel
     let env_id = net.push(Box::new(
-        var(gate) >> adsr_live(adsr.attack, adsr.decay, adsr.sustain, adsr.release),
+        (var(&voice.gate) | var(&voice.retrigger))
+            >> An(Retrigger::new())
+            >> adsr_live(adsr.attack, adsr.decay, adsr.sustain, adsr.release)
+            >> monitor(&voice.envelope, Meter::Sample),
     ));
-
-    // osc × envelope
-    let env_mul_id = net.push(Box::new(An(Mul2::new())));
-    net.connect(osc_id, 0, env_mul_id, 0);
-    net.connect(env_id, 0, env_mul_id, 1);
-
-    // × velocity
-    let vel_id = net.push(Box::new(var(velocity)));
-    let vel_mul_id = net.push(Box::new(An(Mul2::new())));
-    net.connect(env_mul_id, 0, vel_mul_id, 0);
-    net.connect(vel_id, 0, vel_mul_id, 1);
-
-    let mut signal_id = vel_mul_id;
-
-    // Optional filter (3 inputs: audio, cutoff_hz, Q → 1 output)
-    if filter_cfg.enabled {
-        // Cutoff source
-        let cutoff_id = net.push(Box::new(var(cutoff) >> follow(0.01)));
+    let vel_id = net.push(Box::new(var(&voice.velocity)));
+    let filter_ctl = if filter_cfg.enabled {
+        // Cutoff source, swept by the filter envelope: cutoff × 2^(env × amount)
+        let base_cutoff_id = net.push(Box::new(var(&shared.cutoff) >> follow(0.01)));
+        let fenv = &cfg.filter_env;
+        let fenv_id = net.push(Box::new(
+            (var(&voice.gate) | var(&voice.retrigger))
+                >> An(Retrigger::new())
+                >> adsr_live(fenv.attack, fenv.decay, fenv.sustain, fenv.release),
+        ));
+        let fenv_amount_id = net.push(Box::new(var(&shared.filter_env_amount) >> follow(0.01)));
+        let cutoff_id = net.push(Box::new(map(|i: &Frame<f32, U3>| {
+            (i[0] * (i[1] * i[2]).exp2()).clamp(20.0, 20000.0)
+        })));
+        net.connect(base_cutoff_id, 0, cutoff_id, 0);
+        net.connect(fenv_id, 0, cutoff_id, 1);
+        net.connect(fenv_amount_id, 0, cutoff_id, 2);
 
         // Apply LFO to cutoff if targeted
         let filter_cutoff_id = if lfo_cfg.enabled && lfo_cfg.target == LfoTarget::Cutoff {
@@ -219,51 +508,75 @@ pub fn build_voice_unit(
         };
 
         // Q source from resonance via var_fn
-        let q_id = net.push(Box::new(var_fn(resonance, resonance_to_q)));
-
-        // Filter node
-        let filter_id = match filter_cfg.filter_type {
-            FilterType::Lowpass => net.push(Box::new(lowpass())),
-            FilterType::Highpass => net.push(Box::new(highpass())),
-            FilterType::Bandpass => net.push(Box::new(bandpass())),
-        };
-        net.connect(signal_id, 0, filter_id, 0);
-        net.connect(filter_cutoff_id, 0, filter_id, 1);
-        net.connect(q_id, 0, filter_id, 2);
+        let q_id = net.push(Box::new(var_fn(&shared.resonance, resonance_to_q)));
+        Some((filter_cutoff_id, q_id))
+    } else {
+        None
+    };
+    let lvl_id = net.push(Box::new(var(&shared.level) >> follow(0.01)));
+    let amp_id = net.push(Box::new(var(master_amp) >> follow(0.01)));
 
-        signal_id = filter_id;
-    }
+    let mut out_ids = Vec::new();
+    for &osc_id in &osc_ids {
+        // osc × envelope
+        let env_mul_id = net.push(Box::new(An(Mul2::new())));
+        net.connect(osc_id, 0, env_mul_id, 0);
+        net.connect(env_id, 0, env_mul_id, 1);
+
+        // × velocity
+        let vel_mul_id = net.push(Box::new(An(Mul2::new())));
+        net.connect(env_mul_id, 0, vel_mul_id, 0);
+        net.connect(vel_id, 0, vel_mul_id, 1);
+
+        let mut signal_id = vel_mul_id;
+
+        // Optional filter (3 inputs: audio, cutoff_hz, Q → 1 output)
+        if let Some((filter_cutoff_id, q_id)) = filter_ctl {
+            let filter_id = match filter_cfg.filter_type {
+                FilterType::Lowpass => net.push(Box::new(lowpass())),
+                FilterType::Highpass => net.push(Box::new(highpass())),
+                FilterType::Bandpass => net.push(Box::new(bandpass())),
+            };
+            net.connect(signal_id, 0, filter_id, 0);
+            net.connect(filter_cutoff_id, 0, filter_id, 1);
+            net.connect(q_id, 0, filter_id, 2);
 
-    // Optional LFO on amplitude
-    if lfo_cfg.enabled && lfo_cfg.target == LfoTarget::Amplitude {
-        let lfo_id = lfo_mod_id.unwrap();
-        let mul_id = net.push(Box::new(An(Mul2::new())));
-        net.connect(signal_id, 0, mul_id, 0);
-        net.connect(lfo_id, 0, mul_id, 1);
-        signal_id = mul_id;
-    }
+            signal_id = filter_id;
+        }
 
-    // × per-voice level
-    let lvl_id = net.push(Box::new(var(voice_level) >> follow(0.01)));
-    let lvl_mul_id = net.push(Box::new(An(Mul2::new())));
-    net.connect(signal_id, 0, lvl_mul_id, 0);
-    net.connect(lvl_id, 0, lvl_mul_id, 1);
+        // Optional LFO on amplitude
+        if lfo_cfg.enabled && lfo_cfg.target == LfoTarget::Amplitude {
+            let lfo_id = lfo_mod_id.unwrap();
+            let mul_id = net.push(Box::new(An(Mul2::new())));
+            net.connect(signal_id, 0, mul_id, 0);
+            net.connect(lfo_id, 0, mul_id, 1);
+            signal_id = mul_id;
+        }
 
-    // × master amplitude
-    let amp_id = net.push(Box::new(var(master_amp) >> follow(0.01)));
-    let amp_mul_id = net.push(Box::new(An(Mul2::new())));
-    net.connect(lvl_mul_id, 0, amp_mul_id, 0);
-    net.connect(amp_id, 0, amp_mul_id, 1);
+        // × per-voice level
+        let lvl_mul_id = net.push(Box::new(An(Mul2::new())));
+        net.connect(signal_id, 0, lvl_mul_id, 0);
+        net.connect(lvl_id, 0, lvl_mul_id, 1);
 
-    // Split to stereo (1 input → 2 outputs)
-    let split_id = net.push(Box::new(split::<U2>()));
-    net.connect(amp_mul_id, 0, split_id, 0);
+        // × master amplitude
+        let amp_mul_id = net.push(Box::new(An(Mul2::new())));
+        net.connect(lvl_mul_id, 0, amp_mul_id, 0);
+        net.connect(amp_id, 0, amp_mul_id, 1);
 
-    // Connect to net outputs
-    net.connect_output(split_id, 0, 0);
-    net.connect_output(split_id, 1, 1);
+        out_ids.push(amp_mul_id);
+    }
 
-    Box::new(net)
+    // Connect to net outputs (a mono chain feeds both channels)
+    net.connect_output(out_ids[0], 0, 0);
+    net.connect_output(*out_ids.last().unwrap(), 0, 1);
+
+    // Skip the whole chain while the voice is idle
+    Box::new(An(VoiceSleep::new(
+        Box::new(net),
+        &voice.gate,
+        &voice.envelope,
+        &voice.sounding,
+    )))
 }
 
 /// Build a polyphonic audio graph with 8 voices summed together, plus effects chain.
@@ -300,21 +613,7 @@ pub fn build_poly_graph(
     for (i, voice) in voices.iter().enumerate() {
         let cfg = &voice_configs[i];
         let shared = &voice_shared[i];
-        let unit = build_voice_unit(
-            cfg.waveform,
-            &voice.freq,
-            &voice.gate,
-            &voice.velocity,
-            master_amp,
-            &shared.level,
-            &cfg.adsr,
-            &cfg.filter_cfg,
-            &shared.cutoff,
-            &shared.resonance,
-            &cfg.lfo_cfg,
-            &shared.lfo_rate,
-            &shared.lfo_depth,
-        );
+        let unit = build_voice_unit(voice, cfg, shared, master_amp);
         voice_ids.push(net.push(unit));
     }
 
@@ -378,6 +677,8 @@ pub fn build_poly_graph(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::engine::filter::FilterConfig;
+    use crate::engine::voice::{FmConfig, Osc2Config};
 
     const SAMPLE_RATE: f64 = 44100.0;
 
@@ -395,14 +696,29 @@ mod tests {
         LfoConfig::default()
     }
 
-    fn default_shared_params() -> (Shared, Shared, Shared, Shared, Shared) {
-        (
-            Shared::new(1000.0), // cutoff
-            Shared::new(0.0),    // resonance
-            Shared::new(1.0),    // lfo_rate
-            Shared::new(0.0),    // lfo_depth
-            Shared::new(1.0),    // voice_level
-        )
+    /// Voice unit for `cfg` on a fresh, ungated 440 Hz voice.
+    fn voice_unit(cfg: &VoiceConfig) -> Box<dyn AudioUnit> {
+        build_voice_unit(&Voice::new(), cfg, &VoiceShared::new(cfg), &Shared::new(0.5))
+    }
+
+    /// Gate a 220 Hz voice after a short warm-up and return the next `n` samples.
+    fn gated_voice_samples(cfg: &VoiceConfig, shared: &VoiceShared, n: usize) -> Vec<(f32, f32)> {
+        let voice = Voice::new();
+        voice.freq.set_value(220.0);
+        voice.velocity.set_value(1.0);
+        let mut unit = build_voice_unit(&voice, cfg, shared, &Shared::new(0.5));
+        unit.set_sample_rate(SAMPLE_RATE);
+        unit.allocate();
+        for _ in 0..100 {
+            unit.get_stereo();
+        }
+        voice.gate.set_value(1.0);
+        (0..n).map(|_| unit.get_stereo()).collect()
+    }
+
+    /// Count upward zero crossings of one channel (a frequency estimate).
+    fn zero_crossings(samples: &[f32]) -> usize {
+        samples.windows(2).filter(|w| w[0] <= 0.0 && w[1] > 0.0).count()
     }
 
     fn default_voice_configs(n: usize) -> Vec<VoiceConfig> {
@@ -429,12 +745,7 @@ mod tests {
 
     #[test]
     fn build_oscillator_returns_stereo_graph() {
-        for waveform in [
-            Waveform::Sine,
-            Waveform::Saw,
-            Waveform::Square,
-            Waveform::Triangle,
-        ] {
+        for waveform in Waveform::ALL {
             let graph = build_oscillator(waveform, 440.0, 0.5);
             assert_eq!(graph.inputs(), 0, "{waveform} should have 0 inputs");
             assert_eq!(
@@ -447,12 +758,7 @@ mod tests {
 
     #[test]
     fn all_waveforms_produce_nonsilent_output() {
-        for waveform in [
-            Waveform::Sine,
-            Waveform::Saw,
-            Waveform::Square,
-            Waveform::Triangle,
-        ] {
+        for waveform in Waveform::ALL {
             let samples = collect_samples(build_oscillator(waveform, 440.0, 0.5), 512);
             let has_nonzero = samples.iter().any(|(l, r)| *l != 0.0 || *r != 0.0);
             assert!(has_nonzero, "{waveform} should produce non-silent output");
@@ -461,12 +767,7 @@ mod tests {
 
     #[test]
     fn zero_amplitude_produces_silence() {
-        for waveform in [
-            Waveform::Sine,
-            Waveform::Saw,
-            Waveform::Square,
-            Waveform::Triangle,
-        ] {
+        for waveform in Waveform::ALL {
             let samples = collect_samples(build_oscillator(waveform, 440.0, 0.0), 512);
             let all_silent = samples.iter().all(|(l, r)| *l == 0.0 && *r == 0.0);
             assert!(all_silent, "{waveform} at amplitude 0.0 should be silent");
@@ -478,12 +779,7 @@ mod tests {
         let amplitude = 0.5;
         // Allow small margin for filter transients
         let margin = 0.1;
-        for waveform in [
-            Waveform::Sine,
-            Waveform::Saw,
-            Waveform::Square,
-            Waveform::Triangle,
-        ] {
+        for waveform in Waveform::ALL {
             let samples = collect_samples(build_oscillator(waveform, 440.0, amplitude), 4096);
             for (i, (l, r)) in samples.iter().enumerate() {
                 assert!(
@@ -500,12 +796,7 @@ mod tests {
 
     #[test]
     fn stereo_channels_are_identical() {
-        for waveform in [
-            Waveform::Sine,
-            Waveform::Saw,
-            Waveform::Square,
-            Waveform::Triangle,
-        ] {
+        for waveform in Waveform::ALL {
             let samples = collect_samples(build_oscillator(waveform, 440.0, 0.5), 256);
             for (i, (l, r)) in samples.iter().enumerate() {
                 assert_eq!(l, r, "{waveform} sample {i}: left ({l}) != right ({r})");
@@ -519,18 +810,16 @@ mod tests {
         assert_eq!(Waveform::Saw.to_string(), "saw");
         assert_eq!(Waveform::Square.to_string(), "square");
         assert_eq!(Waveform::Triangle.to_string(), "triangle");
+        assert_eq!(Waveform::Supersaw.to_string(), "supersaw");
+        assert_eq!(Waveform::PinkNoise.to_string(), "pink-noise");
+        assert_eq!(Waveform::Wavetable.to_string(), "wavetable");
     }
 
     #[test]
     fn build_oscillator_shared_returns_stereo_graph() {
         let freq = Shared::new(440.0);
         let amp = Shared::new(0.5);
-        for waveform in [
-            Waveform::Sine,
-            Waveform::Saw,
-            Waveform::Square,
-            Waveform::Triangle,
-        ] {
+        for waveform in Waveform::ALL {
             let (graph, _, _) = build_oscillator_shared(waveform, &freq, &amp);
             assert_eq!(graph.inputs(), 0, "{waveform} shared should have 0 inputs");
             assert_eq!(
@@ -588,27 +877,18 @@ mod tests {
         );
     }
 
+    /// Envelope that opens at once and holds while gated.
+    const HELD: AdsrParams = AdsrParams {
+        attack: 0.001,
+        decay: 0.0,
+        sustain: 1.0,
+        release: 0.01,
+    };
+
     #[test]
     fn build_voice_unit_returns_stereo() {
-        let freq = Shared::new(440.0);
-        let gate = Shared::new(1.0);
-        let velocity = Shared::new(1.0);
-        let master_amp = Shared::new(0.5);
-        let adsr = AdsrParams::default();
-        let filter_cfg = default_filter_cfg();
-        let lfo_cfg = default_lfo_cfg();
-        let (cutoff, resonance, lfo_rate, lfo_depth, voice_level) = default_shared_params();
-
-        for waveform in [
-            Waveform::Sine,
-            Waveform::Saw,
-            Waveform::Square,
-            Waveform::Triangle,
-        ] {
-            let unit = build_voice_unit(
-                waveform, &freq, &gate, &velocity, &master_amp, &voice_level, &adsr,
-                &filter_cfg, &cutoff, &resonance, &lfo_cfg, &lfo_rate, &lfo_depth,
-            );
+        for waveform in Waveform::ALL {
+            let unit = voice_unit(&VoiceConfig { waveform, ..VoiceConfig::default() });
             assert_eq!(unit.inputs(), 0, "{waveform} voice should have 0 inputs");
             assert_eq!(unit.outputs(), 2, "{waveform} voice should have 2 outputs");
         }
@@ -616,40 +896,8 @@ mod tests {
 
     #[test]
     fn build_voice_unit_produces_sound_when_gate_triggered() {
-        let freq = Shared::new(440.0);
-        let gate = Shared::new(0.0);
-        let velocity = Shared::new(1.0);
-        let master_amp = Shared::new(0.5);
-        let adsr = AdsrParams {
-            attack: 0.001,
-            decay: 0.0,
-            sustain: 1.0,
-            release: 0.01,
-        };
-        let filter_cfg = default_filter_cfg();
-        let lfo_cfg = default_lfo_cfg();
-        let (cutoff, resonance, lfo_rate, lfo_depth, voice_level) = default_shared_params();
-
-        let mut unit = build_voice_unit(
-            Waveform::Sine, &freq, &gate, &velocity, &master_amp, &voice_level, &adsr,
-            &filter_cfg, &cutoff, &resonance, &lfo_cfg, &lfo_rate, &lfo_depth,
-        );
-        unit.set_sample_rate(SAMPLE_RATE);
-        unit.allocate();
-
-        // Run some samples with gate off
-        for _ in 0..100 {
-            unit.get_stereo();
-        }
-
-        // Trigger the gate
-        gate.set_value(1.0);
-
-        let mut samples = Vec::new();
-        for _ in 0..8192 {
-            samples.push(unit.get_stereo());
-        }
-
+        let cfg = VoiceConfig { adsr: HELD, ..VoiceConfig::default() };
+        let samples = gated_voice_samples(&cfg, &VoiceShared::new(&cfg), 8192);
         let tail = &samples[4096..];
         let has_nonzero = tail.iter().any(|(l, r)| *l != 0.0 || *r != 0.0);
         assert!(has_nonzero, "voice should produce sound after gate trigger");
@@ -657,25 +905,16 @@ mod tests {
 
     #[test]
     fn build_voice_unit_silent_when_gate_off() {
-        let freq = Shared::new(440.0);
-        let gate = Shared::new(0.0);
-        let velocity = Shared::new(1.0);
-        let master_amp = Shared::new(0.5);
-        let adsr = AdsrParams {
-            attack: 0.01,
-            decay: 0.1,
-            sustain: 0.7,
-            release: 0.001,
+        let cfg = VoiceConfig {
+            adsr: AdsrParams {
+                attack: 0.01,
+                decay: 0.1,
+                sustain: 0.7,
+                release: 0.001,
+            },
+            ..VoiceConfig::default()
         };
-        let filter_cfg = default_filter_cfg();
-        let lfo_cfg = default_lfo_cfg();
-        let (cutoff, resonance, lfo_rate, lfo_depth, voice_level) = default_shared_params();
-
-        let unit = build_voice_unit(
-            Waveform::Sine, &freq, &gate, &velocity, &master_amp, &voice_level, &adsr,
-            &filter_cfg, &cutoff, &resonance, &lfo_cfg, &lfo_rate, &lfo_depth,
-        );
-        let samples = collect_samples(unit, 4096);
+        let samples = collect_samples(voice_unit(&cfg), 4096);
         let tail = &samples[samples.len() - 256..];
         let max_tail = tail
             .iter()
@@ -689,27 +928,12 @@ mod tests {
 
     #[test]
     fn build_voice_unit_with_filter_returns_stereo() {
-        let freq = Shared::new(440.0);
-        let gate = Shared::new(1.0);
-        let velocity = Shared::new(1.0);
-        let master_amp = Shared::new(0.5);
-        let voice_level = Shared::new(1.0);
-        let adsr = AdsrParams::default();
-        let cutoff = Shared::new(1000.0);
-        let resonance = Shared::new(0.0);
-        let lfo_cfg = default_lfo_cfg();
-        let lfo_rate = Shared::new(1.0);
-        let lfo_depth = Shared::new(0.0);
-
         for filter_type in [FilterType::Lowpass, FilterType::Highpass, FilterType::Bandpass] {
-            let filter_cfg = FilterConfig {
-                filter_type,
-                enabled: true,
-            };
-            let unit = build_voice_unit(
-                Waveform::Saw, &freq, &gate, &velocity, &master_amp, &voice_level, &adsr,
-                &filter_cfg, &cutoff, &resonance, &lfo_cfg, &lfo_rate, &lfo_depth,
-            );
+            let unit = voice_unit(&VoiceConfig {
+                waveform: Waveform::Saw,
+                filter_cfg: FilterConfig { filter_type, enabled: true },
+                ..VoiceConfig::default()
+            });
             assert_eq!(unit.inputs(), 0);
             assert_eq!(unit.outputs(), 2);
         }
@@ -717,37 +941,14 @@ mod tests {
 
     #[test]
     fn build_voice_unit_with_filter_produces_sound() {
-        let freq = Shared::new(440.0);
-        let gate = Shared::new(0.0);
-        let velocity = Shared::new(1.0);
-        let master_amp = Shared::new(0.5);
-        let voice_level = Shared::new(1.0);
-        let adsr = AdsrParams {
-            attack: 0.001,
-            decay: 0.0,
-            sustain: 1.0,
-            release: 0.01,
+        let cfg = VoiceConfig {
+            waveform: Waveform::Saw,
+            adsr: HELD,
+            filter_cfg: FilterConfig { filter_type: FilterType::Lowpass, enabled: true },
+            cutoff: 5000.0,
+            ..VoiceConfig::default()
         };
-        let filter_cfg = FilterConfig {
-            filter_type: FilterType::Lowpass,
-            enabled: true,
-        };
-        let cutoff = Shared::new(5000.0);
-        let resonance = Shared::new(0.0);
-        let lfo_cfg = default_lfo_cfg();
-        let lfo_rate = Shared::new(1.0);
-        let lfo_depth = Shared::new(0.0);
-
-        let mut unit = build_voice_unit(
-            Waveform::Saw, &freq, &gate, &velocity, &master_amp, &voice_level, &adsr,
-            &filter_cfg, &cutoff, &resonance, &lfo_cfg, &lfo_rate, &lfo_depth,
-        );
-        unit.set_sample_rate(SAMPLE_RATE);
-        unit.allocate();
-        for _ in 0..100 { unit.get_stereo(); }
-        gate.set_value(1.0);
-        let mut samples = Vec::new();
-        for _ in 0..8192 { samples.push(unit.get_stereo()); }
+        let samples = gated_voice_samples(&cfg, &VoiceShared::new(&cfg), 8192);
         let tail = &samples[4096..];
         let has_nonzero = tail.iter().any(|(l, r)| *l != 0.0 || *r != 0.0);
         assert!(has_nonzero, "filtered voice should produce sound");
@@ -755,29 +956,15 @@ mod tests {
 
     #[test]
     fn build_voice_unit_with_lfo_returns_stereo() {
-        let freq = Shared::new(440.0);
-        let gate = Shared::new(1.0);
-        let velocity = Shared::new(1.0);
-        let master_amp = Shared::new(0.5);
-        let voice_level = Shared::new(1.0);
-        let adsr = AdsrParams::default();
-        let filter_cfg = default_filter_cfg();
-        let cutoff = Shared::new(1000.0);
-        let resonance = Shared::new(0.0);
-        let lfo_rate = Shared::new(5.0);
-        let lfo_depth = Shared::new(0.5);
-
         for target in [LfoTarget::Frequency, LfoTarget::Cutoff, LfoTarget::Amplitude] {
             for waveform_lfo in [LfoWaveform::Sine, LfoWaveform::Triangle, LfoWaveform::Saw] {
-                let lfo_cfg = LfoConfig {
-                    waveform: waveform_lfo,
-                    target,
-                    enabled: true,
-                };
-                let unit = build_voice_unit(
-                    Waveform::Saw, &freq, &gate, &velocity, &master_amp, &voice_level, &adsr,
-                    &filter_cfg, &cutoff, &resonance, &lfo_cfg, &lfo_rate, &lfo_depth,
-                );
+                let unit = voice_unit(&VoiceConfig {
+                    waveform: Waveform::Saw,
+                    lfo_cfg: LfoConfig { waveform: waveform_lfo, target, enabled: true },
+                    lfo_rate: 5.0,
+                    lfo_depth: 0.5,
+                    ..VoiceConfig::default()
+                });
                 assert_eq!(unit.inputs(), 0);
                 assert_eq!(unit.outputs(), 2);
             }
@@ -786,38 +973,18 @@ mod tests {
 
     #[test]
     fn build_voice_unit_with_lfo_on_freq_produces_sound() {
-        let freq = Shared::new(440.0);
-        let gate = Shared::new(0.0);
-        let velocity = Shared::new(1.0);
-        let master_amp = Shared::new(0.5);
-        let voice_level = Shared::new(1.0);
-        let adsr = AdsrParams {
-            attack: 0.001,
-            decay: 0.0,
-            sustain: 1.0,
-            release: 0.01,
-        };
-        let filter_cfg = default_filter_cfg();
-        let cutoff = Shared::new(1000.0);
-        let resonance = Shared::new(0.0);
-        let lfo_cfg = LfoConfig {
-            waveform: LfoWaveform::Sine,
-            target: LfoTarget::Frequency,
-            enabled: true,
+        let cfg = VoiceConfig {
+            adsr: HELD,
+            lfo_cfg: LfoConfig {
+                waveform: LfoWaveform::Sine,
+                target: LfoTarget::Frequency,
+                enabled: true,
+            },
+            lfo_rate: 5.0,
+            lfo_depth: 0.3,
+            ..VoiceConfig::default()
         };
-        let lfo_rate = Shared::new(5.0);
-        let lfo_depth = Shared::new(0.3);
-
-        let mut unit = build_voice_unit(
-            Waveform::Sine, &freq, &gate, &velocity, &master_amp, &voice_level, &adsr,
-            &filter_cfg, &cutoff, &resonance, &lfo_cfg, &lfo_rate, &lfo_depth,
-        );
-        unit.set_sample_rate(SAMPLE_RATE);
-        unit.allocate();
-        for _ in 0..100 { unit.get_stereo(); }
-        gate.set_value(1.0);
-        let mut samples = Vec::new();
-        for _ in 0..8192 { samples.push(unit.get_stereo()); }
+        let samples = gated_voice_samples(&cfg, &VoiceShared::new(&cfg), 8192);
         let tail = &samples[4096..];
         let has_nonzero = tail.iter().any(|(l, r)| *l != 0.0 || *r != 0.0);
         assert!(has_nonzero, "voice with LFO on frequency should produce sound");
@@ -825,41 +992,22 @@ mod tests {
 
     #[test]
     fn build_voice_unit_with_filter_and_lfo_on_cutoff() {
-        let freq = Shared::new(440.0);
-        let gate = Shared::new(0.0);
-        let velocity = Shared::new(1.0);
-        let master_amp = Shared::new(0.5);
-        let voice_level = Shared::new(1.0);
-        let adsr = AdsrParams {
-            attack: 0.001,
-            decay: 0.0,
-            sustain: 1.0,
-            release: 0.01,
-        };
-        let filter_cfg = FilterConfig {
-            filter_type: FilterType::Lowpass,
-            enabled: true,
-        };
-        let cutoff = Shared::new(2000.0);
-        let resonance = Shared::new(0.3);
-        let lfo_cfg = LfoConfig {
-            waveform: LfoWaveform::Sine,
-            target: LfoTarget::Cutoff,
-            enabled: true,
+        let cfg = VoiceConfig {
+            waveform: Waveform::Saw,
+            adsr: HELD,
+            filter_cfg: FilterConfig { filter_type: FilterType::Lowpass, enabled: true },
+            cutoff: 2000.0,
+            resonance: 0.3,
+            lfo_cfg: LfoConfig {
+                waveform: LfoWaveform::Sine,
+                target: LfoTarget::Cutoff,
+                enabled: true,
+            },
+            lfo_rate: 2.0,
+            lfo_depth: 0.5,
+            ..VoiceConfig::default()
         };
-        let lfo_rate = Shared::new(2.0);
-        let lfo_depth = Shared::new(0.5);
-
-        let mut unit = build_voice_unit(
-            Waveform::Saw, &freq, &gate, &velocity, &master_amp, &voice_level, &adsr,
-            &filter_cfg, &cutoff, &resonance, &lfo_cfg, &lfo_rate, &lfo_depth,
-        );
-        unit.set_sample_rate(SAMPLE_RATE);
-        unit.allocate();
-        for _ in 0..100 { unit.get_stereo(); }
-        gate.set_value(1.0);
-        let mut samples = Vec::new();
-        for _ in 0..8192 { samples.push(unit.get_stereo()); }
+        let samples = gated_voice_samples(&cfg, &VoiceShared::new(&cfg), 8192);
         let tail = &samples[4096..];
         let has_nonzero = tail.iter().any(|(l, r)| *l != 0.0 || *r != 0.0);
         assert!(has_nonzero, "voice with filter + LFO on cutoff should produce sound");
@@ -873,12 +1021,7 @@ mod tests {
         let (delay_time, delay_feedback, delay_mix, reverb_mix, chorus_mix) =
             default_effects_shared();
 
-        for waveform in [
-            Waveform::Sine,
-            Waveform::Saw,
-            Waveform::Square,
-            Waveform::Triangle,
-        ] {
+        for waveform in Waveform::ALL {
             let voice_configs: Vec<VoiceConfig> = (0..8)
                 .map(|_| VoiceConfig { waveform, ..VoiceConfig::default() })
                 .collect();
@@ -1044,4 +1187,338 @@ mod tests {
         assert_eq!(adsr.sustain, 0.7);
         assert_eq!(adsr.release, 0.3);
     }
+
+    #[test]
+    fn build_voice_unit_pitch_offset_shifts_frequency() {
+        let cfg = VoiceConfig {
+            adsr: AdsrParams { attack: 0.001, decay: 0.0, sustain: 1.0, release: 0.01 },
+            ..VoiceConfig::default()
+        };
+        let base = gated_voice_samples(&cfg, &VoiceShared::new(&cfg), 44100);
+        let up = VoiceConfig { pitch: 12.0, ..cfg.clone() };
+        let octave = gated_voice_samples(&up, &VoiceShared::new(&up), 44100);
+
+        let left = |s: &[(f32, f32)]| s.iter().map(|(l, _)| *l).collect::<Vec<f32>>();
+        let base_hz = zero_crossings(&left(&base)[4410..]) as f32 / 0.9;
+        let octave_hz = zero_crossings(&left(&octave)[4410..]) as f32 / 0.9;
+        assert!((base_hz - 220.0).abs() < 3.0, "base should be ~220 Hz, got {base_hz}");
+        assert!((octave_hz - 440.0).abs() < 5.0, "+12 st should be ~440 Hz, got {octave_hz}");
+    }
+
+    /// Share of positive samples in each window: the pulse's duty cycle.
+    fn duty_cycles(samples: &[(f32, f32)], window: usize) -> Vec<f32> {
+        samples
+            .chunks(window)
+            .map(|w| w.iter().filter(|(l, _)| *l > 0.0).count() as f32 / w.len() as f32)
+            .collect()
+    }
+
+    #[test]
+    fn build_voice_unit_pulse_width_sets_duty_cycle() {
+        let duty = |pulse_width: f32| {
+            let cfg = VoiceConfig {
+                waveform: Waveform::Pulse,
+                filter_cfg: FilterConfig { enabled: false, ..default_filter_cfg() },
+                pulse_width,
+                ..VoiceConfig::default()
+            };
+            let samples = gated_voice_samples(&cfg, &VoiceShared::new(&cfg), 8192);
+            duty_cycles(&samples[4096..], 4096)[0]
+        };
+        let (narrow, wide) = (duty(0.2), duty(0.8));
+        assert!((narrow - wide).abs() > 0.4, "duty cycles {narrow} and {wide} should differ");
+    }
+
+    #[test]
+    fn build_voice_unit_lfo_sweeps_pulse_width() {
+        let spread = |enabled: bool| {
+            let cfg = VoiceConfig {
+                waveform: Waveform::Pulse,
+                filter_cfg: FilterConfig { enabled: false, ..default_filter_cfg() },
+                lfo_cfg: LfoConfig { target: LfoTarget::PulseWidth, enabled, ..default_lfo_cfg() },
+                lfo_rate: 2.0,
+                lfo_depth: 0.8,
+                ..VoiceConfig::default()
+            };
+            let samples = gated_voice_samples(&cfg, &VoiceShared::new(&cfg), 44100);
+            let duties = duty_cycles(&samples[2205..], 2205);
+            let max = duties.iter().copied().fold(0.0f32, f32::max);
+            let min = duties.iter().copied().fold(1.0f32, f32::min);
+            max - min
+        };
+        assert!(spread(false) < 0.1, "a still pulse keeps its width");
+        assert!(spread(true) > 0.3, "PWM should sweep the duty cycle");
+    }
+
+    #[test]
+    fn build_voice_unit_wavetable_position_changes_timbre() {
+        let render = |wavetable_position: f32, lfo_enabled: bool| {
+            let cfg = VoiceConfig {
+                waveform: Waveform::Wavetable,
+                filter_cfg: FilterConfig { enabled: false, ..default_filter_cfg() },
+                lfo_cfg: LfoConfig {
+                    target: LfoTarget::WavetablePosition,
+                    enabled: lfo_enabled,
+                    ..default_lfo_cfg()
+                },
+                lfo_rate: 5.0,
+                lfo_depth: 1.0,
+                wavetable_position,
+                ..VoiceConfig::default()
+            };
+            let samples = gated_voice_samples(&cfg, &VoiceShared::new(&cfg), 8192);
+            samples[4096..].iter().map(|(l, _)| *l).collect::<Vec<f32>>()
+        };
+        // The basic table starts on a sine and ends on a square
+        let sine = render(0.0, false);
+        let square = render(1.0, false);
+        let crossings = zero_crossings(&sine);
+        assert!(crossings > 10, "wavetable voice should play the note");
+        assert_eq!(crossings, zero_crossings(&square), "position keeps the pitch");
+        let mean_abs = |s: &[f32]| s.iter().map(|x| x.abs()).sum::<f32>() / s.len() as f32;
+        assert!(mean_abs(&square) > mean_abs(&sine) * 1.2, "square is fuller than sine");
+
+        let swept = render(0.0, true);
+        assert!(swept.iter().zip(&sine).any(|(a, b)| (a - b).abs() > 0.05), "LFO should sweep the position");
+    }
+
+    /// Magnitude of the `freq` component of `samples` (a single DFT bin).
+    fn magnitude_at(samples: &[f32], freq: f32) -> f32 {
+        let w = std::f32::consts::TAU * freq / SAMPLE_RATE as f32;
+        let (re, im) = samples.iter().enumerate().fold((0.0, 0.0), |(re, im), (i, s)| {
+            (re + s * (w * i as f32).cos(), im - s * (w * i as f32).sin())
+        });
+        (re * re + im * im).sqrt() / samples.len() as f32
+    }
+
+    fn osc2_voice_samples(osc2: Osc2Config) -> Vec<f32> {
+        let cfg = VoiceConfig {
+            waveform: Waveform::Sine,
+            filter_cfg: FilterConfig { enabled: false, ..default_filter_cfg() },
+            osc2,
+            ..VoiceConfig::default()
+        };
+        let samples = gated_voice_samples(&cfg, &VoiceShared::new(&cfg), 8192);
+        samples[4096..].iter().map(|(l, _)| *l).collect()
+    }
+
+    #[test]
+    fn build_voice_unit_osc2_adds_its_pitch() {
+        let single = osc2_voice_samples(Osc2Config::default());
+        let octave_up = Osc2Config { enabled: true, waveform: Waveform::Sine, octave: 1, ..Osc2Config::default() };
+        let layered = osc2_voice_samples(octave_up);
+        assert!(magnitude_at(&single, 440.0) < 0.01, "a lone sine has no octave");
+        assert!(magnitude_at(&layered, 440.0) > 0.05, "osc 2 should sound an octave up");
+        assert!(magnitude_at(&layered, 220.0) > 0.05, "osc 1 keeps playing");
+    }
+
+    #[test]
+    fn build_voice_unit_osc2_ring_mod_makes_sidebands() {
+        // A fifth above 220 Hz is ~329.6 Hz; ring modulation sounds their sum
+        let fifth = Osc2Config { enabled: true, waveform: Waveform::Sine, semitone: 7, level: 1.0, ..Osc2Config::default() };
+        let sum = 220.0 + 220.0 * (7.0f32 / 12.0).exp2();
+        let mixed = osc2_voice_samples(fifth);
+        let ring = osc2_voice_samples(Osc2Config { ring: true, ..fifth });
+        assert!(magnitude_at(&mixed, sum) < 0.01, "mixing adds no sidebands");
+        assert!(magnitude_at(&ring, sum) > 0.05, "ring mod should sound the sum frequency");
+    }
+
+    #[test]
+    fn build_voice_unit_osc2_sync_locks_to_osc1() {
+        // A free osc 2 at a tritone beats against osc 1; synced it repeats every osc 1 cycle
+        let tritone = Osc2Config { enabled: true, waveform: Waveform::Saw, semitone: 6, level: 1.0, ..Osc2Config::default() };
+        let period = (SAMPLE_RATE / 220.0) as f32;
+        let periodicity = |samples: &[f32]| {
+            // Mean difference one osc 1 period apart, at a sub-sample offset
+            let n = 2000;
+            (0..n)
+                .map(|i| {
+                    let j = i as f32 + period;
+                    let (k, frac) = (j as usize, j.fract());
+                    let delayed = samples[k] * (1.0 - frac) + samples[k + 1] * frac;
+                    (samples[i] - delayed).abs()
+                })
+                .sum::<f32>()
+                / n as f32
+        };
+        let free = periodicity(&osc2_voice_samples(tritone));
+        let synced = periodicity(&osc2_voice_samples(Osc2Config { sync: true, ..tritone }));
+        assert!(synced < free * 0.5, "sync should lock osc 2 to osc 1 (free {free}, synced {synced})");
+    }
+
+    fn fm_voice_samples(fm: FmConfig, n: usize) -> Vec<f32> {
+        let cfg = VoiceConfig {
+            filter_cfg: FilterConfig { enabled: false, ..default_filter_cfg() },
+            adsr: AdsrParams { attack: 0.001, sustain: 1.0, ..AdsrParams::default() },
+            engine: VoiceEngine::Fm,
+            fm,
+            ..VoiceConfig::default()
+        };
+        gated_voice_samples(&cfg, &VoiceShared::new(&cfg), n).iter().map(|(l, _)| *l).collect()
+    }
+
+    #[test]
+    fn build_voice_unit_fm_adds_sidebands_at_the_ratio() {
+        let held = AdsrParams { attack: 0.001, sustain: 1.0, ..AdsrParams::default() };
+        // 220 Hz carrier, 3.5 × 220 = 770 Hz modulator: sidebands at 990 and 550 Hz
+        let bell = FmConfig { ratio: 3.5, index: 2.0, index_env: held };
+        let samples = fm_voice_samples(bell, 8192);
+        assert!(magnitude_at(&samples[4096..], 990.0) > 0.05, "upper sideband");
+        assert!(magnitude_at(&samples[4096..], 550.0) > 0.05, "lower sideband (reflected)");
+
+        let pure = fm_voice_samples(FmConfig { index: 0.0, ..bell }, 8192);
+        assert!(magnitude_at(&pure[4096..], 990.0) < 0.01, "no index, no sidebands");
+        assert!(magnitude_at(&pure[4096..], 220.0) > 0.2, "the carrier plays the note");
+    }
+
+    #[test]
+    fn build_voice_unit_fm_index_follows_its_envelope() {
+        // The index decays to nothing within 0.1 s, leaving a pure carrier
+        let pluck = AdsrParams { attack: 0.001, decay: 0.05, sustain: 0.0, release: 0.1 };
+        let samples = fm_voice_samples(FmConfig { ratio: 1.0, index: 3.0, index_env: pluck }, 22050);
+        let early = magnitude_at(&samples[..2048], 440.0);
+        let late = magnitude_at(&samples[16384..], 440.0);
+        assert!(early > 0.05, "the attack is bright ({early})");
+        assert!(late < 0.01, "the tail is a sine ({late})");
+    }
+
+    /// Level of the 10th harmonic of the 220 Hz note early in the note and
+    /// after the filter envelope has decayed.
+    fn filter_env_brightness(cutoff: f32, filter_env_amount: f32) -> (f32, f32) {
+        let cfg = VoiceConfig {
+            waveform: Waveform::Saw,
+            filter_cfg: FilterConfig { enabled: true, filter_type: FilterType::Lowpass },
+            cutoff,
+            adsr: AdsrParams { attack: 0.001, sustain: 1.0, ..AdsrParams::default() },
+            filter_env: AdsrParams { attack: 0.001, decay: 0.05, sustain: 0.0, release: 0.1 },
+            filter_env_amount,
+            ..VoiceConfig::default()
+        };
+        let samples: Vec<f32> =
+            gated_voice_samples(&cfg, &VoiceShared::new(&cfg), 22050).iter().map(|(l, _)| *l).collect();
+        (magnitude_at(&samples[..2048], 2200.0), magnitude_at(&samples[16384..], 2200.0))
+    }
+
+    #[test]
+    fn build_voice_unit_filter_envelope_sweeps_cutoff() {
+        // Positive amount: a pluck opening a dark filter at the start of the note
+        let (early, late) = filter_env_brightness(300.0, 5.0);
+        assert!(early > late * 4.0, "positive amount opens the filter (early {early}, late {late})");
+
+        // Negative amount: a bright filter closed at the start of the note
+        let (early, late) = filter_env_brightness(8000.0, -5.0);
+        assert!(late > early * 4.0, "negative amount closes the filter (early {early}, late {late})");
+
+        // No amount: the filter stays put
+        let (early, late) = filter_env_brightness(300.0, 0.0);
+        assert!((early - late).abs() < early.max(late) * 0.5 + 1e-4, "early {early}, late {late}");
+    }
+
+    #[test]
+    fn build_voice_unit_noise_ignores_pitch() {
+        let cfg = VoiceConfig { waveform: Waveform::Noise, ..VoiceConfig::default() };
+        let samples = gated_voice_samples(&cfg, &VoiceShared::new(&cfg), 4096);
+        let left: Vec<f32> = samples.iter().map(|(l, _)| *l).collect();
+        // A 220 Hz tone crosses zero ~20 times here; noise crosses far more
+        assert!(zero_crossings(&left) > 200, "noise should not follow the note");
+    }
+
+    #[test]
+    fn build_voice_unit_single_copy_is_mono() {
+        let cfg = VoiceConfig {
+            waveform: Waveform::Saw,
+            detune: 50.0,
+            spread: 1.0,
+            ..VoiceConfig::default()
+        };
+        let samples = gated_voice_samples(&cfg, &VoiceShared::new(&cfg), 2048);
+        assert!(samples.iter().any(|(l, _)| *l != 0.0));
+        assert!(samples.iter().all(|(l, r)| l == r), "unison 1 should stay centered");
+    }
+
+    #[test]
+    fn build_voice_unit_unison_spreads_stereo() {
+        for unison in [2, 3, 5, 7] {
+            let cfg = VoiceConfig {
+                waveform: Waveform::Saw,
+                detune: 30.0,
+                unison,
+                spread: 1.0,
+                ..VoiceConfig::default()
+            };
+            let samples = gated_voice_samples(&cfg, &VoiceShared::new(&cfg), 8192);
+            let differs = samples[4096..].iter().any(|(l, r)| (l - r).abs() > 1e-3);
+            assert!(differs, "unison {unison} with spread should differ between L and R");
+            let peak = samples.iter().map(|(l, r)| l.abs().max(r.abs())).fold(0.0f32, f32::max);
+            assert!(peak < 1.5, "unison {unison} should stay near unity gain, got {peak}");
+        }
+    }
+
+    #[test]
+    fn build_voice_unit_unison_without_spread_is_centered() {
+        let cfg = VoiceConfig {
+            waveform: Waveform::Saw,
+            detune: 30.0,
+            unison: 5,
+            spread: 0.0,
+            ..VoiceConfig::default()
+        };
+        let samples = gated_voice_samples(&cfg, &VoiceShared::new(&cfg), 4096);
+        assert!(samples.iter().any(|(l, _)| l.abs() > 0.01));
+        assert!(samples.iter().all(|(l, r)| (l - r).abs() < 1e-6));
+    }
+
+    #[test]
+    fn unison_positions_span_stereo_field() {
+        assert_eq!(unison_position(0, 1), 0.0);
+        assert_eq!(unison_position(0, 3), -1.0);
+        assert_eq!(unison_position(1, 3), 0.0);
+        assert_eq!(unison_position(2, 3), 1.0);
+    }
+
+    #[test]
+    fn build_voice_unit_reports_envelope_level() {
+        let voice = Voice::new();
+        voice.velocity.set_value(1.0);
+        let cfg = VoiceConfig::default();
+        let mut unit = build_voice_unit(&voice, &cfg, &VoiceShared::new(&cfg), &Shared::new(0.5));
+        unit.set_sample_rate(SAMPLE_RATE);
+        unit.allocate();
+        unit.get_stereo();
+        assert_eq!(voice.envelope.value(), 0.0);
+        voice.gate.set_value(1.0);
+        for _ in 0..2000 {
+            unit.get_stereo();
+        }
+        assert!(voice.envelope.value() > 0.1, "envelope {}", voice.envelope.value());
+    }
+
+    #[test]
+    fn build_voice_unit_reports_finished_release() {
+        let voice = Voice::new();
+        voice.velocity.set_value(1.0);
+        let cfg = VoiceConfig {
+            adsr: AdsrParams { attack: 0.001, decay: 0.01, sustain: 0.5, release: 0.05 },
+            ..VoiceConfig::default()
+        };
+        let mut unit = build_voice_unit(&voice, &cfg, &VoiceShared::new(&cfg), &Shared::new(0.5));
+        unit.set_sample_rate(SAMPLE_RATE);
+        unit.allocate();
+        unit.get_stereo();
+        voice.sounding.set_value(1.0);
+        voice.gate.set_value(1.0);
+        for _ in 0..2000 {
+            unit.get_stereo();
+        }
+        voice.gate.set_value(0.0);
+        for _ in 0..1000 {
+            unit.get_stereo();
+        }
+        assert_eq!(voice.sounding.value(), 1.0, "release still ringing");
+        for _ in 0..4000 {
+            unit.get_stereo();
+        }
+        assert_eq!(voice.sounding.value(), 0.0);
+        assert_eq!(unit.get_stereo(), (0.0, 0.0));
+    }
 }
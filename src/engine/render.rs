@@ -0,0 +1,161 @@
+use std::path::Path;
+
+use fundsp::audiounit::AudioUnit;
+
+/// Sample format of a rendered WAV file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum BitDepth {
+    /// 16-bit integer PCM
+    #[value(name = "16")]
+    Int16,
+    /// 24-bit integer PCM
+    #[value(name = "24")]
+    Int24,
+    /// 32-bit IEEE float
+    #[value(name = "32")]
+    Float32,
+}
+
+impl BitDepth {
+    pub fn bits(self) -> u16 {
+        match self {
+            BitDepth::Int16 => 16,
+            BitDepth::Int24 => 24,
+            BitDepth::Float32 => 32,
+        }
+    }
+}
+
+/// Pulls stereo frames from an audio graph without an audio device.
+/// The graph is prepared the same way `start_stream` prepares it, so the
+/// rendered output matches what the live stream would play.
+pub struct OfflineRenderer {
+    graph: Box<dyn AudioUnit>,
+    sample_rate: u32,
+}
+
+impl OfflineRenderer {
+    pub fn new(mut graph: Box<dyn AudioUnit>, sample_rate: u32) -> Self {
+        graph.set_sample_rate(sample_rate as f64);
+        graph.allocate();
+        Self { graph, sample_rate }
+    }
+
+    /// Render the next `frames` stereo frames.
+    pub fn render_frames(&mut self, frames: usize) -> Vec<(f32, f32)> {
+        (0..frames).map(|_| self.graph.get_stereo()).collect()
+    }
+
+    /// Render the next `secs` seconds of audio.
+    pub fn render_secs(&mut self, secs: f32) -> Vec<(f32, f32)> {
+        let frames = (secs.max(0.0) as f64 * self.sample_rate as f64).round() as usize;
+        self.render_frames(frames)
+    }
+}
+
+/// Write stereo frames to a WAV file. Integer formats clip to [-1, 1].
+pub fn write_wav(
+    path: &Path,
+    frames: &[(f32, f32)],
+    sample_rate: u32,
+    bit_depth: BitDepth,
+) -> hound::Result<()> {
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate,
+        bits_per_sample: bit_depth.bits(),
+        sample_format: match bit_depth {
+            BitDepth::Float32 => hound::SampleFormat::Float,
+            _ => hound::SampleFormat::Int,
+        },
+    };
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    for &(l, r) in frames {
+        for s in [l, r] {
+            match bit_depth {
+                BitDepth::Int16 => {
+                    writer.write_sample((s.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16)?
+                }
+                BitDepth::Int24 => {
+                    writer.write_sample((s.clamp(-1.0, 1.0) * 8_388_607.0).round() as i32)?
+                }
+                BitDepth::Float32 => writer.write_sample(s)?,
+            }
+        }
+    }
+    writer.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::oscillator::{Waveform, build_oscillator};
+    use std::env;
+    use std::fs;
+
+    const SAMPLE_RATE: u32 = 44100;
+
+    #[test]
+    fn render_secs_produces_expected_frame_count() {
+        let mut renderer =
+            OfflineRenderer::new(build_oscillator(Waveform::Sine, 440.0, 0.5), SAMPLE_RATE);
+        assert_eq!(renderer.render_secs(0.5).len(), 22050);
+        assert_eq!(renderer.render_frames(100).len(), 100);
+        assert_eq!(renderer.render_secs(0.0).len(), 0);
+    }
+
+    #[test]
+    fn render_is_deterministic() {
+        let render = || {
+            OfflineRenderer::new(build_oscillator(Waveform::Saw, 220.0, 0.5), SAMPLE_RATE)
+                .render_frames(4096)
+        };
+        assert_eq!(render(), render());
+    }
+
+    #[test]
+    fn render_matches_amplitude() {
+        let mut renderer =
+            OfflineRenderer::new(build_oscillator(Waveform::Sine, 440.0, 0.5), SAMPLE_RATE);
+        let frames = renderer.render_frames(4410);
+        let peak = frames.iter().map(|(l, _)| l.abs()).fold(0.0f32, f32::max);
+        assert!((peak - 0.5).abs() < 0.01, "peak should be ~0.5, got {peak}");
+        assert!(frames.iter().all(|(l, r)| l == r), "mono oscillator should be centered");
+    }
+
+    #[test]
+    fn write_wav_all_bit_depths() {
+        let dir = env::temp_dir().join("synthesis_test_render");
+        fs::create_dir_all(&dir).unwrap();
+        let frames = vec![(0.5, -0.5), (1.5, -1.5), (0.0, 0.25)];
+
+        for depth in [BitDepth::Int16, BitDepth::Int24, BitDepth::Float32] {
+            let path = dir.join(format!("render_{}.wav", depth.bits()));
+            write_wav(&path, &frames, SAMPLE_RATE, depth).unwrap();
+
+            let mut reader = hound::WavReader::open(&path).unwrap();
+            let spec = reader.spec();
+            assert_eq!(spec.channels, 2);
+            assert_eq!(spec.sample_rate, SAMPLE_RATE);
+            assert_eq!(spec.bits_per_sample, depth.bits());
+            assert_eq!(reader.len(), 6);
+
+            let samples: Vec<f32> = match depth {
+                BitDepth::Float32 => reader.samples::<f32>().map(|s| s.unwrap()).collect(),
+                _ => {
+                    let scale = (1i32 << (depth.bits() - 1)) as f32;
+                    reader.samples::<i32>().map(|s| s.unwrap() as f32 / scale).collect()
+                }
+            };
+            assert!((samples[0] - 0.5).abs() < 1e-3, "{depth:?}: got {}", samples[0]);
+            assert!((samples[1] + 0.5).abs() < 1e-3, "{depth:?}: got {}", samples[1]);
+            if depth == BitDepth::Float32 {
+                assert_eq!(samples[2], 1.5, "float output should not clip");
+            } else {
+                assert!(samples[2] <= 1.0 && samples[3] >= -1.0, "{depth:?} should clip");
+            }
+            let _ = fs::remove_file(&path);
+        }
+        let _ = fs::remove_dir(&dir);
+    }
+}
@@ -0,0 +1,1520 @@
+use std::sync::mpsc::{self, Receiver, SendError, Sender, SyncSender};
+use std::sync::{Arc, Mutex};
+
+use fundsp::prelude32::*;
+
+use super::drum::DrumEngine;
+use super::voice::{StealPolicy, Voice, VoiceAllocator, VoiceMode};
+
+/// Number of sequencer tracks (lead, drums, bass).
+pub const NUM_TRACKS: usize = 3;
+
+/// Samples between checks for UI commands: once per fundsp block.
+const POLL_INTERVAL: usize = MAX_BUFFER_SIZE;
+
+/// Replaced patterns and songs waiting to be freed by the UI thread.
+const RETIRED_CAPACITY: usize = 16;
+
+/// Note events the mirror receiver may fall behind by before new ones are
+/// dropped; the audio thread never waits for it.
+pub const MIRROR_CAPACITY: usize = 256;
+
+/// Clock ticks per quarter note. Every step resolution, straight or
+/// triplet, is a whole number of ticks.
+pub const PPQ: u32 = 48;
+
+/// A sequencer track, one per Matrix channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Track {
+    Lead,
+    Drum,
+    Bass,
+}
+
+impl Track {
+    pub const ALL: [Track; NUM_TRACKS] = [Track::Lead, Track::Drum, Track::Bass];
+
+    pub fn index(self) -> usize {
+        match self {
+            Track::Lead => 0,
+            Track::Drum => 1,
+            Track::Bass => 2,
+        }
+    }
+}
+
+/// A note triggered by one track on one step.
+/// For the drum track, `note` is the drum row (sample index).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepNote {
+    pub note: u8,
+    pub velocity: u8,
+    /// Note length in steps; above 1 the note is tied over the next steps.
+    pub length: f32,
+    /// Chance in percent that the note plays when its step fires.
+    pub probability: u8,
+    /// Held into the next step's note so a mono track glides to it without
+    /// restarting the envelope; the same note is tied instead.
+    pub slide: bool,
+}
+
+impl StepNote {
+    /// A one-step note that always plays.
+    pub fn new(note: u8, velocity: u8) -> Self {
+        Self {
+            note,
+            velocity,
+            length: 1.0,
+            probability: 100,
+            slide: false,
+        }
+    }
+}
+
+/// A sounding note and the samples left until its release.
+#[derive(Debug, Clone, Copy)]
+struct HeldNote {
+    note: u8,
+    remaining: f64,
+    slide: bool,
+}
+
+/// Snapshot of the pattern played by the sequencer, built by the UI from the grids.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeqPattern {
+    /// Notes per step for each track, indexed `[track][step]`. Tracks may
+    /// differ in length and loop independently (polymeter).
+    pub steps: [Vec<Vec<StepNote>>; NUM_TRACKS],
+    /// Length of one step of each track in `PPQ` ticks (12 = 1/16).
+    pub step_ticks: [u32; NUM_TRACKS],
+    pub bpm: f32,
+    /// Swing amount 0–100%: delays odd steps, MPC-style 50%–75% of each step pair.
+    pub swing: f32,
+    /// Playback speed applied to drum samples on each hit.
+    pub drum_pitch_ratio: f32,
+    /// Voice allocation of each track.
+    pub modes: [VoiceMode; NUM_TRACKS],
+    /// Note each track cuts when its voices run out.
+    pub steal: [StealPolicy; NUM_TRACKS],
+}
+
+impl Default for SeqPattern {
+    fn default() -> Self {
+        Self {
+            steps: Default::default(),
+            step_ticks: [PPQ / 4; NUM_TRACKS],
+            bpm: 120.0,
+            swing: 0.0,
+            drum_pitch_ratio: 1.0,
+            modes: Default::default(),
+            steal: Default::default(),
+        }
+    }
+}
+
+impl SeqPattern {
+    /// Loop length of one track in steps (at least 1).
+    pub fn track_len(&self, track: Track) -> usize {
+        Ord::max(self.steps[track.index()].len(), 1)
+    }
+
+    /// Track with the longest loop in time. Its loop is the pattern's bar:
+    /// queued patterns and song entries change when it wraps.
+    pub fn bar_track(&self) -> Track {
+        let ticks = |t: Track| self.steps[t.index()].len() as u64 * self.step_ticks[t.index()] as u64;
+        Track::ALL
+            .into_iter()
+            .fold(Track::Lead, |best, t| if ticks(t) > ticks(best) { t } else { best })
+    }
+}
+
+/// One link of a song chain: a pattern played `repeats` bars in a row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SongEntry {
+    pub pattern: SeqPattern,
+    pub repeats: u32,
+}
+
+/// Note events emitted by the sequencer at exact sample positions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SeqEvent {
+    NoteOn { track: Track, note: u8, velocity: u8 },
+    NoteOff { track: Track, note: u8 },
+}
+
+/// Commands sent from the UI thread to the audio-thread sequencer.
+pub enum SeqCommand {
+    /// Replace the playing pattern now, keeping the playhead (live edits).
+    SetPattern(Box<SeqPattern>),
+    /// Switch to the pattern when the current bar ends.
+    QueuePattern(Box<SeqPattern>),
+    /// Play a chain of patterns instead of looping one; empty leaves song mode.
+    SetSong(Vec<SongEntry>),
+    /// Start playback from the given step.
+    Start(usize),
+    Stop,
+    /// Play a note now, outside the pattern (live MIDI input).
+    Play(SeqEvent),
+    /// Move a track's pitch wheel (-1.0 to 1.0), retuning its voices.
+    PitchBend { track: Track, amount: f32 },
+    /// Copy every note event played to a receiver (MIDI out); `None` stops.
+    /// Create the channel with `MIRROR_CAPACITY`: events are dropped, not
+    /// queued, while it is full.
+    Mirror(Option<SyncSender<SeqEvent>>),
+}
+
+/// Pattern or song replaced on the audio thread, handed back so the UI
+/// thread frees it instead of the audio callback.
+pub enum Retired {
+    Pattern(Box<SeqPattern>),
+    Song(Vec<SongEntry>),
+}
+
+/// Step clock driven by the sample counter. Each `tick` advances one sample;
+/// notes start on step boundaries and are released at the next boundary of
+/// their track. Every track keeps its own step position and length.
+#[derive(Clone)]
+pub struct Sequencer {
+    pattern: SeqPattern,
+    /// Pattern waiting for the end of the bar.
+    queued: Option<Box<SeqPattern>>,
+    /// Box of the last pattern a queued one replaced, until `take_retired`.
+    retired: Option<Box<SeqPattern>>,
+    /// Number of queued patterns that have taken over so far.
+    adoptions: u32,
+    /// Song chain; while non-empty it is played instead of `pattern`.
+    song: Vec<SongEntry>,
+    song_pos: usize,
+    /// Bars already played of the current song entry.
+    repeat: u32,
+    sample_rate: f64,
+    playing: bool,
+    next_step: [usize; NUM_TRACKS],
+    current_step: [Option<usize>; NUM_TRACKS],
+    /// Samples left until each track's next step fires (fractional, so tempo never drifts).
+    countdown: [f64; NUM_TRACKS],
+    /// Clock ticks from the start of playback to each track's next step.
+    /// Swing pairs follow this absolute grid, so odd-length loops keep it.
+    ticks: [u64; NUM_TRACKS],
+    held: [Vec<HeldNote>; NUM_TRACKS],
+    /// Xorshift state for step probability.
+    rng: u32,
+}
+
+impl Sequencer {
+    pub fn new(pattern: SeqPattern) -> Self {
+        Self {
+            pattern,
+            queued: None,
+            retired: None,
+            adoptions: 0,
+            song: Vec::new(),
+            song_pos: 0,
+            repeat: 0,
+            sample_rate: DEFAULT_SR,
+            playing: false,
+            next_step: [0; NUM_TRACKS],
+            current_step: [None; NUM_TRACKS],
+            countdown: [0.0; NUM_TRACKS],
+            ticks: [0; NUM_TRACKS],
+            held: Default::default(),
+            rng: 0x9E37_79B9,
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+    }
+
+    /// Pattern currently played: the song entry in song mode.
+    fn active(&self) -> &SeqPattern {
+        self.song.get(self.song_pos).map_or(&self.pattern, |e| &e.pattern)
+    }
+
+    /// Keep every track position inside its (possibly shorter) loop.
+    fn wrap_positions(&mut self) {
+        for track in Track::ALL {
+            self.next_step[track.index()] %= self.active().track_len(track);
+        }
+    }
+
+    /// Replace the pattern without moving the playhead. Returns the old
+    /// pattern in the box the new one came in.
+    pub fn set_pattern(&mut self, mut pattern: Box<SeqPattern>) -> Box<SeqPattern> {
+        std::mem::swap(&mut self.pattern, &mut pattern);
+        self.wrap_positions();
+        pattern
+    }
+
+    /// Play `pattern` from the next bar on; applies at once while stopped.
+    /// Returns the pattern it displaces, if any.
+    pub fn queue_pattern(&mut self, pattern: Box<SeqPattern>) -> Option<Box<SeqPattern>> {
+        if self.playing {
+            self.queued.replace(pattern)
+        } else {
+            self.adoptions += 1;
+            Some(self.set_pattern(pattern))
+        }
+    }
+
+    /// Pattern replaced by a queued one at a bar line, to be freed elsewhere.
+    pub fn take_retired(&mut self) -> Option<Box<SeqPattern>> {
+        self.retired.take()
+    }
+
+    /// Count of queued patterns that have taken over, for change detection.
+    pub fn adoptions(&self) -> u32 {
+        self.adoptions
+    }
+
+    fn adopt_queued(&mut self) -> bool {
+        let Some(mut pattern) = self.queued.take() else {
+            return false;
+        };
+        std::mem::swap(&mut self.pattern, &mut pattern);
+        self.retired = Some(pattern);
+        self.adoptions += 1;
+        true
+    }
+
+    /// Replace the song chain, returning the old one. Edits keep the song
+    /// position; entering song mode starts from the first entry.
+    pub fn set_song(&mut self, song: Vec<SongEntry>) -> Vec<SongEntry> {
+        if self.song.is_empty() {
+            self.song_pos = 0;
+            self.repeat = 0;
+        }
+        let old = std::mem::replace(&mut self.song, song);
+        if self.song_pos >= self.song.len() {
+            self.song_pos = 0;
+            self.repeat = 0;
+        }
+        self.wrap_positions();
+        old
+    }
+
+    /// Jump to a song entry (used to resume after a graph rebuild).
+    pub fn seek_song(&mut self, entry: usize) {
+        if entry < self.song.len() {
+            self.song_pos = entry;
+            self.repeat = 0;
+        }
+    }
+
+    /// Song entry currently playing, or None outside song mode.
+    pub fn song_position(&self) -> Option<usize> {
+        (!self.song.is_empty()).then_some(self.song_pos)
+    }
+
+    /// Start playback; `step` of every track fires on the next tick.
+    pub fn start(&mut self, step: usize) {
+        self.start_tracks([step; NUM_TRACKS]);
+    }
+
+    /// Start playback with each track at its own step (resuming a polymeter).
+    pub fn start_tracks(&mut self, steps: [usize; NUM_TRACKS]) {
+        self.playing = true;
+        self.current_step = [None; NUM_TRACKS];
+        for track in Track::ALL {
+            let i = track.index();
+            self.next_step[i] = steps[i] % self.active().track_len(track);
+            self.countdown[i] = 0.0;
+            self.ticks[i] = self.next_step[i] as u64 * self.track_ticks(track);
+        }
+    }
+
+    /// Stop playback and release every held note. A queued pattern takes
+    /// over at once and the song rewinds to its first entry.
+    pub fn stop(&mut self, events: &mut Vec<SeqEvent>) {
+        self.release_held(events);
+        self.playing = false;
+        self.current_step = [None; NUM_TRACKS];
+        self.adopt_queued();
+        self.song_pos = 0;
+        self.repeat = 0;
+    }
+
+    /// Step of the bar track currently sounding, or None when stopped.
+    #[cfg(test)]
+    pub fn current_step(&self) -> Option<usize> {
+        self.current_step[self.active().bar_track().index()]
+    }
+
+    /// Step of one track currently sounding, or None when stopped.
+    pub fn track_step(&self, track: Track) -> Option<usize> {
+        self.current_step[track.index()]
+    }
+
+    /// Clock ticks per step of a track.
+    fn track_ticks(&self, track: Track) -> u64 {
+        Ord::max(self.active().step_ticks[track.index()], 1) as u64
+    }
+
+    /// Position of a track's next step on its grid since playback started.
+    fn grid_step(&self, track: Track) -> u64 {
+        self.ticks[track.index()] / self.track_ticks(track)
+    }
+
+    /// Length in samples of the step at grid position `pos` (see `grid_step`).
+    /// Swing lengthens even steps and shortens odd ones, so odd steps start
+    /// late while each pair keeps its length.
+    pub fn step_samples(&self, track: Track, pos: u64) -> f64 {
+        let pattern = self.active();
+        let beat = self.sample_rate * 60.0 / pattern.bpm.max(1.0) as f64;
+        let base = beat * self.track_ticks(track) as f64 / PPQ as f64;
+        let ratio = 0.5 + 0.25 * (pattern.swing as f64 / 100.0).clamp(0.0, 1.0);
+        if pos.is_multiple_of(2) {
+            2.0 * base * ratio
+        } else {
+            2.0 * base * (1.0 - ratio)
+        }
+    }
+
+    /// Advance one sample, pushing any note events due on this sample.
+    pub fn tick(&mut self, events: &mut Vec<SeqEvent>) {
+        if !self.playing {
+            return;
+        }
+        self.release_due(events);
+        let bar = self.active().bar_track().index();
+        if self.countdown[bar] <= 0.0
+            && self.next_step[bar] == 0
+            && self.current_step[bar].is_some()
+            && self.end_bar()
+        {
+            // A new pattern starts all tracks together on its first step.
+            for i in 0..NUM_TRACKS {
+                self.next_step[i] = 0;
+                self.countdown[i] = self.countdown[bar];
+                self.ticks[i] = self.ticks[bar];
+            }
+        }
+        for track in Track::ALL {
+            let i = track.index();
+            if self.countdown[i] <= 0.0 {
+                let pos = self.grid_step(track);
+                self.fire_step(track, events);
+                self.countdown[i] += self.step_samples(track, pos);
+            }
+            self.countdown[i] -= 1.0;
+        }
+        for held in self.held.iter_mut().flatten() {
+            held.remaining -= 1.0;
+        }
+    }
+
+    /// Release every note whose length has run out, before this sample's steps fire.
+    fn release_due(&mut self, events: &mut Vec<SeqEvent>) {
+        for track in Track::ALL {
+            self.held[track.index()].retain(|held| {
+                let due = held.remaining <= 0.0;
+                if due {
+                    events.push(SeqEvent::NoteOff { track, note: held.note });
+                }
+                !due
+            });
+        }
+    }
+
+    /// Samples from the start of the step at grid position `pos` to the end
+    /// of a note `length` steps long, following swing across tied steps.
+    fn note_samples(&self, track: Track, pos: u64, length: f32) -> f64 {
+        let length = length.clamp(0.0, 64.0) as f64;
+        let whole = length.floor() as u64;
+        let tied: f64 = (0..whole).map(|k| self.step_samples(track, pos + k)).sum();
+        tied + (length - whole as f64) * self.step_samples(track, pos + whole)
+    }
+
+    /// Uniform 0–99 for probability rolls.
+    fn roll(&mut self) -> u32 {
+        let mut x = self.rng;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.rng = x;
+        x % 100
+    }
+
+    fn fire_step(&mut self, track: Track, events: &mut Vec<SeqEvent>) {
+        let i = track.index();
+        let step = self.next_step[i];
+        let pos = self.grid_step(track);
+        let len = self.active().steps[i].len();
+        if len > 0 {
+            let idx = step % len;
+            for k in 0..self.active().steps[i][idx].len() {
+                let n = self.active().steps[i][idx][k];
+                if n.probability < 100 && self.roll() >= n.probability as u32 {
+                    continue;
+                }
+                // A note still ringing from a tie is retriggered, unless it
+                // slid into this step: then it just carries on.
+                let mut tied = false;
+                if let Some(pos) = self.held[i].iter().position(|h| h.note == n.note) {
+                    tied = self.held[i].swap_remove(pos).slide;
+                    if !tied {
+                        events.push(SeqEvent::NoteOff { track, note: n.note });
+                    }
+                }
+                if !tied {
+                    events.push(SeqEvent::NoteOn {
+                        track,
+                        note: n.note,
+                        velocity: n.velocity,
+                    });
+                }
+                // Measured from the step boundary, like the step countdown,
+                // so a one-step note releases exactly as the next step fires.
+                let mut remaining = self.countdown[i] + self.note_samples(track, pos, n.length);
+                if n.slide {
+                    // Still held one sample after the next step's note starts
+                    let next = self.countdown[i] + self.step_samples(track, pos);
+                    remaining = remaining.max(next) + 1.0;
+                }
+                self.held[i].push(HeldNote {
+                    note: n.note,
+                    remaining: remaining.max(1.0),
+                    slide: n.slide,
+                });
+            }
+        }
+
+        self.current_step[i] = Some(step);
+        self.next_step[i] = (step + 1) % Ord::max(len, 1);
+        self.ticks[i] += self.track_ticks(track);
+    }
+
+    /// Bar boundary: take over a queued pattern and advance the song chain.
+    /// Returns true when a different pattern plays from here on.
+    fn end_bar(&mut self) -> bool {
+        let adopted = self.adopt_queued() && self.song.is_empty();
+        if let Some(entry) = self.song.get(self.song_pos) {
+            self.repeat += 1;
+            if self.repeat >= Ord::max(entry.repeats, 1) {
+                self.repeat = 0;
+                self.song_pos = (self.song_pos + 1) % self.song.len();
+                return true;
+            }
+        }
+        adopted
+    }
+
+    fn release_held(&mut self, events: &mut Vec<SeqEvent>) {
+        for track in Track::ALL {
+            for held in self.held[track.index()].drain(..) {
+                events.push(SeqEvent::NoteOff { track, note: held.note });
+            }
+        }
+    }
+}
+
+/// Playback position written by the audio thread for the UI to display.
+#[derive(Clone)]
+pub struct SeqStatus {
+    /// Step currently sounding on each track, -1 when stopped.
+    pub steps: [Shared; NUM_TRACKS],
+    /// Song entry currently playing, -1 outside song mode.
+    pub song_entry: Shared,
+    /// Set to 1 by the UI before sending `QueuePattern`; the sequencer
+    /// clears it once the queued pattern is playing.
+    pub queued: Shared,
+}
+
+impl Default for SeqStatus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SeqStatus {
+    pub fn new() -> Self {
+        Self {
+            steps: std::array::from_fn(|_| Shared::new(-1.0)),
+            song_entry: Shared::new(-1.0),
+            queued: Shared::new(0.0),
+        }
+    }
+}
+
+/// Audio-thread end of the sequencer, moved into the graph by `build_matrix_graph`.
+pub struct SequencerPort {
+    sequencer: Sequencer,
+    commands: Receiver<SeqCommand>,
+    retired: SyncSender<Retired>,
+    status: SeqStatus,
+}
+
+/// UI end of the sequencer: sends commands and frees what they replaced.
+pub struct SeqControl {
+    commands: Sender<SeqCommand>,
+    retired: Receiver<Retired>,
+}
+
+impl SeqControl {
+    pub fn send(&self, command: SeqCommand) -> Result<(), SendError<SeqCommand>> {
+        self.commands.send(command)
+    }
+
+    /// Free the patterns and songs the audio thread has replaced. Call regularly.
+    pub fn free_retired(&self) {
+        while let Ok(retired) = self.retired.try_recv() {
+            match retired {
+                Retired::Pattern(pattern) => drop(pattern),
+                Retired::Song(song) => drop(song),
+            }
+        }
+    }
+}
+
+/// Pair a sequencer with a command channel. The UI keeps the control end and
+/// reads the playback position back from `status`.
+pub fn sequencer_channel(sequencer: Sequencer, status: &SeqStatus) -> (SeqControl, SequencerPort) {
+    let (tx, rx) = mpsc::channel();
+    let (retired_tx, retired_rx) = mpsc::sync_channel(RETIRED_CAPACITY);
+    let port = SequencerPort {
+        sequencer,
+        commands: rx,
+        retired: retired_tx,
+        status: status.clone(),
+    };
+    (SeqControl { commands: tx, retired: retired_rx }, port)
+}
+
+/// AudioNode that runs the sequencer once per sample and plays its events on
+/// the lead, drum and bass voices. It has no audio inputs or outputs: the Net
+/// still ticks it every sample, before the voices that read its gates.
+#[derive(Clone)]
+pub struct SequencerNode {
+    sequencer: Sequencer,
+    commands: Arc<Mutex<Receiver<SeqCommand>>>,
+    retired: SyncSender<Retired>,
+    /// Samples until the next command poll.
+    until_poll: usize,
+    status: SeqStatus,
+    allocators: [VoiceAllocator; NUM_TRACKS],
+    drums: DrumEngine,
+    events: Vec<SeqEvent>,
+    mirror: Option<SyncSender<SeqEvent>>,
+}
+
+impl SequencerNode {
+    pub fn new(
+        port: SequencerPort,
+        lead_voices: &[Voice],
+        drum_voices: &[Voice],
+        drums: &DrumEngine,
+        bass_voices: &[Voice],
+    ) -> Self {
+        // Voices may still be gated by the previous graph's sequencer.
+        let allocator = |voices: &[Voice]| {
+            for v in voices {
+                v.gate.set_value(0.0);
+            }
+            VoiceAllocator::from_voices(voices.to_vec())
+        };
+        Self {
+            sequencer: port.sequencer,
+            commands: Arc::new(Mutex::new(port.commands)),
+            retired: port.retired,
+            until_poll: 0,
+            status: port.status,
+            allocators: [
+                allocator(lead_voices),
+                allocator(drum_voices),
+                allocator(bass_voices),
+            ],
+            drums: drums.clone(),
+            events: Vec::with_capacity(128),
+            mirror: None,
+        }
+    }
+
+    /// Hand a replaced pattern or song to the UI thread. Only if the UI has
+    /// fallen far behind is it freed here.
+    fn retire(&self, retired: Retired) {
+        let _ = self.retired.try_send(retired);
+    }
+
+    fn poll_commands(&mut self) {
+        if let Some(pattern) = self.sequencer.take_retired() {
+            self.retire(Retired::Pattern(pattern));
+        }
+        // Never block the audio thread: if the UI holds the lock, retry next block.
+        let Ok(commands) = self.commands.try_lock() else {
+            return;
+        };
+        while let Ok(cmd) = commands.try_recv() {
+            match cmd {
+                SeqCommand::SetPattern(pattern) => {
+                    let old = self.sequencer.set_pattern(pattern);
+                    self.retire(Retired::Pattern(old));
+                }
+                SeqCommand::QueuePattern(pattern) => {
+                    if let Some(old) = self.sequencer.queue_pattern(pattern) {
+                        self.retire(Retired::Pattern(old));
+                    }
+                }
+                SeqCommand::SetSong(song) => {
+                    let old = self.sequencer.set_song(song);
+                    self.retire(Retired::Song(old));
+                }
+                SeqCommand::Start(step) => self.sequencer.start(step),
+                SeqCommand::Stop => self.sequencer.stop(&mut self.events),
+                SeqCommand::Play(event) => self.events.push(event),
+                SeqCommand::PitchBend { track, amount } => {
+                    self.allocators[track.index()].pitch_bend(amount);
+                }
+                SeqCommand::Mirror(mirror) => self.mirror = mirror,
+            }
+        }
+    }
+
+    fn apply_events(&mut self) {
+        let active = self.sequencer.active();
+        let (modes, steal, pitch_ratio) = (active.modes, active.steal, active.drum_pitch_ratio);
+        for ((allocator, mode), policy) in self.allocators.iter_mut().zip(modes).zip(steal) {
+            allocator.set_mode(mode);
+            allocator.set_steal_policy(policy);
+        }
+        for event in self.events.drain(..) {
+            if let Some(mirror) = &self.mirror {
+                let _ = mirror.try_send(event);
+            }
+            match event {
+                SeqEvent::NoteOn { track, note, velocity } => {
+                    let allocator = &mut self.allocators[track.index()];
+                    allocator.note_on(note, Ord::max(velocity, 1));
+                    if track == Track::Drum
+                        && let Some(idx) = allocator.voices.iter().position(|v| v.note == Some(note))
+                    {
+                        self.drums.hit(idx, note as usize, pitch_ratio);
+                    }
+                }
+                SeqEvent::NoteOff { track, note } => {
+                    self.allocators[track.index()].note_off(note);
+                }
+            }
+        }
+    }
+}
+
+impl AudioNode for SequencerNode {
+    const ID: u64 = 900_030;
+    type Inputs = U0;
+    type Outputs = U0;
+
+    fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sequencer.set_sample_rate(sample_rate);
+    }
+
+    #[inline]
+    fn tick(&mut self, _: &Frame<f32, U0>) -> Frame<f32, U0> {
+        let adoptions = self.sequencer.adoptions();
+        if self.until_poll == 0 {
+            self.poll_commands();
+            self.until_poll = POLL_INTERVAL;
+        }
+        self.until_poll -= 1;
+        self.sequencer.tick(&mut self.events);
+        if !self.events.is_empty() {
+            self.apply_events();
+        }
+        // Only clear the flag on adoption: the UI raises it before sending.
+        if self.sequencer.adoptions() != adoptions {
+            self.status.queued.set_value(0.0);
+        }
+        for track in Track::ALL {
+            let step = self.sequencer.track_step(track).map_or(-1.0, |s| s as f32);
+            self.status.steps[track.index()].set_value(step);
+        }
+        let entry = self.sequencer.song_position().map_or(-1.0, |e| e as f32);
+        self.status.song_entry.set_value(entry);
+        Frame::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::drum::DRUM_KIT_ROCK;
+    use crate::engine::voice::{midi_note_to_freq, DEFAULT_BEND_RANGE};
+
+    const SAMPLE_RATE: f64 = 44100.0;
+
+    fn note(note: u8) -> StepNote {
+        StepNote::new(note, 100)
+    }
+
+    /// 16-step pattern with a lead note on every step and a kick on step 0.
+    fn test_pattern(bpm: f32) -> SeqPattern {
+        let lead = (0..16).map(|s| vec![note(60 + s as u8)]).collect();
+        let mut drum = vec![Vec::new(); 16];
+        drum[0].push(note(14));
+        SeqPattern {
+            steps: [lead, drum, vec![Vec::new(); 16]],
+            step_ticks: [PPQ / 4; NUM_TRACKS],
+            bpm,
+            swing: 0.0,
+            drum_pitch_ratio: 1.0,
+            modes: Default::default(),
+            steal: Default::default(),
+        }
+    }
+
+    /// Tick the node through one command poll interval, so it has picked up
+    /// everything sent before.
+    fn tick_block(node: &mut SequencerNode) {
+        for _ in 0..POLL_INTERVAL {
+            node.tick(&Frame::default());
+        }
+    }
+
+    /// Run the sequencer and record (sample, event) pairs.
+    fn run(seq: &mut Sequencer, samples: usize) -> Vec<(usize, SeqEvent)> {
+        let mut out = Vec::new();
+        let mut events = Vec::new();
+        for i in 0..samples {
+            seq.tick(&mut events);
+            out.extend(events.drain(..).map(|e| (i, e)));
+        }
+        out
+    }
+
+    fn note_on_samples(log: &[(usize, SeqEvent)], track: Track) -> Vec<usize> {
+        log.iter()
+            .filter(|(_, e)| matches!(e, SeqEvent::NoteOn { track: t, .. } if *t == track))
+            .map(|(i, _)| *i)
+            .collect()
+    }
+
+    #[test]
+    fn stopped_sequencer_emits_nothing() {
+        let mut seq = Sequencer::new(test_pattern(120.0));
+        seq.set_sample_rate(SAMPLE_RATE);
+        assert!(run(&mut seq, 44100).is_empty());
+        assert_eq!(seq.current_step(), None);
+    }
+
+    #[test]
+    fn steps_fire_at_exact_sample_offsets() {
+        // 125 BPM at 48 kHz: 1/16 step = 5760 samples exactly
+        let mut seq = Sequencer::new(test_pattern(125.0));
+        seq.set_sample_rate(48000.0);
+        seq.start(0);
+        let log = run(&mut seq, 5760 * 4 + 1);
+        assert_eq!(note_on_samples(&log, Track::Lead), [0, 5760, 11520, 17280, 23040]);
+    }
+
+    #[test]
+    fn fractional_step_length_does_not_drift() {
+        // 120 BPM at 44.1 kHz: 5512.5 samples per step
+        let mut seq = Sequencer::new(test_pattern(120.0));
+        seq.set_sample_rate(SAMPLE_RATE);
+        seq.start(0);
+        let log = run(&mut seq, 44100 * 4);
+        let ons = note_on_samples(&log, Track::Lead);
+        assert_eq!(ons.len(), 32);
+        for (k, &sample) in ons.iter().enumerate() {
+            let ideal = k as f64 * 5512.5;
+            assert!(
+                (sample as f64 - ideal).abs() <= 1.0,
+                "step {k} fired at {sample}, ideal {ideal}"
+            );
+        }
+    }
+
+    #[test]
+    fn full_swing_delays_odd_steps() {
+        // 125 BPM at 48 kHz: 11520-sample step pairs, split 75% / 25%
+        let mut seq = Sequencer::new(SeqPattern {
+            swing: 100.0,
+            ..test_pattern(125.0)
+        });
+        seq.set_sample_rate(48000.0);
+        seq.start(0);
+        let log = run(&mut seq, 11520 * 2 + 1);
+        assert_eq!(note_on_samples(&log, Track::Lead), [0, 8640, 11520, 20160, 23040]);
+        assert_eq!(note_on_samples(&log, Track::Drum), [0], "drums follow the same clock");
+    }
+
+    #[test]
+    fn half_swing_lengthens_even_steps() {
+        // 50% swing: even steps take 62.5% of the pair, odd steps 37.5%
+        let mut seq = Sequencer::new(SeqPattern {
+            swing: 50.0,
+            ..test_pattern(125.0)
+        });
+        seq.set_sample_rate(48000.0);
+        seq.start(0);
+        let log = run(&mut seq, 11521);
+        assert_eq!(note_on_samples(&log, Track::Lead), [0, 7200, 11520]);
+    }
+
+    #[test]
+    fn swing_applies_to_odd_step_when_starting_mid_pattern() {
+        let mut seq = Sequencer::new(SeqPattern {
+            swing: 100.0,
+            ..test_pattern(125.0)
+        });
+        seq.set_sample_rate(48000.0);
+        seq.start(3);
+        let log = run(&mut seq, 2881);
+        // Step 3 is odd (short), so step 4 fires after 25% of the pair
+        assert_eq!(note_on_samples(&log, Track::Lead), [0, 2880]);
+    }
+
+    #[test]
+    fn swing_follows_the_clock_across_odd_length_loops() {
+        // A 3-step lead loop: its step 0 lands on odd grid positions every
+        // other pass and must be short there, like any other odd step.
+        let mut pattern = SeqPattern {
+            swing: 100.0,
+            ..test_pattern(125.0)
+        };
+        pattern.steps[Track::Lead.index()].truncate(3);
+        let mut seq = Sequencer::new(pattern);
+        seq.set_sample_rate(48000.0);
+        seq.start(0);
+        let log = run(&mut seq, 11520 * 3 + 1);
+        assert_eq!(
+            note_on_samples(&log, Track::Lead),
+            [0, 8640, 11520, 20160, 23040, 31680, 34560]
+        );
+    }
+
+    #[test]
+    fn notes_release_at_next_step() {
+        let mut seq = Sequencer::new(test_pattern(125.0));
+        seq.set_sample_rate(48000.0);
+        seq.start(0);
+        let log = run(&mut seq, 5761);
+        let offs: Vec<_> = log
+            .iter()
+            .filter(|(_, e)| matches!(e, SeqEvent::NoteOff { .. }))
+            .collect();
+        assert_eq!(offs.len(), 2, "lead note and kick released at step 1");
+        assert!(offs.iter().all(|(i, _)| *i == 5760));
+        assert_eq!(
+            offs[0].1,
+            SeqEvent::NoteOff { track: Track::Lead, note: 60 }
+        );
+        // Release comes before the next step's note-on on the same sample
+        let first_step1 = log.iter().position(|(i, _)| *i == 5760).unwrap();
+        assert!(matches!(log[first_step1].1, SeqEvent::NoteOff { .. }));
+    }
+
+    #[test]
+    fn pattern_wraps_around() {
+        let mut seq = Sequencer::new(test_pattern(125.0));
+        seq.set_sample_rate(48000.0);
+        seq.start(15);
+        let log = run(&mut seq, 5761);
+        let notes: Vec<u8> = log
+            .iter()
+            .filter_map(|(_, e)| match e {
+                SeqEvent::NoteOn { track: Track::Lead, note, .. } => Some(*note),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(notes, [75, 60]);
+        assert_eq!(seq.current_step(), Some(0));
+    }
+
+    #[test]
+    fn stop_releases_held_notes() {
+        let mut seq = Sequencer::new(test_pattern(120.0));
+        seq.set_sample_rate(SAMPLE_RATE);
+        seq.start(0);
+        run(&mut seq, 10);
+        let mut events = Vec::new();
+        seq.stop(&mut events);
+        assert_eq!(events.len(), 2);
+        assert!(events.iter().all(|e| matches!(e, SeqEvent::NoteOff { .. })));
+        assert_eq!(seq.current_step(), None);
+        assert!(run(&mut seq, 44100).is_empty());
+    }
+
+    #[test]
+    fn set_pattern_keeps_position() {
+        let mut seq = Sequencer::new(test_pattern(125.0));
+        seq.set_sample_rate(48000.0);
+        seq.start(0);
+        run(&mut seq, 5761); // steps 0 and 1 fired
+        seq.set_pattern(Box::new(test_pattern(125.0)));
+        assert_eq!(seq.current_step(), Some(1));
+        let log = run(&mut seq, 5760);
+        assert!(log.iter().any(|(_, e)| *e == SeqEvent::NoteOn { track: Track::Lead, note: 62, velocity: 100 }));
+    }
+
+    /// 16-step pattern with a single lead note on step 0, identifying each bar.
+    fn marker_pattern(marker: u8) -> SeqPattern {
+        let mut lead = vec![Vec::new(); 16];
+        lead[0].push(note(marker));
+        SeqPattern {
+            steps: [lead, vec![Vec::new(); 16], vec![Vec::new(); 16]],
+            step_ticks: [PPQ / 4; NUM_TRACKS],
+            bpm: 120.0,
+            swing: 0.0,
+            drum_pitch_ratio: 1.0,
+            modes: Default::default(),
+            steal: Default::default(),
+        }
+    }
+
+    fn bar_markers(log: &[(usize, SeqEvent)]) -> Vec<u8> {
+        log.iter()
+            .filter_map(|(_, e)| match e {
+                SeqEvent::NoteOn { track: Track::Lead, note, .. } => Some(*note),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // 120 BPM at 44.1 kHz: one 16-step bar is 88200 samples.
+    const BAR: usize = 88200;
+
+    #[test]
+    fn queued_pattern_switches_at_bar_end() {
+        let mut seq = Sequencer::new(marker_pattern(60));
+        seq.set_sample_rate(SAMPLE_RATE);
+        seq.start(0);
+        let mut log = run(&mut seq, BAR / 2);
+
+        seq.queue_pattern(Box::new(marker_pattern(72)));
+        assert_eq!(seq.adoptions(), 0);
+        log.extend(run(&mut seq, BAR * 2));
+        assert_eq!(seq.adoptions(), 1);
+        assert_eq!(bar_markers(&log), vec![60, 72, 72]);
+        // The new pattern starts exactly on the bar line
+        let on = note_on_samples(&log, Track::Lead);
+        assert_eq!(on[1] + BAR / 2, BAR);
+    }
+
+    #[test]
+    fn queue_while_stopped_applies_at_once() {
+        let mut seq = Sequencer::new(marker_pattern(60));
+        seq.set_sample_rate(SAMPLE_RATE);
+        seq.queue_pattern(Box::new(marker_pattern(72)));
+        assert_eq!(seq.adoptions(), 1);
+        seq.start(0);
+        assert_eq!(bar_markers(&run(&mut seq, 10)), vec![72]);
+    }
+
+    #[test]
+    fn song_plays_entries_with_repeats() {
+        let mut seq = Sequencer::new(marker_pattern(50));
+        seq.set_sample_rate(SAMPLE_RATE);
+        seq.set_song(vec![
+            SongEntry { pattern: marker_pattern(60), repeats: 2 },
+            SongEntry { pattern: marker_pattern(72), repeats: 1 },
+        ]);
+        assert_eq!(seq.song_position(), Some(0));
+        seq.start(0);
+        let log = run(&mut seq, BAR * 4);
+        assert_eq!(bar_markers(&log), vec![60, 60, 72, 60]);
+
+        // Leaving song mode falls back to the looped pattern
+        seq.set_song(Vec::new());
+        assert_eq!(seq.song_position(), None);
+        assert_eq!(bar_markers(&run(&mut seq, BAR)), vec![50]);
+    }
+
+    #[test]
+    fn stop_rewinds_song_and_takes_queued_pattern() {
+        let mut seq = Sequencer::new(marker_pattern(50));
+        seq.set_sample_rate(SAMPLE_RATE);
+        seq.set_song(vec![
+            SongEntry { pattern: marker_pattern(60), repeats: 1 },
+            SongEntry { pattern: marker_pattern(72), repeats: 1 },
+        ]);
+        seq.start(0);
+        run(&mut seq, BAR + 10);
+        assert_eq!(seq.song_position(), Some(1));
+        seq.queue_pattern(Box::new(marker_pattern(40)));
+
+        let mut events = Vec::new();
+        seq.stop(&mut events);
+        assert_eq!(seq.song_position(), Some(0));
+        assert_eq!(seq.adoptions(), 1);
+
+        seq.set_song(Vec::new());
+        seq.start(0);
+        assert_eq!(bar_markers(&run(&mut seq, 10)), vec![40]);
+    }
+
+    /// Tracks of the given lengths and step sizes; each step plays its own index.
+    fn timed_pattern(lens: [usize; NUM_TRACKS], step_ticks: [u32; NUM_TRACKS]) -> SeqPattern {
+        SeqPattern {
+            steps: lens.map(|len| (0..len).map(|s| vec![note(s as u8)]).collect()),
+            step_ticks,
+            bpm: 125.0,
+            ..SeqPattern::default()
+        }
+    }
+
+    fn track_notes(log: &[(usize, SeqEvent)], track: Track) -> Vec<u8> {
+        log.iter()
+            .filter_map(|(_, e)| match e {
+                SeqEvent::NoteOn { track: t, note, .. } if *t == track => Some(*note),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tracks_follow_their_own_resolution() {
+        // 125 BPM at 48 kHz: a beat is 23040 samples
+        let mut seq = Sequencer::new(timed_pattern([4, 4, 6], [PPQ / 2, PPQ / 4, PPQ / 6]));
+        seq.set_sample_rate(48000.0);
+        seq.start(0);
+        let log = run(&mut seq, 23041);
+        assert_eq!(note_on_samples(&log, Track::Lead), [0, 11520, 23040], "1/8");
+        assert_eq!(note_on_samples(&log, Track::Drum), [0, 5760, 11520, 17280, 23040], "1/16");
+        assert_eq!(
+            note_on_samples(&log, Track::Bass),
+            [0, 3840, 7680, 11520, 15360, 19200, 23040],
+            "1/16 triplets"
+        );
+    }
+
+    #[test]
+    fn polymeter_tracks_loop_independently() {
+        let mut seq = Sequencer::new(timed_pattern([3, 4, 0], [PPQ / 4; NUM_TRACKS]));
+        seq.set_sample_rate(48000.0);
+        seq.start(0);
+        let log = run(&mut seq, 5760 * 12);
+        assert_eq!(track_notes(&log, Track::Lead), [0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2]);
+        assert_eq!(track_notes(&log, Track::Drum), [0, 1, 2, 3, 0, 1, 2, 3, 0, 1, 2, 3]);
+        assert_eq!(seq.current_step(), Some(3), "the longest track sets the bar");
+        assert_eq!(seq.track_step(Track::Lead), Some(2));
+    }
+
+    #[test]
+    fn queued_pattern_restarts_every_track_on_the_bar() {
+        let mut seq = Sequencer::new(timed_pattern([3, 4, 0], [PPQ / 4; NUM_TRACKS]));
+        seq.set_sample_rate(48000.0);
+        seq.start(0);
+        let mut log = run(&mut seq, 10);
+        seq.queue_pattern(Box::new(timed_pattern([3, 4, 0], [PPQ / 4; NUM_TRACKS])));
+        log.extend(run(&mut seq, 5760 * 6));
+        // The 4-step drum track is the bar: lead restarts with it at step 4
+        assert_eq!(track_notes(&log, Track::Lead), [0, 1, 2, 0, 0, 1, 2]);
+        assert_eq!(track_notes(&log, Track::Drum), [0, 1, 2, 3, 0, 1, 2]);
+    }
+
+    /// One lead track of 16 steps with `steps` notes placed by step index.
+    fn lead_pattern(notes: &[(usize, StepNote)]) -> SeqPattern {
+        let mut lead = vec![Vec::new(); 16];
+        for &(step, n) in notes {
+            lead[step].push(n);
+        }
+        SeqPattern {
+            steps: [lead, vec![Vec::new(); 16], vec![Vec::new(); 16]],
+            bpm: 125.0,
+            ..SeqPattern::default()
+        }
+    }
+
+    fn note_off_samples(log: &[(usize, SeqEvent)]) -> Vec<usize> {
+        log.iter()
+            .filter(|(_, e)| matches!(e, SeqEvent::NoteOff { .. }))
+            .map(|(i, _)| *i)
+            .collect()
+    }
+
+    #[test]
+    fn note_length_sets_release() {
+        // 125 BPM at 48 kHz: 5760 samples per step
+        let short = StepNote { length: 0.5, ..note(60) };
+        let tied = StepNote { length: 3.0, ..note(64) };
+        let mut seq = Sequencer::new(lead_pattern(&[(0, short), (4, tied)]));
+        seq.set_sample_rate(48000.0);
+        seq.start(0);
+        let log = run(&mut seq, 5760 * 8);
+        assert_eq!(note_off_samples(&log), [2880, 5760 * 7]);
+    }
+
+    #[test]
+    fn tied_note_follows_swing() {
+        let tied = StepNote { length: 2.0, ..note(60) };
+        let mut seq = Sequencer::new(SeqPattern {
+            swing: 100.0,
+            ..lead_pattern(&[(1, tied)])
+        });
+        seq.set_sample_rate(48000.0);
+        seq.start(0);
+        let log = run(&mut seq, 5760 * 6);
+        // Step 1 starts late (8640) and the tie ends on step 3 (20160)
+        assert_eq!(note_on_samples(&log, Track::Lead), [8640]);
+        assert_eq!(note_off_samples(&log), [20160]);
+    }
+
+    #[test]
+    fn retriggered_tie_releases_first() {
+        let tied = StepNote { length: 4.0, ..note(60) };
+        let mut seq = Sequencer::new(lead_pattern(&[(0, tied), (1, note(60))]));
+        seq.set_sample_rate(48000.0);
+        seq.start(0);
+        let log = run(&mut seq, 5760 * 3);
+        let at_step1: Vec<_> = log.iter().filter(|(i, _)| *i == 5760).map(|(_, e)| *e).collect();
+        assert_eq!(
+            at_step1,
+            [
+                SeqEvent::NoteOff { track: Track::Lead, note: 60 },
+                SeqEvent::NoteOn { track: Track::Lead, note: 60, velocity: 100 },
+            ]
+        );
+        assert_eq!(note_off_samples(&log), [5760, 11520]);
+    }
+
+    #[test]
+    fn slide_overlaps_the_next_note() {
+        let slide = StepNote { slide: true, length: 0.5, ..note(60) };
+        let mut seq = Sequencer::new(lead_pattern(&[(0, slide), (1, note(67))]));
+        seq.set_sample_rate(48000.0);
+        seq.start(0);
+        let log = run(&mut seq, 5760 * 3);
+        assert_eq!(note_on_samples(&log, Track::Lead), [0, 5760]);
+        // Released one sample after the next note starts, whatever its length
+        assert_eq!(note_off_samples(&log), [5761, 11520]);
+    }
+
+    #[test]
+    fn slide_into_same_note_ties() {
+        let slide = StepNote { slide: true, ..note(60) };
+        let mut seq = Sequencer::new(lead_pattern(&[(0, slide), (1, note(60))]));
+        seq.set_sample_rate(48000.0);
+        seq.start(0);
+        let log = run(&mut seq, 5760 * 3);
+        assert_eq!(note_on_samples(&log, Track::Lead), [0]);
+        assert_eq!(note_off_samples(&log), [11520]);
+    }
+
+    #[test]
+    fn node_applies_track_voice_modes() {
+        let voices = || -> Vec<Voice> { (0..8).map(|_| Voice::new()).collect() };
+        let (lead, drum, bass) = (voices(), voices(), voices());
+        let drums = DrumEngine::sample(8, &Arc::new(vec![Vec::new(); 16]));
+        let slide = StepNote { slide: true, ..note(36) };
+        let mut bass_steps = vec![Vec::new(); 16];
+        bass_steps[0].push(slide);
+        bass_steps[1].push(note(43));
+        let pattern = SeqPattern {
+            steps: [vec![Vec::new(); 16], vec![Vec::new(); 16], bass_steps],
+            modes: [VoiceMode::Poly, VoiceMode::Poly, VoiceMode::Legato],
+            bpm: 125.0,
+            ..SeqPattern::default()
+        };
+        let (tx, port) = sequencer_channel(Sequencer::new(pattern), &SeqStatus::new());
+        let mut node = SequencerNode::new(port, &lead, &drum, &drums, &bass);
+        node.set_sample_rate(48000.0);
+
+        tx.send(SeqCommand::Start(0)).unwrap();
+        for _ in 0..5762 {
+            node.tick(&Frame::default());
+        }
+        // The slide keeps one gated voice and glides it to the second note
+        assert_eq!(bass.iter().filter(|v| v.gate.value() == 1.0).count(), 1);
+        assert_eq!(bass[0].retrigger.value(), 1.0);
+        assert_eq!(bass[0].slide.value(), 1.0);
+        assert!((bass[0].freq.value() - midi_note_to_freq(43)).abs() < 0.01);
+    }
+
+    #[test]
+    fn node_applies_track_steal_policies() {
+        let lead = vec![Voice::new()];
+        let voices = || -> Vec<Voice> { (0..8).map(|_| Voice::new()).collect() };
+        let (drum, bass) = (voices(), voices());
+        let drums = DrumEngine::sample(8, &Arc::new(vec![Vec::new(); 16]));
+        let mut lead_steps = vec![Vec::new(); 16];
+        lead_steps[0].extend([note(60), note(64)]);
+        let pattern = SeqPattern {
+            steps: [lead_steps, vec![Vec::new(); 16], vec![Vec::new(); 16]],
+            steal: [StealPolicy::None, StealPolicy::Oldest, StealPolicy::Oldest],
+            ..SeqPattern::default()
+        };
+        let (tx, port) = sequencer_channel(Sequencer::new(pattern), &SeqStatus::new());
+        let mut node = SequencerNode::new(port, &lead, &drum, &drums, &bass);
+        node.set_sample_rate(48000.0);
+
+        tx.send(SeqCommand::Start(0)).unwrap();
+        tick_block(&mut node);
+        assert!((lead[0].freq.value() - midi_note_to_freq(60)).abs() < 0.01, "no stealing keeps the first note");
+    }
+
+    #[test]
+    fn node_plays_live_notes() {
+        let (lead, bass) = (vec![Voice::new()], vec![Voice::new()]);
+        let drum: Vec<Voice> = (0..8).map(|_| Voice::new()).collect();
+        let drums = DrumEngine::sample(8, &Arc::new(vec![Vec::new(); 16]));
+        let (tx, port) = sequencer_channel(Sequencer::new(SeqPattern::default()), &SeqStatus::new());
+        let mut node = SequencerNode::new(port, &lead, &drum, &drums, &bass);
+        node.set_sample_rate(48000.0);
+
+        tx.send(SeqCommand::Play(SeqEvent::NoteOn { track: Track::Bass, note: 40, velocity: 100 }))
+            .unwrap();
+        tick_block(&mut node);
+        assert_eq!(bass[0].gate.value(), 1.0, "plays while stopped");
+        assert!((bass[0].freq.value() - midi_note_to_freq(40)).abs() < 0.01);
+        assert_eq!(lead[0].gate.value(), 0.0);
+
+        tx.send(SeqCommand::Play(SeqEvent::NoteOff { track: Track::Bass, note: 40 })).unwrap();
+        tick_block(&mut node);
+        assert_eq!(bass[0].gate.value(), 0.0);
+    }
+
+    #[test]
+    fn node_bends_one_track() {
+        let (lead, bass) = (vec![Voice::new()], vec![Voice::new()]);
+        let drum: Vec<Voice> = (0..8).map(|_| Voice::new()).collect();
+        let drums = DrumEngine::sample(8, &Arc::new(vec![Vec::new(); 16]));
+        let (tx, port) = sequencer_channel(Sequencer::new(SeqPattern::default()), &SeqStatus::new());
+        let mut node = SequencerNode::new(port, &lead, &drum, &drums, &bass);
+        node.set_sample_rate(48000.0);
+
+        for track in [Track::Lead, Track::Bass] {
+            tx.send(SeqCommand::Play(SeqEvent::NoteOn { track, note: 48, velocity: 100 })).unwrap();
+        }
+        tx.send(SeqCommand::PitchBend { track: Track::Bass, amount: 1.0 }).unwrap();
+        tick_block(&mut node);
+        let up = midi_note_to_freq(48) * 2.0f32.powf(DEFAULT_BEND_RANGE / 12.0);
+        assert!((bass[0].freq.value() - up).abs() < 0.01);
+        assert!((lead[0].freq.value() - midi_note_to_freq(48)).abs() < 0.01, "lead not bent");
+    }
+
+    #[test]
+    fn node_mirrors_played_events() {
+        let voices = || -> Vec<Voice> { (0..8).map(|_| Voice::new()).collect() };
+        let (lead, drum, bass) = (voices(), voices(), voices());
+        let drums = DrumEngine::sample(8, &Arc::new(vec![Vec::new(); 16]));
+        let mut steps = vec![Vec::new(); 16];
+        steps[0].push(note(3));
+        let pattern = SeqPattern {
+            steps: [Vec::new(), steps, Vec::new()],
+            ..SeqPattern::default()
+        };
+        let (tx, port) = sequencer_channel(Sequencer::new(pattern), &SeqStatus::new());
+        let mut node = SequencerNode::new(port, &lead, &drum, &drums, &bass);
+        node.set_sample_rate(48000.0);
+
+        let (mirror, mirrored) = mpsc::sync_channel(MIRROR_CAPACITY);
+        tx.send(SeqCommand::Mirror(Some(mirror))).unwrap();
+        tx.send(SeqCommand::Start(0)).unwrap();
+        tick_block(&mut node);
+        assert_eq!(
+            mirrored.try_iter().collect::<Vec<_>>(),
+            [SeqEvent::NoteOn { track: Track::Drum, note: 3, velocity: 100 }]
+        );
+
+        // A full mirror drops events instead of blocking the audio thread
+        let (full, backlog) = mpsc::sync_channel(0);
+        tx.send(SeqCommand::Mirror(Some(full))).unwrap();
+        tx.send(SeqCommand::Start(0)).unwrap();
+        tick_block(&mut node);
+        assert!(backlog.try_recv().is_err());
+
+        tx.send(SeqCommand::Mirror(None)).unwrap();
+        tx.send(SeqCommand::Stop).unwrap();
+        tick_block(&mut node);
+        assert!(mirrored.try_recv().is_err(), "sender dropped after Mirror(None)");
+    }
+
+    #[test]
+    fn probability_thins_out_notes() {
+        let never = StepNote { probability: 0, ..note(60) };
+        let half = StepNote { probability: 50, ..note(62) };
+        let steps = (0..16).map(|_| vec![never, half]).collect();
+        let mut seq = Sequencer::new(SeqPattern {
+            steps: [steps, Vec::new(), Vec::new()],
+            bpm: 125.0,
+            ..SeqPattern::default()
+        });
+        seq.set_sample_rate(48000.0);
+        seq.start(0);
+        let log = run(&mut seq, 5760 * 200);
+        let notes = track_notes(&log, Track::Lead);
+        assert!(!notes.contains(&60));
+        assert!((70..130).contains(&notes.len()), "{} of 200 played", notes.len());
+    }
+
+    #[test]
+    fn empty_pattern_is_safe() {
+        let mut seq = Sequencer::new(SeqPattern::default());
+        seq.set_sample_rate(SAMPLE_RATE);
+        seq.start(3);
+        assert!(run(&mut seq, 44100).is_empty());
+        assert_eq!(seq.current_step(), Some(0));
+    }
+
+    #[test]
+    fn node_gates_voices_from_commands() {
+        let lead: Vec<Voice> = (0..8).map(|_| Voice::new()).collect();
+        let drum: Vec<Voice> = (0..8).map(|_| Voice::new()).collect();
+        let drums = DrumEngine::sample(8, &Arc::new(vec![Vec::new(); 16]));
+        let DrumEngine::Sample { shared: drum_shared, .. } = &drums else {
+            unreachable!()
+        };
+        let bass: Vec<Voice> = (0..8).map(|_| Voice::new()).collect();
+        let status = SeqStatus::new();
+        let step = &status.steps[0];
+
+        let (tx, port) = sequencer_channel(Sequencer::new(test_pattern(120.0)), &status);
+        let mut node = SequencerNode::new(port, &lead, &drum, &drums, &bass);
+        node.set_sample_rate(SAMPLE_RATE);
+
+        node.tick(&Frame::default());
+        assert_eq!(step.value(), -1.0);
+        assert!(lead.iter().all(|v| v.gate.value() == 0.0));
+
+        tx.send(SeqCommand::Start(0)).unwrap();
+        tick_block(&mut node);
+        assert_eq!(step.value(), 0.0);
+        assert_eq!(lead.iter().filter(|v| v.gate.value() == 1.0).count(), 1);
+        assert_eq!(drum_shared[0].trigger.value(), 1.0);
+        assert_eq!(drum_shared[0].sample_index.value(), 14.0);
+
+        tx.send(SeqCommand::Stop).unwrap();
+        tick_block(&mut node);
+        assert_eq!(step.value(), -1.0);
+        assert!(lead.iter().all(|v| v.gate.value() == 0.0));
+    }
+
+    #[test]
+    fn node_hits_synth_drum_kit() {
+        let lead: Vec<Voice> = (0..8).map(|_| Voice::new()).collect();
+        let drum: Vec<Voice> = (0..8).map(|_| Voice::new()).collect();
+        let drums = DrumEngine::synth(8, &DRUM_KIT_ROCK);
+        let DrumEngine::Synth { shared: drum_shared, .. } = &drums else {
+            unreachable!()
+        };
+        let bass: Vec<Voice> = (0..8).map(|_| Voice::new()).collect();
+        let status = SeqStatus::new();
+
+        let mut pattern = test_pattern(120.0);
+        pattern.drum_pitch_ratio = 2.0;
+        let (tx, port) = sequencer_channel(Sequencer::new(pattern), &status);
+        let mut node = SequencerNode::new(port, &lead, &drum, &drums, &bass);
+        node.set_sample_rate(SAMPLE_RATE);
+
+        tx.send(SeqCommand::Start(0)).unwrap();
+        tick_block(&mut node);
+        let kick = DRUM_KIT_ROCK[14];
+        assert_eq!(drum[0].gate.value(), 1.0);
+        assert_eq!(drum_shared[0].trigger.value(), 1.0);
+        assert_eq!(drum_shared[0].freq.value(), kick.freq * 2.0);
+        assert_eq!(drum_shared[0].cutoff.value(), kick.cutoff);
+        assert_eq!(drum_shared[0].noise_level.value(), kick.noise_level);
+    }
+
+    #[test]
+    fn node_tunes_drums_from_song_entry() {
+        let (lead, bass) = (vec![Voice::new()], vec![Voice::new()]);
+        let drum: Vec<Voice> = (0..8).map(|_| Voice::new()).collect();
+        let drums = DrumEngine::synth(8, &DRUM_KIT_ROCK);
+        let DrumEngine::Synth { shared: drum_shared, .. } = &drums else {
+            unreachable!()
+        };
+        let mut tuned = test_pattern(120.0);
+        tuned.drum_pitch_ratio = 2.0;
+        let (tx, port) = sequencer_channel(Sequencer::new(test_pattern(120.0)), &SeqStatus::new());
+        let mut node = SequencerNode::new(port, &lead, &drum, &drums, &bass);
+        node.set_sample_rate(SAMPLE_RATE);
+
+        tx.send(SeqCommand::SetSong(vec![SongEntry { pattern: tuned, repeats: 1 }])).unwrap();
+        tx.send(SeqCommand::Start(0)).unwrap();
+        tick_block(&mut node);
+        assert_eq!(drum_shared[0].freq.value(), DRUM_KIT_ROCK[14].freq * 2.0);
+    }
+
+    #[test]
+    fn node_polls_commands_once_per_block() {
+        let (lead, bass) = (vec![Voice::new()], vec![Voice::new()]);
+        let drum: Vec<Voice> = (0..8).map(|_| Voice::new()).collect();
+        let drums = DrumEngine::sample(8, &Arc::new(vec![Vec::new(); 16]));
+        let (tx, port) = sequencer_channel(Sequencer::new(SeqPattern::default()), &SeqStatus::new());
+        let mut node = SequencerNode::new(port, &lead, &drum, &drums, &bass);
+        node.set_sample_rate(48000.0);
+
+        node.tick(&Frame::default());
+        tx.send(SeqCommand::Play(SeqEvent::NoteOn { track: Track::Lead, note: 60, velocity: 100 }))
+            .unwrap();
+        for _ in 1..POLL_INTERVAL {
+            node.tick(&Frame::default());
+        }
+        assert_eq!(lead[0].gate.value(), 0.0, "not polled within the block");
+        node.tick(&Frame::default());
+        assert_eq!(lead[0].gate.value(), 1.0, "polled at the next block");
+    }
+
+    #[test]
+    fn node_hands_replaced_patterns_back() {
+        let voices = || -> Vec<Voice> { (0..8).map(|_| Voice::new()).collect() };
+        let (lead, drum, bass) = (voices(), voices(), voices());
+        let drums = DrumEngine::sample(8, &Arc::new(vec![Vec::new(); 16]));
+        let (tx, port) = sequencer_channel(Sequencer::new(marker_pattern(60)), &SeqStatus::new());
+        let mut node = SequencerNode::new(port, &lead, &drum, &drums, &bass);
+        node.set_sample_rate(SAMPLE_RATE);
+        let retired_pattern = |tx: &SeqControl| match tx.retired.try_recv() {
+            Ok(Retired::Pattern(pattern)) => Some(*pattern),
+            _ => None,
+        };
+
+        tx.send(SeqCommand::SetPattern(Box::new(marker_pattern(62)))).unwrap();
+        tick_block(&mut node);
+        assert_eq!(retired_pattern(&tx), Some(marker_pattern(60)), "live edit");
+
+        tx.send(SeqCommand::Start(0)).unwrap();
+        tx.send(SeqCommand::QueuePattern(Box::new(marker_pattern(72)))).unwrap();
+        tick_block(&mut node);
+        assert_eq!(retired_pattern(&tx), None, "still queued");
+        for _ in 0..BAR {
+            node.tick(&Frame::default());
+        }
+        tick_block(&mut node);
+        assert_eq!(retired_pattern(&tx), Some(marker_pattern(62)), "replaced at the bar line");
+
+        tx.send(SeqCommand::SetSong(vec![SongEntry { pattern: marker_pattern(60), repeats: 1 }])).unwrap();
+        tx.send(SeqCommand::SetSong(Vec::new())).unwrap();
+        tick_block(&mut node);
+        assert!(matches!(tx.retired.try_recv(), Ok(Retired::Song(song)) if song.is_empty()));
+        assert!(matches!(tx.retired.try_recv(), Ok(Retired::Song(song)) if song.len() == 1));
+        tx.free_retired();
+    }
+
+    #[test]
+    fn node_reports_queue_adoption_and_song_entry() {
+        let voices = || -> Vec<Voice> { (0..8).map(|_| Voice::new()).collect() };
+        let (lead, drum, bass) = (voices(), voices(), voices());
+        let drums = DrumEngine::sample(8, &Arc::new(vec![Vec::new(); 16]));
+        let status = SeqStatus::new();
+
+        let (tx, port) = sequencer_channel(Sequencer::new(marker_pattern(60)), &status);
+        let mut node = SequencerNode::new(port, &lead, &drum, &drums, &bass);
+        node.set_sample_rate(SAMPLE_RATE);
+
+        tx.send(SeqCommand::Start(0)).unwrap();
+        tick_block(&mut node);
+        assert_eq!(status.song_entry.value(), -1.0);
+
+        status.queued.set_value(1.0);
+        tx.send(SeqCommand::QueuePattern(Box::new(marker_pattern(72)))).unwrap();
+        tick_block(&mut node);
+        assert_eq!(status.queued.value(), 1.0, "flag stays up until the bar ends");
+        for _ in 0..BAR {
+            node.tick(&Frame::default());
+        }
+        assert_eq!(status.queued.value(), 0.0);
+
+        tx.send(SeqCommand::SetSong(vec![
+            SongEntry { pattern: marker_pattern(60), repeats: 1 },
+            SongEntry { pattern: marker_pattern(72), repeats: 1 },
+        ]))
+        .unwrap();
+        tick_block(&mut node);
+        assert_eq!(status.song_entry.value(), 0.0);
+        for _ in 0..BAR {
+            node.tick(&Frame::default());
+        }
+        assert_eq!(status.song_entry.value(), 1.0);
+    }
+}
@@ -1,14 +1,179 @@
+use std::sync::Arc;
+
 use fundsp::shared::Shared;
 use serde::{Deserialize, Serialize};
 
 use super::filter::{FilterConfig, LfoConfig};
-use super::oscillator::{AdsrParams, Waveform};
+use super::oscillator::{AdsrParams, DEFAULT_PULSE_WIDTH, Waveform};
+use super::wavetable::Wavetable;
 
 /// Convert a MIDI note number to frequency in Hz.
 pub fn midi_note_to_freq(note: u8) -> f32 {
     440.0 * 2.0f32.powf((note as f32 - 69.0) / 12.0)
 }
 
+/// Default pitch bend range in semitones (either direction).
+pub const DEFAULT_BEND_RANGE: f32 = 2.0;
+
+/// Widest pitch bend range offered, two octaves.
+pub const MAX_BEND_RANGE: f32 = 24.0;
+
+/// Widest filter envelope sweep offered, in octaves either direction.
+pub const MAX_FILTER_ENV_OCTAVES: f32 = 6.0;
+
+/// How the allocator spreads notes over its voices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum VoiceMode {
+    /// One voice per note.
+    #[default]
+    Poly,
+    /// A single voice; every new note restarts the envelope.
+    Mono,
+    /// A single voice; overlapping notes change pitch without restarting the envelope.
+    Legato,
+}
+
+impl VoiceMode {
+    pub fn is_mono(self) -> bool {
+        self != Self::Poly
+    }
+}
+
+/// Which held key a mono voice plays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum NotePriority {
+    #[default]
+    Last,
+    Low,
+    High,
+}
+
+/// Envelope level below which a released voice counts as finished.
+pub const SILENT_LEVEL: f32 = 1e-4;
+
+/// Most voices a synth may run at once.
+pub const MAX_VOICES: usize = 64;
+
+/// Which sounding voice a new note takes over when every voice is busy.
+/// Idle voices, release tails and pedal-held notes are always used first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, Serialize, Deserialize)]
+pub enum StealPolicy {
+    /// The note that started first.
+    #[default]
+    Oldest,
+    /// The voice with the lowest envelope level times velocity.
+    Quietest,
+    Lowest,
+    Highest,
+    /// Drop the new note rather than cut a held one.
+    None,
+}
+
+impl StealPolicy {
+    pub const ALL: [Self; 5] = [
+        Self::Oldest,
+        Self::Quietest,
+        Self::Lowest,
+        Self::Highest,
+        Self::None,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Oldest => "Oldest",
+            Self::Quietest => "Quietest",
+            Self::Lowest => "Lowest",
+            Self::Highest => "Highest",
+            Self::None => "None",
+        }
+    }
+}
+
+/// How a voice makes its sound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum VoiceEngine {
+    /// Oscillators straight into the filter.
+    #[default]
+    Subtractive,
+    /// Two-operator FM: a sine modulator at `FmConfig::ratio` × the note
+    /// frequency modulates the carrier, which plays the voice waveform.
+    Fm,
+}
+
+/// Modulator settings for `VoiceEngine::Fm`.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FmConfig {
+    /// Modulator frequency as a multiple of the note frequency.
+    pub ratio: f32,
+    /// Peak modulation index (carrier phase deviation in radians).
+    pub index: f32,
+    /// Envelope scaling the index over each note.
+    pub index_env: AdsrParams,
+}
+
+impl Default for FmConfig {
+    fn default() -> Self {
+        Self {
+            ratio: 1.0,
+            index: 2.0,
+            index_env: AdsrParams {
+                attack: 0.001,
+                decay: 0.5,
+                sustain: 0.3,
+                release: 0.3,
+            },
+        }
+    }
+}
+
+/// Optional second oscillator of a voice, mixed into the first.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Osc2Config {
+    pub enabled: bool,
+    pub waveform: Waveform,
+    /// Offset from oscillator 1 in octaves (-3..=3).
+    pub octave: i32,
+    /// Offset from oscillator 1 in semitones (-12..=12).
+    pub semitone: i32,
+    /// Offset from oscillator 1 in cents.
+    pub fine: f32,
+    /// Mix level relative to oscillator 1 (0.0–1.0).
+    pub level: f32,
+    /// Restart oscillator 2's cycle with every cycle of oscillator 1.
+    pub sync: bool,
+    /// Mix oscillator 1 × oscillator 2 instead of oscillator 2 alone.
+    pub ring: bool,
+}
+
+impl Default for Osc2Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            waveform: Waveform::Saw,
+            octave: 0,
+            semitone: 0,
+            fine: 0.0,
+            level: 0.5,
+            sync: false,
+            ring: false,
+        }
+    }
+}
+
+impl Osc2Config {
+    /// Total offset from oscillator 1 in semitones.
+    pub fn pitch(&self) -> f32 {
+        (self.octave * 12 + self.semitone) as f32 + self.fine / 100.0
+    }
+
+    fn topology_differs(&self, other: &Self) -> bool {
+        self.enabled != other.enabled
+            || self.waveform != other.waveform
+            || self.sync != other.sync
+            || self.ring != other.ring
+    }
+}
+
 /// Per-voice configuration (topology-changing params trigger audio graph rebuild).
 #[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct VoiceConfig {
@@ -17,10 +182,35 @@ pub struct VoiceConfig {
     pub filter_cfg: FilterConfig,
     pub cutoff: f32,
     pub resonance: f32,
+    /// Envelope sweeping the filter cutoff over each note.
+    pub filter_env: AdsrParams,
+    /// Filter envelope depth in octaves at full envelope; negative sweeps down.
+    pub filter_env_amount: f32,
     pub lfo_cfg: LfoConfig,
     pub lfo_rate: f32,
     pub lfo_depth: f32,
     pub level: f32,
+    /// Coarse pitch offset in semitones.
+    pub pitch: f32,
+    /// Unison detune in cents: copies are spread evenly over ±detune.
+    pub detune: f32,
+    /// Number of stacked oscillator copies (1 = single oscillator).
+    pub unison: usize,
+    /// Stereo width of the unison copies (0.0 = mono, 1.0 = hard left/right).
+    pub spread: f32,
+    /// Portamento time in seconds for mono notes played over a held key.
+    pub glide: f32,
+    /// Duty cycle of `Waveform::Pulse` (0.0–1.0, 0.5 = square).
+    pub pulse_width: f32,
+    /// Table played by `Waveform::Wavetable`; `None` plays `Wavetable::basic()`.
+    /// Loaded tables are not saved with the config.
+    #[serde(skip)]
+    pub wavetable: Option<Arc<Wavetable>>,
+    /// Frame position in the wavetable (0.0 = first frame, 1.0 = last).
+    pub wavetable_position: f32,
+    pub osc2: Osc2Config,
+    pub engine: VoiceEngine,
+    pub fm: FmConfig,
 }
 
 impl Default for VoiceConfig {
@@ -31,10 +221,28 @@ impl Default for VoiceConfig {
             filter_cfg: FilterConfig::default(),
             cutoff: 1000.0,
             resonance: 0.0,
+            filter_env: AdsrParams {
+                attack: 0.01,
+                decay: 0.3,
+                sustain: 0.0,
+                release: 0.3,
+            },
+            filter_env_amount: 0.0,
             lfo_cfg: LfoConfig::default(),
             lfo_rate: 1.0,
             lfo_depth: 0.0,
             level: 1.0,
+            pitch: 0.0,
+            detune: 0.0,
+            unison: 1,
+            spread: 0.5,
+            glide: 0.0,
+            pulse_width: DEFAULT_PULSE_WIDTH,
+            wavetable: None,
+            wavetable_position: 0.0,
+            osc2: Osc2Config::default(),
+            engine: VoiceEngine::Subtractive,
+            fm: FmConfig::default(),
         }
     }
 }
@@ -45,7 +253,13 @@ impl VoiceConfig {
         self.waveform != other.waveform
             || self.adsr != other.adsr
             || self.filter_cfg != other.filter_cfg
+            || self.filter_env != other.filter_env
             || self.lfo_cfg != other.lfo_cfg
+            || self.unison != other.unison
+            || self.wavetable != other.wavetable
+            || self.osc2.topology_differs(&other.osc2)
+            || self.engine != other.engine
+            || self.fm.index_env != other.fm.index_env
     }
 }
 
@@ -53,9 +267,21 @@ impl VoiceConfig {
 pub struct VoiceShared {
     pub cutoff: Shared,
     pub resonance: Shared,
+    pub filter_env_amount: Shared,
     pub lfo_rate: Shared,
     pub lfo_depth: Shared,
     pub level: Shared,
+    pub pitch: Shared,
+    pub detune: Shared,
+    pub spread: Shared,
+    pub glide: Shared,
+    pub pulse_width: Shared,
+    pub wavetable_position: Shared,
+    /// Oscillator 2 offset in semitones (`Osc2Config::pitch`).
+    pub osc2_pitch: Shared,
+    pub osc2_level: Shared,
+    pub fm_ratio: Shared,
+    pub fm_index: Shared,
 }
 
 impl VoiceShared {
@@ -63,9 +289,20 @@ impl VoiceShared {
         Self {
             cutoff: Shared::new(cfg.cutoff),
             resonance: Shared::new(cfg.resonance),
+            filter_env_amount: Shared::new(cfg.filter_env_amount),
             lfo_rate: Shared::new(cfg.lfo_rate),
             lfo_depth: Shared::new(cfg.lfo_depth),
             level: Shared::new(cfg.level),
+            pitch: Shared::new(cfg.pitch),
+            detune: Shared::new(cfg.detune),
+            spread: Shared::new(cfg.spread),
+            glide: Shared::new(cfg.glide),
+            pulse_width: Shared::new(cfg.pulse_width),
+            wavetable_position: Shared::new(cfg.wavetable_position),
+            osc2_pitch: Shared::new(cfg.osc2.pitch()),
+            osc2_level: Shared::new(cfg.osc2.level),
+            fm_ratio: Shared::new(cfg.fm.ratio),
+            fm_index: Shared::new(cfg.fm.index),
         }
     }
 
@@ -73,19 +310,57 @@ impl VoiceShared {
     pub fn sync(&self, cfg: &VoiceConfig) {
         self.cutoff.set_value(cfg.cutoff);
         self.resonance.set_value(cfg.resonance);
+        self.filter_env_amount.set_value(cfg.filter_env_amount);
         self.lfo_rate.set_value(cfg.lfo_rate);
         self.lfo_depth.set_value(cfg.lfo_depth);
         self.level.set_value(cfg.level);
+        self.pitch.set_value(cfg.pitch);
+        self.detune.set_value(cfg.detune);
+        self.spread.set_value(cfg.spread);
+        self.glide.set_value(cfg.glide);
+        self.pulse_width.set_value(cfg.pulse_width);
+        self.wavetable_position.set_value(cfg.wavetable_position);
+        self.osc2_pitch.set_value(cfg.osc2.pitch());
+        self.osc2_level.set_value(cfg.osc2.level);
+        self.fm_ratio.set_value(cfg.fm.ratio);
+        self.fm_index.set_value(cfg.fm.index);
+    }
+
+    /// Raise the LFO depth from the configured value toward full as the mod
+    /// wheel (0.0–1.0) goes up. Call after `sync`.
+    pub fn apply_mod_wheel(&self, cfg: &VoiceConfig, wheel: f32) {
+        let wheel = wheel.clamp(0.0, 1.0);
+        self.lfo_depth.set_value(cfg.lfo_depth + (1.0 - cfg.lfo_depth) * wheel);
     }
 }
 
 /// A single synthesizer voice with shared audio parameters.
+/// Clones share the same `Shared` atomics.
+#[derive(Clone)]
 pub struct Voice {
     pub freq: Shared,
     pub gate: Shared,
     pub velocity: Shared,
+    /// 1.0 while the voice glides to its note at the configured glide time.
+    pub slide: Shared,
+    /// Bumped to restart the envelope of a voice whose gate stays open.
+    pub retrigger: Shared,
+    /// Envelope level written back by the audio graph.
+    pub envelope: Shared,
+    /// 1.0 from note-on until the graph has played the release out and put
+    /// the voice to sleep.
+    pub sounding: Shared,
+    /// Allocator clock at the last note-on; lower is older.
+    pub started: u64,
     pub note: Option<u8>,
+    /// Last note played, kept through the release so pitch bend still applies.
+    pub last_note: u8,
     pub releasing: bool,
+    /// Key released while a pedal holds the note: the gate stays open until
+    /// the pedal comes up.
+    pub sustained: bool,
+    /// Key was down when the sostenuto pedal went down.
+    pub sostenuto: bool,
 }
 
 impl Voice {
@@ -94,96 +369,399 @@ impl Voice {
             freq: Shared::new(440.0),
             gate: Shared::new(0.0),
             velocity: Shared::new(0.0),
+            slide: Shared::new(0.0),
+            retrigger: Shared::new(0.0),
+            envelope: Shared::new(0.0),
+            sounding: Shared::new(0.0),
+            started: 0,
             note: None,
+            last_note: 69,
             releasing: false,
+            sustained: false,
+            sostenuto: false,
         }
     }
 
+    fn start(&mut self, freq: f32, note: u8, velocity: u8) {
+        // A stolen voice may still hold its key: without a gate edge the
+        // new note would take over in the middle of the old envelope.
+        if self.gate.value() > 0.0 {
+            self.restart_envelope();
+        }
+        self.sounding.set_value(1.0);
+        self.slide.set_value(0.0);
+        self.freq.set_value(freq);
+        self.gate.set_value(1.0);
+        self.velocity.set_value(velocity as f32 / 127.0);
+        self.note = Some(note);
+        self.last_note = note;
+        self.releasing = false;
+        self.sustained = false;
+        self.sostenuto = false;
+    }
+
+    /// Make the graph restart the envelope although the gate stays open.
+    fn restart_envelope(&self) {
+        self.retrigger.set_value(self.retrigger.value() + 1.0);
+    }
+
+    fn release(&mut self) {
+        self.gate.set_value(0.0);
+        self.note = None;
+        self.releasing = true;
+        self.sustained = false;
+        self.sostenuto = false;
+    }
+
+    /// No key held and no release tail still sounding. Reads `sounding`,
+    /// so it is current before `reap()` clears `releasing`.
     pub fn is_idle(&self) -> bool {
-        self.note.is_none() && !self.releasing
+        self.note.is_none() && (!self.releasing || self.sounding.value() == 0.0)
     }
+
+    /// Stealing tier: release tails go first, then notes only a pedal
+    /// holds, then notes whose key is down.
+    fn busy_tier(&self) -> u8 {
+        if self.releasing {
+            0
+        } else if self.sustained {
+            1
+        } else {
+            2
+        }
+    }
+
+    fn loudness(&self) -> f32 {
+        self.envelope.value() * self.velocity.value()
+    }
+}
+
+/// Split a voice pool into `parts` allocators over consecutive voices, the
+/// first parts taking any leftover voice (multitimbral MIDI). Voices keep
+/// their atomics, so a running graph is unaffected; held notes are released.
+pub fn split_voices(voices: Vec<Voice>, parts: usize) -> Vec<VoiceAllocator> {
+    let parts = parts.clamp(1, voices.len().max(1));
+    let (size, extra) = (voices.len() / parts, voices.len() % parts);
+    let mut voices = voices.into_iter();
+    (0..parts)
+        .map(|i| {
+            let group = voices
+                .by_ref()
+                .take(size + usize::from(i < extra))
+                .map(|mut v| {
+                    if v.note.is_some() {
+                        v.release();
+                    }
+                    v
+                })
+                .collect();
+            VoiceAllocator::from_voices(group)
+        })
+        .collect()
 }
 
-/// Polyphonic voice allocator managing a fixed number of voices.
+/// Polyphonic voice allocator. The voice count can change with `set_voice_count`,
+/// after which the audio graph must be rebuilt over the new voices.
+#[derive(Clone)]
 pub struct VoiceAllocator {
     pub voices: Vec<Voice>,
-    round_robin: usize,
+    /// Counts note-ons to date voices for oldest-first stealing.
+    clock: u64,
+    steal: StealPolicy,
+    /// Pitch wheel position, -1.0 (full down) to 1.0 (full up).
+    bend: f32,
+    /// Semitones reached at full pitch wheel travel.
+    bend_range: f32,
+    /// Sustain pedal (CC 64) down: note-offs wait for it to come up.
+    sustain: bool,
+    /// Sostenuto pedal (CC 66) down: only notes held when it went down wait.
+    sostenuto: bool,
+    mode: VoiceMode,
+    priority: NotePriority,
+    /// Keys down in mono modes, oldest first, with their velocities.
+    keys: Vec<(u8, u8)>,
 }
 
 impl VoiceAllocator {
     pub fn new(num_voices: usize) -> Self {
-        let voices = (0..num_voices).map(|_| Voice::new()).collect();
+        Self::from_voices((0..num_voices).map(|_| Voice::new()).collect())
+    }
+
+    /// Allocate over existing voices (e.g. clones whose atomics are already wired into a graph).
+    pub fn from_voices(voices: Vec<Voice>) -> Self {
         Self {
             voices,
-            round_robin: 0,
+            clock: 0,
+            steal: StealPolicy::Oldest,
+            bend: 0.0,
+            bend_range: DEFAULT_BEND_RANGE,
+            sustain: false,
+            sostenuto: false,
+            mode: VoiceMode::Poly,
+            priority: NotePriority::Last,
+            keys: Vec::new(),
         }
     }
 
     /// Allocate a voice for a note-on event.
-    /// Priority: releasing voice (reuse) > idle voice > round-robin (voice stealing).
+    /// Priority: idle voice > releasing voice > pedal-held voice > held voice
+    /// picked by the steal policy.
     pub fn note_on(&mut self, note: u8, velocity: u8) {
-        // Check if this note is already playing — retrigger it
-        if let Some(v) = self.voices.iter_mut().find(|v| v.note == Some(note)) {
-            v.freq.set_value(midi_note_to_freq(note));
-            v.gate.set_value(1.0);
-            v.velocity.set_value(velocity as f32 / 127.0);
-            v.releasing = false;
+        if self.mode.is_mono() {
+            self.mono_note_on(note, velocity);
             return;
         }
+        self.reap();
+        let freq = self.note_freq(note);
+        if let Some(v) = self.voices.iter_mut().find(|v| v.note == Some(note)) {
+            if v.sustained {
+                // Striking a pedal-held note again: let the old one ring out
+                // and start a fresh attack on another voice.
+                v.release();
+            } else {
+                // Key still down — retrigger it
+                v.restart_envelope();
+                v.sounding.set_value(1.0);
+                v.freq.set_value(freq);
+                v.gate.set_value(1.0);
+                v.velocity.set_value(velocity as f32 / 127.0);
+                v.releasing = false;
+                return;
+            }
+        }
 
+        let Some(idx) = self.free_voice() else {
+            return;
+        };
+        self.clock += 1;
+        self.voices[idx].start(freq, note, velocity);
+        self.voices[idx].started = self.clock;
+    }
+
+    /// Voice a new note should take, or `None` when every voice holds a key
+    /// and the policy forbids stealing.
+    fn free_voice(&self) -> Option<usize> {
         // Prefer an idle voice so consecutive same-note triggers use separate voices
         // (the releasing voice keeps its ADSR tail while the new voice starts a fresh attack)
-        let idx = if let Some(i) = self.voices.iter().position(|v| v.is_idle()) {
-            i
-        // Then a releasing voice
-        } else if let Some(i) = self.voices.iter().position(|v| v.releasing) {
-            i
-        // Round-robin steal
-        } else {
-            let i = self.round_robin % self.voices.len();
-            self.round_robin = (self.round_robin + 1) % self.voices.len();
-            i
-        };
+        if let Some(i) = self.voices.iter().position(|v| v.is_idle()) {
+            return Some(i);
+        }
+        let tier = self.voices.iter().map(Voice::busy_tier).min()?;
+        if tier == 2 && self.steal == StealPolicy::None {
+            return None;
+        }
+        self.voices
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| v.busy_tier() == tier)
+            .min_by(|(_, a), (_, b)| match self.steal {
+                StealPolicy::Oldest | StealPolicy::None => a.started.cmp(&b.started),
+                StealPolicy::Quietest => a.loudness().total_cmp(&b.loudness()),
+                StealPolicy::Lowest => a.last_note.cmp(&b.last_note),
+                StealPolicy::Highest => b.last_note.cmp(&a.last_note),
+            })
+            .map(|(i, _)| i)
+    }
+
+    /// Return voices whose release the audio graph has finished to idle.
+    pub fn reap(&mut self) {
+        for v in &mut self.voices {
+            if v.releasing && v.sounding.value() == 0.0 {
+                v.releasing = false;
+            }
+        }
+    }
+
+    pub fn set_steal_policy(&mut self, policy: StealPolicy) {
+        self.steal = policy;
+    }
 
-        let voice = &mut self.voices[idx];
-        voice.freq.set_value(midi_note_to_freq(note));
-        voice.gate.set_value(1.0);
-        voice.velocity.set_value(velocity as f32 / 127.0);
-        voice.note = Some(note);
-        voice.releasing = false;
+    /// Change the number of voices (clamped to 1–`MAX_VOICES`). Voices past
+    /// the new count are dropped; the caller rebuilds the audio graph.
+    pub fn set_voice_count(&mut self, count: usize) {
+        self.voices.resize_with(count.clamp(1, MAX_VOICES), Voice::new);
     }
 
-    /// Release a voice matching the given note.
+    /// Release a voice matching the given note, or leave it sounding while a
+    /// pedal holds it.
     pub fn note_off(&mut self, note: u8) {
+        if self.mode.is_mono() {
+            self.mono_note_off(note);
+            return;
+        }
+        let (sustain, sostenuto) = (self.sustain, self.sostenuto);
         if let Some(v) = self.voices.iter_mut().find(|v| v.note == Some(note)) {
-            v.gate.set_value(0.0);
-            v.note = None;
-            v.releasing = true;
+            if sustain || (sostenuto && v.sostenuto) {
+                v.sustained = true;
+            } else {
+                v.release();
+            }
+        }
+    }
+
+    /// Switch between poly and mono modes. Sounding notes are released.
+    pub fn set_mode(&mut self, mode: VoiceMode) {
+        if mode != self.mode {
+            self.mode = mode;
+            self.keys.clear();
+            for v in &mut self.voices {
+                if v.note.is_some() {
+                    v.release();
+                }
+            }
+        }
+    }
+
+    pub fn set_priority(&mut self, priority: NotePriority) {
+        self.priority = priority;
+    }
+
+    /// Held key a mono voice should play, with its velocity.
+    fn mono_key(&self) -> Option<(u8, u8)> {
+        let keys = self.keys.iter().copied();
+        match self.priority {
+            NotePriority::Last => self.keys.last().copied(),
+            NotePriority::Low => keys.min_by_key(|&(note, _)| note),
+            NotePriority::High => keys.max_by_key(|&(note, _)| note),
+        }
+    }
+
+    fn mono_note_on(&mut self, note: u8, velocity: u8) {
+        let overlap = !self.keys.is_empty();
+        self.keys.retain(|&(n, _)| n != note);
+        self.keys.push((note, velocity));
+        let Some((key, velocity)) = self.mono_key() else {
+            return;
+        };
+        // A key that loses on priority leaves the sounding note alone.
+        if overlap && self.voices[0].note == Some(key) {
+            return;
+        }
+        self.mono_play(key, velocity, overlap);
+    }
+
+    fn mono_note_off(&mut self, note: u8) {
+        let sounding = self.mono_key().map(|(n, _)| n);
+        self.keys.retain(|&(n, _)| n != note);
+        if sounding != Some(note) {
+            return;
+        }
+        match self.mono_key() {
+            // Fall back to the key still held
+            Some((key, velocity)) => self.mono_play(key, velocity, true),
+            None => {
+                let (sustain, sostenuto) = (self.sustain, self.sostenuto);
+                let v = &mut self.voices[0];
+                if v.note != Some(note) {
+                    return;
+                }
+                if sustain || (sostenuto && v.sostenuto) {
+                    v.sustained = true;
+                } else {
+                    v.release();
+                }
+            }
+        }
+    }
+
+    /// Play a note on the mono voice. Moving from one held key to another
+    /// glides; in legato mode it also keeps the envelope running.
+    fn mono_play(&mut self, note: u8, velocity: u8, overlap: bool) {
+        let freq = self.note_freq(note);
+        let legato = overlap && self.mode == VoiceMode::Legato && self.voices[0].note.is_some();
+        let v = &mut self.voices[0];
+        v.slide.set_value(if overlap { 1.0 } else { 0.0 });
+        v.freq.set_value(freq);
+        if !legato {
+            // The gate may have closed on this very sample: force a new edge.
+            v.restart_envelope();
+            v.sounding.set_value(1.0);
+            v.gate.set_value(1.0);
+            v.velocity.set_value(velocity as f32 / 127.0);
+        }
+        v.note = Some(note);
+        v.last_note = note;
+        v.releasing = false;
+        v.sustained = false;
+    }
+
+    /// Press or lift the sustain pedal. Lifting it releases every note whose
+    /// key is already up, unless the sostenuto pedal still holds it.
+    pub fn set_sustain(&mut self, down: bool) {
+        self.sustain = down;
+        if !down {
+            let sostenuto = self.sostenuto;
+            for v in &mut self.voices {
+                if v.sustained && !(sostenuto && v.sostenuto) {
+                    v.release();
+                }
+            }
+        }
+    }
+
+    /// Press or lift the sostenuto pedal. Pressing it latches the notes whose
+    /// keys are down; later notes play normally.
+    pub fn set_sostenuto(&mut self, down: bool) {
+        if down == self.sostenuto {
+            return;
+        }
+        self.sostenuto = down;
+        let sustain = self.sustain;
+        for v in &mut self.voices {
+            if down {
+                v.sostenuto = v.note.is_some() && !v.sustained;
+            } else {
+                v.sostenuto = false;
+                if v.sustained && !sustain {
+                    v.release();
+                }
+            }
         }
     }
 
     /// Force a note onto a specific voice index (used by per-voice Test buttons).
     pub fn force_note_on(&mut self, voice_idx: usize, note: u8, velocity: u8) {
+        let freq = self.note_freq(note);
         if let Some(voice) = self.voices.get_mut(voice_idx) {
-            voice.freq.set_value(midi_note_to_freq(note));
-            voice.gate.set_value(1.0);
-            voice.velocity.set_value(velocity as f32 / 127.0);
-            voice.note = Some(note);
-            voice.releasing = false;
+            voice.start(freq, note, velocity);
         }
     }
 
     /// Force note-off on a specific voice index.
     pub fn force_note_off(&mut self, voice_idx: usize) {
         if let Some(voice) = self.voices.get_mut(voice_idx) {
-            voice.gate.set_value(0.0);
-            voice.note = None;
-            voice.releasing = true;
+            voice.release();
+        }
+    }
+
+    /// Frequency of a note with the current pitch bend applied.
+    fn note_freq(&self, note: u8) -> f32 {
+        midi_note_to_freq(note) * 2.0f32.powf(self.bend * self.bend_range / 12.0)
+    }
+
+    /// Retune every voice, including releasing ones, to its note plus bend.
+    fn retune(&self) {
+        for v in &self.voices {
+            v.freq.set_value(self.note_freq(v.last_note));
+        }
+    }
+
+    /// Move the pitch wheel (-1.0 to 1.0).
+    pub fn pitch_bend(&mut self, amount: f32) {
+        self.bend = amount.clamp(-1.0, 1.0);
+        self.retune();
+    }
+
+    /// Set how many semitones full pitch wheel travel reaches.
+    pub fn set_bend_range(&mut self, semitones: f32) {
+        let semitones = semitones.clamp(0.0, MAX_BEND_RANGE);
+        if semitones != self.bend_range {
+            self.bend_range = semitones;
+            self.retune();
         }
     }
 
     /// Count how many voices are currently active (playing or releasing).
-    #[allow(dead_code)]
     pub fn active_count(&self) -> usize {
         self.voices.iter().filter(|v| !v.is_idle()).count()
     }
@@ -245,6 +823,25 @@ mod tests {
         assert_eq!(alloc.active_count(), 1); // still "active" (releasing)
     }
 
+    #[test]
+    fn finished_release_returns_voice_to_idle() {
+        let mut alloc = VoiceAllocator::new(2);
+        alloc.note_on(60, 100);
+        alloc.note_off(60);
+        alloc.reap();
+        assert!(alloc.voices[0].releasing, "the graph has not finished the release yet");
+
+        alloc.voices[0].sounding.set_value(0.0); // what the graph reports
+        assert_eq!(alloc.active_count(), 0, "counted idle before reap");
+        alloc.reap();
+        assert!(alloc.voices[0].is_idle());
+        assert_eq!(alloc.active_count(), 0);
+
+        alloc.note_on(64, 100);
+        assert_eq!(alloc.voices[0].note, Some(64), "the first idle voice is reused");
+        assert_eq!(alloc.voices[0].sounding.value(), 1.0);
+    }
+
     #[test]
     fn allocator_prefers_idle_over_releasing() {
         let mut alloc = VoiceAllocator::new(2);
@@ -274,7 +871,7 @@ mod tests {
     }
 
     #[test]
-    fn allocator_round_robin_steals_when_all_active() {
+    fn allocator_steals_when_all_active() {
         let mut alloc = VoiceAllocator::new(2);
         alloc.note_on(60, 100);
         alloc.note_on(64, 100);
@@ -292,6 +889,22 @@ mod tests {
         // Should not allocate a second voice
         let count = alloc.voices.iter().filter(|v| v.note == Some(60)).count();
         assert_eq!(count, 1, "retriggering same note should reuse the voice");
+        assert_eq!(alloc.voices[0].retrigger.value(), 1.0, "envelope restarts");
+    }
+
+    #[test]
+    fn stealing_held_voice_restarts_envelope() {
+        let mut alloc = VoiceAllocator::new(1);
+        alloc.note_on(60, 100);
+        assert_eq!(alloc.voices[0].retrigger.value(), 0.0, "idle voice gets a gate edge");
+        alloc.note_on(64, 100);
+        assert_eq!(alloc.voices[0].note, Some(64));
+        assert_eq!(alloc.voices[0].retrigger.value(), 1.0);
+
+        // A released voice closes its gate, so the next note needs no bump
+        alloc.note_off(64);
+        alloc.note_on(67, 100);
+        assert_eq!(alloc.voices[0].retrigger.value(), 1.0);
     }
 
     #[test]
@@ -342,6 +955,33 @@ mod tests {
         assert!(a.topology_differs(&b));
     }
 
+    #[test]
+    fn osc2_routing_changes_topology_but_tuning_does_not() {
+        let a = VoiceConfig { osc2: Osc2Config { enabled: true, ..Osc2Config::default() }, ..VoiceConfig::default() };
+        let mut b = a.clone();
+        b.osc2.octave = 1;
+        b.osc2.fine = 12.0;
+        b.osc2.level = 0.9;
+        assert!(!a.topology_differs(&b));
+        assert_eq!(VoiceShared::new(&b).osc2_pitch.value(), 12.12);
+
+        b.osc2.ring = true;
+        assert!(a.topology_differs(&b));
+    }
+
+    #[test]
+    fn fm_engine_and_index_envelope_change_topology() {
+        let a = VoiceConfig { engine: VoiceEngine::Fm, ..VoiceConfig::default() };
+        let mut b = a.clone();
+        b.fm.ratio = 3.5;
+        b.fm.index = 6.0;
+        assert!(!a.topology_differs(&b));
+
+        b.fm.index_env.decay = 1.5;
+        assert!(a.topology_differs(&b));
+        assert!(a.topology_differs(&VoiceConfig::default()));
+    }
+
     #[test]
     fn voice_config_runtime_change_no_topology_diff() {
         let a = VoiceConfig::default();
@@ -349,7 +989,329 @@ mod tests {
         b.cutoff = 5000.0;
         b.resonance = 0.8;
         b.level = 0.5;
+        b.pitch = 12.0;
+        b.detune = 25.0;
+        b.spread = 1.0;
         // Runtime-only changes should NOT trigger topology diff
         assert!(!a.topology_differs(&b));
     }
+
+    #[test]
+    fn voice_config_unison_count_changes_topology() {
+        let a = VoiceConfig::default();
+        assert_eq!(a.unison, 1);
+        let b = VoiceConfig { unison: 5, ..a.clone() };
+        assert!(a.topology_differs(&b));
+    }
+
+    #[test]
+    fn voice_shared_syncs_pitch_and_detune() {
+        let mut cfg = VoiceConfig::default();
+        let shared = VoiceShared::new(&cfg);
+        cfg.pitch = -7.0;
+        cfg.detune = 30.0;
+        cfg.spread = 0.25;
+        shared.sync(&cfg);
+        assert_eq!(shared.pitch.value(), -7.0);
+        assert_eq!(shared.detune.value(), 30.0);
+        assert_eq!(shared.spread.value(), 0.25);
+    }
+
+    #[test]
+    fn pitch_bend_retunes_held_and_releasing_voices() {
+        let mut alloc = VoiceAllocator::new(4);
+        alloc.note_on(69, 100);
+        alloc.note_on(57, 100);
+        alloc.note_off(57);
+        alloc.pitch_bend(1.0);
+        // Default range: a whole tone up
+        let up = 2.0f32.powf(2.0 / 12.0);
+        assert!((alloc.voices[0].freq.value() - 440.0 * up).abs() < 0.01);
+        assert!((alloc.voices[1].freq.value() - 220.0 * up).abs() < 0.01);
+
+        alloc.pitch_bend(0.0);
+        assert!((alloc.voices[0].freq.value() - 440.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn pitch_bend_applies_to_new_notes_and_range() {
+        let mut alloc = VoiceAllocator::new(4);
+        alloc.set_bend_range(12.0);
+        alloc.pitch_bend(-1.0);
+        alloc.note_on(69, 100);
+        assert!((alloc.voices[0].freq.value() - 220.0).abs() < 0.01);
+
+        alloc.set_bend_range(100.0);
+        assert!((alloc.voices[0].freq.value() - 110.0).abs() < 0.01, "range clamps to two octaves");
+    }
+
+    #[test]
+    fn mod_wheel_raises_lfo_depth() {
+        let cfg = VoiceConfig { lfo_depth: 0.2, ..VoiceConfig::default() };
+        let shared = VoiceShared::new(&cfg);
+        shared.apply_mod_wheel(&cfg, 0.0);
+        assert_eq!(shared.lfo_depth.value(), 0.2);
+        shared.apply_mod_wheel(&cfg, 0.5);
+        assert!((shared.lfo_depth.value() - 0.6).abs() < 1e-6);
+        shared.apply_mod_wheel(&cfg, 1.0);
+        assert_eq!(shared.lfo_depth.value(), 1.0);
+    }
+
+    #[test]
+    fn sustain_defers_note_off_until_pedal_up() {
+        let mut alloc = VoiceAllocator::new(4);
+        alloc.note_on(60, 100);
+        alloc.set_sustain(true);
+        alloc.note_off(60);
+        assert_eq!(alloc.voices[0].note, Some(60));
+        assert_eq!(alloc.voices[0].gate.value(), 1.0);
+        assert!(alloc.voices[0].sustained);
+
+        // Notes played while the pedal is down are held too
+        alloc.note_on(64, 100);
+        alloc.note_off(64);
+        assert!(alloc.voices[1].sustained);
+
+        alloc.set_sustain(false);
+        assert!(alloc.voices.iter().take(2).all(|v| v.releasing && v.note.is_none()));
+        assert_eq!(alloc.voices[0].gate.value(), 0.0);
+    }
+
+    #[test]
+    fn sustain_keeps_keys_still_down() {
+        let mut alloc = VoiceAllocator::new(4);
+        alloc.set_sustain(true);
+        alloc.note_on(60, 100);
+        alloc.set_sustain(false);
+        assert_eq!(alloc.voices[0].note, Some(60), "key still down");
+        alloc.note_off(60);
+        assert!(alloc.voices[0].releasing);
+    }
+
+    #[test]
+    fn restriking_sustained_note_starts_new_voice() {
+        let mut alloc = VoiceAllocator::new(4);
+        alloc.set_sustain(true);
+        alloc.note_on(60, 100);
+        alloc.note_off(60);
+        alloc.note_on(60, 90);
+        // The held voice rings out; the new strike gets a fresh gate
+        assert!(alloc.voices[0].releasing);
+        assert_eq!(alloc.voices[1].note, Some(60));
+        assert!(!alloc.voices[1].sustained);
+        assert_eq!(alloc.voices.iter().filter(|v| v.note == Some(60)).count(), 1);
+
+        alloc.note_off(60);
+        alloc.set_sustain(false);
+        assert!(alloc.voices[1].releasing);
+    }
+
+    #[test]
+    fn stealing_prefers_pedal_held_voices() {
+        let mut alloc = VoiceAllocator::new(2);
+        alloc.set_sustain(true);
+        alloc.note_on(60, 100);
+        alloc.note_on(64, 100);
+        alloc.note_off(64); // only the pedal holds 64
+        alloc.note_on(67, 100);
+        assert_eq!(alloc.voices[0].note, Some(60), "key-held note survives");
+        assert_eq!(alloc.voices[1].note, Some(67));
+        assert!(!alloc.voices[1].sustained);
+    }
+
+    /// Fill two voices with 60 then 72, steal with 67 and report which note
+    /// survived.
+    fn survivor(policy: StealPolicy) -> Option<u8> {
+        let mut alloc = VoiceAllocator::new(2);
+        alloc.set_steal_policy(policy);
+        alloc.note_on(72, 100);
+        alloc.note_on(60, 100);
+        alloc.note_on(67, 100);
+        alloc.voices.iter().filter_map(|v| v.note).find(|&n| n != 67)
+    }
+
+    #[test]
+    fn steal_oldest_lowest_and_highest() {
+        assert_eq!(survivor(StealPolicy::Oldest), Some(60));
+        assert_eq!(survivor(StealPolicy::Lowest), Some(72));
+        assert_eq!(survivor(StealPolicy::Highest), Some(60));
+    }
+
+    #[test]
+    fn steal_quietest_envelope() {
+        let mut alloc = VoiceAllocator::new(2);
+        alloc.set_steal_policy(StealPolicy::Quietest);
+        alloc.note_on(60, 100);
+        alloc.note_on(64, 100);
+        alloc.voices[0].envelope.set_value(0.9);
+        alloc.voices[1].envelope.set_value(0.2);
+        alloc.note_on(67, 100);
+        assert_eq!(alloc.voices[0].note, Some(60));
+        assert_eq!(alloc.voices[1].note, Some(67));
+    }
+
+    #[test]
+    fn no_stealing_drops_the_note() {
+        let mut alloc = VoiceAllocator::new(1);
+        alloc.set_steal_policy(StealPolicy::None);
+        alloc.note_on(60, 100);
+        alloc.note_on(64, 100);
+        assert_eq!(alloc.voices[0].note, Some(60));
+        alloc.note_off(60);
+        alloc.note_on(64, 100);
+        assert_eq!(alloc.voices[0].note, Some(64), "release tails are still reused");
+    }
+
+    #[test]
+    fn split_voices_into_parts() {
+        let mut alloc = VoiceAllocator::new(8);
+        alloc.note_on(60, 100);
+        let gate = alloc.voices[0].gate.clone();
+        let parts = split_voices(alloc.voices, 3);
+        let sizes: Vec<usize> = parts.iter().map(|p| p.voices.len()).collect();
+        assert_eq!(sizes, [3, 3, 2]);
+        assert_eq!(gate.value(), 0.0, "held notes are released");
+        parts[0].voices[0].gate.set_value(1.0);
+        assert_eq!(gate.value(), 1.0, "parts share the voices' atomics");
+
+        assert_eq!(split_voices((0..2).map(|_| Voice::new()).collect(), 5).len(), 2);
+        assert_eq!(split_voices(Vec::new(), 4).len(), 1);
+    }
+
+    #[test]
+    fn voice_count_is_clamped() {
+        let mut alloc = VoiceAllocator::new(8);
+        alloc.set_voice_count(0);
+        assert_eq!(alloc.voices.len(), 1);
+        alloc.set_voice_count(100);
+        assert_eq!(alloc.voices.len(), MAX_VOICES);
+        alloc.set_voice_count(16);
+        assert_eq!(alloc.voices.len(), 16);
+    }
+
+    #[test]
+    fn sostenuto_holds_only_notes_down_when_pressed() {
+        let mut alloc = VoiceAllocator::new(4);
+        alloc.note_on(48, 100);
+        alloc.set_sostenuto(true);
+        alloc.note_on(60, 100);
+        alloc.note_off(48);
+        alloc.note_off(60);
+        assert_eq!(alloc.voices[0].note, Some(48), "latched by sostenuto");
+        assert!(alloc.voices[1].releasing, "played after the pedal went down");
+
+        alloc.set_sostenuto(false);
+        assert!(alloc.voices[0].releasing);
+    }
+
+    #[test]
+    fn sostenuto_and_sustain_release_independently() {
+        let mut alloc = VoiceAllocator::new(4);
+        alloc.note_on(48, 100);
+        alloc.set_sostenuto(true);
+        alloc.set_sustain(true);
+        alloc.note_on(60, 100);
+        alloc.note_off(48);
+        alloc.note_off(60);
+
+        alloc.set_sustain(false);
+        assert_eq!(alloc.voices[0].note, Some(48), "sostenuto still down");
+        assert!(alloc.voices[1].releasing);
+
+        alloc.set_sustain(true);
+        alloc.note_on(52, 100);
+        alloc.note_off(52);
+        alloc.set_sostenuto(false);
+        assert_eq!(alloc.voices[0].note, Some(48), "sustain is down again");
+        assert_eq!(alloc.voices[2].note, Some(52));
+
+        alloc.set_sustain(false);
+        assert!(alloc.voices[0].releasing && alloc.voices[2].releasing);
+    }
+
+    #[test]
+    fn mono_plays_one_voice_with_last_priority() {
+        let mut alloc = VoiceAllocator::new(4);
+        alloc.set_mode(VoiceMode::Mono);
+        alloc.note_on(60, 100);
+        alloc.note_on(64, 100);
+        assert_eq!(alloc.voices[0].note, Some(64));
+        assert_eq!(alloc.active_count(), 1);
+        assert_eq!(alloc.voices[0].retrigger.value(), 2.0, "mono restarts the envelope");
+        assert_eq!(alloc.voices[0].slide.value(), 1.0, "overlapping notes glide");
+
+        // Releasing the newest key returns to the one still held
+        alloc.note_off(64);
+        assert_eq!(alloc.voices[0].note, Some(60));
+        alloc.note_off(60);
+        assert!(alloc.voices[0].releasing);
+    }
+
+    #[test]
+    fn mono_low_and_high_priority() {
+        let mut alloc = VoiceAllocator::new(4);
+        alloc.set_mode(VoiceMode::Mono);
+        alloc.set_priority(NotePriority::Low);
+        alloc.note_on(60, 100);
+        alloc.note_on(67, 100);
+        assert_eq!(alloc.voices[0].note, Some(60), "higher key is ignored");
+        alloc.note_off(67);
+        assert_eq!(alloc.voices[0].note, Some(60));
+        alloc.note_off(60);
+
+        alloc.set_priority(NotePriority::High);
+        alloc.note_on(60, 100);
+        alloc.note_on(67, 100);
+        alloc.note_on(64, 100);
+        assert_eq!(alloc.voices[0].note, Some(67));
+        alloc.note_off(67);
+        assert_eq!(alloc.voices[0].note, Some(64));
+    }
+
+    #[test]
+    fn legato_keeps_envelope_while_notes_overlap() {
+        let mut alloc = VoiceAllocator::new(4);
+        alloc.set_mode(VoiceMode::Legato);
+        alloc.note_on(48, 100);
+        let trigger = alloc.voices[0].retrigger.value();
+        assert_eq!(alloc.voices[0].slide.value(), 0.0, "first note jumps");
+
+        alloc.note_on(55, 60);
+        assert_eq!(alloc.voices[0].note, Some(55));
+        assert_eq!(alloc.voices[0].retrigger.value(), trigger);
+        assert_eq!(alloc.voices[0].slide.value(), 1.0);
+        assert!((alloc.voices[0].freq.value() - midi_note_to_freq(55)).abs() < 0.01);
+        assert_eq!(alloc.voices[0].velocity.value(), 100.0 / 127.0, "velocity of the first note");
+
+        // Detached notes start a new envelope without gliding
+        alloc.note_off(48);
+        alloc.note_off(55);
+        alloc.note_on(60, 100);
+        assert_eq!(alloc.voices[0].retrigger.value(), trigger + 1.0);
+        assert_eq!(alloc.voices[0].slide.value(), 0.0);
+    }
+
+    #[test]
+    fn switching_mode_releases_notes() {
+        let mut alloc = VoiceAllocator::new(4);
+        alloc.note_on(60, 100);
+        alloc.note_on(64, 100);
+        alloc.set_mode(VoiceMode::Legato);
+        assert!(alloc.voices.iter().all(|v| v.note.is_none()));
+        alloc.note_on(67, 100);
+        alloc.set_mode(VoiceMode::Poly);
+        assert!(alloc.voices[0].releasing);
+    }
+
+    #[test]
+    fn mono_note_held_by_sustain_pedal() {
+        let mut alloc = VoiceAllocator::new(4);
+        alloc.set_mode(VoiceMode::Mono);
+        alloc.set_sustain(true);
+        alloc.note_on(60, 100);
+        alloc.note_off(60);
+        assert_eq!(alloc.voices[0].note, Some(60));
+        alloc.set_sustain(false);
+        assert!(alloc.voices[0].releasing);
+    }
 }
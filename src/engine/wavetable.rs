@@ -0,0 +1,373 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
+
+use fundsp::fft::{fix_negative, inverse_fft, real_fft};
+use fundsp::prelude32::*;
+
+use super::drum_sample::read_wav;
+
+/// Samples per wavetable frame (one cycle), the common Serum/Vital layout.
+pub const FRAME_SIZE: usize = 2048;
+/// Frames kept from a multi-frame file.
+pub const MAX_FRAMES: usize = 256;
+/// Mipmap levels: level `k` keeps the first `FRAME_SIZE / 2 >> k` harmonics,
+/// down to a pure sine.
+const LEVELS: usize = 11;
+/// Smallest table stored for a mipmap level.
+const MIN_TABLE_SIZE: usize = 64;
+
+/// Single-cycle frames stored as band-limited mipmaps, so every pitch reads
+/// a table whose harmonics stay below Nyquist.
+pub struct Wavetable {
+    pub name: String,
+    /// `levels[k][frame]`: one cycle of `frame` band-limited to level `k`.
+    levels: Vec<Vec<Vec<f32>>>,
+}
+
+/// Tables are equal only to themselves: voices sharing one `Arc` compare equal
+/// without comparing samples, and loading a table always rebuilds the graph.
+impl PartialEq for Wavetable {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+static BASIC: LazyLock<Arc<Wavetable>> = LazyLock::new(|| {
+    let shapes: [fn(f32) -> f32; 4] = [
+        |t| (t * std::f32::consts::TAU).sin(),
+        |t| 1.0 - 4.0 * (t - 0.5).abs(),
+        |t| 2.0 * t - 1.0,
+        |t| if t < 0.5 { 1.0 } else { -1.0 },
+    ];
+    let frames = shapes
+        .iter()
+        .map(|shape| (0..FRAME_SIZE).map(|i| shape(i as f32 / FRAME_SIZE as f32)).collect())
+        .collect();
+    Arc::new(Wavetable::from_frames("Basic", frames))
+});
+
+impl Wavetable {
+    /// Built-in table morphing sine → triangle → saw → square, used when no
+    /// file is loaded.
+    pub fn basic() -> Arc<Wavetable> {
+        Arc::clone(&BASIC)
+    }
+
+    /// Build a table from frames of exactly `FRAME_SIZE` samples. DC is removed
+    /// and the table is normalized to a peak of 1.0.
+    pub fn from_frames(name: &str, frames: Vec<Vec<f32>>) -> Self {
+        let spectra: Vec<Vec<Complex32>> = frames
+            .into_iter()
+            .map(|mut frame| real_fft(&mut frame).to_vec())
+            .collect();
+        let mut levels: Vec<Vec<Vec<f32>>> = (0..LEVELS)
+            .map(|level| spectra.iter().map(|spectrum| band_limit(spectrum, level)).collect())
+            .collect();
+
+        let peak = levels[0]
+            .iter()
+            .flatten()
+            .fold(0.0f32, |peak, s| peak.max(s.abs()));
+        if peak > 0.0 {
+            for s in levels.iter_mut().flatten().flatten() {
+                *s /= peak;
+            }
+        }
+        Self {
+            name: name.to_string(),
+            levels,
+        }
+    }
+
+    /// Split samples into frames: a file of whole `FRAME_SIZE` frames is a
+    /// multi-frame table; anything shorter is a single cycle stretched to one
+    /// frame.
+    pub fn from_samples(name: &str, samples: &[f32]) -> io::Result<Self> {
+        let frames: Vec<Vec<f32>> = if samples.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "wavetable has no samples"));
+        } else if samples.len().is_multiple_of(FRAME_SIZE) {
+            samples.chunks(FRAME_SIZE).take(MAX_FRAMES).map(<[f32]>::to_vec).collect()
+        } else if samples.len() < FRAME_SIZE {
+            vec![stretch_cycle(samples)]
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} samples is neither one cycle nor whole {FRAME_SIZE}-sample frames",
+                    samples.len()
+                ),
+            ));
+        };
+        Ok(Self::from_frames(name, frames))
+    }
+
+    /// Load a WAV file; the name is the file stem. The file's sample rate is
+    /// ignored since each frame is one cycle.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let (samples, _) = read_wav(path)?;
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self::from_samples(&name, &samples)
+    }
+
+    /// `~/.synthesis/wavetables`, next to the presets.
+    pub fn wavetables_dir() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(".synthesis")
+            .join("wavetables")
+    }
+
+    pub fn path_for(name: &str) -> PathBuf {
+        Self::wavetables_dir().join(format!("{name}.wav"))
+    }
+
+    /// Names of the WAV files in `wavetables_dir()`.
+    pub fn list_wavetables() -> Vec<String> {
+        let mut names = Vec::new();
+        if let Ok(entries) = fs::read_dir(Self::wavetables_dir()) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("wav"))
+                    && let Some(stem) = path.file_stem()
+                {
+                    names.push(stem.to_string_lossy().into_owned());
+                }
+            }
+        }
+        names.sort();
+        names
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// Mipmap level for a frequency in cycles per sample: the most detailed
+    /// level whose top harmonic stays below Nyquist.
+    fn level_for(cycles_per_sample: f32) -> usize {
+        let level = (FRAME_SIZE as f32 * cycles_per_sample.abs()).log2().ceil();
+        level.clamp(0.0, (LEVELS - 1) as f32) as usize
+    }
+
+    /// Sample at `phase` (0.0–1.0) of the table at `position` (0.0–1.0,
+    /// crossfading neighbouring frames), band-limited for a pitch of
+    /// `cycles_per_sample`.
+    pub fn sample(&self, cycles_per_sample: f32, position: f32, phase: f32) -> f32 {
+        let level = Self::level_for(cycles_per_sample);
+        let last = self.frame_count() - 1;
+        let pos = position.clamp(0.0, 1.0) * last as f32;
+        let frame = pos as usize;
+        let frac = pos - frame as f32;
+        let a = self.read(level, frame, phase);
+        if frac > 0.0 {
+            let b = self.read(level, Ord::min(frame + 1, last), phase);
+            a + (b - a) * frac
+        } else {
+            a
+        }
+    }
+
+    /// Linearly interpolated sample of one frame at `phase` (0.0–1.0).
+    fn read(&self, level: usize, frame: usize, phase: f32) -> f32 {
+        let table = &self.levels[level][frame];
+        let pos = phase * table.len() as f32;
+        let i = pos as usize % table.len();
+        let frac = pos - pos.floor();
+        let s0 = table[i];
+        let s1 = table[(i + 1) % table.len()];
+        s0 + (s1 - s0) * frac
+    }
+}
+
+/// One cycle of a spectrum (from `real_fft` of a `FRAME_SIZE` frame) keeping
+/// only the harmonics of a mipmap level, without DC or Nyquist.
+fn band_limit(spectrum: &[Complex32], level: usize) -> Vec<f32> {
+    let harmonics = Ord::min((FRAME_SIZE / 2) >> level, FRAME_SIZE / 2 - 1);
+    let size = (4 * harmonics).clamp(MIN_TABLE_SIZE, FRAME_SIZE);
+    let mut bins = vec![Complex32::new(0.0, 0.0); size];
+    bins[1..=harmonics].copy_from_slice(&spectrum[1..=harmonics]);
+    fix_negative(&mut bins);
+    // The inverse FFT divides by its own size, the forward one not at all
+    inverse_fft(&mut bins);
+    let scale = size as f32 / FRAME_SIZE as f32;
+    bins.iter().map(|bin| bin.re * scale).collect()
+}
+
+/// Resample a single cycle of any length to `FRAME_SIZE`, wrapping around.
+fn stretch_cycle(cycle: &[f32]) -> Vec<f32> {
+    (0..FRAME_SIZE)
+        .map(|i| {
+            let pos = i as f32 * cycle.len() as f32 / FRAME_SIZE as f32;
+            let idx = pos as usize;
+            let frac = pos - idx as f32;
+            let s0 = cycle[idx];
+            let s1 = cycle[(idx + 1) % cycle.len()];
+            s0 + (s1 - s0) * frac
+        })
+        .collect()
+}
+
+/// Wavetable oscillator: 2 inputs (frequency in Hz, position 0.0–1.0) → 1 output.
+/// The position crossfades between neighbouring frames.
+#[derive(Clone)]
+pub struct WavetableOsc {
+    table: Arc<Wavetable>,
+    sample_rate: f32,
+    phase: f32,
+}
+
+impl WavetableOsc {
+    pub fn new(table: Arc<Wavetable>) -> Self {
+        Self {
+            table,
+            sample_rate: DEFAULT_SR as f32,
+            phase: 0.0,
+        }
+    }
+}
+
+impl AudioNode for WavetableOsc {
+    const ID: u64 = 900_006;
+    type Inputs = U2;
+    type Outputs = U1;
+
+    fn reset(&mut self) {
+        self.phase = 0.0;
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate as f32;
+    }
+
+    #[inline]
+    fn tick(&mut self, input: &Frame<f32, Self::Inputs>) -> Frame<f32, Self::Outputs> {
+        let step = input[0] / self.sample_rate;
+        let out = self.table.sample(step, input[1], self.phase);
+        self.phase = (self.phase + step).rem_euclid(1.0);
+        [out].into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f64 = 44100.0;
+
+    fn saw_frame() -> Vec<f32> {
+        (0..FRAME_SIZE).map(|i| 2.0 * i as f32 / FRAME_SIZE as f32 - 1.0).collect()
+    }
+
+    fn render(table: Arc<Wavetable>, freq: f32, position: f32, n: usize) -> Vec<f32> {
+        let mut osc = WavetableOsc::new(table);
+        osc.set_sample_rate(SAMPLE_RATE);
+        (0..n).map(|_| osc.tick(&[freq, position].into())[0]).collect()
+    }
+
+    /// Magnitude of one DFT bin of `samples` at `freq`.
+    fn magnitude_at(samples: &[f32], freq: f32) -> f32 {
+        let w = std::f32::consts::TAU * freq / SAMPLE_RATE as f32;
+        let (re, im) = samples.iter().enumerate().fold((0.0, 0.0), |(re, im), (i, s)| {
+            (re + s * (w * i as f32).cos(), im - s * (w * i as f32).sin())
+        });
+        (re * re + im * im).sqrt() / samples.len() as f32
+    }
+
+    #[test]
+    fn mipmaps_reconstruct_a_sine() {
+        let sine = (0..FRAME_SIZE)
+            .map(|i| (std::f32::consts::TAU * i as f32 / FRAME_SIZE as f32).sin() * 0.5)
+            .collect();
+        let table = Wavetable::from_frames("sine", vec![sine]);
+        for level in 0..LEVELS {
+            for i in 0..16 {
+                let phase = i as f32 / 16.0;
+                let expected = (std::f32::consts::TAU * phase).sin();
+                let got = table.read(level, 0, phase);
+                assert!((got - expected).abs() < 0.01, "level {level} phase {phase}: {got}");
+            }
+        }
+    }
+
+    #[test]
+    fn higher_levels_drop_harmonics() {
+        assert_eq!(Wavetable::level_for(10.0 / SAMPLE_RATE as f32), 0);
+        assert_eq!(Wavetable::level_for(440.0 / SAMPLE_RATE as f32), 5);
+        assert_eq!(Wavetable::level_for(0.5), LEVELS - 1);
+
+        // A high saw keeps its fundamental but nothing above Nyquist folds back
+        let table = Arc::new(Wavetable::from_frames("saw", vec![saw_frame()]));
+        let freq = 3000.0;
+        let samples = render(table, freq, 0.0, 8820);
+        assert!(magnitude_at(&samples, freq) > 0.2, "fundamental should be present");
+        // The 8th harmonic (24 kHz) would alias to 20.1 kHz
+        let alias = SAMPLE_RATE as f32 - 8.0 * freq;
+        assert!(magnitude_at(&samples, alias) < 0.005, "aliased harmonic should be absent");
+    }
+
+    #[test]
+    fn position_morphs_between_frames() {
+        let table = Wavetable::basic();
+        assert_eq!(table.frame_count(), 4);
+        let sine = render(Arc::clone(&table), 220.0, 0.0, 4410);
+        let square = render(Arc::clone(&table), 220.0, 1.0, 4410);
+        let between = render(table, 220.0, 0.5, 4410);
+        // A square's 3rd harmonic is a third of its fundamental; a sine has none
+        assert!(magnitude_at(&sine, 660.0) < 0.01);
+        assert!(magnitude_at(&square, 660.0) > 0.1);
+        assert!(between.iter().zip(&sine).any(|(a, b)| (a - b).abs() > 0.05));
+        assert!(between.iter().zip(&square).any(|(a, b)| (a - b).abs() > 0.05));
+    }
+
+    #[test]
+    fn samples_split_into_frames() {
+        let two_frames: Vec<f32> = saw_frame().into_iter().chain(saw_frame()).collect();
+        assert_eq!(Wavetable::from_samples("two", &two_frames).unwrap().frame_count(), 2);
+        let cycle: Vec<f32> = (0..600).map(|i| (i as f32 / 600.0 * std::f32::consts::TAU).sin()).collect();
+        assert_eq!(Wavetable::from_samples("cycle", &cycle).unwrap().frame_count(), 1);
+        assert!(Wavetable::from_samples("empty", &[]).is_err());
+        assert!(Wavetable::from_samples("odd", &vec![0.0; FRAME_SIZE + 1]).is_err());
+    }
+
+    #[test]
+    fn load_reads_wav_frames() {
+        let path = std::env::temp_dir().join("synthesis_test_wavetable.wav");
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 48000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for _ in 0..3 {
+            for s in saw_frame() {
+                writer.write_sample((s * 0.25 * i16::MAX as f32) as i16).unwrap();
+            }
+        }
+        writer.finalize().unwrap();
+
+        let table = Wavetable::load(&path).unwrap();
+        assert_eq!(table.name, "synthesis_test_wavetable");
+        assert_eq!(table.frame_count(), 3);
+        let peak = render(Arc::new(table), 100.0, 0.5, 4410)
+            .iter()
+            .fold(0.0f32, |peak, s| peak.max(s.abs()));
+        assert!(peak > 0.8 && peak < 1.2, "quiet files are normalized, got peak {peak}");
+        let _ = fs::remove_file(&path);
+
+        assert!(Wavetable::load(&path).is_err());
+    }
+
+    #[test]
+    fn tables_equal_only_themselves() {
+        let a = Wavetable::basic();
+        assert!(a == Wavetable::basic());
+        let b = Wavetable::from_frames("saw", vec![saw_frame()]);
+        assert!(*a != b);
+    }
+}
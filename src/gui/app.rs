@@ -7,11 +7,23 @@ use fundsp::snoop::Snoop;
 use crate::engine;
 use crate::engine::effects::EffectsConfig;
 use crate::engine::oscillator::build_poly_graph;
-use crate::engine::voice::{VoiceAllocator, VoiceConfig, VoiceShared};
-use crate::midi::{MidiHandler, NoteEvent};
+use crate::engine::wavetable::Wavetable;
+use crate::engine::voice::{
+    DEFAULT_BEND_RANGE, MAX_FILTER_ENV_OCTAVES, NotePriority, StealPolicy, Voice, VoiceAllocator, VoiceConfig, VoiceMode,
+    VoiceShared, split_voices,
+};
+use crate::midi::{
+    CC_MOD_WHEEL, CC_SOSTENUTO, CC_SUSTAIN, MidiChannels, MidiHandler, MidiOutHandler, NoteEvent,
+};
+use crate::midi_learn::{self, MidiMap};
 use crate::preset::Preset;
 use crate::synth_ui::{SynthParams, SynthUI};
 
+/// Voices the synth starts with.
+const DEFAULT_POLYPHONY: usize = 8;
+/// Parts the voices are split into when MIDI is multitimbral.
+const DEFAULT_MULTI_PARTS: usize = 4;
+
 pub struct SynthApp {
     device: Device,
     supported_config: SupportedStreamConfig,
@@ -21,7 +33,18 @@ pub struct SynthApp {
     amplitude: f32,
     master_amp: Shared,
 
-    allocator: VoiceAllocator,
+    /// One allocator per MIDI part: the whole voice pool, or consecutive
+    /// groups of voices for channels 1, 2, ... when MIDI is multitimbral.
+    parts: Vec<VoiceAllocator>,
+    /// Voice count chosen in the Voicing panel (1–64).
+    polyphony: usize,
+    /// Parts (MIDI channels) used in multitimbral mode.
+    multi_parts: usize,
+    steal_policy: StealPolicy,
+    voice_mode: VoiceMode,
+    note_priority: NotePriority,
+    /// Mono glide time in seconds, applied to every voice.
+    glide: f32,
 
     // Per-voice configs (UI values)
     voice_configs: Vec<VoiceConfig>,
@@ -32,6 +55,14 @@ pub struct SynthApp {
     snoop_right: Option<Snoop>,
 
     midi: MidiHandler,
+    /// External synth the on-screen and computer keyboards also play.
+    midi_out: MidiOutHandler,
+    /// MIDI out channel, 1–16.
+    midi_out_channel: u8,
+    /// Pitch bend range in semitones, set from the MIDI section.
+    bend_range: f32,
+    /// Mod wheel position (0.0–1.0), raises every voice's LFO depth.
+    mod_wheel: f32,
 
     // Effects (global, post-mix)
     effects_cfg: EffectsConfig,
@@ -54,6 +85,8 @@ pub struct SynthApp {
     preset_names: Vec<String>,
     current_preset: Option<String>,
     save_name: String,
+    /// Wavetable files the voice strips can load.
+    wavetable_names: Vec<String>,
 
     // New UI
     synth_ui: SynthUI,
@@ -61,10 +94,13 @@ pub struct SynthApp {
 }
 
 impl SynthApp {
-    pub fn new(_cc: &eframe::CreationContext) -> Self {
+    pub fn new(cc: &eframe::CreationContext) -> Self {
+        let map_path = MidiMap::path();
+        midi_learn::install(&cc.egui_ctx, MidiMap::load_or_default(&map_path), Some(map_path));
         let preset_names = Self::collect_preset_names();
         let (device, supported_config) = engine::init_audio_device();
-        let voice_configs: Vec<VoiceConfig> = (0..8).map(|_| VoiceConfig::default()).collect();
+        let voice_configs: Vec<VoiceConfig> =
+            (0..DEFAULT_POLYPHONY).map(|_| VoiceConfig::default()).collect();
         let voice_shared: Vec<VoiceShared> =
             voice_configs.iter().map(VoiceShared::new).collect();
         Self {
@@ -74,12 +110,22 @@ impl SynthApp {
             playing: false,
             amplitude: 0.5,
             master_amp: Shared::new(0.5),
-            allocator: VoiceAllocator::new(8),
+            parts: vec![VoiceAllocator::new(DEFAULT_POLYPHONY)],
+            polyphony: DEFAULT_POLYPHONY,
+            multi_parts: DEFAULT_MULTI_PARTS,
+            steal_policy: StealPolicy::Oldest,
+            voice_mode: VoiceMode::Poly,
+            note_priority: NotePriority::Last,
+            glide: 0.0,
             voice_configs,
             voice_shared,
             snoop_left: None,
             snoop_right: None,
             midi: MidiHandler::new(),
+            midi_out: MidiOutHandler::new(),
+            midi_out_channel: 1,
+            bend_range: DEFAULT_BEND_RANGE,
+            mod_wheel: 0.0,
             effects_cfg: EffectsConfig::default(),
             delay_time: 0.3,
             delay_time_shared: Shared::new(0.3),
@@ -96,6 +142,7 @@ impl SynthApp {
             preset_names,
             current_preset: None,
             save_name: String::new(),
+            wavetable_names: Wavetable::list_wavetables(),
             synth_ui: SynthUI::new(),
             prev_preset: None,
         }
@@ -138,7 +185,7 @@ impl SynthApp {
         self.voice_shared = self.voice_configs.iter().map(VoiceShared::new).collect();
 
         let (graph, snoop_l, snoop_r) = build_poly_graph(
-            &self.allocator.voices,
+            &self.voices(),
             &self.voice_configs,
             &self.voice_shared,
             &self.master_amp,
@@ -158,6 +205,48 @@ impl SynthApp {
         self.active_effects_cfg = Some(self.effects_cfg.clone());
     }
 
+    /// Every voice, part by part.
+    fn voices(&self) -> Vec<Voice> {
+        self.parts.iter().flat_map(|p| p.voices.iter().cloned()).collect()
+    }
+
+    fn voice_count(&self) -> usize {
+        self.parts.iter().map(|p| p.voices.len()).sum()
+    }
+
+    /// Parts the MIDI channel setting asks for; never more than the voices.
+    fn part_count(&self) -> usize {
+        let parts = match self.midi.channels() {
+            MidiChannels::Multi => self.multi_parts,
+            MidiChannels::Omni | MidiChannels::Single(_) => 1,
+        };
+        parts.min(self.voice_count())
+    }
+
+    /// Part and index within it of a voice counted across all parts.
+    fn part_voice(&mut self, mut idx: usize) -> Option<(&mut VoiceAllocator, usize)> {
+        for part in &mut self.parts {
+            if idx < part.voices.len() {
+                return Some((part, idx));
+            }
+            idx -= part.voices.len();
+        }
+        None
+    }
+
+    /// Resize the voice pool. New voices copy the last voice's settings; the
+    /// graph is rebuilt because the config count changed.
+    fn set_polyphony(&mut self, count: usize) {
+        let mut pool = VoiceAllocator::from_voices(self.voices());
+        pool.set_voice_count(count);
+        let count = pool.voices.len();
+        self.parts = split_voices(pool.voices, self.part_count());
+        let template = self.voice_configs.last().cloned().unwrap_or_default();
+        self.voice_configs.resize(count, template);
+        self.voice_shared = self.voice_configs.iter().map(VoiceShared::new).collect();
+        self.polyphony = count;
+    }
+
     fn needs_rebuild(&self) -> bool {
         // Check if any voice's topology-changing params differ
         if let Some(ref active) = self.active_voice_configs {
@@ -175,32 +264,101 @@ impl SynthApp {
         self.active_effects_cfg.as_ref() != Some(&self.effects_cfg)
     }
 
-    fn dispatch_event(&mut self, event: NoteEvent) {
+    /// Play an event on a part. Test events name a voice across all parts.
+    fn dispatch_event(&mut self, part: usize, event: NoteEvent) {
+        // Channels past the last part are not heard
+        if part >= self.parts.len() {
+            return;
+        }
         match event {
-            NoteEvent::On { note, velocity } => self.allocator.note_on(note, velocity),
-            NoteEvent::Off { note } => self.allocator.note_off(note),
+            NoteEvent::On { note, velocity } => self.parts[part].note_on(note, velocity),
+            NoteEvent::Off { note } => self.parts[part].note_off(note),
             NoteEvent::TestOn { voice_idx, note, velocity } => {
-                self.allocator.force_note_on(voice_idx, note, velocity);
+                if let Some((allocator, idx)) = self.part_voice(voice_idx) {
+                    allocator.force_note_on(idx, note, velocity);
+                }
             }
             NoteEvent::TestOff { voice_idx } => {
-                self.allocator.force_note_off(voice_idx);
+                if let Some((allocator, idx)) = self.part_voice(voice_idx) {
+                    allocator.force_note_off(idx);
+                }
+            }
+            NoteEvent::ControlChange { controller: CC_MOD_WHEEL, value } => {
+                self.mod_wheel = value as f32 / 127.0;
+            }
+            // Pedals count as down from value 64
+            NoteEvent::ControlChange { controller: CC_SUSTAIN, value } => {
+                self.parts[part].set_sustain(value >= 64);
+            }
+            NoteEvent::ControlChange { controller: CC_SOSTENUTO, value } => {
+                self.parts[part].set_sostenuto(value >= 64);
             }
+            NoteEvent::PitchBend { value } => {
+                self.parts[part].pitch_bend(value as f32 / 8192.0);
+            }
+            // Program change picks a preset by its position in the list
+            NoteEvent::ProgramChange { program } => {
+                if let Some(name) = self.preset_names.get(program as usize) {
+                    self.current_preset = Some(name.clone());
+                }
+            }
+            NoteEvent::ControlChange { .. } | NoteEvent::ChannelPressure { .. } => {}
         }
     }
 
-    fn apply_preset(&mut self, preset: &Preset) {
-        // Apply preset settings to ALL 8 voices
-        let cfg = VoiceConfig {
-            waveform: preset.waveform,
-            adsr: preset.adsr,
-            filter_cfg: preset.filter_cfg,
-            cutoff: preset.cutoff,
-            resonance: preset.resonance,
-            lfo_cfg: preset.lfo_cfg,
-            lfo_rate: preset.lfo_rate,
-            lfo_depth: preset.lfo_depth,
-            level: 1.0,
+    /// Field and range of a MIDI-learnable parameter by `midi_learn` key.
+    fn learn_param_mut(&mut self, key: &str) -> Option<(&mut f32, f32, f32)> {
+        let fx = &mut self.effects_cfg;
+        let param = match key {
+            "synth.volume" => (&mut self.amplitude, 0.0, 1.0),
+            "synth.glide" => (&mut self.glide, 0.0, 2.0),
+            "synth.delay.time" => (&mut self.delay_time, 0.01, 2.0),
+            "synth.delay.feedback" => (&mut self.delay_feedback, 0.0, 0.99),
+            "synth.delay.mix" => (&mut self.delay_mix, 0.0, 1.0),
+            "synth.reverb.room" => (&mut fx.reverb_room_size, 1.0, 100.0),
+            "synth.reverb.time" => (&mut fx.reverb_time, 0.1, 10.0),
+            "synth.reverb.mix" => (&mut self.reverb_mix, 0.0, 1.0),
+            "synth.chorus.separation" => (&mut fx.chorus_separation, 0.0, 1.0),
+            "synth.chorus.variation" => (&mut fx.chorus_variation, 0.0, 1.0),
+            "synth.chorus.mod_freq" => (&mut fx.chorus_mod_freq, 0.1, 10.0),
+            "synth.chorus.mix" => (&mut self.chorus_mix, 0.0, 1.0),
+            _ => {
+                // synth.voice<N>.<param>, N counted from 1
+                let (voice, name) = key.strip_prefix("synth.voice")?.split_once('.')?;
+                let cfg = self.voice_configs.get_mut(voice.parse::<usize>().ok()?.checked_sub(1)?)?;
+                match name {
+                    "attack" => (&mut cfg.adsr.attack, 0.001, 2.0),
+                    "decay" => (&mut cfg.adsr.decay, 0.001, 2.0),
+                    "sustain" => (&mut cfg.adsr.sustain, 0.0, 1.0),
+                    "release" => (&mut cfg.adsr.release, 0.001, 5.0),
+                    "cutoff" => (&mut cfg.cutoff, 20.0, 20000.0),
+                    "resonance" => (&mut cfg.resonance, 0.0, 1.0),
+                    "filter_env_amount" => {
+                        (&mut cfg.filter_env_amount, -MAX_FILTER_ENV_OCTAVES, MAX_FILTER_ENV_OCTAVES)
+                    }
+                    "filter_attack" => (&mut cfg.filter_env.attack, 0.001, 2.0),
+                    "filter_decay" => (&mut cfg.filter_env.decay, 0.001, 2.0),
+                    "filter_sustain" => (&mut cfg.filter_env.sustain, 0.0, 1.0),
+                    "filter_release" => (&mut cfg.filter_env.release, 0.001, 5.0),
+                    "lfo_rate" => (&mut cfg.lfo_rate, 0.1, 20.0),
+                    "lfo_depth" => (&mut cfg.lfo_depth, 0.0, 1.0),
+                    "level" => (&mut cfg.level, 0.0, 1.0),
+                    "pulse_width" => (&mut cfg.pulse_width, 0.05, 0.95),
+                    "wavetable_position" => (&mut cfg.wavetable_position, 0.0, 1.0),
+                    "osc2_fine" => (&mut cfg.osc2.fine, -100.0, 100.0),
+                    "osc2_level" => (&mut cfg.osc2.level, 0.0, 1.0),
+                    "fm_ratio" => (&mut cfg.fm.ratio, 0.5, 16.0),
+                    "fm_index" => (&mut cfg.fm.index, 0.0, 10.0),
+                    _ => return None,
+                }
+            }
         };
+        Some(param)
+    }
+
+    fn apply_preset(&mut self, preset: &Preset) {
+        // Apply preset settings to every voice
+        let cfg = preset.voice_config();
         for vc in &mut self.voice_configs {
             *vc = cfg.clone();
         }
@@ -248,8 +406,23 @@ impl SynthApp {
 impl eframe::App for SynthApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         // Process MIDI events
-        while let Some(event) = self.midi.try_recv() {
-            self.dispatch_event(event);
+        while let Some((channel, event)) = self.midi.try_recv() {
+            if let NoteEvent::ControlChange { controller, value } = event {
+                for (key, amount) in midi_learn::handle_cc(ctx, controller, value) {
+                    midi_learn::apply(self.learn_param_mut(&key), amount);
+                }
+            }
+            // In multitimbral mode each channel plays its own part
+            let part = match self.midi.channels() {
+                MidiChannels::Multi => channel as usize,
+                MidiChannels::Omni | MidiChannels::Single(_) => 0,
+            };
+            self.dispatch_event(part, event);
+        }
+
+        // Voices whose release the graph has finished are free again
+        for part in &mut self.parts {
+            part.reap();
         }
 
         // Auto-start playing on first frame
@@ -267,12 +440,22 @@ impl eframe::App for SynthApp {
             delay_mix: &mut self.delay_mix,
             reverb_mix: &mut self.reverb_mix,
             chorus_mix: &mut self.chorus_mix,
-            allocator: &self.allocator,
+            parts: &self.parts,
+            polyphony: &mut self.polyphony,
+            multi_parts: &mut self.multi_parts,
+            steal_policy: &mut self.steal_policy,
+            voice_mode: &mut self.voice_mode,
+            note_priority: &mut self.note_priority,
+            glide: &mut self.glide,
             snoop_left: &mut self.snoop_left,
             snoop_right: &mut self.snoop_right,
             midi: &mut self.midi,
+            midi_out: &mut self.midi_out,
+            midi_out_channel: &mut self.midi_out_channel,
+            bend_range: &mut self.bend_range,
             playing: self.playing,
             preset_names: &self.preset_names,
+            wavetable_names: &self.wavetable_names,
             current_preset: &mut self.current_preset,
             save_name: &mut self.save_name,
         };
@@ -280,9 +463,13 @@ impl eframe::App for SynthApp {
         // Render UI
         let events = self.synth_ui.show(ctx, &mut params);
 
-        // Dispatch note events from UI
+        // Dispatch note events from UI (the first part, as on channel 1);
+        // keyboard notes also go to the MIDI output
         for event in events {
-            self.dispatch_event(event);
+            if let NoteEvent::On { .. } | NoteEvent::Off { .. } = event {
+                self.midi_out.send_event(self.midi_out_channel - 1, event);
+            }
+            self.dispatch_event(0, event);
         }
 
         // Handle preset selection change
@@ -296,14 +483,7 @@ impl eframe::App for SynthApp {
                     }
                     self.save_name.clear();
                 } else {
-                    let preset = Preset::factory_presets()
-                        .into_iter()
-                        .find(|p| p.name == *name)
-                        .or_else(|| {
-                            let path = Preset::presets_dir().join(format!("{name}.json"));
-                            Preset::load(&path).ok()
-                        });
-                    if let Some(p) = preset {
+                    if let Some(p) = Preset::find(name) {
                         self.apply_preset(&p);
                     }
                 }
@@ -313,8 +493,24 @@ impl eframe::App for SynthApp {
 
         // Sync continuous parameters to Shared atomics
         self.master_amp.set_value(self.amplitude);
+        if self.polyphony != self.voice_count() {
+            self.set_polyphony(self.polyphony);
+        }
+        if self.parts.len() != self.part_count() {
+            self.parts = split_voices(self.voices(), self.part_count());
+        }
+        for part in &mut self.parts {
+            part.set_steal_policy(self.steal_policy);
+            part.set_mode(self.voice_mode);
+            part.set_priority(self.note_priority);
+            part.set_bend_range(self.bend_range);
+        }
+        for vc in &mut self.voice_configs {
+            vc.glide = self.glide;
+        }
         for (i, shared) in self.voice_shared.iter().enumerate() {
             shared.sync(&self.voice_configs[i]);
+            shared.apply_mod_wheel(&self.voice_configs[i], self.mod_wheel);
         }
         self.delay_time_shared.set_value(self.delay_time);
         self.delay_feedback_shared.set_value(self.delay_feedback);
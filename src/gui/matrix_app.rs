@@ -1,22 +1,79 @@
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use eframe::egui;
 use fundsp::shared::Shared;
 
 use crate::engine;
-use crate::engine::drum_sample::{load_drum_kit, SampleDrumVoiceShared};
+use crate::engine::drum::{
+    DrumEngine, DrumParams, DRUM_KIT, DRUM_KIT_DANCE, DRUM_KIT_ELECTRONIC, DRUM_KIT_JAZZ,
+    DRUM_KIT_LATIN, DRUM_KIT_ROCK,
+};
+use crate::engine::drum_sample::load_drum_kit;
 use crate::engine::effects::EffectsConfig;
 use crate::engine::filter::{FilterConfig, FilterType, LfoConfig, LfoTarget, LfoWaveform};
 use crate::engine::oscillator::{AdsrParams, Waveform};
+use crate::engine::render::OfflineRenderer;
 use crate::engine::matrix::build_matrix_graph;
-use crate::engine::voice::{VoiceAllocator, VoiceConfig, VoiceShared};
-use crate::matrix_synth::state::{self as ts, BassPreset, DrumPreset};
-use crate::matrix_synth::MatrixSynth;
+use crate::engine::sequencer::{
+    sequencer_channel, SeqCommand, SeqControl, SeqEvent, SeqPattern, SeqStatus, Sequencer, SongEntry, StepNote,
+    Track, PPQ,
+};
+use crate::engine::voice::{
+    FmConfig, MAX_FILTER_ENV_OCTAVES, MAX_VOICES, Osc2Config, StealPolicy, Voice, VoiceConfig, VoiceEngine, VoiceMode, VoiceShared,
+};
+use crate::engine::wavetable::Wavetable;
+use crate::matrix_synth::state::{
+    self as ts, BassPreset, ChannelMode, DrumPreset, MatrixState, PatternSlot, VoiceSteal,
+};
+use crate::matrix_synth::project::Project;
+use crate::matrix_synth::midi_mirror::MidiMirror;
+use crate::matrix_synth::{smf, MatrixSynth};
+use crate::midi::{MidiChannels, NoteEvent, CC_MOD_WHEEL};
+use crate::midi_learn::{self, MidiMap};
 
 use cpal::{Device, SupportedStreamConfig};
 
+/// Voices each channel starts with; the drums always use this many.
 const NUM_VOICES: usize = 8;
 
+fn engine_waveform(waveform: ts::Waveform) -> Waveform {
+    match waveform {
+        ts::Waveform::Sine => Waveform::Sine,
+        ts::Waveform::Saw => Waveform::Saw,
+        ts::Waveform::Square => Waveform::Square,
+        ts::Waveform::Tri => Waveform::Triangle,
+        ts::Waveform::Pulse => Waveform::Pulse,
+        ts::Waveform::Super => Waveform::Supersaw,
+        ts::Waveform::Noise => Waveform::Noise,
+        ts::Waveform::Pink => Waveform::PinkNoise,
+        ts::Waveform::Table => Waveform::Wavetable,
+    }
+}
+
+fn steal_policy(steal: VoiceSteal) -> StealPolicy {
+    match steal {
+        VoiceSteal::Oldest => StealPolicy::Oldest,
+        VoiceSteal::Quietest => StealPolicy::Quietest,
+        VoiceSteal::Lowest => StealPolicy::Lowest,
+        VoiceSteal::Highest => StealPolicy::Highest,
+        VoiceSteal::None => StealPolicy::None,
+    }
+}
+
+/// Synthesized kit parameters for a drum preset; `None` for WAV kits.
+fn synth_drum_kit(preset: DrumPreset) -> Option<&'static [DrumParams; 16]> {
+    match preset {
+        DrumPreset::SynthStandard => Some(&DRUM_KIT),
+        DrumPreset::SynthRock => Some(&DRUM_KIT_ROCK),
+        DrumPreset::SynthJazz => Some(&DRUM_KIT_JAZZ),
+        DrumPreset::SynthDance => Some(&DRUM_KIT_DANCE),
+        DrumPreset::SynthElectronic => Some(&DRUM_KIT_ELECTRONIC),
+        DrumPreset::SynthLatin => Some(&DRUM_KIT_LATIN),
+        DrumPreset::LinnDrum | DrumPreset::TR505 | DrumPreset::CR78 => None,
+    }
+}
+
 fn bass_preset_to_config(preset: BassPreset) -> VoiceConfig {
     let (waveform, attack, decay, sustain, release, cutoff, resonance, lfo_enabled, lfo_rate, lfo_depth) =
         match preset {
@@ -63,9 +120,295 @@ fn bass_preset_to_config(preset: BassPreset) -> VoiceConfig {
         lfo_rate,
         lfo_depth,
         level: 1.0,
+        ..VoiceConfig::default()
+    }
+}
+
+/// Collect the notes each played grid column sounds on one channel.
+fn track_steps(
+    pattern: &PatternSlot,
+    mute: &[bool; ts::ROWS],
+    volume: &[f32; ts::ROWS],
+    row_note: impl Fn(usize) -> u8,
+) -> Vec<Vec<StepNote>> {
+    let grid = &pattern.cells;
+    (0..pattern.steps.clamp(1, ts::MAX_STEPS))
+        .map(|col| {
+            (0..ts::ROWS)
+                .filter(|&row| grid[row][col] && !mute[row])
+                .map(|row| {
+                    let params = pattern.params[row][col];
+                    let velocity = (params.velocity as f32 * volume[row]) as u8;
+                    StepNote {
+                        length: params.length.clamp(0.1, ts::MAX_NOTE_LENGTH),
+                        probability: params.probability.min(100),
+                        slide: params.slide,
+                        ..StepNote::new(row_note(row), velocity.clamp(1, 127))
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Snapshot the Matrix grids into the pattern played by the audio-thread sequencer.
+fn pattern_from_matrix(state: &MatrixState) -> SeqPattern {
+    pattern_from_slots(state, state.pattern_slot)
+}
+
+/// Pattern made of the given slot of each channel bank (lead, drums, bass).
+fn pattern_from_slots(state: &MatrixState, slots: [usize; 3]) -> SeqPattern {
+    let tune = (state.drum_tune - 50.0) / 50.0; // -1.0 .. +1.0
+    let [lead, drums, bass] =
+        ChannelMode::ALL.map(|mode| state.slot_pattern(mode, slots[mode.index()]));
+    SeqPattern {
+        step_ticks: [&lead, &drums, &bass].map(|p| PPQ / p.resolution.steps_per_beat()),
+        steps: [
+            track_steps(&lead, &state.lead_row_mute, &state.lead_row_volume, |row| {
+                state.row_to_midi(row)
+            }),
+            track_steps(&drums, &state.drum_row_mute, &state.drum_row_volume, |row| {
+                row as u8
+            }),
+            track_steps(&bass, &state.bass_row_mute, &state.bass_row_volume, |row| {
+                state.row_to_bass_midi(row)
+            }),
+        ],
+        bpm: state.bpm,
+        swing: state.swing,
+        drum_pitch_ratio: (1.0 + tune * 0.5).clamp(0.5, 2.0),
+        modes: [
+            VoiceMode::Poly,
+            VoiceMode::Poly,
+            if state.bass_mono { VoiceMode::Legato } else { VoiceMode::Poly },
+        ],
+        steal: [
+            steal_policy(state.lead_steal),
+            StealPolicy::Oldest,
+            steal_policy(state.bass_steal),
+        ],
+    }
+}
+
+/// Song chain sent to the sequencer; empty outside song mode.
+fn song_from_matrix(state: &MatrixState) -> Vec<SongEntry> {
+    if !state.song_mode {
+        return Vec::new();
+    }
+    state
+        .song
+        .iter()
+        .map(|step| SongEntry {
+            pattern: pattern_from_slots(state, [step.slot; 3]),
+            repeats: step.repeats,
+        })
+        .collect()
+}
+
+/// Lead voice config for the Matrix synth knobs.
+fn lead_config(state: &MatrixState, wavetable: Option<Arc<Wavetable>>) -> VoiceConfig {
+    let waveform = engine_waveform(state.osc_waveform);
+
+    let attack = (state.env_attack / 100.0 * 2.0).max(0.001);
+    let decay = (state.env_decay / 100.0 * 2.0).max(0.001);
+    let sustain = state.env_sustain / 100.0;
+    let release = (state.env_release / 100.0 * 2.0).max(0.001);
+
+    let adsr = AdsrParams {
+        attack,
+        decay,
+        sustain,
+        release,
+    };
+
+    let filter_type = match state.filter_type {
+        ts::FilterType::LP => FilterType::Lowpass,
+        ts::FilterType::HP => FilterType::Highpass,
+        ts::FilterType::BP => FilterType::Bandpass,
+    };
+
+    let cutoff_t = state.filter_cutoff / 100.0;
+    let cutoff = 20.0 * (1000.0_f32).powf(cutoff_t);
+    let resonance = state.filter_reso / 100.0;
+
+    let filter_cfg = FilterConfig {
+        filter_type,
+        enabled: true,
+    };
+
+    // Filter envelope: same time scale as the amp envelope; Amount ±100 is ±6 octaves
+    let filter_env = AdsrParams {
+        attack: (state.filter_env_attack / 100.0 * 2.0).max(0.001),
+        decay: (state.filter_env_decay / 100.0 * 2.0).max(0.001),
+        sustain: state.filter_env_sustain / 100.0,
+        release: (state.filter_env_release / 100.0 * 2.0).max(0.001),
+    };
+    let filter_env_amount = state.filter_env_amount / 100.0 * MAX_FILTER_ENV_OCTAVES;
+
+    let lfo_target = match state.lfo_dest {
+        ts::LfoDest::Pitch => LfoTarget::Frequency,
+        ts::LfoDest::Filter => LfoTarget::Cutoff,
+        ts::LfoDest::Amp => LfoTarget::Amplitude,
+        ts::LfoDest::Width => LfoTarget::PulseWidth,
+        ts::LfoDest::Position => LfoTarget::WavetablePosition,
+    };
+
+    let lfo_rate = state.lfo_rate / 100.0 * 20.0;
+    let lfo_depth = state.lfo_depth / 100.0;
+
+    let lfo_cfg = LfoConfig {
+        waveform: LfoWaveform::Sine,
+        target: lfo_target,
+        enabled: state.lfo_depth > 1.0,
+    };
+
+    // Pitch knob: ±24 semitones around the center; Detune: 0–100 cents.
+    // Undetuned copies would only cost CPU and phase-cancel, so the stack is
+    // built once Detune leaves zero (a single rebuild, not one per move).
+    let pitch = ((state.osc_pitch - 50.0) / 50.0 * 24.0).round();
+    let detune = state.osc_detune;
+    let unison = if detune > 0.0 { state.osc_unison } else { 1 };
+    let spread = state.osc_spread / 100.0;
+
+    VoiceConfig {
+        waveform,
+        adsr,
+        filter_cfg,
+        cutoff,
+        resonance,
+        filter_env,
+        filter_env_amount,
+        lfo_cfg,
+        lfo_rate,
+        lfo_depth,
+        level: 1.0,
+        pitch,
+        detune,
+        unison,
+        spread,
+        glide: 0.0,
+        pulse_width: state.osc_width / 100.0,
+        wavetable,
+        wavetable_position: state.osc_position / 100.0,
+        osc2: Osc2Config {
+            enabled: state.osc2_enabled,
+            waveform: engine_waveform(state.osc2_waveform),
+            octave: state.osc2_octave,
+            semitone: state.osc2_semitone,
+            fine: state.osc2_fine,
+            level: state.osc2_level / 100.0,
+            sync: state.osc2_sync,
+            ring: state.osc2_ring,
+        },
+        engine: if state.fm_enabled { VoiceEngine::Fm } else { VoiceEngine::Subtractive },
+        // FM index envelope: instant attack, Decay 0–2 s, release with the amp envelope
+        fm: FmConfig {
+            ratio: state.fm_ratio,
+            index: state.fm_index / 100.0 * 10.0,
+            index_env: AdsrParams {
+                attack: 0.001,
+                decay: (state.fm_decay / 100.0 * 2.0).max(0.001),
+                sustain: state.fm_sustain / 100.0,
+                release,
+            },
+        },
+    }
+}
+
+/// Bass voice config: the preset, with Glide giving 0–500 ms slides.
+fn bass_config(state: &MatrixState) -> VoiceConfig {
+    VoiceConfig {
+        glide: state.bass_glide / 100.0 * 0.5,
+        ..bass_preset_to_config(state.bass_preset)
     }
 }
 
+/// Drum Decay: 30 ms .. 3 s to -60 dB, fully open plays whole samples.
+/// Drum Color: centered knob tilts from dark (lowpass) to bright (shelf).
+fn drum_shape(state: &MatrixState) -> (f32, f32) {
+    let decay = if state.drum_decay >= 99.5 {
+        f32::INFINITY
+    } else {
+        0.03 * 100.0_f32.powf(state.drum_decay / 100.0)
+    };
+    (decay, (state.drum_color - 50.0) / 50.0)
+}
+
+/// Delay, reverb and chorus mix levels (0–1).
+fn fx_mixes(state: &MatrixState) -> [f32; 3] {
+    [state.fx_delay, state.fx_reverb, state.fx_chorus].map(|knob| knob / 100.0)
+}
+
+/// Build an effect into the graph only while its mix is turned up.
+fn enable_effects(cfg: &mut EffectsConfig, [delay, reverb, chorus]: [f32; 3]) {
+    cfg.delay_enabled = delay > 0.01;
+    cfg.reverb_enabled = reverb > 0.01;
+    cfg.chorus_enabled = chorus > 0.01;
+}
+
+/// Lead wavetable file by name, or `None` (the built-in table) if it fails to load.
+fn load_wavetable(name: &str) -> Option<Arc<Wavetable>> {
+    Wavetable::load(&Wavetable::path_for(name))
+        .inspect_err(|err| eprintln!("failed to read wavetable {name}: {err}"))
+        .ok()
+        .map(Arc::new)
+}
+
+/// Render a Matrix project offline: the voices, drum kit, effects and
+/// sequencer the Matrix window builds, with the transport started from the
+/// first step (and song entry, in song mode).
+pub fn render_matrix(state: &MatrixState, sample_rate: u32, secs: f32) -> Vec<(f32, f32)> {
+    let voices = |count: usize| -> Vec<Voice> { (0..count.clamp(1, MAX_VOICES)).map(|_| Voice::new()).collect() };
+    let (lead_voices, drum_voices, bass_voices) =
+        (voices(state.lead_voices), voices(NUM_VOICES), voices(state.bass_voices));
+
+    let wavetable = state.osc_wavetable.as_deref().and_then(load_wavetable);
+    let lead_configs = vec![lead_config(state, wavetable); lead_voices.len()];
+    let lead_shared: Vec<VoiceShared> = lead_configs.iter().map(VoiceShared::new).collect();
+    let bass_configs = vec![bass_config(state); bass_voices.len()];
+    let bass_shared: Vec<VoiceShared> = bass_configs.iter().map(VoiceShared::new).collect();
+
+    let drums = match synth_drum_kit(state.drum_preset) {
+        Some(kit) => DrumEngine::synth(NUM_VOICES, kit),
+        None => {
+            let dir = state.drum_preset.dir_name().unwrap_or_default();
+            DrumEngine::sample(NUM_VOICES, &load_drum_kit(dir, sample_rate as f64))
+        }
+    };
+    let (drum_decay, drum_color) = drum_shape(state);
+    drums.set_shape(drum_decay, drum_color);
+
+    let mut sequencer = Sequencer::new(pattern_from_matrix(state));
+    sequencer.set_song(song_from_matrix(state));
+    sequencer.start(0);
+    let (_commands, port) = sequencer_channel(sequencer, &SeqStatus::new());
+
+    let mixes = fx_mixes(state);
+    let mut effects_cfg = EffectsConfig::default();
+    enable_effects(&mut effects_cfg, mixes);
+    let [delay_mix, reverb_mix, chorus_mix] = mixes.map(Shared::new);
+
+    let (graph, _, _) = build_matrix_graph(
+        &lead_voices,
+        &lead_configs,
+        &lead_shared,
+        &drum_voices,
+        &drums,
+        &bass_voices,
+        &bass_configs,
+        &bass_shared,
+        port,
+        &Shared::new(0.5),
+        &effects_cfg,
+        &Shared::new(0.3),
+        &Shared::new(0.3),
+        &delay_mix,
+        &reverb_mix,
+        &chorus_mix,
+    );
+    OfflineRenderer::new(graph, sample_rate).render_secs(secs)
+}
+
 pub struct MatrixApp {
     matrix: MatrixSynth,
 
@@ -76,20 +419,24 @@ pub struct MatrixApp {
     playing: bool,
 
     // Lead audio state (always active)
-    allocator: VoiceAllocator,
+    voices: Vec<Voice>,
     voice_configs: Vec<VoiceConfig>,
     voice_shared: Vec<VoiceShared>,
     master_amp: Shared,
     amplitude: f32,
-
-    // Drum audio state (always active, sample-based)
-    drum_allocator: VoiceAllocator,
-    drum_shared: Vec<SampleDrumVoiceShared>,
+    /// Lead wavetable file last requested by the Matrix, and its loaded table
+    /// (`None` plays the built-in table).
+    wavetable_name: Option<String>,
+    wavetable: Option<Arc<Wavetable>>,
+
+    // Drum audio state (always active, sampled or synthesized kit)
+    drum_voices: Vec<Voice>,
+    drums: DrumEngine,
     drum_buffers: Arc<Vec<Vec<f32>>>,
     active_drum_preset: Option<DrumPreset>,
 
     // Bass audio state (always active)
-    bass_allocator: VoiceAllocator,
+    bass_voices: Vec<Voice>,
     bass_configs: Vec<VoiceConfig>,
     bass_shared: Vec<VoiceShared>,
 
@@ -106,15 +453,27 @@ pub struct MatrixApp {
     active_bass_configs: Option<Vec<VoiceConfig>>,
     active_effects_cfg: Option<EffectsConfig>,
 
-    // Track previous step for note triggering
-    prev_step: i32,
-    prev_active_notes: Vec<u8>,
-    prev_active_drums: Vec<u8>,
-    prev_active_bass: Vec<u8>,
+    // Sequencer (runs on the audio thread; the UI only reads back the step)
+    seq_commands: Option<SeqControl>,
+    seq_status: SeqStatus,
+    seq_pattern: SeqPattern,
+    seq_song: Vec<SongEntry>,
+    seq_queued: Option<SeqPattern>,
+    seq_playing: bool,
+    /// Live pitch wheel (-1.0 to 1.0) and mod wheel (0.0 to 1.0) of each
+    /// channel, by `ChannelMode::index()`.
+    bend: [f32; 3],
+    mod_wheel: [f32; 3],
+    /// MIDI out channel of each sequencer track for the mirror thread, -1
+    /// when the track is not sent.
+    midi_out_channels: [Shared; 3],
+    /// Sends each graph's notes to the MIDI output and releases them when
+    /// the graph is rebuilt.
+    mirror: MidiMirror,
 }
 
 impl MatrixApp {
-    pub fn new(_cc: &eframe::CreationContext) -> Self {
+    pub fn new(cc: &eframe::CreationContext, project: Option<(PathBuf, Project)>) -> Self {
         let (device, supported_config) = engine::init_audio_device();
         let output_sr = supported_config.sample_rate() as f64;
         let voice_configs: Vec<VoiceConfig> =
@@ -124,25 +483,39 @@ impl MatrixApp {
             (0..NUM_VOICES).map(|_| bass_preset_to_config(BassPreset::SubBass)).collect();
         let bass_shared: Vec<VoiceShared> = bass_configs.iter().map(VoiceShared::new).collect();
 
-        let default_preset = DrumPreset::LinnDrum;
-        let drum_buffers = load_drum_kit(default_preset.dir_name(), output_sr);
+        let map_path = MidiMap::path();
+        midi_learn::install(&cc.egui_ctx, MidiMap::load_or_default(&map_path), Some(map_path));
+
+        let mut matrix = MatrixSynth::new();
+        if let Some((path, project)) = project {
+            matrix.open_project(path, project);
+        }
+
+        let drum_buffers = matrix
+            .state()
+            .drum_preset
+            .dir_name()
+            .map_or_else(|| Arc::new(vec![Vec::new(); 16]), |dir| load_drum_kit(dir, output_sr));
+
+        let midi_out_channels: [Shared; 3] = std::array::from_fn(|_| Shared::new(-1.0));
+        let mirror = MidiMirror::spawn(matrix.midi_out.sender(), midi_out_channels.clone());
 
         Self {
-            matrix: MatrixSynth::new(),
+            matrix,
             device,
             supported_config,
             stream: None,
             playing: false,
-            allocator: VoiceAllocator::new(NUM_VOICES),
+            voices: (0..NUM_VOICES).map(|_| Voice::new()).collect(),
             voice_configs,
             voice_shared,
             master_amp: Shared::new(0.5),
             amplitude: 0.5,
-            drum_allocator: VoiceAllocator::new(NUM_VOICES),
-            drum_shared: (0..NUM_VOICES).map(|_| SampleDrumVoiceShared::new()).collect(),
+            drum_voices: (0..NUM_VOICES).map(|_| Voice::new()).collect(),
+            drums: DrumEngine::sample(NUM_VOICES, &drum_buffers),
             drum_buffers,
             active_drum_preset: None,
-            bass_allocator: VoiceAllocator::new(NUM_VOICES),
+            bass_voices: (0..NUM_VOICES).map(|_| Voice::new()).collect(),
             bass_configs,
             bass_shared,
             effects_cfg: EffectsConfig::default(),
@@ -154,10 +527,18 @@ impl MatrixApp {
             active_voice_configs: None,
             active_bass_configs: None,
             active_effects_cfg: None,
-            prev_step: -1,
-            prev_active_notes: Vec::new(),
-            prev_active_drums: Vec::new(),
-            prev_active_bass: Vec::new(),
+            wavetable_name: None,
+            wavetable: None,
+            seq_commands: None,
+            seq_status: SeqStatus::new(),
+            seq_pattern: SeqPattern::default(),
+            seq_song: Vec::new(),
+            seq_queued: None,
+            seq_playing: false,
+            bend: [0.0; 3],
+            mod_wheel: [0.0; 3],
+            midi_out_channels,
+            mirror,
         }
     }
 
@@ -166,24 +547,60 @@ impl MatrixApp {
         self.playing = true;
     }
 
-    /// Build a combined graph with 8 lead + 8 drum + 8 bass voices playing simultaneously.
+    /// Build a combined graph with the lead, drum and bass voices playing simultaneously.
     fn rebuild_stream(&mut self) {
+        // Dropping the graph disconnects its mirror channel: once the events
+        // already sent are out, the mirror releases the notes it still held.
         self.stream = None;
 
         self.voice_shared = self.voice_configs.iter().map(VoiceShared::new).collect();
-        self.drum_shared = (0..NUM_VOICES).map(|_| SampleDrumVoiceShared::new()).collect();
+        self.drums = match synth_drum_kit(self.matrix.state().drum_preset) {
+            Some(kit) => DrumEngine::synth(NUM_VOICES, kit),
+            None => DrumEngine::sample(NUM_VOICES, &self.drum_buffers),
+        };
         self.bass_shared = self.bass_configs.iter().map(VoiceShared::new).collect();
 
+        // The new sequencer resumes on the step after the one last played,
+        // in the same song entry. Pending slot switches happen now.
+        self.matrix.state_mut().apply_pending_slots();
+        self.seq_queued = None;
+        self.seq_status.queued.set_value(0.0);
+        self.seq_pattern = pattern_from_matrix(self.matrix.state());
+        self.seq_song = song_from_matrix(self.matrix.state());
+        self.seq_playing = self.matrix.state().is_playing;
+        let mut sequencer = Sequencer::new(self.seq_pattern.clone());
+        sequencer.set_song(self.seq_song.clone());
+        if self.seq_playing {
+            let entry = self.seq_status.song_entry.value();
+            if entry >= 0.0 {
+                sequencer.seek_song(entry as usize);
+            }
+            let steps = self.seq_status.steps.each_ref().map(|step| {
+                let step = step.value();
+                if step >= 0.0 { step as usize + 1 } else { 0 }
+            });
+            sequencer.start_tracks(steps);
+        }
+        let (commands, port) = sequencer_channel(sequencer, &self.seq_status);
+        // The new voices start where the pitch wheels are now
+        for (track, amount) in Track::ALL.into_iter().zip(self.bend) {
+            if amount != 0.0 {
+                let _ = commands.send(SeqCommand::PitchBend { track, amount });
+            }
+        }
+        let _ = commands.send(SeqCommand::Mirror(Some(self.mirror.connect())));
+        self.seq_commands = Some(commands);
+
         let (graph, _, _) = build_matrix_graph(
-            &self.allocator.voices,
+            &self.voices,
             &self.voice_configs,
             &self.voice_shared,
-            &self.drum_allocator.voices,
-            &self.drum_shared,
-            &self.drum_buffers,
-            &self.bass_allocator.voices,
+            &self.drum_voices,
+            &self.drums,
+            &self.bass_voices,
             &self.bass_configs,
             &self.bass_shared,
+            port,
             &self.master_amp,
             &self.effects_cfg,
             &self.delay_time_shared,
@@ -235,7 +652,7 @@ impl MatrixApp {
             return true;
         }
 
-        // Drum preset change triggers rebuild (new sample buffers)
+        // Drum preset change triggers rebuild (new sample buffers or synth kit)
         if self.active_drum_preset != Some(self.matrix.state().drum_preset) {
             return true;
         }
@@ -243,188 +660,193 @@ impl MatrixApp {
         false
     }
 
+    /// Load the lead wavetable when the Matrix picks another file. A file that
+    /// fails to load is reported once and plays the built-in table.
+    fn sync_wavetable(&mut self) {
+        let name = &self.matrix.state().osc_wavetable;
+        if *name == self.wavetable_name {
+            return;
+        }
+        self.wavetable_name = name.clone();
+        self.wavetable = name.as_deref().and_then(load_wavetable);
+    }
+
     /// Map MatrixState synth parameters to engine VoiceConfig.
     /// Lead params are always synced (graph always active). Effects sync always runs.
     fn sync_voice_configs_from_matrix(&mut self) {
+        self.sync_wavetable();
         let state = self.matrix.state();
 
-        // Always sync lead params (lead graph always active)
-        let waveform = match state.osc_waveform {
-            ts::Waveform::Sine => Waveform::Sine,
-            ts::Waveform::Saw => Waveform::Saw,
-            ts::Waveform::Square => Waveform::Square,
-            ts::Waveform::Tri => Waveform::Triangle,
-        };
-
-        let attack = (state.env_attack / 100.0 * 2.0).max(0.001);
-        let decay = (state.env_decay / 100.0 * 2.0).max(0.001);
-        let sustain = state.env_sustain / 100.0;
-        let release = (state.env_release / 100.0 * 2.0).max(0.001);
-
-        let adsr = AdsrParams {
-            attack,
-            decay,
-            sustain,
-            release,
-        };
-
-        let filter_type = match state.filter_type {
-            ts::FilterType::LP => FilterType::Lowpass,
-            ts::FilterType::HP => FilterType::Highpass,
-            ts::FilterType::BP => FilterType::Bandpass,
-        };
-
-        let cutoff_t = state.filter_cutoff / 100.0;
-        let cutoff = 20.0 * (1000.0_f32).powf(cutoff_t);
-        let resonance = state.filter_reso / 100.0;
-
-        let filter_cfg = FilterConfig {
-            filter_type,
-            enabled: true,
-        };
-
-        let lfo_target = match state.lfo_dest {
-            ts::LfoDest::Pitch => LfoTarget::Frequency,
-            ts::LfoDest::Filter => LfoTarget::Cutoff,
-            ts::LfoDest::Amp => LfoTarget::Amplitude,
-        };
-
-        let lfo_rate = state.lfo_rate / 100.0 * 20.0;
-        let lfo_depth = state.lfo_depth / 100.0;
-
-        let lfo_cfg = LfoConfig {
-            waveform: LfoWaveform::Sine,
-            target: lfo_target,
-            enabled: state.lfo_depth > 1.0,
-        };
-
-        let cfg = VoiceConfig {
-            waveform,
-            adsr,
-            filter_cfg,
-            cutoff,
-            resonance,
-            lfo_cfg,
-            lfo_rate,
-            lfo_depth,
-            level: 1.0,
-        };
+        let cfg = lead_config(state, self.wavetable.clone());
 
+        // A new voice count changes the config count, which rebuilds the graph
+        let lead_count = state.lead_voices.clamp(1, MAX_VOICES);
+        self.voices.resize_with(lead_count, Voice::new);
+        self.voice_configs.resize(lead_count, cfg.clone());
         for vc in &mut self.voice_configs {
             *vc = cfg.clone();
         }
 
-        // Bass preset sync (always active)
-        let bass_cfg = bass_preset_to_config(state.bass_preset);
+        let bass_cfg = bass_config(state);
+        let bass_count = state.bass_voices.clamp(1, MAX_VOICES);
+        self.bass_voices.resize_with(bass_count, Voice::new);
+        self.bass_configs.resize(bass_count, bass_cfg.clone());
         for vc in &mut self.bass_configs {
             *vc = bass_cfg.clone();
         }
 
-        // Reload drum samples if preset changed (before graph rebuild)
-        if self.active_drum_preset != Some(state.drum_preset) {
+        // Reload drum samples if preset changed (before graph rebuild).
+        // Synthesized kits need no samples.
+        if self.active_drum_preset != Some(state.drum_preset)
+            && let Some(dir) = state.drum_preset.dir_name()
+        {
             let output_sr = self.supported_config.sample_rate() as f64;
-            self.drum_buffers = load_drum_kit(state.drum_preset.dir_name(), output_sr);
+            self.drum_buffers = load_drum_kit(dir, output_sr);
         }
 
-        // Effects sync (always)
-        let delay_mix = state.fx_delay / 100.0;
-        let reverb_mix = state.fx_reverb / 100.0;
-        let chorus_mix = state.fx_chorus / 100.0;
+        let (drum_decay, drum_color) = drum_shape(state);
+        self.drums.set_shape(drum_decay, drum_color);
 
-        self.effects_cfg.delay_enabled = delay_mix > 0.01;
-        self.effects_cfg.reverb_enabled = reverb_mix > 0.01;
-        self.effects_cfg.chorus_enabled = chorus_mix > 0.01;
+        // Effects sync (always)
+        let mixes = fx_mixes(state);
+        enable_effects(&mut self.effects_cfg, mixes);
+        let [delay_mix, reverb_mix, chorus_mix] = mixes;
 
         self.delay_mix_shared.set_value(delay_mix);
         self.reverb_mix_shared.set_value(reverb_mix);
         self.chorus_mix_shared.set_value(chorus_mix);
     }
 
-    /// Handle playhead step changes: trigger BOTH lead and drum notes simultaneously.
-    fn handle_step_change(&mut self) {
-        let state = self.matrix.state();
-        let current_step = state.play_col;
+    /// Play a live MIDI note on a Matrix channel through the sequencer, so
+    /// it shares the channel's voices. Drum keys pick rows by their General
+    /// MIDI sound. The pitch wheel bends the lead and bass voices and the
+    /// mod wheel deepens their LFO; channel pressure is ignored, as in the
+    /// Synthwave.
+    fn play_live(&mut self, mode: ChannelMode, event: NoteEvent) {
+        let Some(commands) = &self.seq_commands else {
+            return;
+        };
+        let track = Track::ALL[mode.index()];
+        let (key, velocity) = match event {
+            NoteEvent::On { note, velocity } => (note, Some(velocity)),
+            NoteEvent::Off { note } => (note, None),
+            NoteEvent::PitchBend { value } if mode != ChannelMode::Drummer => {
+                let amount = value as f32 / 8192.0;
+                self.bend[mode.index()] = amount;
+                let _ = commands.send(SeqCommand::PitchBend { track, amount });
+                return;
+            }
+            NoteEvent::ControlChange { controller: CC_MOD_WHEEL, value } => {
+                self.mod_wheel[mode.index()] = value as f32 / 127.0;
+                return;
+            }
+            _ => return,
+        };
+        let note = match mode {
+            ChannelMode::Drummer => match smf::drum_row(key) {
+                Some(row) => row as u8,
+                None => return,
+            },
+            ChannelMode::Lead | ChannelMode::Bass => key,
+        };
+        let event = match velocity {
+            Some(velocity) => SeqEvent::NoteOn { track, note, velocity },
+            None => SeqEvent::NoteOff { track, note },
+        };
+        let _ = commands.send(SeqCommand::Play(event));
+    }
 
-        if current_step == self.prev_step {
+    /// Send grid, pattern slot, song and transport changes to the sequencer
+    /// and read back its position.
+    fn sync_sequencer(&mut self) {
+        let Some(commands) = &self.seq_commands else {
             return;
+        };
+        commands.free_retired();
+
+        // The queued pattern took over at the bar line: show its slots.
+        if self.seq_queued.is_some() && self.seq_status.queued.value() == 0.0 {
+            self.seq_queued = None;
+            self.matrix.state_mut().apply_pending_slots();
         }
 
-        // ── Lead notes ──
-        for &note in &self.prev_active_notes {
-            self.allocator.note_off(note);
+        let state = self.matrix.state();
+        let song = song_from_matrix(state);
+        if song != self.seq_song {
+            let _ = commands.send(SeqCommand::SetSong(song.clone()));
+            self.seq_song = song;
         }
-        let mut new_lead_active = Vec::new();
-        if current_step >= 0 && state.is_playing {
-            let col = current_step as usize;
-            for row in 0..ts::ROWS {
-                if state.grid[row][col] && !state.lead_row_mute[row] {
-                    let vel = (100.0 * state.lead_row_volume[row]) as u8;
-                    let midi_note = state.row_to_midi(row);
-                    self.allocator.note_on(midi_note, vel.max(1));
-                    new_lead_active.push(midi_note);
-                }
-            }
+
+        let pattern = pattern_from_matrix(state);
+        if pattern != self.seq_pattern {
+            let _ = commands.send(SeqCommand::SetPattern(Box::new(pattern.clone())));
+            self.seq_pattern = pattern;
         }
-        self.prev_active_notes = new_lead_active;
 
-        // ── Drum notes (sample-based) ──
-        for &drum_id in &self.prev_active_drums {
-            self.drum_allocator.note_off(drum_id);
+        // Slot switches requested while playing wait for the end of the bar.
+        // Once queued, later edits (or a cancelled switch) re-queue.
+        if state.pending_slot.iter().any(Option::is_some) || self.seq_queued.is_some() {
+            let slots: [usize; 3] =
+                std::array::from_fn(|ch| state.pending_slot[ch].unwrap_or(state.pattern_slot[ch]));
+            let queued = pattern_from_slots(state, slots);
+            if self.seq_queued.as_ref() != Some(&queued) {
+                self.seq_status.queued.set_value(1.0);
+                let _ = commands.send(SeqCommand::QueuePattern(Box::new(queued.clone())));
+                self.seq_queued = Some(queued);
+            }
         }
-        let mut new_drum_active = Vec::new();
-        if current_step >= 0 && state.is_playing {
-            let col = current_step as usize;
-            let tune = (state.drum_tune - 50.0) / 50.0; // -1.0 .. +1.0
-            let pitch_ratio = (1.0 + tune * 0.5).clamp(0.5, 2.0);
-
-            for row in 0..ts::ROWS {
-                if state.drum_grid[row][col] && !state.drum_row_mute[row] {
-                    let vel = (100.0 * state.drum_row_volume[row]) as u8;
-                    let drum_id = row as u8;
-                    self.drum_allocator.note_on(drum_id, vel.max(1));
-
-                    if let Some(voice_idx) = self
-                        .drum_allocator
-                        .voices
-                        .iter()
-                        .position(|v| v.note == Some(drum_id))
-                    {
-                        let shared = &self.drum_shared[voice_idx];
-                        shared.sample_index.set_value(row as f32);
-                        shared.pitch_ratio.set_value(pitch_ratio);
-                        shared.trigger.set_value(shared.trigger.value() + 1.0);
-                    }
 
-                    new_drum_active.push(drum_id);
-                }
+        let is_playing = state.is_playing;
+        if is_playing != self.seq_playing {
+            let cmd = if is_playing { SeqCommand::Start(0) } else { SeqCommand::Stop };
+            let _ = commands.send(cmd);
+            for step in &self.seq_status.steps {
+                step.set_value(-1.0);
             }
+            self.seq_playing = is_playing;
         }
-        self.prev_active_drums = new_drum_active;
 
-        // ── Bass notes ──
-        for &note in &self.prev_active_bass {
-            self.bass_allocator.note_off(note);
+        let mode = self.matrix.state().mode;
+        let step = self.seq_status.steps[mode.index()].value();
+        let entry = self.seq_status.song_entry.value();
+        let state = self.matrix.state_mut();
+        if is_playing && step >= 0.0 {
+            state.play_col = step as i32;
         }
-        let mut new_bass_active = Vec::new();
-        if current_step >= 0 && state.is_playing {
-            let col = current_step as usize;
-            for row in 0..ts::ROWS {
-                if state.bass_grid[row][col] && !state.bass_row_mute[row] {
-                    let vel = (100.0 * state.bass_row_volume[row]) as u8;
-                    let midi_note = state.row_to_bass_midi(row);
-                    self.bass_allocator.note_on(midi_note, vel.max(1));
-                    new_bass_active.push(midi_note);
+
+        // In song mode the grid follows the song onto each new entry.
+        let song_playing = (is_playing && entry >= 0.0).then_some(entry as usize);
+        if song_playing != state.song_playing {
+            state.song_playing = song_playing;
+            if let Some(song_step) = song_playing.and_then(|i| state.song.get(i).copied()) {
+                for mode in ChannelMode::ALL {
+                    state.select_slot(mode, song_step.slot);
                 }
             }
         }
-        self.prev_active_bass = new_bass_active;
-
-        self.prev_step = current_step;
     }
 }
 
 impl eframe::App for MatrixApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Controller CCs drive MIDI-learned parameters; notes play the
+        // channel being edited, or lead, bass and drums on MIDI channels
+        // 1, 2 and 10 when multitimbral.
+        while let Some((channel, event)) = self.matrix.midi.try_recv() {
+            if let NoteEvent::ControlChange { controller, value } = event {
+                for (key, amount) in midi_learn::handle_cc(ctx, controller, value) {
+                    midi_learn::apply(self.matrix.state_mut().learn_param_mut(&key), amount);
+                }
+            }
+            let mode = match self.matrix.midi.channels() {
+                MidiChannels::Multi => smf::channel_mode(channel),
+                MidiChannels::Omni | MidiChannels::Single(_) => Some(self.matrix.state().mode),
+            };
+            if let Some(mode) = mode {
+                self.play_live(mode, event);
+            }
+        }
+
         // Auto-start
         if !self.playing {
             self.start();
@@ -440,16 +862,32 @@ impl eframe::App for MatrixApp {
         // Map matrix state to engine params
         self.sync_voice_configs_from_matrix();
 
-        // Handle step changes (trigger both lead and drum notes)
-        self.handle_step_change();
+        // Pattern and transport go to the audio-thread sequencer
+        self.sync_sequencer();
+
+        // MIDI out channels go to the mirror thread; a channel that changes
+        // or stops sending releases what it still holds
+        let out = self.matrix.midi_out.sender();
+        for (shared, channel) in self.midi_out_channels.iter().zip(self.matrix.state().midi_out_channels) {
+            let (old, new) = (shared.value(), channel.map_or(-1.0, f32::from));
+            if old != new {
+                if old >= 0.0 {
+                    out.all_notes_off(old as u8);
+                }
+                shared.set_value(new);
+            }
+        }
 
         // Sync shared params (always, all graphs active)
         self.master_amp.set_value(self.amplitude);
-        for (i, shared) in self.voice_shared.iter().enumerate() {
-            shared.sync(&self.voice_configs[i]);
+        let wheel = |mode: ChannelMode| self.mod_wheel[mode.index()];
+        for (shared, cfg) in self.voice_shared.iter().zip(&self.voice_configs) {
+            shared.sync(cfg);
+            shared.apply_mod_wheel(cfg, wheel(ChannelMode::Lead));
         }
-        for (i, shared) in self.bass_shared.iter().enumerate() {
-            shared.sync(&self.bass_configs[i]);
+        for (shared, cfg) in self.bass_shared.iter().zip(&self.bass_configs) {
+            shared.sync(cfg);
+            shared.apply_mod_wheel(cfg, wheel(ChannelMode::Bass));
         }
 
         // Rebuild if needed
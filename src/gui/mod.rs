@@ -1,7 +1,13 @@
+use std::path::PathBuf;
+
+use crate::matrix_synth::project::Project;
+
 mod app;
 pub mod oscilloscope;
 mod matrix_app;
 
+pub use matrix_app::render_matrix;
+
 pub fn run() {
     let options = eframe::NativeOptions {
         viewport: eframe::egui::ViewportBuilder::default().with_inner_size([1400.0, 850.0]),
@@ -16,7 +22,7 @@ pub fn run() {
     .expect("failed to run eframe");
 }
 
-pub fn run_matrix() {
+pub fn run_matrix(project: Option<(PathBuf, Project)>) {
     let options = eframe::NativeOptions {
         viewport: eframe::egui::ViewportBuilder::default().with_inner_size([1100.0, 750.0]),
         ..Default::default()
@@ -25,7 +31,7 @@ pub fn run_matrix() {
     eframe::run_native(
         "Matrix-Synth",
         options,
-        Box::new(|cc| Ok(Box::new(matrix_app::MatrixApp::new(cc)))),
+        Box::new(|cc| Ok(Box::new(matrix_app::MatrixApp::new(cc, project)))),
     )
     .expect("failed to run eframe");
 }
@@ -1,16 +1,32 @@
 mod engine;
 mod gui;
 mod midi;
+mod midi_learn;
 mod preset;
 mod synth_ui;
 mod matrix_synth;
 
-use clap::Parser;
-use engine::oscillator::Waveform;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use fundsp::prelude32::Shared;
+
+use engine::effects::EffectsConfig;
+use engine::oscillator::{Waveform, build_oscillator, build_poly_graph};
+use engine::render::{BitDepth, OfflineRenderer, write_wav};
+use engine::voice::{MAX_VOICES, StealPolicy, VoiceAllocator, VoiceConfig, VoiceShared};
+use engine::wavetable::Wavetable;
+use matrix_synth::project::Project;
+use matrix_synth::state::MatrixState;
+use preset::Preset;
 
 #[derive(Parser)]
 #[command(name = "synthesis", about = "A Rust audio synthesizer")]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Waveform type
     #[arg(short, long, default_value = "sine")]
     waveform: Waveform,
@@ -31,15 +47,257 @@ struct Cli {
     #[arg(long)]
     gui: bool,
 
-    /// Launch the Matrix sequencer interface
+    /// Launch the Matrix sequencer interface, optionally opening a project file
+    #[arg(long, value_name = "FILE", num_args = 0..=1)]
+    matrix: Option<Option<PathBuf>>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Render audio offline to a WAV file instead of playing it
+    Render(RenderArgs),
+    /// Export a Matrix project's patterns as a Standard MIDI File
+    ExportMidi(ExportMidiArgs),
+    /// Quantize a Standard MIDI File into a Matrix project
+    ImportMidi(ImportMidiArgs),
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum RenderSource {
+    /// Single oscillator, as played by the default command
+    Tone,
+    /// Polyphonic synth with effects, as used by the GUI
+    Poly,
+    /// Matrix project (`--project`) played by its sequencer from the first step
+    Matrix,
+}
+
+#[derive(clap::Args)]
+struct RenderArgs {
+    /// Output WAV file
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Graph to render
+    #[arg(short, long, default_value = "tone")]
+    source: RenderSource,
+
+    /// Waveform type
+    #[arg(short, long, default_value = "sine")]
+    waveform: Waveform,
+
+    /// Frequency in Hz (tone source)
+    #[arg(short, long, default_value_t = 440.0)]
+    frequency: f32,
+
+    /// Amplitude (0.0 to 1.0)
+    #[arg(short, long, default_value_t = 0.5)]
+    amplitude: f32,
+
+    /// Duration in seconds (poly notes are held this long, then released;
+    /// the matrix source plays its pattern this long)
+    #[arg(short, long, default_value_t = 5.0)]
+    duration: f32,
+
+    /// MIDI notes held by the poly source, comma-separated
+    #[arg(short, long, value_delimiter = ',', default_value = "60")]
+    notes: Vec<u8>,
+
+    /// Voices available to the poly source (1 to 64)
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u8).range(1..=MAX_VOICES as i64))]
+    voices: u8,
+
+    /// Which held note the poly source cuts when it runs out of voices
+    #[arg(long, default_value = "oldest")]
+    steal: StealPolicy,
+
+    /// Factory or user preset for the poly source (overrides --waveform and --amplitude)
+    #[arg(short, long)]
+    preset: Option<String>,
+
+    /// Matrix project file played by the matrix source
+    #[arg(long, required_if_eq("source", "matrix"))]
+    project: Option<PathBuf>,
+
+    /// WAV wavetable played by the poly source with `--waveform wavetable`
+    /// (one cycle, or frames of 2048 samples)
     #[arg(long)]
-    matrix: bool,
+    wavetable: Option<PathBuf>,
+
+    /// Sample rate in Hz
+    #[arg(long, default_value_t = 44100)]
+    sample_rate: u32,
+
+    /// Bit depth: 16 or 24 (integer PCM) or 32 (float)
+    #[arg(short, long, default_value = "16")]
+    bit_depth: BitDepth,
+}
+
+#[derive(clap::Args)]
+struct ExportMidiArgs {
+    /// Matrix project file
+    project: PathBuf,
+
+    /// Output MIDI file
+    #[arg(short, long)]
+    output: PathBuf,
+}
+
+fn export_midi(args: ExportMidiArgs) {
+    let project = load_project(&args.project);
+    if let Err(err) = matrix_synth::smf::save(&project.state, &args.output) {
+        eprintln!("failed to write {}: {err}", args.output.display());
+        std::process::exit(1);
+    }
+    println!("Exported {} to {}", args.project.display(), args.output.display());
+}
+
+#[derive(clap::Args)]
+struct ImportMidiArgs {
+    /// MIDI file to import
+    midi: PathBuf,
+
+    /// Output project file
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Project to import into (default: a new project)
+    #[arg(short, long)]
+    project: Option<PathBuf>,
+}
+
+fn load_project(path: &Path) -> Project {
+    match Project::load(path) {
+        Ok(project) => project,
+        Err(err) => {
+            eprintln!("failed to open {}: {err}", path.display());
+            std::process::exit(1);
+        }
+    }
+}
+
+fn import_midi(args: ImportMidiArgs) {
+    let mut project = match &args.project {
+        Some(path) => load_project(path),
+        None => Project::new(&MatrixState::default()),
+    };
+    let report = match matrix_synth::smf::load(&mut project.state, &args.midi) {
+        Ok(report) => report,
+        Err(err) => {
+            eprintln!("failed to import {}: {err}", args.midi.display());
+            std::process::exit(1);
+        }
+    };
+    if let Err(err) = project.save(&args.output) {
+        eprintln!("failed to write {}: {err}", args.output.display());
+        std::process::exit(1);
+    }
+    println!("{} into {}", report.summary(), args.output.display());
+}
+
+fn render(args: RenderArgs) {
+    let frames = match args.source {
+        RenderSource::Tone => {
+            let graph = build_oscillator(args.waveform, args.frequency, args.amplitude);
+            OfflineRenderer::new(graph, args.sample_rate).render_secs(args.duration)
+        }
+        RenderSource::Poly => {
+            let preset = match &args.preset {
+                Some(name) => match Preset::find(name) {
+                    Some(p) => Some(p),
+                    None => {
+                        eprintln!("unknown preset: {name}");
+                        std::process::exit(1);
+                    }
+                },
+                None => None,
+            };
+            let (cfg, amplitude, effects_cfg) = match &preset {
+                Some(p) => (p.voice_config(), p.amplitude, p.effects_cfg.clone()),
+                None => (
+                    VoiceConfig { waveform: args.waveform, ..VoiceConfig::default() },
+                    args.amplitude,
+                    EffectsConfig::default(),
+                ),
+            };
+            let wavetable = args.wavetable.as_deref().map(|path| match Wavetable::load(path) {
+                Ok(table) => Arc::new(table),
+                Err(err) => {
+                    eprintln!("failed to read wavetable {}: {err}", path.display());
+                    std::process::exit(1);
+                }
+            });
+            let cfg = VoiceConfig { wavetable, ..cfg };
+            let (delay_time, delay_feedback, delay_mix, reverb_mix, chorus_mix) = match &preset {
+                Some(p) => (p.delay_time, p.delay_feedback, p.delay_mix, p.reverb_mix, p.chorus_mix),
+                None => (0.3, 0.3, 0.0, 0.0, 0.0),
+            };
+
+            let mut allocator = VoiceAllocator::new(args.voices as usize);
+            allocator.set_steal_policy(args.steal);
+            let configs: Vec<VoiceConfig> = allocator.voices.iter().map(|_| cfg.clone()).collect();
+            let shared: Vec<VoiceShared> = configs.iter().map(VoiceShared::new).collect();
+            let (graph, _, _) = build_poly_graph(
+                &allocator.voices,
+                &configs,
+                &shared,
+                &Shared::new(amplitude),
+                &effects_cfg,
+                &Shared::new(delay_time),
+                &Shared::new(delay_feedback),
+                &Shared::new(delay_mix),
+                &Shared::new(reverb_mix),
+                &Shared::new(chorus_mix),
+            );
+            let mut renderer = OfflineRenderer::new(graph, args.sample_rate);
+
+            for &note in &args.notes {
+                allocator.note_on(note, 100);
+            }
+            let mut frames = renderer.render_secs(args.duration);
+            for &note in &args.notes {
+                allocator.note_off(note);
+            }
+            frames.extend(renderer.render_secs(cfg.adsr.release));
+            frames
+        }
+        RenderSource::Matrix => {
+            let path = args.project.as_deref().expect("clap requires --project for the matrix source");
+            gui::render_matrix(&load_project(path).state, args.sample_rate, args.duration)
+        }
+    };
+
+    if let Err(err) = write_wav(&args.output, &frames, args.sample_rate, args.bit_depth) {
+        eprintln!("failed to write {}: {err}", args.output.display());
+        std::process::exit(1);
+    }
+    println!(
+        "Rendered {:.2}s ({} Hz, {}-bit) to {}",
+        frames.len() as f32 / args.sample_rate as f32,
+        args.sample_rate,
+        args.bit_depth.bits(),
+        args.output.display()
+    );
 }
 
 fn main() {
     let cli = Cli::parse();
-    if cli.matrix {
-        gui::run_matrix();
+    if let Some(command) = cli.command {
+        match command {
+            Command::Render(args) => render(args),
+            Command::ExportMidi(args) => export_midi(args),
+            Command::ImportMidi(args) => import_midi(args),
+        }
+    } else if let Some(file) = cli.matrix {
+        // Fail before opening a window if the project cannot be read
+        let project = file.map(|path| match Project::load(&path) {
+            Ok(project) => (path, project),
+            Err(err) => {
+                eprintln!("failed to open {}: {err}", path.display());
+                std::process::exit(1);
+            }
+        });
+        gui::run_matrix(project);
     } else if cli.gui {
         gui::run();
     } else {
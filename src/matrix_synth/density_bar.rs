@@ -1,19 +1,22 @@
 use eframe::egui;
 use eframe::egui::Stroke;
 
-use super::state::{ChannelMode, MatrixState, COLS};
+use super::grid::col_step;
+use super::state::{ChannelMode, MatrixState};
 use super::theme::Theme;
 
 pub fn draw(ui: &mut egui::Ui, state: &MatrixState) {
     ui.horizontal(|ui| {
         ui.add_space(Theme::NOTE_LABEL_WIDTH);
+        let steps = state.active_steps();
+        let col_w = col_step(steps);
 
-        for col in 0..COLS {
+        for col in 0..steps {
             let density = state.col_density(col);
             let is_active = state.is_playing && state.play_col == col as i32;
 
             let (rect, _) = ui.allocate_exact_size(
-                egui::vec2(Theme::CELL_SIZE, Theme::DENSITY_HEIGHT),
+                egui::vec2(col_w - Theme::CELL_GAP, Theme::DENSITY_HEIGHT),
                 egui::Sense::hover(),
             );
 
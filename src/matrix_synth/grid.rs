@@ -2,17 +2,29 @@ use eframe::egui;
 use eframe::egui::{Color32, Stroke};
 
 use super::history::History;
-use super::state::{ChannelMode, DrawMode, MatrixState, COLS, ROWS};
+use super::state::{ChannelMode, DrawMode, MatrixState, StepParams, DEFAULT_STEPS, MAX_STEPS, ROWS};
 use super::theme::Theme;
 use super::widgets;
+use crate::midi_learn::learnable;
+
+/// Horizontal distance between step columns: full-size cells up to 16 steps,
+/// longer patterns shrink their columns to keep the 16-step width.
+pub fn col_step(steps: usize) -> f32 {
+    let full = Theme::CELL_SIZE + Theme::CELL_GAP;
+    full * DEFAULT_STEPS as f32 / steps.max(DEFAULT_STEPS) as f32
+}
 
 #[allow(clippy::needless_range_loop)]
 pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState, history: &mut History) {
     let cell = Theme::CELL_SIZE;
     let gap = Theme::CELL_GAP;
     let step = cell + gap;
+    let steps = state.active_steps();
+    let col_w = col_step(steps);
+    let cell_w = col_w - gap;
     let ctrl_width = Theme::ROW_CTRL_GAP + Theme::MUTE_BTN_SIZE + Theme::CTRL_GAP + Theme::VOL_BAR_WIDTH;
-    let grid_width = Theme::NOTE_LABEL_WIDTH + COLS as f32 * step + ctrl_width;
+    let grid_width = Theme::NOTE_LABEL_WIDTH + steps as f32 * col_w + ctrl_width;
+    let cells_width = steps as f32 * col_w - gap;
     let grid_height = Theme::COL_HEADER_HEIGHT + ROWS as f32 * step;
 
     widgets::panel::synth_panel_no_title(ui, |ui| {
@@ -20,10 +32,15 @@ pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState, history: &mut History) {
             ui.allocate_exact_size(egui::vec2(grid_width, grid_height), egui::Sense::hover());
         let painter = ui.painter_at(total_rect);
         let origin = total_rect.min;
+        let cells_end = origin.x + Theme::NOTE_LABEL_WIDTH + cells_width;
 
         // ── Column numbers ──
-        for col in 0..COLS {
-            let x = origin.x + Theme::NOTE_LABEL_WIDTH + col as f32 * step + cell / 2.0;
+        for col in 0..steps {
+            // Narrow columns only number every beat
+            if col_w < 16.0 && col % 4 != 0 {
+                continue;
+            }
+            let x = origin.x + Theme::NOTE_LABEL_WIDTH + col as f32 * col_w + cell_w / 2.0;
             let y = origin.y + Theme::COL_HEADER_HEIGHT / 2.0;
             let is_active = state.is_playing && state.play_col == col as i32;
             painter.text(
@@ -42,11 +59,11 @@ pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState, history: &mut History) {
         // ── Playhead column background ──
         if state.is_playing && state.play_col >= 0 {
             let col = state.play_col as usize;
-            let x = origin.x + Theme::NOTE_LABEL_WIDTH + col as f32 * step - 1.0;
+            let x = origin.x + Theme::NOTE_LABEL_WIDTH + col as f32 * col_w - 1.0;
             let y = origin.y + Theme::COL_HEADER_HEIGHT;
             let h = ROWS as f32 * step;
             painter.rect_filled(
-                egui::Rect::from_min_size(egui::pos2(x, y), egui::vec2(cell + 2.0, h)),
+                egui::Rect::from_min_size(egui::pos2(x, y), egui::vec2(cell_w + 2.0, h)),
                 4.0,
                 Theme::PLAYHEAD_BG,
             );
@@ -82,14 +99,14 @@ pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState, history: &mut History) {
         let mut grid_changed = false;
 
         // Track hover state per cell
-        let mut hovered = [[false; COLS]; ROWS];
+        let mut hovered = [[false; MAX_STEPS]; ROWS];
 
         for row in 0..ROWS {
-            for col in 0..COLS {
-                let x = origin.x + Theme::NOTE_LABEL_WIDTH + col as f32 * step;
+            for col in 0..steps {
+                let x = origin.x + Theme::NOTE_LABEL_WIDTH + col as f32 * col_w;
                 let y = origin.y + Theme::COL_HEADER_HEIGHT + row as f32 * step;
                 let cell_rect =
-                    egui::Rect::from_min_size(egui::pos2(x, y), egui::vec2(cell, cell));
+                    egui::Rect::from_min_size(egui::pos2(x, y), egui::vec2(cell_w, cell));
 
                 let cell_id = ui.id().with(("cell", row, col));
                 let response =
@@ -97,6 +114,23 @@ pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState, history: &mut History) {
 
                 hovered[row][col] = response.hovered();
 
+                // Right-click opens the cell in the step inspector
+                if response.secondary_clicked() {
+                    state.selected_step = Some((state.mode, row, col));
+                }
+
+                // Alt+drag up/down sets the velocity of an active cell
+                let alt = ui.input(|i| i.modifiers.alt);
+                if alt {
+                    if response.dragged() && state.active_grid()[row][col] {
+                        let params = &mut state.active_step_params_mut()[row][col];
+                        let velocity = params.velocity as f32 - response.drag_delta().y;
+                        params.velocity = velocity.clamp(1.0, 127.0) as u8;
+                        state.selected_step = Some((state.mode, row, col));
+                    }
+                    continue;
+                }
+
                 let should_apply =
                     response.clicked() || (response.dragged() && response.hovered());
 
@@ -140,28 +174,30 @@ pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState, history: &mut History) {
         }
 
         // Pass 0: Ghost overlay (other modes' cells in transparency)
-        let ghosts: Vec<(&[[bool; COLS]; ROWS], Color32)> = match state.mode {
-            ChannelMode::Lead => vec![
-                (&state.drum_grid, Theme::GHOST_DRUM),
-                (&state.bass_grid, Theme::GHOST_BASS),
+        let ghost_modes = match state.mode {
+            ChannelMode::Lead => [
+                (ChannelMode::Drummer, Theme::GHOST_DRUM),
+                (ChannelMode::Bass, Theme::GHOST_BASS),
             ],
-            ChannelMode::Drummer => vec![
-                (&state.grid, Theme::GHOST_LEAD),
-                (&state.bass_grid, Theme::GHOST_BASS),
+            ChannelMode::Drummer => [
+                (ChannelMode::Lead, Theme::GHOST_LEAD),
+                (ChannelMode::Bass, Theme::GHOST_BASS),
             ],
-            ChannelMode::Bass => vec![
-                (&state.grid, Theme::GHOST_LEAD),
-                (&state.drum_grid, Theme::GHOST_DRUM),
+            ChannelMode::Bass => [
+                (ChannelMode::Lead, Theme::GHOST_LEAD),
+                (ChannelMode::Drummer, Theme::GHOST_DRUM),
             ],
         };
-        for (ghost_grid, ghost_color) in &ghosts {
+        for (ghost_mode, ghost_color) in &ghost_modes {
+            let ghost_grid = state.channel_grid(*ghost_mode);
+            let ghost_steps = state.pattern_steps[ghost_mode.index()].min(steps);
             for row in 0..ROWS {
-                for col in 0..COLS {
+                for col in 0..ghost_steps {
                     if ghost_grid[row][col] {
-                        let x = origin.x + Theme::NOTE_LABEL_WIDTH + col as f32 * step;
+                        let x = origin.x + Theme::NOTE_LABEL_WIDTH + col as f32 * col_w;
                         let y = origin.y + Theme::COL_HEADER_HEIGHT + row as f32 * step;
                         let cell_rect =
-                            egui::Rect::from_min_size(egui::pos2(x, y), egui::vec2(cell, cell));
+                            egui::Rect::from_min_size(egui::pos2(x, y), egui::vec2(cell_w, cell));
                         painter.rect_filled(cell_rect, Theme::CELL_ROUNDING, *ghost_color);
                     }
                 }
@@ -170,11 +206,11 @@ pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState, history: &mut History) {
 
         // Pass 1: Draw glows for ON and HIT cells (mode-aware colors)
         for row in 0..ROWS {
-            for col in 0..COLS {
-                let x = origin.x + Theme::NOTE_LABEL_WIDTH + col as f32 * step;
+            for col in 0..steps {
+                let x = origin.x + Theme::NOTE_LABEL_WIDTH + col as f32 * col_w;
                 let y = origin.y + Theme::COL_HEADER_HEIGHT + row as f32 * step;
                 let cell_rect =
-                    egui::Rect::from_min_size(egui::pos2(x, y), egui::vec2(cell, cell));
+                    egui::Rect::from_min_size(egui::pos2(x, y), egui::vec2(cell_w, cell));
 
                 let is_on = state.active_grid()[row][col];
                 let is_playhead = state.is_playing && state.play_col == col as i32;
@@ -208,11 +244,11 @@ pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState, history: &mut History) {
 
         // Pass 2: Draw all cells (mode-aware colors)
         for row in 0..ROWS {
-            for col in 0..COLS {
-                let x = origin.x + Theme::NOTE_LABEL_WIDTH + col as f32 * step;
+            for col in 0..steps {
+                let x = origin.x + Theme::NOTE_LABEL_WIDTH + col as f32 * col_w;
                 let y = origin.y + Theme::COL_HEADER_HEIGHT + row as f32 * step;
                 let cell_rect =
-                    egui::Rect::from_min_size(egui::pos2(x, y), egui::vec2(cell, cell));
+                    egui::Rect::from_min_size(egui::pos2(x, y), egui::vec2(cell_w, cell));
 
                 let is_hovered = hovered[row][col];
                 let is_on = state.active_grid()[row][col];
@@ -274,11 +310,40 @@ pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState, history: &mut History) {
                     Stroke::new(1.0, border_color),
                     egui::StrokeKind::Inside,
                 );
+
+                if is_on {
+                    draw_step_marks(&painter, cell_rect, &state.active_step_params()[row][col]);
+                }
+                if state.selected_step == Some((state.mode, row, col)) {
+                    painter.rect_stroke(
+                        cell_rect.expand(1.0),
+                        Theme::CELL_ROUNDING,
+                        Stroke::new(2.0, Theme::TEXT_WHITE),
+                        egui::StrokeKind::Outside,
+                    );
+                }
+            }
+        }
+
+        // Pass 3: Tie lines from long notes across the following steps
+        for row in 0..ROWS {
+            for col in 0..steps {
+                let length = state.active_step_params()[row][col].length;
+                if !state.active_grid()[row][col] || length <= 1.0 {
+                    continue;
+                }
+                let x = origin.x + Theme::NOTE_LABEL_WIDTH + col as f32 * col_w;
+                let y = origin.y + Theme::COL_HEADER_HEIGHT + row as f32 * step + cell / 2.0;
+                let end = (x + col_w * length - gap).min(cells_end);
+                painter.line_segment(
+                    [egui::pos2(x + cell_w, y), egui::pos2(end, y)],
+                    Stroke::new(2.0, Theme::TIE_LINE),
+                );
             }
         }
 
         // ── Row controls (mute button + volume bar) ──
-        let cells_right = origin.x + Theme::NOTE_LABEL_WIDTH + COLS as f32 * step;
+        let cells_right = origin.x + Theme::NOTE_LABEL_WIDTH + steps as f32 * col_w;
         let mute_x = cells_right + Theme::ROW_CTRL_GAP;
 
         for row in 0..ROWS {
@@ -314,6 +379,8 @@ pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState, history: &mut History) {
             );
             let vol_id = ui.id().with(("vol", row));
             let vol_resp = ui.interact(vol_rect, vol_id, egui::Sense::click_and_drag());
+            let vol_key = format!("matrix.{}.volume.{}", state.mode.label().to_lowercase(), row + 1);
+            learnable(ui, &vol_resp, &vol_key);
             if (vol_resp.clicked() || vol_resp.dragged())
                 && let Some(pos) = vol_resp.interact_pointer_pos()
             {
@@ -332,7 +399,7 @@ pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState, history: &mut History) {
             if is_muted {
                 let overlay_rect = egui::Rect::from_min_size(
                     egui::pos2(origin.x + Theme::NOTE_LABEL_WIDTH, row_y),
-                    egui::vec2(COLS as f32 * step, cell),
+                    egui::vec2(steps as f32 * col_w, cell),
                 );
                 painter.rect_filled(overlay_rect, 0.0, Theme::MUTED_ROW_OVERLAY);
             }
@@ -344,3 +411,36 @@ pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState, history: &mut History) {
         }
     });
 }
+
+/// Velocity bar along the bottom, a dot for notes that only play some
+/// of the time and a rising stroke for slides.
+fn draw_step_marks(painter: &egui::Painter, rect: egui::Rect, params: &StepParams) {
+    let mark = Theme::STEP_MARK;
+    if params.slide {
+        let inset = rect.width() * 0.2;
+        painter.line_segment(
+            [
+                egui::pos2(rect.left() + inset * 0.5, rect.center().y),
+                egui::pos2(rect.left() + inset * 1.5, rect.top() + inset * 0.5),
+            ],
+            Stroke::new(1.5, mark),
+        );
+    }
+    let bar_w = rect.width() * params.velocity as f32 / 127.0;
+    painter.rect_filled(
+        egui::Rect::from_min_size(
+            egui::pos2(rect.left(), rect.bottom() - 3.0),
+            egui::vec2(bar_w, 3.0),
+        ),
+        1.0,
+        mark,
+    );
+    if params.probability < 100 {
+        let radius = (rect.width() * 0.12).clamp(1.5, 3.0);
+        painter.circle_filled(
+            egui::pos2(rect.right() - radius - 2.0, rect.top() + radius + 2.0),
+            radius,
+            mark,
+        );
+    }
+}
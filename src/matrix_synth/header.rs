@@ -1,10 +1,203 @@
 use eframe::egui;
-use eframe::egui::Color32;
+use eframe::egui::{Color32, Stroke};
 
 use super::state::{ChannelMode, MatrixState};
 use super::theme::Theme;
+use crate::midi::{MidiChannels, MidiHandler, MidiOutHandler, VIRTUAL_OUT_NAME};
 
-pub fn draw(ui: &mut egui::Ui, state: &MatrixState) {
+/// Project controls shown in the header, owned by `MatrixSynth`.
+pub struct ProjectBar {
+    /// Name of the open project, `None` until saved or opened.
+    pub current: Option<String>,
+    /// Projects found in `~/.synthesis/projects`.
+    pub names: Vec<String>,
+    /// MIDI files found in `~/.synthesis/exports`.
+    pub midi_files: Vec<String>,
+    pub save_as_name: String,
+    /// Result of the last save/open, shown next to the controls.
+    pub status: Option<String>,
+}
+
+/// Project file request from the header, handled by `MatrixSynth`.
+pub enum ProjectAction {
+    Save,
+    SaveAs(String),
+    Open(String),
+    /// Write the selected patterns to `~/.synthesis/exports` as a MIDI file.
+    ExportMidi,
+    /// Quantize a MIDI file from `~/.synthesis/exports` into the grids.
+    ImportMidi(String),
+}
+
+fn header_button(ui: &mut egui::Ui, label: &str, enabled: bool) -> egui::Response {
+    let btn = egui::Button::new(
+        egui::RichText::new(label)
+            .size(9.0)
+            .strong()
+            .color(if enabled { Theme::TEXT } else { Theme::TEXT_DIM }),
+    )
+    .fill(Theme::KNOB_BG)
+    .stroke(Stroke::new(1.0, Theme::BORDER))
+    .corner_radius(4.0)
+    .min_size(egui::vec2(0.0, 20.0));
+    ui.add_enabled(enabled, btn)
+}
+
+fn draw_project_bar(ui: &mut egui::Ui, bar: &mut ProjectBar) -> Option<ProjectAction> {
+    let mut action = None;
+    ui.spacing_mut().item_spacing.x = 4.0;
+
+    ui.label(
+        egui::RichText::new(bar.current.as_deref().unwrap_or("untitled"))
+            .size(10.0)
+            .color(Theme::TEXT),
+    );
+
+    egui::ComboBox::from_id_salt("matrix_project_open")
+        .selected_text(egui::RichText::new("Open").size(9.0))
+        .width(70.0)
+        .show_ui(ui, |ui| {
+            if bar.names.is_empty() {
+                ui.label(egui::RichText::new("No projects").size(9.0).color(Theme::TEXT_DIM));
+            }
+            for name in &bar.names {
+                if ui.selectable_label(bar.current.as_ref() == Some(name), name).clicked() {
+                    action = Some(ProjectAction::Open(name.clone()));
+                }
+            }
+        });
+
+    if header_button(ui, "Save", bar.current.is_some()).clicked() {
+        action = Some(ProjectAction::Save);
+    }
+
+    ui.add(
+        egui::TextEdit::singleline(&mut bar.save_as_name)
+            .hint_text("name")
+            .desired_width(80.0)
+            .font(egui::FontId::proportional(10.0)),
+    );
+    let name = bar.save_as_name.trim();
+    if header_button(ui, "Save As", !name.is_empty()).clicked() {
+        action = Some(ProjectAction::SaveAs(name.to_string()));
+    }
+
+    if header_button(ui, "Export MIDI", true).clicked() {
+        action = Some(ProjectAction::ExportMidi);
+    }
+
+    egui::ComboBox::from_id_salt("matrix_midi_import")
+        .selected_text(egui::RichText::new("Import MIDI").size(9.0))
+        .width(80.0)
+        .show_ui(ui, |ui| {
+            if bar.midi_files.is_empty() {
+                ui.label(egui::RichText::new("No MIDI files").size(9.0).color(Theme::TEXT_DIM));
+            }
+            for name in &bar.midi_files {
+                if ui.selectable_label(false, name).clicked() {
+                    action = Some(ProjectAction::ImportMidi(name.clone()));
+                }
+            }
+        });
+
+    if let Some(status) = &bar.status {
+        ui.label(egui::RichText::new(status).size(9.0).color(Theme::TEXT_DIM));
+    }
+
+    action
+}
+
+/// MIDI input port for controllers and live notes, the channels it listens
+/// to, plus the MIDI learn table toggle.
+fn draw_midi_bar(ui: &mut egui::Ui, midi: &mut MidiHandler, map_open: &mut bool) {
+    if header_button(ui, "MIDI Map", true).clicked() {
+        *map_open = !*map_open;
+    }
+    let mut channels = midi.channels();
+    egui::ComboBox::from_id_salt("matrix_midi_channels")
+        .selected_text(egui::RichText::new(channels.label()).size(9.0))
+        .width(52.0)
+        .show_ui(ui, |ui| {
+            for option in MidiChannels::options() {
+                ui.selectable_value(&mut channels, option, option.label());
+            }
+        })
+        .response
+        .on_hover_text("Multi: channel 1 plays the lead, 2 the bass and 10 the drums");
+    midi.set_channels(channels);
+    if midi.is_connected() {
+        if header_button(ui, "Disconnect", true).clicked() {
+            midi.disconnect();
+        }
+        ui.label(egui::RichText::new("MIDI IN").size(9.0).color(Theme::LED_PLAYING));
+        return;
+    }
+    let names = midi.port_names().to_vec();
+    let label = midi
+        .selected_port()
+        .and_then(|i| names.get(i).cloned())
+        .unwrap_or_else(|| "MIDI In".to_string());
+    egui::ComboBox::from_id_salt("matrix_midi_in")
+        .selected_text(egui::RichText::new(label).size(9.0))
+        .width(90.0)
+        .show_ui(ui, |ui| {
+            if names.is_empty() {
+                ui.label(egui::RichText::new("No MIDI ports").size(9.0).color(Theme::TEXT_DIM));
+            }
+            for (i, name) in names.iter().enumerate() {
+                if ui.selectable_label(false, name).clicked() {
+                    midi.connect(i, Some(ui.ctx().clone()));
+                }
+            }
+            if ui.button("Refresh").clicked() {
+                midi.refresh_ports();
+            }
+        });
+}
+
+/// MIDI output port the sequencer's notes are mirrored to.
+fn draw_midi_out(ui: &mut egui::Ui, out: &mut MidiOutHandler) {
+    if let Some(name) = out.connected_port() {
+        let name = name.to_string();
+        if header_button(ui, "Close", true).clicked() {
+            out.disconnect();
+        }
+        ui.label(egui::RichText::new("MIDI OUT").size(9.0).color(Theme::LED_PLAYING))
+            .on_hover_text(name);
+        return;
+    }
+    let names = out.port_names().to_vec();
+    egui::ComboBox::from_id_salt("matrix_midi_out")
+        .selected_text(egui::RichText::new("MIDI Out").size(9.0))
+        .width(90.0)
+        .show_ui(ui, |ui| {
+            if names.is_empty() {
+                ui.label(egui::RichText::new("No MIDI ports").size(9.0).color(Theme::TEXT_DIM));
+            }
+            for (i, name) in names.iter().enumerate() {
+                if ui.selectable_label(false, name).clicked() {
+                    out.connect(i);
+                }
+            }
+            #[cfg(unix)]
+            if ui.selectable_label(false, format!("Virtual ({VIRTUAL_OUT_NAME})")).clicked() {
+                out.connect_virtual();
+            }
+            if ui.button("Refresh").clicked() {
+                out.refresh_ports();
+            }
+        });
+}
+
+pub fn draw(
+    ui: &mut egui::Ui,
+    state: &MatrixState,
+    bar: &mut ProjectBar,
+    midi: &mut MidiHandler,
+    midi_out: &mut MidiOutHandler,
+    midi_map_open: &mut bool,
+) -> Option<ProjectAction> {
+    let mut action = None;
     ui.horizontal(|ui| {
         // LED
         let (led_rect, _) = ui.allocate_exact_size(egui::vec2(10.0, 10.0), egui::Sense::hover());
@@ -59,10 +252,17 @@ pub fn draw(ui: &mut egui::Ui, state: &MatrixState) {
                 );
             });
 
+        ui.add_space(16.0);
+        action = draw_project_bar(ui, bar);
+
         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
             // Version
             ui.label(egui::RichText::new("v0.12.0").size(9.0).color(Theme::TEXT_DIM));
             ui.add_space(12.0);
+            draw_midi_bar(ui, midi, midi_map_open);
+            ui.add_space(8.0);
+            draw_midi_out(ui, midi_out);
+            ui.add_space(12.0);
 
             // Active notes (badges)
             let notes = state.active_note_names();
@@ -90,4 +290,5 @@ pub fn draw(ui: &mut egui::Ui, state: &MatrixState) {
             }
         });
     });
+    action
 }
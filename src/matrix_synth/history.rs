@@ -1,6 +1,4 @@
-use super::state::{COLS, ROWS};
-
-type Grid = [[bool; COLS]; ROWS];
+use super::state::Grid;
 
 const MAX_HISTORY: usize = 20;
 
@@ -45,9 +43,10 @@ impl History {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::matrix_synth::state::{MAX_STEPS, ROWS};
 
     fn empty_grid() -> Grid {
-        [[false; COLS]; ROWS]
+        [[false; MAX_STEPS]; ROWS]
     }
 
     fn grid_with_cell(row: usize, col: usize) -> Grid {
@@ -0,0 +1,132 @@
+use std::collections::HashSet;
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+
+use fundsp::shared::Shared;
+
+use crate::engine::sequencer::{SeqEvent, Track, MIRROR_CAPACITY};
+use crate::midi::{MidiSender, NoteEvent};
+
+use super::smf;
+
+/// Forwards the sequencer's notes to the MIDI output on one thread that
+/// lives as long as the mirror. Each audio graph gets its own event channel;
+/// when a graph is dropped its channel disconnects and the thread releases
+/// every note that graph left held, so rebuilds never strand notes on
+/// external gear.
+pub struct MidiMirror {
+    graphs: Sender<Receiver<SeqEvent>>,
+}
+
+impl MidiMirror {
+    /// `channels` holds the MIDI out channel of each sequencer track, -1
+    /// when the track is not sent.
+    pub fn spawn(out: MidiSender, channels: [Shared; 3]) -> Self {
+        let (graphs, incoming) = mpsc::channel::<Receiver<SeqEvent>>();
+        std::thread::spawn(move || {
+            for events in incoming {
+                forward(events, &channels, |channel, event| out.send_event(channel, event));
+            }
+        });
+        Self { graphs }
+    }
+
+    /// Event sender for a new graph's sequencer (`SeqCommand::Mirror`).
+    pub fn connect(&self) -> SyncSender<SeqEvent> {
+        let (events, mirrored) = mpsc::sync_channel(MIRROR_CAPACITY);
+        let _ = self.graphs.send(mirrored);
+        events
+    }
+}
+
+/// Send one graph's events until it disconnects, then release the notes it
+/// still held on the channels they went out on.
+fn forward(events: Receiver<SeqEvent>, channels: &[Shared; 3], mut send: impl FnMut(u8, NoteEvent)) {
+    let mut held = HashSet::new();
+    for event in events {
+        let Some((channel, event)) = midi_out_event(event, channels) else {
+            continue;
+        };
+        match event {
+            NoteEvent::On { note, .. } => held.insert((channel, note)),
+            NoteEvent::Off { note } => held.remove(&(channel, note)),
+            _ => false,
+        };
+        send(channel, event);
+    }
+    for (channel, note) in held {
+        send(channel, NoteEvent::Off { note });
+    }
+}
+
+/// MIDI channel and note a sequencer event goes out as, if its track is
+/// sent. Drum rows become General MIDI percussion notes.
+fn midi_out_event(event: SeqEvent, channels: &[Shared; 3]) -> Option<(u8, NoteEvent)> {
+    let (SeqEvent::NoteOn { track, note, .. } | SeqEvent::NoteOff { track, note }) = event;
+    let channel = channels[track.index()].value();
+    if channel < 0.0 {
+        return None;
+    }
+    let note = match track {
+        Track::Drum => *smf::GM_DRUM_NOTES.get(note as usize)?,
+        Track::Lead | Track::Bass => note,
+    };
+    let event = match event {
+        SeqEvent::NoteOn { velocity, .. } => NoteEvent::On { note, velocity: Ord::max(velocity, 1) },
+        SeqEvent::NoteOff { .. } => NoteEvent::Off { note },
+    };
+    Some((channel as u8, event))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channels(lead: f32, drum: f32, bass: f32) -> [Shared; 3] {
+        [Shared::new(lead), Shared::new(drum), Shared::new(bass)]
+    }
+
+    fn on(track: Track, note: u8) -> SeqEvent {
+        SeqEvent::NoteOn { track, note, velocity: 100 }
+    }
+
+    #[test]
+    fn forwards_sent_tracks_only() {
+        let (tx, rx) = mpsc::sync_channel(MIRROR_CAPACITY);
+        tx.send(on(Track::Lead, 60)).unwrap();
+        tx.send(on(Track::Drum, 14)).unwrap();
+        tx.send(SeqEvent::NoteOff { track: Track::Lead, note: 60 }).unwrap();
+        tx.send(SeqEvent::NoteOff { track: Track::Drum, note: 14 }).unwrap();
+        drop(tx);
+
+        let mut sent = Vec::new();
+        forward(rx, &channels(0.0, 9.0, -1.0), |channel, event| sent.push((channel, event)));
+        assert_eq!(
+            sent,
+            [
+                (0, NoteEvent::On { note: 60, velocity: 100 }),
+                (9, NoteEvent::On { note: 36, velocity: 100 }),
+                (0, NoteEvent::Off { note: 60 }),
+                (9, NoteEvent::Off { note: 36 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn dropped_graph_releases_held_notes() {
+        let (tx, rx) = mpsc::sync_channel(MIRROR_CAPACITY);
+        tx.send(on(Track::Lead, 60)).unwrap();
+        tx.send(on(Track::Bass, 40)).unwrap();
+        tx.send(on(Track::Lead, 64)).unwrap();
+        tx.send(SeqEvent::NoteOff { track: Track::Lead, note: 64 }).unwrap();
+        drop(tx); // the graph is rebuilt while two notes still sound
+
+        let mut sent = Vec::new();
+        forward(rx, &channels(0.0, -1.0, 1.0), |channel, event| sent.push((channel, event)));
+        let mut released = sent.split_off(4);
+        released.sort_by_key(|&(channel, _)| channel);
+        assert_eq!(
+            released,
+            [(0, NoteEvent::Off { note: 60 }), (1, NoteEvent::Off { note: 40 })]
+        );
+    }
+}
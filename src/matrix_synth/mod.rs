@@ -2,19 +2,29 @@ mod density_bar;
 mod grid;
 mod header;
 mod history;
+pub mod midi_mirror;
+mod patterns;
 pub mod panels;
+pub mod project;
 mod shortcuts;
+pub mod smf;
 pub mod state;
 mod theme;
 mod transport;
 pub mod widgets;
 
+use std::path::{Path, PathBuf};
+
 use eframe::egui;
 
 pub use state::MatrixState;
 
+use crate::midi::{MidiHandler, MidiOutHandler};
+use crate::midi_learn;
+use header::{ProjectAction, ProjectBar};
 use history::History;
-use state::{ChannelMode, COLS};
+use project::Project;
+use state::ChannelMode;
 use theme::Theme;
 
 #[allow(dead_code)]
@@ -22,6 +32,14 @@ pub struct MatrixSynth {
     state: MatrixState,
     history: History,
     prev_play_col: i32,
+    prev_pattern_slot: [usize; 3],
+    project_path: Option<PathBuf>,
+    project_bar: ProjectBar,
+    /// Controller input; its CCs drive MIDI-learned parameters.
+    pub midi: MidiHandler,
+    /// External gear the sequencer's notes are mirrored to.
+    pub midi_out: MidiOutHandler,
+    midi_map_open: bool,
 }
 
 impl MatrixSynth {
@@ -30,6 +48,81 @@ impl MatrixSynth {
             state: MatrixState::default(),
             history: History::new(),
             prev_play_col: -1,
+            prev_pattern_slot: [0; 3],
+            project_path: None,
+            project_bar: ProjectBar {
+                current: None,
+                names: Project::list_projects(),
+                midi_files: smf::list_midi_files(),
+                save_as_name: String::new(),
+                status: None,
+            },
+            midi: MidiHandler::new(),
+            midi_out: MidiOutHandler::new(),
+            midi_map_open: false,
+        }
+    }
+
+    /// Replace the session with a loaded project. Playback stops.
+    pub fn open_project(&mut self, path: PathBuf, project: Project) {
+        self.state = project.state;
+        self.history = History::new();
+        self.project_bar.current = Some(Project::name_of(&path));
+        self.project_bar.status = None;
+        self.project_path = Some(path);
+    }
+
+    fn save_project(&mut self, path: &Path) {
+        let name = Project::name_of(path);
+        self.project_bar.status = Some(match Project::new(&self.state).save(path) {
+            Ok(()) => {
+                self.project_bar.current = Some(name.clone());
+                self.project_path = Some(path.to_path_buf());
+                self.project_bar.names = Project::list_projects();
+                format!("Saved {name}")
+            }
+            Err(err) => format!("Save failed: {err}"),
+        });
+    }
+
+    fn handle_project_action(&mut self, action: ProjectAction) {
+        match action {
+            ProjectAction::Save => {
+                if let Some(path) = self.project_path.clone() {
+                    self.save_project(&path);
+                }
+            }
+            ProjectAction::SaveAs(name) => {
+                self.save_project(&Project::path_for(&name));
+                self.project_bar.save_as_name.clear();
+            }
+            ProjectAction::Open(name) => {
+                let path = Project::path_for(&name);
+                match Project::load(&path) {
+                    Ok(project) => self.open_project(path, project),
+                    Err(err) => self.project_bar.status = Some(format!("Open failed: {err}")),
+                }
+            }
+            ProjectAction::ExportMidi => {
+                let name = self.project_bar.current.as_deref().unwrap_or("untitled");
+                let path = smf::path_for(name);
+                self.project_bar.status = Some(match smf::save(&self.state, &path) {
+                    Ok(()) => {
+                        self.project_bar.midi_files = smf::list_midi_files();
+                        format!("Exported {}", path.display())
+                    }
+                    Err(err) => format!("Export failed: {err}"),
+                });
+            }
+            ProjectAction::ImportMidi(name) => {
+                self.project_bar.status = Some(match smf::load(&mut self.state, &smf::path_for(&name)) {
+                    Ok(report) => {
+                        self.history = History::new();
+                        report.summary()
+                    }
+                    Err(err) => format!("Import failed: {err}"),
+                });
+            }
         }
     }
 
@@ -44,8 +137,11 @@ impl MatrixSynth {
             Theme::BG_GRADIENT_TOP,
         );
 
-        // Update playhead
-        self.update_playhead(ctx);
+        // Undo history belongs to the pattern it was recorded on
+        if self.state.pattern_slot != self.prev_pattern_slot {
+            self.history = History::new();
+            self.prev_pattern_slot = self.state.pattern_slot;
+        }
 
         // Keyboard shortcuts
         shortcuts::handle(ui, &mut self.state, &mut self.history);
@@ -53,7 +149,16 @@ impl MatrixSynth {
         // Layout
         ui.vertical(|ui| {
             // Header
-            header::draw(ui, &self.state);
+            if let Some(action) = header::draw(
+                ui,
+                &self.state,
+                &mut self.project_bar,
+                &mut self.midi,
+                &mut self.midi_out,
+                &mut self.midi_map_open,
+            ) {
+                self.handle_project_action(action);
+            }
             ui.add_space(14.0);
 
             // Body: sidebar left + main area right
@@ -67,23 +172,31 @@ impl MatrixSynth {
                         egui::ScrollArea::vertical()
                             .auto_shrink([false, false])
                             .show(ui, |ui| {
+                                panels::step::draw(ui, &mut self.state);
                                 match self.state.mode {
                                     ChannelMode::Lead => {
                                         panels::oscillator::draw(ui, &mut self.state);
+                                        panels::osc2::draw(ui, &mut self.state);
+                                        panels::fm::draw(ui, &mut self.state);
                                         panels::envelope::draw(ui, &mut self.state);
                                         panels::filter::draw(ui, &mut self.state);
                                         panels::lfo::draw(ui, &mut self.state);
                                         panels::effects::draw(ui, &mut self.state);
                                         panels::scale::draw(ui, &mut self.state);
+                                        panels::voices::draw(ui, &mut self.state);
+                                        panels::midi_out::draw(ui, &mut self.state);
                                     }
                                     ChannelMode::Drummer => {
                                         panels::drum_kit::draw(ui, &mut self.state);
                                         panels::effects::draw(ui, &mut self.state);
+                                        panels::midi_out::draw(ui, &mut self.state);
                                     }
                                     ChannelMode::Bass => {
                                         panels::bass_preset::draw(ui, &mut self.state);
                                         panels::effects::draw(ui, &mut self.state);
                                         panels::scale::draw(ui, &mut self.state);
+                                        panels::voices::draw(ui, &mut self.state);
+                                        panels::midi_out::draw(ui, &mut self.state);
                                     }
                                 }
                             });
@@ -96,6 +209,8 @@ impl MatrixSynth {
                 ui.vertical(|ui| {
                     transport::draw(ui, &mut self.state, &mut self.history);
                     ui.add_space(8.0);
+                    patterns::draw(ui, &mut self.state);
+                    ui.add_space(8.0);
                     grid::draw(ui, &mut self.state, &mut self.history);
                     ui.add_space(8.0);
                     density_bar::draw(ui, &self.state);
@@ -108,7 +223,7 @@ impl MatrixSynth {
             ui.centered_and_justified(|ui| {
                 ui.label(
                     egui::RichText::new(
-                        "CLICK CELLS TO ACTIVATE \u{00b7} SPACE PLAY/PAUSE \u{00b7} C CLEAR \u{00b7} M MODE",
+                        "CLICK CELLS TO ACTIVATE \u{00b7} RIGHT-CLICK TO INSPECT \u{00b7} ALT+DRAG VELOCITY \u{00b7} SPACE PLAY/PAUSE \u{00b7} C CLEAR \u{00b7} M MODE",
                     )
                     .size(9.0)
                     .color(Theme::TEXT_DIM),
@@ -116,29 +231,14 @@ impl MatrixSynth {
             });
         });
 
+        midi_learn::map_window(ctx, &mut self.midi_map_open);
+
         // Continuous repaint while playing
         if self.state.is_playing {
             ctx.request_repaint();
         }
     }
 
-    fn update_playhead(&mut self, ctx: &egui::Context) {
-        if !self.state.is_playing {
-            return;
-        }
-
-        let dt = ctx.input(|i| i.stable_dt) as f64;
-        self.state.elapsed_secs += dt;
-
-        // Step duration = 1/16 note
-        let step_secs = 60.0 / self.state.bpm as f64 / 4.0;
-
-        while self.state.elapsed_secs >= step_secs {
-            self.state.elapsed_secs -= step_secs;
-            self.state.play_col = (self.state.play_col + 1) % COLS as i32;
-        }
-    }
-
     // ── Public API ──
 
     #[allow(dead_code)]
@@ -146,7 +246,6 @@ impl MatrixSynth {
         &self.state
     }
 
-    #[allow(dead_code)]
     pub fn state_mut(&mut self) -> &mut MatrixState {
         &mut self.state
     }
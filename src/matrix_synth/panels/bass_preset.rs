@@ -1,13 +1,24 @@
 use eframe::egui;
 
 use crate::matrix_synth::state::{BassPreset, MatrixState};
+use crate::matrix_synth::widgets::knob::knob;
 use crate::matrix_synth::widgets::panel::synth_panel;
 use crate::matrix_synth::widgets::select_buttons::select_buttons;
+use crate::midi_learn::learnable;
 
 pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState) {
     synth_panel(ui, "Bass", |ui| {
         let options: Vec<(BassPreset, &str)> =
             BassPreset::ALL.iter().map(|p| (*p, p.label())).collect();
         select_buttons(ui, &mut state.bass_preset, &options);
+
+        ui.add_space(6.0);
+        ui.horizontal(|ui| {
+            select_buttons(ui, &mut state.bass_mono, &[(false, "Poly"), (true, "Mono")]);
+            ui.add_enabled_ui(state.bass_mono, |ui| {
+                let glide = knob(ui, "Glide", &mut state.bass_glide, 0.0, 100.0, 12.0, "", 36.0);
+                learnable(ui, &glide.response, "matrix.bass.glide");
+            });
+        });
     });
 }
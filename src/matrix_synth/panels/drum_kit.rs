@@ -4,15 +4,20 @@ use crate::matrix_synth::state::{DrumPreset, MatrixState};
 use crate::matrix_synth::widgets::knob::knob;
 use crate::matrix_synth::widgets::panel::synth_panel;
 use crate::matrix_synth::widgets::select_buttons::select_buttons;
+use crate::midi_learn::learnable;
 
 pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState) {
     synth_panel(ui, "Drum Kit", |ui| {
-        let options: Vec<(DrumPreset, &str)> =
-            DrumPreset::ALL.iter().map(|p| (*p, p.label())).collect();
-        select_buttons(ui, &mut state.drum_preset, &options);
+        let sampled: Vec<(DrumPreset, &str)> =
+            DrumPreset::SAMPLED.iter().map(|p| (*p, p.label())).collect();
+        select_buttons(ui, &mut state.drum_preset, &sampled);
+        ui.add_space(2.0);
+        let synth: Vec<(DrumPreset, &str)> =
+            DrumPreset::SYNTH.iter().map(|p| (*p, p.label())).collect();
+        select_buttons(ui, &mut state.drum_preset, &synth);
         ui.add_space(4.0);
         ui.horizontal(|ui| {
-            knob(
+            let tune = knob(
                 ui,
                 "Tune",
                 &mut state.drum_tune,
@@ -22,17 +27,19 @@ pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState) {
                 "",
                 40.0,
             );
-            knob(
+            learnable(ui, &tune.response, "matrix.drums.tune");
+            let decay = knob(
                 ui,
                 "Decay",
                 &mut state.drum_decay,
                 0.0,
                 100.0,
-                50.0,
+                100.0,
                 "",
                 40.0,
             );
-            knob(
+            learnable(ui, &decay.response, "matrix.drums.decay");
+            let color = knob(
                 ui,
                 "Color",
                 &mut state.drum_color,
@@ -42,6 +49,7 @@ pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState) {
                 "",
                 40.0,
             );
+            learnable(ui, &color.response, "matrix.drums.color");
         });
     });
 }
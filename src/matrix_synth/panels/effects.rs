@@ -3,11 +3,15 @@ use eframe::egui;
 use crate::matrix_synth::state::MatrixState;
 use crate::matrix_synth::widgets::hslider::hslider;
 use crate::matrix_synth::widgets::panel::synth_panel;
+use crate::midi_learn::learnable;
 
 pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState) {
     synth_panel(ui, "Effects", |ui| {
-        hslider(ui, "Reverb", &mut state.fx_reverb, 0.0, 100.0);
-        hslider(ui, "Delay", &mut state.fx_delay, 0.0, 100.0);
-        hslider(ui, "Chorus", &mut state.fx_chorus, 0.0, 100.0);
+        let reverb = hslider(ui, "Reverb", &mut state.fx_reverb, 0.0, 100.0);
+        learnable(ui, &reverb, "matrix.fx.reverb");
+        let delay = hslider(ui, "Delay", &mut state.fx_delay, 0.0, 100.0);
+        learnable(ui, &delay, "matrix.fx.delay");
+        let chorus = hslider(ui, "Chorus", &mut state.fx_chorus, 0.0, 100.0);
+        learnable(ui, &chorus, "matrix.fx.chorus");
     });
 }
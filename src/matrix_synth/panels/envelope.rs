@@ -3,13 +3,16 @@ use eframe::egui;
 use crate::matrix_synth::state::MatrixState;
 use crate::matrix_synth::widgets::knob::knob;
 use crate::matrix_synth::widgets::panel::synth_panel;
+use crate::midi_learn::learnable;
 
 pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState) {
     synth_panel(ui, "Envelope", |ui| {
         ui.horizontal(|ui| {
-            knob(ui, "A", &mut state.env_attack, 0.0, 100.0, 10.0, "", 36.0);
-            knob(ui, "D", &mut state.env_decay, 0.0, 100.0, 30.0, "", 36.0);
-            knob(
+            let attack = knob(ui, "A", &mut state.env_attack, 0.0, 100.0, 10.0, "", 36.0);
+            learnable(ui, &attack.response, "matrix.env.attack");
+            let decay = knob(ui, "D", &mut state.env_decay, 0.0, 100.0, 30.0, "", 36.0);
+            learnable(ui, &decay.response, "matrix.env.decay");
+            let sustain = knob(
                 ui,
                 "S",
                 &mut state.env_sustain,
@@ -19,7 +22,8 @@ pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState) {
                 "",
                 36.0,
             );
-            knob(
+            learnable(ui, &sustain.response, "matrix.env.sustain");
+            let release = knob(
                 ui,
                 "R",
                 &mut state.env_release,
@@ -29,6 +33,7 @@ pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState) {
                 "",
                 36.0,
             );
+            learnable(ui, &release.response, "matrix.env.release");
         });
     });
 }
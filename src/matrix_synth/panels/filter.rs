@@ -4,6 +4,7 @@ use crate::matrix_synth::state::{FilterType, MatrixState};
 use crate::matrix_synth::widgets::hslider::hslider;
 use crate::matrix_synth::widgets::panel::synth_panel;
 use crate::matrix_synth::widgets::select_buttons::select_buttons;
+use crate::midi_learn::learnable;
 
 pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState) {
     synth_panel(ui, "Filter", |ui| {
@@ -11,7 +12,21 @@ pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState) {
             FilterType::ALL.iter().map(|f| (*f, f.label())).collect();
         select_buttons(ui, &mut state.filter_type, &options);
         ui.add_space(8.0);
-        hslider(ui, "Cutoff", &mut state.filter_cutoff, 0.0, 100.0);
-        hslider(ui, "Reso", &mut state.filter_reso, 0.0, 100.0);
+        let cutoff = hslider(ui, "Cutoff", &mut state.filter_cutoff, 0.0, 100.0);
+        learnable(ui, &cutoff, "matrix.filter.cutoff");
+        let reso = hslider(ui, "Reso", &mut state.filter_reso, 0.0, 100.0);
+        learnable(ui, &reso, "matrix.filter.resonance");
+        ui.add_space(6.0);
+        // Filter envelope; a negative amount sweeps the cutoff down
+        let amount = hslider(ui, "Env", &mut state.filter_env_amount, -100.0, 100.0);
+        learnable(ui, &amount, "matrix.filter.env_amount");
+        let attack = hslider(ui, "Atk", &mut state.filter_env_attack, 0.0, 100.0);
+        learnable(ui, &attack, "matrix.filter.env_attack");
+        let decay = hslider(ui, "Dec", &mut state.filter_env_decay, 0.0, 100.0);
+        learnable(ui, &decay, "matrix.filter.env_decay");
+        let sustain = hslider(ui, "Sus", &mut state.filter_env_sustain, 0.0, 100.0);
+        learnable(ui, &sustain, "matrix.filter.env_sustain");
+        let release = hslider(ui, "Rel", &mut state.filter_env_release, 0.0, 100.0);
+        learnable(ui, &release, "matrix.filter.env_release");
     });
 }
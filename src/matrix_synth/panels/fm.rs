@@ -0,0 +1,63 @@
+use eframe::egui;
+
+use crate::matrix_synth::state::MatrixState;
+use crate::matrix_synth::widgets::knob::knob;
+use crate::matrix_synth::widgets::panel::synth_panel;
+use crate::matrix_synth::widgets::select_buttons::select_buttons;
+use crate::midi_learn::learnable;
+
+pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState) {
+    synth_panel(ui, "FM", |ui| {
+        // On: the oscillator waveform becomes the carrier of a sine modulator
+        select_buttons(ui, &mut state.fm_enabled, &[(false, "Off"), (true, "On")]);
+        if !state.fm_enabled {
+            return;
+        }
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            let ratio = knob(
+                ui,
+                "Ratio",
+                &mut state.fm_ratio,
+                0.5,
+                16.0,
+                1.0,
+                "x",
+                40.0,
+            );
+            learnable(ui, &ratio.response, "matrix.fm.ratio");
+            let index = knob(
+                ui,
+                "Index",
+                &mut state.fm_index,
+                0.0,
+                100.0,
+                20.0,
+                "%",
+                40.0,
+            );
+            learnable(ui, &index.response, "matrix.fm.index");
+            let decay = knob(
+                ui,
+                "Decay",
+                &mut state.fm_decay,
+                0.0,
+                100.0,
+                25.0,
+                "",
+                40.0,
+            );
+            learnable(ui, &decay.response, "matrix.fm.decay");
+            knob(
+                ui,
+                "Sustain",
+                &mut state.fm_sustain,
+                0.0,
+                100.0,
+                30.0,
+                "%",
+                40.0,
+            );
+        });
+    });
+}
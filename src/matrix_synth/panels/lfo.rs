@@ -4,6 +4,7 @@ use crate::matrix_synth::state::{LfoDest, MatrixState};
 use crate::matrix_synth::widgets::knob::knob;
 use crate::matrix_synth::widgets::panel::synth_panel;
 use crate::matrix_synth::widgets::select_buttons::select_buttons;
+use crate::midi_learn::learnable;
 
 pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState) {
     synth_panel(ui, "LFO", |ui| {
@@ -12,7 +13,7 @@ pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState) {
         select_buttons(ui, &mut state.lfo_dest, &options);
         ui.add_space(8.0);
         ui.horizontal(|ui| {
-            knob(
+            let rate = knob(
                 ui,
                 "Rate",
                 &mut state.lfo_rate,
@@ -22,7 +23,8 @@ pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState) {
                 "Hz",
                 36.0,
             );
-            knob(
+            learnable(ui, &rate.response, "matrix.lfo.rate");
+            let depth = knob(
                 ui,
                 "Depth",
                 &mut state.lfo_depth,
@@ -32,6 +34,7 @@ pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState) {
                 "",
                 36.0,
             );
+            learnable(ui, &depth.response, "matrix.lfo.depth");
         });
     });
 }
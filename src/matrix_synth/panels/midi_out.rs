@@ -0,0 +1,27 @@
+use eframe::egui;
+
+use crate::matrix_synth::smf;
+use crate::matrix_synth::state::MatrixState;
+use crate::matrix_synth::widgets::knob::knob;
+use crate::matrix_synth::widgets::panel::synth_panel;
+use crate::matrix_synth::widgets::select_buttons::select_buttons;
+
+/// Whether the channel being edited plays on the MIDI output, and on which
+/// MIDI channel.
+pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState) {
+    let mode = state.mode;
+    let out = &mut state.midi_out_channels[mode.index()];
+    synth_panel(ui, "MIDI Out", |ui| {
+        let mut send = out.is_some();
+        select_buttons(ui, &mut send, &[(false, "Off"), (true, "Send")]);
+        if !send {
+            *out = None;
+            return;
+        }
+        ui.add_space(6.0);
+        let default = smf::midi_channel(mode);
+        let mut channel = out.unwrap_or(default) as f32 + 1.0;
+        knob(ui, "Channel", &mut channel, 1.0, 16.0, default as f32 + 1.0, "", 40.0);
+        *out = Some(channel.round() as u8 - 1);
+    });
+}
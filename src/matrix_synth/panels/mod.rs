@@ -3,7 +3,12 @@ pub mod draw_mode;
 pub mod drum_kit;
 pub mod effects;
 pub mod envelope;
+pub mod fm;
 pub mod filter;
 pub mod lfo;
+pub mod midi_out;
 pub mod oscillator;
+pub mod osc2;
 pub mod scale;
+pub mod step;
+pub mod voices;
@@ -0,0 +1,59 @@
+use eframe::egui;
+
+use crate::matrix_synth::state::{MatrixState, Waveform};
+use crate::matrix_synth::widgets::knob::knob;
+use crate::matrix_synth::widgets::panel::synth_panel;
+use crate::matrix_synth::widgets::select_buttons::select_buttons;
+use crate::midi_learn::learnable;
+
+pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState) {
+    synth_panel(ui, "Osc 2", |ui| {
+        select_buttons(ui, &mut state.osc2_enabled, &[(false, "Off"), (true, "On")]);
+        if !state.osc2_enabled {
+            return;
+        }
+        ui.add_space(6.0);
+        // Osc 2 shares oscillator 1's wavetable
+        let options: Vec<(Waveform, &str)> =
+            Waveform::ALL.iter().map(|w| (*w, w.label())).collect();
+        for waveforms in options.chunks(3) {
+            select_buttons(ui, &mut state.osc2_waveform, waveforms);
+            ui.add_space(4.0);
+        }
+        ui.add_space(6.0);
+        ui.horizontal(|ui| {
+            let mut octave = state.osc2_octave as f32;
+            knob(ui, "Octave", &mut octave, -3.0, 3.0, 0.0, "", 40.0);
+            state.osc2_octave = octave.round() as i32;
+            let mut semitone = state.osc2_semitone as f32;
+            knob(ui, "Semi", &mut semitone, -12.0, 12.0, 0.0, "st", 40.0);
+            state.osc2_semitone = semitone.round() as i32;
+            let fine = knob(
+                ui,
+                "Fine",
+                &mut state.osc2_fine,
+                -100.0,
+                100.0,
+                0.0,
+                "ct",
+                40.0,
+            );
+            learnable(ui, &fine.response, "matrix.osc2.fine");
+            let level = knob(
+                ui,
+                "Level",
+                &mut state.osc2_level,
+                0.0,
+                100.0,
+                50.0,
+                "%",
+                40.0,
+            );
+            learnable(ui, &level.response, "matrix.osc2.level");
+        });
+        ui.add_space(6.0);
+        select_buttons(ui, &mut state.osc2_sync, &[(false, "FREE"), (true, "SYNC")]);
+        ui.add_space(4.0);
+        select_buttons(ui, &mut state.osc2_ring, &[(false, "MIX"), (true, "RING")]);
+    });
+}
@@ -1,18 +1,40 @@
 use eframe::egui;
 
+use crate::engine::wavetable::Wavetable;
 use crate::matrix_synth::state::{MatrixState, Waveform};
 use crate::matrix_synth::widgets::knob::knob;
 use crate::matrix_synth::widgets::panel::synth_panel;
 use crate::matrix_synth::widgets::select_buttons::select_buttons;
+use crate::midi_learn::learnable;
 
 pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState) {
     synth_panel(ui, "Oscillator", |ui| {
         let options: Vec<(Waveform, &str)> =
             Waveform::ALL.iter().map(|w| (*w, w.label())).collect();
-        select_buttons(ui, &mut state.osc_waveform, &options);
+        for (row, waveforms) in options.chunks(3).enumerate() {
+            if row > 0 {
+                ui.add_space(4.0);
+            }
+            select_buttons(ui, &mut state.osc_waveform, waveforms);
+        }
+        if state.osc_waveform == Waveform::Table {
+            ui.add_space(6.0);
+            // Tables are listed from disk only while the list is open
+            let current = state.osc_wavetable.clone();
+            egui::ComboBox::from_id_salt("matrix_wavetable")
+                .selected_text(egui::RichText::new(current.as_deref().unwrap_or("Basic")).size(9.0))
+                .width(ui.available_width() - 8.0)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut state.osc_wavetable, None, "Basic");
+                    for name in Wavetable::list_wavetables() {
+                        let label = name.clone();
+                        ui.selectable_value(&mut state.osc_wavetable, Some(name), label);
+                    }
+                });
+        }
         ui.add_space(10.0);
         ui.horizontal(|ui| {
-            knob(
+            let pitch = knob(
                 ui,
                 "Pitch",
                 &mut state.osc_pitch,
@@ -22,7 +44,8 @@ pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState) {
                 "",
                 40.0,
             );
-            knob(
+            learnable(ui, &pitch.response, "matrix.osc.pitch");
+            let detune = knob(
                 ui,
                 "Detune",
                 &mut state.osc_detune,
@@ -32,6 +55,49 @@ pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState) {
                 "ct",
                 40.0,
             );
+            learnable(ui, &detune.response, "matrix.osc.detune");
+            let spread = knob(
+                ui,
+                "Spread",
+                &mut state.osc_spread,
+                0.0,
+                100.0,
+                50.0,
+                "%",
+                40.0,
+            );
+            learnable(ui, &spread.response, "matrix.osc.spread");
+            let width = knob(
+                ui,
+                "Width",
+                &mut state.osc_width,
+                0.0,
+                100.0,
+                50.0,
+                "%",
+                40.0,
+            );
+            learnable(ui, &width.response, "matrix.osc.width");
         });
+        if state.osc_waveform == Waveform::Table {
+            ui.add_space(6.0);
+            let position = knob(
+                ui,
+                "Position",
+                &mut state.osc_position,
+                0.0,
+                100.0,
+                0.0,
+                "%",
+                40.0,
+            );
+            learnable(ui, &position.response, "matrix.osc.position");
+        }
+        ui.add_space(6.0);
+        select_buttons(
+            ui,
+            &mut state.osc_unison,
+            &[(3, "3 VOICES"), (5, "5 VOICES"), (7, "7 VOICES")],
+        );
     });
 }
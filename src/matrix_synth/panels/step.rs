@@ -0,0 +1,77 @@
+use eframe::egui;
+
+use crate::matrix_synth::state::{ChannelMode, MatrixState, StepParams, MAX_NOTE_LENGTH};
+use crate::matrix_synth::theme::Theme;
+use crate::matrix_synth::widgets::knob::knob;
+use crate::matrix_synth::widgets::panel::synth_panel;
+use crate::matrix_synth::widgets::select_buttons::select_buttons;
+
+/// Step inspector: velocity, length, probability (and slide on bass) of the cell picked with
+/// a right-click in the grid. Hidden while no cell is selected.
+pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState) {
+    let Some((mode, row, col)) = state.selected_step else {
+        return;
+    };
+    if mode != state.mode {
+        return;
+    }
+    let label = state.row_labels()[row];
+    let is_on = state.active_grid()[row][col];
+
+    synth_panel(ui, "Step", |ui| {
+        ui.horizontal(|ui| {
+            ui.label(
+                egui::RichText::new(format!("{label} \u{00b7} step {}", col + 1))
+                    .size(10.0)
+                    .color(Theme::TEXT),
+            );
+            if !is_on {
+                ui.label(egui::RichText::new("(off)").size(9.0).color(Theme::TEXT_DIM));
+            }
+        });
+        ui.add_space(4.0);
+
+        let defaults = StepParams::default();
+        let params = &mut state.active_step_params_mut()[row][col];
+        let mut velocity = params.velocity as f32;
+        let mut probability = params.probability as f32;
+        ui.horizontal(|ui| {
+            knob(ui, "Vel", &mut velocity, 1.0, 127.0, defaults.velocity as f32, "", 40.0);
+            knob(
+                ui,
+                "Length",
+                &mut params.length,
+                0.1,
+                MAX_NOTE_LENGTH,
+                defaults.length,
+                "",
+                40.0,
+            );
+            knob(
+                ui,
+                "Prob",
+                &mut probability,
+                0.0,
+                100.0,
+                defaults.probability as f32,
+                "%",
+                40.0,
+            );
+        });
+        params.velocity = velocity.round() as u8;
+        params.probability = probability.round() as u8;
+
+        // 303-style slides glide the mono bass into the next note
+        if mode == ChannelMode::Bass {
+            ui.add_space(4.0);
+            select_buttons(ui, &mut params.slide, &[(false, "Normal"), (true, "Slide")]);
+        }
+
+        if ui
+            .small_button(egui::RichText::new("Close").size(9.0).color(Theme::TEXT_DIM))
+            .clicked()
+        {
+            state.selected_step = None;
+        }
+    });
+}
@@ -0,0 +1,25 @@
+use eframe::egui;
+
+use crate::matrix_synth::state::{ChannelMode, MatrixState, VoiceSteal};
+use crate::matrix_synth::widgets::knob::knob;
+use crate::matrix_synth::widgets::panel::synth_panel;
+use crate::matrix_synth::widgets::select_buttons::select_buttons;
+
+/// Voice count and stealing policy of the lead or bass channel.
+pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState) {
+    let (count, steal) = match state.mode {
+        ChannelMode::Lead => (&mut state.lead_voices, &mut state.lead_steal),
+        ChannelMode::Bass => (&mut state.bass_voices, &mut state.bass_steal),
+        ChannelMode::Drummer => return,
+    };
+    synth_panel(ui, "Voices", |ui| {
+        let mut voices = *count as f32;
+        knob(ui, "Count", &mut voices, 1.0, 64.0, 8.0, "", 40.0);
+        *count = voices.round() as usize;
+
+        ui.add_space(6.0);
+        let options: Vec<(VoiceSteal, &str)> =
+            VoiceSteal::ALL.iter().map(|s| (*s, s.label())).collect();
+        select_buttons(ui, steal, &options);
+    });
+}
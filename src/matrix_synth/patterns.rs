@@ -0,0 +1,116 @@
+use eframe::egui;
+use eframe::egui::Stroke;
+
+use super::state::{MatrixState, SongStep, StepResolution, MAX_STEPS, PATTERN_SLOTS};
+use super::theme::Theme;
+use super::widgets::select_buttons::select_buttons;
+
+/// Most bars a song step repeats; clicking past it wraps back to 1.
+const MAX_REPEATS: u32 = 8;
+
+fn slot_name(slot: usize) -> String {
+    format!("A{}", slot + 1)
+}
+
+fn chip(ui: &mut egui::Ui, label: &str, fill: egui::Color32, text: egui::Color32) -> egui::Response {
+    ui.add(
+        egui::Button::new(egui::RichText::new(label).size(9.0).strong().color(text))
+            .fill(fill)
+            .stroke(Stroke::new(1.0, Theme::BORDER))
+            .corner_radius(4.0)
+            .min_size(egui::vec2(28.0, 20.0)),
+    )
+}
+
+/// Pattern slots of the current channel with their length and step
+/// resolution, and the song chain below them.
+pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState) {
+    let mode = state.mode;
+    let ch = mode.index();
+
+    ui.horizontal(|ui| {
+        ui.spacing_mut().item_spacing.x = 6.0;
+        ui.label(egui::RichText::new("STEPS").size(9.0).strong().color(Theme::TEXT_DIM));
+        ui.add(egui::DragValue::new(&mut state.pattern_steps[ch]).range(1..=MAX_STEPS));
+        ui.add_space(6.0);
+        ui.label(egui::RichText::new("RES").size(9.0).strong().color(Theme::TEXT_DIM));
+        let options: Vec<(StepResolution, &str)> =
+            StepResolution::ALL.iter().map(|r| (*r, r.label())).collect();
+        select_buttons(ui, &mut state.resolution[ch], &options);
+    });
+
+    ui.add_space(4.0);
+
+    ui.horizontal(|ui| {
+        ui.spacing_mut().item_spacing.x = 3.0;
+        ui.label(egui::RichText::new("PATTERN").size(9.0).strong().color(Theme::TEXT_DIM));
+        for slot in 0..PATTERN_SLOTS {
+            let selected = state.pattern_slot[ch] == slot;
+            let pending = state.pending_slot[ch] == Some(slot);
+            let used = state.slot_pattern(mode, slot).cells.iter().flatten().any(|&on| on);
+            let (fill, text) = if selected {
+                (Theme::ACCENT_DARK, Theme::TEXT_WHITE)
+            } else if pending {
+                (Theme::PANEL_LIGHT, Theme::ACCENT_LIGHT)
+            } else if used {
+                (Theme::PANEL_LIGHT, Theme::TEXT)
+            } else {
+                (Theme::KNOB_BG, Theme::TEXT_DIM)
+            };
+            let response = chip(ui, &slot_name(slot), fill, text)
+                .on_hover_text("Click to switch at the end of the bar, Shift+click to copy the current pattern here");
+            if response.clicked() {
+                if ui.input(|i| i.modifiers.shift) {
+                    state.copy_slot(mode, slot);
+                } else {
+                    state.request_slot(mode, slot);
+                }
+            }
+        }
+    });
+
+    ui.add_space(4.0);
+
+    ui.horizontal(|ui| {
+        ui.spacing_mut().item_spacing.x = 3.0;
+        let (fill, text) = if state.song_mode {
+            (Theme::ACCENT_DARK, Theme::TEXT_WHITE)
+        } else {
+            (Theme::KNOB_BG, Theme::TEXT_DIM)
+        };
+        if chip(ui, "SONG", fill, text).clicked() {
+            state.song_mode = !state.song_mode;
+        }
+
+        let mut remove = None;
+        let playing = state.song_playing.filter(|_| state.song_mode && state.is_playing);
+        for (i, step) in state.song.iter_mut().enumerate() {
+            let label = format!("{} \u{00d7}{}", slot_name(step.slot), step.repeats);
+            let fill = if playing == Some(i) { Theme::ACCENT_DARK } else { Theme::PANEL_LIGHT };
+            let response = chip(ui, &label, fill, Theme::TEXT)
+                .on_hover_text("Click for more repeats, right-click to remove");
+            if response.clicked() {
+                step.repeats = step.repeats % MAX_REPEATS + 1;
+            }
+            if response.secondary_clicked() {
+                remove = Some(i);
+            }
+        }
+        if let Some(i) = remove {
+            state.song.remove(i);
+        }
+
+        if chip(ui, "+", Theme::KNOB_BG, Theme::TEXT)
+            .on_hover_text("Append the selected pattern to the song")
+            .clicked()
+        {
+            state.song.push(SongStep {
+                slot: state.pattern_slot[ch],
+                repeats: 1,
+            });
+        }
+        if !state.song.is_empty() && chip(ui, "CLEAR SONG", Theme::KNOB_BG, Theme::TEXT_DIM).clicked() {
+            state.song.clear();
+        }
+    });
+}
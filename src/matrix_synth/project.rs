@@ -0,0 +1,240 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::state::MatrixState;
+
+/// Current project file format. Bump when a change needs a migration;
+/// fields added with defaults do not.
+pub const PROJECT_VERSION: u32 = 1;
+
+/// A saved Matrix session: grids, transport, scale, all knobs and row mixes.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Project {
+    pub version: u32,
+    pub state: MatrixState,
+}
+
+impl Project {
+    pub fn new(state: &MatrixState) -> Self {
+        Self {
+            version: PROJECT_VERSION,
+            state: state.clone(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+
+    /// Load a project file. Files written by a newer version are rejected
+    /// rather than silently losing what this version does not understand;
+    /// out-of-range slot indices and pattern lengths are clamped.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        let mut project: Self = serde_json::from_str(&json)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if project.version > PROJECT_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "project version {} is newer than supported version {PROJECT_VERSION}",
+                    project.version
+                ),
+            ));
+        }
+        project.state.clamp_loaded();
+        Ok(project)
+    }
+
+    /// Project name shown in the UI: the file stem.
+    pub fn name_of(path: &Path) -> String {
+        path.file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// `~/.synthesis/projects`, next to the synth presets.
+    pub fn projects_dir() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(".synthesis")
+            .join("projects")
+    }
+
+    /// File for project `name` inside `projects_dir()`. The name is passed
+    /// through `file_stem_for`, so it can never point outside that folder.
+    pub fn path_for(name: &str) -> PathBuf {
+        Self::projects_dir().join(format!("{}.json", Self::file_stem_for(name)))
+    }
+
+    /// Make a user-typed name safe to use as a file stem: path separators
+    /// become `_` and leading dots are dropped, which also rules out `..`.
+    pub fn file_stem_for(name: &str) -> String {
+        let stem: String = name
+            .trim()
+            .chars()
+            .map(|c| if matches!(c, '/' | '\\' | ':') || c.is_control() { '_' } else { c })
+            .collect();
+        let stem = stem.trim_start_matches('.');
+        if stem.is_empty() { "untitled".to_string() } else { stem.to_string() }
+    }
+
+    pub fn list_projects() -> Vec<String> {
+        let mut names = Vec::new();
+        if let Ok(entries) = fs::read_dir(Self::projects_dir()) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().is_some_and(|ext| ext == "json") {
+                    names.push(Self::name_of(&path));
+                }
+            }
+        }
+        names.sort();
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix_synth::state::{BassPreset, ChannelMode, DrumPreset, Scale, MAX_STEPS, PATTERN_SLOTS};
+    use std::env;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join("synthesis_test_projects");
+        fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    #[test]
+    fn project_roundtrip() {
+        let mut state = MatrixState {
+            bpm: 96.0,
+            scale: Scale::Minor,
+            drum_preset: DrumPreset::SynthRock,
+            bass_preset: BassPreset::AcidBass,
+            filter_cutoff: 33.0,
+            ..MatrixState::default()
+        };
+        state.grid[3][5] = true;
+        state.drum_grid[14][0] = true;
+        state.bass_grid[15][8] = true;
+        state.drum_row_mute[2] = true;
+        state.bass_row_volume[7] = 0.25;
+
+        let path = temp_path("roundtrip.json");
+        Project::new(&state).save(&path).unwrap();
+        let loaded = Project::load(&path).unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(loaded.version, PROJECT_VERSION);
+        let s = loaded.state;
+        assert_eq!(s.grid, state.grid);
+        assert_eq!(s.drum_grid, state.drum_grid);
+        assert_eq!(s.bass_grid, state.bass_grid);
+        assert_eq!(s.bpm, 96.0);
+        assert_eq!(s.scale, Scale::Minor);
+        assert_eq!(s.drum_preset, DrumPreset::SynthRock);
+        assert_eq!(s.bass_preset, BassPreset::AcidBass);
+        assert_eq!(s.filter_cutoff, 33.0);
+        assert!(s.drum_row_mute[2]);
+        assert_eq!(s.bass_row_volume[7], 0.25);
+    }
+
+    #[test]
+    fn transport_position_is_not_saved() {
+        let state = MatrixState {
+            is_playing: true,
+            play_col: 7,
+            ..MatrixState::default()
+        };
+
+        let json = serde_json::to_string(&Project::new(&state)).unwrap();
+        assert!(!json.contains("is_playing"));
+        let loaded: Project = serde_json::from_str(&json).unwrap();
+        assert!(!loaded.state.is_playing);
+        assert_eq!(loaded.state.play_col, -1);
+    }
+
+    #[test]
+    fn missing_fields_keep_defaults() {
+        let json = r#"{ "version": 1, "state": { "bpm": 140.0 } }"#;
+        let loaded: Project = serde_json::from_str(json).unwrap();
+        let defaults = MatrixState::default();
+        assert_eq!(loaded.state.bpm, 140.0);
+        assert_eq!(loaded.state.swing, defaults.swing);
+        assert_eq!(loaded.state.drum_preset, defaults.drum_preset);
+    }
+
+    #[test]
+    fn newer_version_is_rejected() {
+        let path = temp_path("future.json");
+        let json = format!(r#"{{ "version": {}, "state": {{}} }}"#, PROJECT_VERSION + 1);
+        fs::write(&path, json).unwrap();
+        let err = Project::load(&path).err().expect("newer project should fail to load");
+        let _ = fs::remove_file(&path);
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn out_of_range_slots_are_clamped() {
+        let path = temp_path("bad_slots.json");
+        let json = r#"{ "version": 1, "state": {
+            "pattern_slot": [99, 3, 16],
+            "song": [{ "slot": 40, "repeats": 1 }, { "slot": 2, "repeats": 1 }]
+        } }"#;
+        fs::write(&path, json).unwrap();
+        let loaded = Project::load(&path).unwrap();
+        let _ = fs::remove_file(&path);
+
+        let s = loaded.state;
+        assert_eq!(s.pattern_slot, [PATTERN_SLOTS - 1, 3, PATTERN_SLOTS - 1]);
+        assert_eq!(s.song[0].slot, PATTERN_SLOTS - 1);
+        assert_eq!(s.song[1].slot, 2);
+        for mode in ChannelMode::ALL {
+            let _ = s.slot_pattern(mode, s.song[0].slot);
+        }
+    }
+
+    #[test]
+    fn out_of_range_lengths_are_clamped() {
+        let path = temp_path("bad_lengths.json");
+        let json = r#"{ "version": 1, "state": { "pattern_steps": [500, 0, 12] } }"#;
+        fs::write(&path, json).unwrap();
+        let loaded = Project::load(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(loaded.state.pattern_steps, [MAX_STEPS, 1, 12]);
+    }
+
+    #[test]
+    fn load_missing_file_fails() {
+        assert!(Project::load(Path::new("/nonexistent/project.json")).is_err());
+    }
+
+    #[test]
+    fn path_for_stays_in_projects_dir() {
+        let dir = Project::projects_dir();
+        for name in ["../../etc/passwd", "/abs/beat", "a\\..\\b", "..", " .hidden "] {
+            let path = Project::path_for(name);
+            assert_eq!(path.parent(), Some(dir.as_path()), "{name:?} escaped to {path:?}");
+            let stem = Project::name_of(&path);
+            assert!(!stem.starts_with('.'), "{name:?} gave hidden stem {stem:?}");
+        }
+        assert_eq!(Project::file_stem_for("my beat"), "my beat");
+        assert_eq!(Project::file_stem_for("../x"), "_x");
+        assert_eq!(Project::file_stem_for(".."), "untitled");
+    }
+
+    #[test]
+    fn name_of_uses_file_stem() {
+        assert_eq!(Project::name_of(Path::new("/tmp/my beat.json")), "my beat");
+    }
+}
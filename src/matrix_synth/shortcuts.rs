@@ -70,6 +70,21 @@ pub fn handle(ui: &mut egui::Ui, state: &mut MatrixState, history: &mut History)
         if ui.input(|i| i.key_pressed(Key::Num4)) {
             state.osc_waveform = Waveform::Tri;
         }
+        if ui.input(|i| i.key_pressed(Key::Num5)) {
+            state.osc_waveform = Waveform::Pulse;
+        }
+        if ui.input(|i| i.key_pressed(Key::Num6)) {
+            state.osc_waveform = Waveform::Super;
+        }
+        if ui.input(|i| i.key_pressed(Key::Num7)) {
+            state.osc_waveform = Waveform::Noise;
+        }
+        if ui.input(|i| i.key_pressed(Key::Num8)) {
+            state.osc_waveform = Waveform::Pink;
+        }
+        if ui.input(|i| i.key_pressed(Key::Num9)) {
+            state.osc_waveform = Waveform::Table;
+        }
     }
 
     // ── Filter / LFO cycle ──
@@ -0,0 +1,795 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::state::{ChannelMode, MatrixState, MAX_NOTE_LENGTH, MAX_STEPS, ROWS};
+use super::project::Project;
+use super::state::StepParams;
+
+/// Resolution of exported files. Divisible by every step resolution.
+pub const TICKS_PER_QUARTER: u16 = 96;
+
+/// General MIDI percussion note for each drum row (`DRUM_LABELS` order).
+pub const GM_DRUM_NOTES: [u8; ROWS] = [
+    49, 51, 46, 42, 39, 37, 38, 50, 47, 45, 63, 64, 56, 75, 36, 35,
+];
+
+/// Other General MIDI percussion notes and the drum row they land on.
+const GM_DRUM_ALIASES: [(u8, usize); 10] = [
+    (57, 0),  // Crash Cymbal 2
+    (52, 0),  // Chinese Cymbal
+    (59, 1),  // Ride Cymbal 2
+    (53, 1),  // Ride Bell
+    (44, 3),  // Pedal Hi-Hat
+    (40, 6),  // Electric Snare
+    (48, 7),  // Hi-Mid Tom
+    (43, 9),  // High Floor Tom
+    (41, 9),  // Low Floor Tom
+    (62, 10), // Mute Hi Conga
+];
+
+/// MIDI channel (0-based) of each Matrix channel: lead 1, drums 10, bass 2.
+pub fn midi_channel(mode: ChannelMode) -> u8 {
+    match mode {
+        ChannelMode::Lead => 0,
+        ChannelMode::Drummer => 9,
+        ChannelMode::Bass => 1,
+    }
+}
+
+/// Matrix channel a live MIDI channel plays in multitimbral mode: the
+/// channels `export` writes, others are not heard.
+pub fn channel_mode(channel: u8) -> Option<ChannelMode> {
+    ChannelMode::ALL.into_iter().find(|&mode| midi_channel(mode) == channel)
+}
+
+fn track_name(mode: ChannelMode) -> &'static str {
+    match mode {
+        ChannelMode::Lead => "Lead",
+        ChannelMode::Drummer => "Drums",
+        ChannelMode::Bass => "Bass",
+    }
+}
+
+/// A note of an exported track, in ticks from the start of the bar.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Note {
+    on: u32,
+    off: u32,
+    key: u8,
+    velocity: u8,
+}
+
+fn step_ticks(state: &MatrixState, mode: ChannelMode) -> u32 {
+    TICKS_PER_QUARTER as u32 / state.resolution[mode.index()].steps_per_beat()
+}
+
+fn loop_ticks(state: &MatrixState, mode: ChannelMode) -> u32 {
+    state.pattern_steps[mode.index()].clamp(1, MAX_STEPS) as u32 * step_ticks(state, mode)
+}
+
+/// One bar is the longest channel loop; shorter channels repeat to fill it.
+pub fn bar_ticks(state: &MatrixState) -> u32 {
+    ChannelMode::ALL
+        .iter()
+        .map(|&mode| loop_ticks(state, mode))
+        .fold(1, Ord::max)
+}
+
+/// Notes one channel plays over a bar, with swing, note lengths and row
+/// volumes applied. Probability is ignored: every note is written.
+fn channel_notes(state: &MatrixState, mode: ChannelMode, bar: u32) -> Vec<Note> {
+    let ch = mode.index();
+    let steps = state.pattern_steps[ch].clamp(1, MAX_STEPS);
+    let tps = step_ticks(state, mode);
+    let grid = state.channel_grid(mode);
+    let params = state.channel_step_params(mode);
+    let (mute, volume) = match mode {
+        ChannelMode::Lead => (&state.lead_row_mute, &state.lead_row_volume),
+        ChannelMode::Drummer => (&state.drum_row_mute, &state.drum_row_volume),
+        ChannelMode::Bass => (&state.bass_row_mute, &state.bass_row_volume),
+    };
+    // Odd steps start late by the swing amount, as in the sequencer.
+    let swing = (tps as f32 * 0.5 * state.swing.clamp(0.0, 100.0) / 100.0).round() as u32;
+    let start = |k: usize| k as u32 * tps + if k % 2 == 1 { swing } else { 0 };
+
+    let mut notes = Vec::new();
+    let mut k = 0;
+    while start(k) < bar {
+        let col = k % steps;
+        for row in 0..ROWS {
+            if !grid[row][col] || mute[row] {
+                continue;
+            }
+            let p = params[row][col];
+            let length = p.length.clamp(0.1, MAX_NOTE_LENGTH);
+            let off = if length.fract() == 0.0 {
+                start(k + length as usize)
+            } else {
+                start(k) + (length * tps as f32).round() as u32
+            };
+            let key = match mode {
+                ChannelMode::Lead => state.row_to_midi(row),
+                ChannelMode::Drummer => GM_DRUM_NOTES[row],
+                ChannelMode::Bass => state.row_to_bass_midi(row),
+            };
+            notes.push(Note {
+                on: start(k),
+                off: off.clamp(start(k) + 1, bar),
+                key,
+                velocity: ((p.velocity as f32 * volume[row]) as u8).clamp(1, 127),
+            });
+        }
+        k += 1;
+    }
+
+    // A repeated key cuts the previous one short so note pairs never overlap.
+    for i in 0..notes.len() {
+        let next_on = notes[i + 1..]
+            .iter()
+            .find(|n| n.key == notes[i].key)
+            .map(|n| n.on);
+        if let Some(next_on) = next_on {
+            notes[i].off = notes[i].off.min(next_on);
+        }
+    }
+    notes
+}
+
+fn write_vlq(out: &mut Vec<u8>, mut value: u32) {
+    let mut bytes = [0u8; 4];
+    let mut n = 0;
+    loop {
+        bytes[n] = (value & 0x7F) as u8;
+        n += 1;
+        value >>= 7;
+        if value == 0 {
+            break;
+        }
+    }
+    for i in (0..n).rev() {
+        out.push(if i > 0 { bytes[i] | 0x80 } else { bytes[i] });
+    }
+}
+
+fn write_chunk(out: &mut Vec<u8>, id: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(id);
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(data);
+}
+
+/// Track data from (tick, event bytes) pairs already in play order.
+fn track_data(name: &str, events: &[(u32, Vec<u8>)], end: u32) -> Vec<u8> {
+    let mut data = Vec::new();
+    write_vlq(&mut data, 0);
+    data.extend_from_slice(&[0xFF, 0x03]);
+    write_vlq(&mut data, name.len() as u32);
+    data.extend_from_slice(name.as_bytes());
+
+    let mut now = 0;
+    for (tick, bytes) in events {
+        write_vlq(&mut data, tick - now);
+        data.extend_from_slice(bytes);
+        now = *tick;
+    }
+    write_vlq(&mut data, end.saturating_sub(now));
+    data.extend_from_slice(&[0xFF, 0x2F, 0x00]);
+    data
+}
+
+/// Encode the selected pattern of each channel as a Type-1 Standard MIDI
+/// File: a tempo track, then lead, drum and bass tracks, one bar long.
+pub fn export(state: &MatrixState) -> Vec<u8> {
+    let bar = bar_ticks(state);
+    let mut out = Vec::new();
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&1u16.to_be_bytes());
+    header.extend_from_slice(&(1 + ChannelMode::ALL.len() as u16).to_be_bytes());
+    header.extend_from_slice(&TICKS_PER_QUARTER.to_be_bytes());
+    write_chunk(&mut out, b"MThd", &header);
+
+    let tempo = (60_000_000.0 / state.bpm.max(1.0)).round() as u32;
+    let tempo_events = [
+        (0, vec![0xFF, 0x51, 0x03, (tempo >> 16) as u8, (tempo >> 8) as u8, tempo as u8]),
+        (0, vec![0xFF, 0x58, 0x04, 4, 2, 24, 8]),
+    ];
+    write_chunk(&mut out, b"MTrk", &track_data("Matrix", &tempo_events, bar));
+
+    for mode in ChannelMode::ALL {
+        let channel = midi_channel(mode);
+        let mut events = Vec::new();
+        for n in channel_notes(state, mode, bar) {
+            events.push((n.on, 1u8, vec![0x90 | channel, n.key, n.velocity]));
+            events.push((n.off, 0u8, vec![0x80 | channel, n.key, 0]));
+        }
+        // Note-offs go before note-ons on the same tick.
+        events.sort_by_key(|(tick, order, _)| (*tick, *order));
+        let events: Vec<(u32, Vec<u8>)> = events.into_iter().map(|(t, _, e)| (t, e)).collect();
+        write_chunk(&mut out, b"MTrk", &track_data(track_name(mode), &events, bar));
+    }
+    out
+}
+
+pub fn save(state: &MatrixState, path: &Path) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, export(state))
+}
+
+/// `~/.synthesis/exports`, where the Matrix header writes MIDI files and
+/// looks for files to import.
+pub fn exports_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".synthesis")
+        .join("exports")
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// A note read from a file, in file ticks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FileNote {
+    pub on: u32,
+    pub off: u32,
+    /// 0-based MIDI channel.
+    pub channel: u8,
+    pub key: u8,
+    pub velocity: u8,
+}
+
+/// The parts of a Standard MIDI File the Matrix can use.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MidiFile {
+    pub ticks_per_quarter: u16,
+    /// First tempo in the file, in microseconds per quarter note.
+    pub tempo: Option<u32>,
+    /// Notes of all tracks, ordered by start tick.
+    pub notes: Vec<FileNote>,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + n)
+            .ok_or_else(|| invalid("unexpected end of MIDI data"))?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn vlq(&mut self) -> io::Result<u32> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let b = self.byte()?;
+            value = (value << 7) | (b & 0x7F) as u32;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid("variable-length value longer than 4 bytes"))
+    }
+
+    fn chunk(&mut self) -> io::Result<(&'a [u8], &'a [u8])> {
+        let id = self.take(4)?;
+        let len = u32::from_be_bytes(self.take(4)?.try_into().unwrap()) as usize;
+        Ok((id, self.take(len)?))
+    }
+}
+
+/// Collect the notes and first tempo of one track chunk. Notes still held at
+/// the end of the track end there.
+fn parse_track(data: &[u8], notes: &mut Vec<FileNote>, tempo: &mut Option<u32>) -> io::Result<()> {
+    let mut r = Reader { data, pos: 0 };
+    let mut tick = 0u32;
+    let mut running = None;
+    let mut held: Vec<(u8, u8, u32, u8)> = Vec::new();
+
+    while r.pos < data.len() {
+        tick = tick.saturating_add(r.vlq()?);
+        let first = r.byte()?;
+        match first {
+            0xFF => {
+                let kind = r.byte()?;
+                let len = r.vlq()? as usize;
+                let body = r.take(len)?;
+                if kind == 0x51 && len == 3 && tempo.is_none() {
+                    *tempo = Some(u32::from_be_bytes([0, body[0], body[1], body[2]]));
+                }
+                if kind == 0x2F {
+                    break;
+                }
+            }
+            0xF0 | 0xF7 => {
+                let len = r.vlq()? as usize;
+                r.take(len)?;
+            }
+            _ => {
+                // Data byte first: running status repeats the last status
+                let (status, data1) = if first & 0x80 != 0 {
+                    running = Some(first);
+                    (first, r.byte()?)
+                } else {
+                    (running.ok_or_else(|| invalid("data byte without a status"))?, first)
+                };
+                let channel = status & 0x0F;
+                match status & 0xF0 {
+                    0xC0 | 0xD0 => {}
+                    kind => {
+                        let data2 = r.byte()?;
+                        if kind == 0x90 && data2 > 0 {
+                            held.push((channel, data1, tick, data2));
+                        } else if (kind == 0x80 || kind == 0x90)
+                            && let Some(i) = held.iter().position(|h| h.0 == channel && h.1 == data1)
+                        {
+                            let (_, key, on, velocity) = held.remove(i);
+                            notes.push(FileNote { on, off: tick, channel, key, velocity });
+                        }
+                    }
+                }
+            }
+        }
+    }
+    for (channel, key, on, velocity) in held {
+        notes.push(FileNote { on, off: tick, channel, key, velocity });
+    }
+    Ok(())
+}
+
+/// Read a Type-0 or Type-1 Standard MIDI File.
+pub fn parse(bytes: &[u8]) -> io::Result<MidiFile> {
+    let mut r = Reader { data: bytes, pos: 0 };
+    let (id, header) = r.chunk()?;
+    if id != b"MThd" || header.len() < 6 {
+        return Err(invalid("not a Standard MIDI File"));
+    }
+    let format = u16::from_be_bytes([header[0], header[1]]);
+    if format > 1 {
+        return Err(invalid(format!("MIDI file type {format} is not supported")));
+    }
+    let ticks_per_quarter = u16::from_be_bytes([header[4], header[5]]);
+    if ticks_per_quarter & 0x8000 != 0 || ticks_per_quarter == 0 {
+        return Err(invalid("SMPTE time division is not supported"));
+    }
+
+    let mut tempo = None;
+    let mut notes = Vec::new();
+    while r.pos < bytes.len() {
+        let (id, data) = r.chunk()?;
+        // Unknown chunk types are skipped, as the format requires
+        if id == b"MTrk" {
+            parse_track(data, &mut notes, &mut tempo)?;
+        }
+    }
+    notes.sort_by_key(|n| n.on);
+    Ok(MidiFile { ticks_per_quarter, tempo, notes })
+}
+
+/// Matrix channel a file channel is imported into: MIDI channel 10 to the
+/// drums and 2 to the bass, as written by `export`; all others to the lead.
+fn import_channel(channel: u8) -> ChannelMode {
+    match channel {
+        9 => ChannelMode::Drummer,
+        1 => ChannelMode::Bass,
+        _ => ChannelMode::Lead,
+    }
+}
+
+fn key_to_row(state: &MatrixState, mode: ChannelMode, key: u8) -> Option<usize> {
+    match mode {
+        ChannelMode::Lead => (0..ROWS).find(|&row| state.row_to_midi(row) == key),
+        ChannelMode::Bass => (0..ROWS).find(|&row| state.row_to_bass_midi(row) == key),
+        ChannelMode::Drummer => drum_row(key),
+    }
+}
+
+/// Drum row a General MIDI percussion note plays.
+pub fn drum_row(key: u8) -> Option<usize> {
+    GM_DRUM_NOTES
+        .iter()
+        .position(|&n| n == key)
+        .or_else(|| GM_DRUM_ALIASES.iter().find(|a| a.0 == key).map(|a| a.1))
+}
+
+/// Note name with octave, middle C (60) being C4.
+pub fn note_name(key: u8) -> String {
+    const NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+    format!("{}{}", NAMES[key as usize % 12], key as i32 / 12 - 1)
+}
+
+/// What `import` placed and what it had to leave out.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportReport {
+    /// Notes written to each channel's grid.
+    pub imported: [usize; 3],
+    /// Notes with no row in the current scale or drum map.
+    pub outside: Vec<(ChannelMode, u8)>,
+    /// Notes starting after the last step of the pattern.
+    pub past_end: usize,
+}
+
+impl ImportReport {
+    /// One line for the header status and the CLI.
+    pub fn summary(&self) -> String {
+        let [lead, drums, bass] = self.imported;
+        let mut text = format!("Imported {lead} lead, {drums} drum and {bass} bass notes");
+        if !self.outside.is_empty() {
+            let mut keys: Vec<String> = Vec::new();
+            for &(mode, key) in &self.outside {
+                let name = match mode {
+                    ChannelMode::Drummer => format!("drum {key}"),
+                    _ => note_name(key),
+                };
+                if !keys.contains(&name) {
+                    keys.push(name);
+                }
+            }
+            text += &format!("; {} outside the grid ({})", self.outside.len(), keys.join(", "));
+        }
+        if self.past_end > 0 {
+            text += &format!("; {} past the end of the pattern", self.past_end);
+        }
+        text
+    }
+}
+
+/// Quantize a MIDI file into the selected patterns. Each channel that gets
+/// notes is cleared first and keeps its step count and resolution; the
+/// tempo is taken from the file when it has one.
+pub fn import(state: &mut MatrixState, bytes: &[u8]) -> io::Result<ImportReport> {
+    let file = parse(bytes)?;
+    let mut report = ImportReport::default();
+
+    if let Some(tempo) = file.tempo.filter(|&t| t > 0) {
+        state.bpm = (60_000_000.0 / tempo as f32).round().clamp(40.0, 240.0);
+    }
+    for mode in ChannelMode::ALL {
+        if file.notes.iter().any(|n| import_channel(n.channel) == mode) {
+            state.clear_channel(mode);
+        }
+    }
+
+    for n in &file.notes {
+        let mode = import_channel(n.channel);
+        let ch = mode.index();
+        let step_ticks = file.ticks_per_quarter as f32 / state.resolution[ch].steps_per_beat() as f32;
+        // Halfway notes round down, so swung steps stay on their own step
+        let step = (n.on as f32 / step_ticks - 0.5).ceil().max(0.0) as usize;
+        if step >= state.pattern_steps[ch].clamp(1, MAX_STEPS) {
+            report.past_end += 1;
+            continue;
+        }
+        let Some(row) = key_to_row(state, mode, n.key) else {
+            report.outside.push((mode, n.key));
+            continue;
+        };
+        let length = (n.off.saturating_sub(n.on) as f32 / step_ticks * 10.0).round() / 10.0;
+        state.channel_grid_mut(mode)[row][step] = true;
+        state.channel_step_params_mut(mode)[row][step] = StepParams {
+            velocity: n.velocity.clamp(1, 127),
+            length: length.clamp(0.1, MAX_NOTE_LENGTH),
+            ..StepParams::default()
+        };
+        report.imported[ch] += 1;
+    }
+    Ok(report)
+}
+
+pub fn load(state: &mut MatrixState, path: &Path) -> io::Result<ImportReport> {
+    import(state, &fs::read(path)?)
+}
+
+/// Names of the `.mid` files in `exports_dir()`, offered by the header's
+/// Import menu.
+pub fn list_midi_files() -> Vec<String> {
+    let mut names = Vec::new();
+    if let Ok(entries) = fs::read_dir(exports_dir()) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "mid") {
+                names.push(Project::name_of(&path));
+            }
+        }
+    }
+    names.sort();
+    names
+}
+
+pub fn path_for(name: &str) -> PathBuf {
+    exports_dir().join(format!("{}.mid", Project::file_stem_for(name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix_synth::state::StepResolution;
+
+    /// Decoded channel event: (absolute tick, status, data1, data2).
+    type Event = (u32, u8, u8, u8);
+
+    fn read_vlq(data: &[u8], pos: &mut usize) -> u32 {
+        let mut value = 0;
+        loop {
+            let b = data[*pos];
+            *pos += 1;
+            value = (value << 7) | (b & 0x7F) as u32;
+            if b & 0x80 == 0 {
+                return value;
+            }
+        }
+    }
+
+    /// Split a file into track chunks and decode their channel events.
+    fn decode(file: &[u8]) -> (Vec<u8>, Vec<Vec<Event>>, Vec<Vec<u8>>) {
+        let header = file[8..14].to_vec();
+        let mut tracks = Vec::new();
+        let mut metas = Vec::new();
+        let mut pos = 14;
+        while pos < file.len() {
+            assert_eq!(&file[pos..pos + 4], b"MTrk");
+            let len = u32::from_be_bytes(file[pos + 4..pos + 8].try_into().unwrap()) as usize;
+            let data = &file[pos + 8..pos + 8 + len];
+            let (mut events, mut meta) = (Vec::new(), Vec::new());
+            let (mut p, mut tick) = (0, 0);
+            while p < data.len() {
+                tick += read_vlq(data, &mut p);
+                if data[p] == 0xFF {
+                    let kind = data[p + 1];
+                    p += 2;
+                    let n = read_vlq(data, &mut p) as usize;
+                    meta.push(kind);
+                    if kind == 0x51 {
+                        meta.extend_from_slice(&data[p..p + n]);
+                    }
+                    p += n;
+                } else {
+                    events.push((tick, data[p], data[p + 1], data[p + 2]));
+                    p += 3;
+                }
+            }
+            tracks.push(events);
+            metas.push(meta);
+            pos += 8 + len;
+        }
+        (header, tracks, metas)
+    }
+
+    #[test]
+    fn header_and_tempo() {
+        let state = MatrixState { bpm: 120.0, ..MatrixState::default() };
+        let file = export(&state);
+        assert_eq!(&file[0..4], b"MThd");
+        let (header, tracks, metas) = decode(&file);
+        assert_eq!(header, [0, 1, 0, 4, 0, 96], "type 1, 4 tracks, 96 ticks per quarter");
+        assert_eq!(tracks.len(), 4);
+        // 120 BPM = 500000 µs per quarter
+        assert!(metas[0].windows(4).any(|w| w == [0x51, 0x07, 0xA1, 0x20]));
+    }
+
+    #[test]
+    fn lead_notes_use_scale_and_row_volume() {
+        let mut state = MatrixState::default();
+        state.grid[15][0] = true;
+        state.grid[0][2] = true;
+        state.lead_row_volume[0] = 0.5;
+        let (_, tracks, _) = decode(&export(&state));
+        let lead = &tracks[1];
+        assert_eq!(lead[0], (0, 0x90, state.row_to_midi(15), 100));
+        assert_eq!(lead[1], (24, 0x80, state.row_to_midi(15), 0));
+        assert_eq!(lead[2], (48, 0x90, state.row_to_midi(0), 50));
+    }
+
+    #[test]
+    fn drums_map_to_general_midi_on_channel_10() {
+        let mut state = MatrixState::default();
+        state.drum_grid[14][0] = true; // Kick
+        state.drum_grid[6][4] = true; // Snare
+        let (_, tracks, _) = decode(&export(&state));
+        let ons: Vec<_> = tracks[2].iter().filter(|e| e.1 == 0x99).map(|e| (e.0, e.2)).collect();
+        assert_eq!(ons, [(0, 36), (96, 38)]);
+    }
+
+    #[test]
+    fn live_channels_follow_export_channels() {
+        assert_eq!(channel_mode(0), Some(ChannelMode::Lead));
+        assert_eq!(channel_mode(1), Some(ChannelMode::Bass));
+        assert_eq!(channel_mode(9), Some(ChannelMode::Drummer));
+        assert_eq!(channel_mode(4), None);
+        assert_eq!(drum_row(36), Some(14));
+        assert_eq!(drum_row(40), Some(6));
+        assert_eq!(drum_row(100), None);
+    }
+
+    #[test]
+    fn bass_uses_bass_mapping_and_mutes_rows() {
+        let mut state = MatrixState::default();
+        state.bass_grid[15][0] = true;
+        state.bass_grid[3][1] = true;
+        state.bass_row_mute[3] = true;
+        let (_, tracks, _) = decode(&export(&state));
+        assert_eq!(tracks[3], [(0, 0x91, state.row_to_bass_midi(15), 100), (24, 0x81, state.row_to_bass_midi(15), 0)]);
+    }
+
+    #[test]
+    fn shorter_channels_repeat_over_the_bar() {
+        let mut state = MatrixState { pattern_steps: [3, 16, 16], ..MatrixState::default() };
+        state.grid[15][0] = true;
+        state.drum_grid[14][15] = true;
+        let (_, tracks, _) = decode(&export(&state));
+        let ons: Vec<u32> = tracks[1].iter().filter(|e| e.1 == 0x90).map(|e| e.0).collect();
+        assert_eq!(ons, [0, 72, 144, 216, 288, 360]);
+    }
+
+    #[test]
+    fn lengths_ties_and_resolution() {
+        // Four eighths fill the bar, so the lead plays once
+        let mut state = MatrixState { pattern_steps: [4, 1, 1], ..MatrixState::default() };
+        state.resolution[0] = StepResolution::Eighth;
+        state.grid[15][0] = true;
+        state.lead_step_params[15][0].length = 2.0;
+        state.grid[15][1] = true; // Same key while the tie rings: cut at its start
+        state.grid[14][2] = true;
+        state.lead_step_params[14][2].length = 0.5;
+        let (_, tracks, _) = decode(&export(&state));
+        let key = |row| state.row_to_midi(row);
+        assert_eq!(
+            tracks[1],
+            [
+                (0, 0x90, key(15), 100),
+                (48, 0x80, key(15), 0),
+                (48, 0x90, key(15), 100),
+                (96, 0x80, key(15), 0),
+                (96, 0x90, key(14), 100),
+                (120, 0x80, key(14), 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn swing_delays_odd_steps() {
+        let mut state = MatrixState { swing: 100.0, ..MatrixState::default() };
+        state.grid[15][1] = true;
+        let (_, tracks, _) = decode(&export(&state));
+        assert_eq!(tracks[1][0].0, 36);
+    }
+
+    /// A file from raw track data (events without the MTrk header).
+    fn file(format: u16, division: u16, tracks: &[&[u8]]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut header = format.to_be_bytes().to_vec();
+        header.extend_from_slice(&(tracks.len() as u16).to_be_bytes());
+        header.extend_from_slice(&division.to_be_bytes());
+        write_chunk(&mut out, b"MThd", &header);
+        for track in tracks {
+            write_chunk(&mut out, b"MTrk", track);
+        }
+        out
+    }
+
+    #[test]
+    fn import_round_trips_export() {
+        let mut state = MatrixState { bpm: 97.0, ..MatrixState::default() };
+        state.grid[15][0] = true;
+        state.grid[3][5] = true;
+        state.lead_step_params[3][5].velocity = 64;
+        state.lead_step_params[3][5].length = 2.5;
+        state.drum_grid[14][0] = true;
+        state.drum_grid[2][15] = true;
+        state.bass_grid[10][8] = true;
+        state.bass_step_params[10][8].length = 0.5;
+
+        let mut imported = MatrixState::default();
+        let report = import(&mut imported, &export(&state)).unwrap();
+        assert_eq!(report, ImportReport { imported: [2, 2, 1], ..ImportReport::default() });
+        assert_eq!(imported.bpm, 97.0);
+        assert_eq!(imported.grid, state.grid);
+        assert_eq!(imported.drum_grid, state.drum_grid);
+        assert_eq!(imported.bass_grid, state.bass_grid);
+        assert_eq!(imported.lead_step_params[3][5], state.lead_step_params[3][5]);
+        assert_eq!(imported.bass_step_params[10][8].length, 0.5);
+    }
+
+    #[test]
+    fn swung_export_imports_onto_the_same_steps() {
+        let mut state = MatrixState { swing: 100.0, ..MatrixState::default() };
+        state.grid[15][1] = true;
+        state.grid[15][3] = true;
+        let mut imported = MatrixState::default();
+        import(&mut imported, &export(&state)).unwrap();
+        assert_eq!(imported.grid, state.grid);
+    }
+
+    #[test]
+    fn parse_handles_running_status_and_zero_velocity_off() {
+        // Type 0, 480 ticks per quarter: C4 then E4 with running status,
+        // released by note-ons of velocity 0
+        let track = [
+            0x00, 0xFF, 0x51, 0x03, 0x09, 0x27, 0xC0, // 600000 µs = 100 BPM
+            0x00, 0x90, 60, 90, 0x83, 0x60, 60, 0, 0x00, 64, 80, 0x83, 0x60, 64, 0,
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+        let midi = parse(&file(0, 480, &[&track])).unwrap();
+        assert_eq!(midi.ticks_per_quarter, 480);
+        assert_eq!(midi.tempo, Some(600_000));
+        assert_eq!(
+            midi.notes,
+            [
+                FileNote { on: 0, off: 480, channel: 0, key: 60, velocity: 90 },
+                FileNote { on: 480, off: 960, channel: 0, key: 64, velocity: 80 },
+            ]
+        );
+    }
+
+    #[test]
+    fn import_reports_notes_outside_the_grid() {
+        let mut state = MatrixState { scale: crate::matrix_synth::state::Scale::Major, ..MatrixState::default() };
+        let root = state.row_to_midi(15);
+        let track = [
+            0x00, 0x90, root, 100, 0x18, 0x80, root, 0, // in scale
+            0x00, 0x90, root + 1, 100, 0x18, 0x80, root + 1, 0, // A minor second is not in the major scale
+            0x00, 0x99, 40, 100, 0x18, 0x89, 40, 0, // Electric snare: Snare row
+            0x00, 0x99, 81, 100, 0x18, 0x89, 81, 0, // Open triangle: no row
+            0x83, 0x00, 0x90, root, 100, 0x18, 0x80, root, 0, // Step 20, past the 16 steps
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+        let report = import(&mut state, &file(1, 96, &[&track])).unwrap();
+        assert_eq!(report.imported, [1, 1, 0]);
+        assert_eq!(report.outside, [(ChannelMode::Lead, root + 1), (ChannelMode::Drummer, 81)]);
+        assert_eq!(report.past_end, 1);
+        assert!(state.drum_grid[6][2]);
+        assert_eq!(
+            report.summary(),
+            "Imported 1 lead, 1 drum and 0 bass notes; 2 outside the grid (A#3, drum 81); 1 past the end of the pattern"
+        );
+    }
+
+    #[test]
+    fn import_leaves_channels_without_notes_alone() {
+        let mut state = MatrixState::default();
+        state.grid[0][0] = true;
+        state.bass_grid[0][0] = true;
+        let track = [0x00, 0x99, 36, 100, 0x18, 0x89, 36, 0, 0x00, 0xFF, 0x2F, 0x00];
+        import(&mut state, &file(1, 96, &[&track])).unwrap();
+        assert!(state.grid[0][0] && state.bass_grid[0][0]);
+        assert!(state.drum_grid[14][0]);
+        assert_eq!(state.bpm, 120.0, "no tempo in the file");
+    }
+
+    #[test]
+    fn parse_rejects_unsupported_files() {
+        assert!(parse(b"RIFF0000WAVE").is_err());
+        assert!(parse(&file(2, 96, &[])).is_err());
+        assert!(parse(&file(1, 0xE728, &[])).is_err(), "SMPTE division");
+        let truncated = [0x00, 0x90, 60];
+        assert!(parse(&file(1, 96, &[&truncated])).is_err());
+    }
+
+    #[test]
+    fn note_names() {
+        assert_eq!(note_name(60), "C4");
+        assert_eq!(note_name(49), "C#3");
+        assert_eq!(note_name(0), "C-1");
+    }
+
+    #[test]
+    fn vlq_encoding() {
+        for (value, bytes) in [(0, vec![0x00]), (0x7F, vec![0x7F]), (0x80, vec![0x81, 0x00]), (0x0FFF_FFFF, vec![0xFF, 0xFF, 0xFF, 0x7F])] {
+            let mut out = Vec::new();
+            write_vlq(&mut out, value);
+            assert_eq!(out, bytes);
+        }
+    }
+}
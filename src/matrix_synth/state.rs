@@ -1,5 +1,19 @@
+use serde::{Deserialize, Serialize};
+
 pub const ROWS: usize = 16;
-pub const COLS: usize = 16;
+/// Longest pattern, in steps. Grids always store this many columns;
+/// a pattern's `steps` says how many of them play.
+pub const MAX_STEPS: usize = 64;
+pub const DEFAULT_STEPS: usize = 16;
+
+/// Pattern slots per channel bank (A1–A16).
+pub const PATTERN_SLOTS: usize = 16;
+
+pub type Grid = [[bool; MAX_STEPS]; ROWS];
+pub type StepGrid = [[StepParams; MAX_STEPS]; ROWS];
+
+/// Longest note, in steps.
+pub const MAX_NOTE_LENGTH: f32 = 16.0;
 
 /// Notes displayed top (high) to bottom (low)
 pub const NOTE_LABELS: [&str; ROWS] = [
@@ -35,7 +49,7 @@ pub const DRUM_LABELS: [&str; ROWS] = [
 
 // ── Enums ──
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ChannelMode {
     Lead,
     Drummer,
@@ -44,6 +58,13 @@ pub enum ChannelMode {
 
 impl ChannelMode {
     pub const ALL: [ChannelMode; 3] = [Self::Lead, Self::Drummer, Self::Bass];
+    pub fn index(&self) -> usize {
+        match self {
+            Self::Lead => 0,
+            Self::Drummer => 1,
+            Self::Bass => 2,
+        }
+    }
     pub fn label(&self) -> &'static str {
         match self {
             Self::Lead => "Lead",
@@ -53,7 +74,7 @@ impl ChannelMode {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[allow(clippy::enum_variant_names)]
 pub enum BassPreset {
     SubBass,
@@ -85,52 +106,92 @@ impl BassPreset {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DrumPreset {
     LinnDrum,
     TR505,
     CR78,
+    SynthStandard,
+    SynthRock,
+    SynthJazz,
+    SynthDance,
+    SynthElectronic,
+    SynthLatin,
 }
 
 impl DrumPreset {
-    pub const ALL: [DrumPreset; 3] = [Self::LinnDrum, Self::TR505, Self::CR78];
+    /// WAV kits loaded from `samples/`.
+    pub const SAMPLED: [DrumPreset; 3] = [Self::LinnDrum, Self::TR505, Self::CR78];
+    /// Noise + sine kits synthesized by the drum engine.
+    pub const SYNTH: [DrumPreset; 6] = [
+        Self::SynthStandard, Self::SynthRock, Self::SynthJazz,
+        Self::SynthDance, Self::SynthElectronic, Self::SynthLatin,
+    ];
     pub fn label(&self) -> &'static str {
         match self {
             Self::LinnDrum => "LinnDrum",
             Self::TR505 => "TR-505",
             Self::CR78 => "CR-78",
+            Self::SynthStandard => "Standard",
+            Self::SynthRock => "Rock",
+            Self::SynthJazz => "Jazz",
+            Self::SynthDance => "Dance",
+            Self::SynthElectronic => "Electro",
+            Self::SynthLatin => "Latin",
         }
     }
-    pub fn dir_name(&self) -> &'static str {
+    /// Sample directory for WAV kits; `None` for synthesized kits.
+    pub fn dir_name(&self) -> Option<&'static str> {
         match self {
-            Self::LinnDrum => "lm2",
-            Self::TR505 => "tr505",
-            Self::CR78 => "cr78",
+            Self::LinnDrum => Some("lm2"),
+            Self::TR505 => Some("tr505"),
+            Self::CR78 => Some("cr78"),
+            _ => None,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Waveform {
     Sine,
     Saw,
     Square,
     Tri,
+    Pulse,
+    Super,
+    Noise,
+    Pink,
+    Table,
 }
 
 impl Waveform {
-    pub const ALL: [Waveform; 4] = [Self::Sine, Self::Saw, Self::Square, Self::Tri];
+    pub const ALL: [Waveform; 9] = [
+        Self::Sine,
+        Self::Saw,
+        Self::Square,
+        Self::Tri,
+        Self::Pulse,
+        Self::Super,
+        Self::Noise,
+        Self::Pink,
+        Self::Table,
+    ];
     pub fn label(&self) -> &'static str {
         match self {
             Self::Sine => "Sine",
             Self::Saw => "Saw",
             Self::Square => "Square",
             Self::Tri => "Tri",
+            Self::Pulse => "Pulse",
+            Self::Super => "Super",
+            Self::Noise => "Noise",
+            Self::Pink => "Pink",
+            Self::Table => "Table",
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FilterType {
     LP,
     HP,
@@ -155,32 +216,69 @@ impl FilterType {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum LfoDest {
     Pitch,
     Filter,
     Amp,
+    /// Pulse width of the `Pulse` waveform.
+    Width,
+    /// Frame position of the `Table` waveform.
+    Position,
 }
 
 impl LfoDest {
-    pub const ALL: [LfoDest; 3] = [Self::Pitch, Self::Filter, Self::Amp];
+    pub const ALL: [LfoDest; 5] = [Self::Pitch, Self::Filter, Self::Amp, Self::Width, Self::Position];
     pub fn label(&self) -> &'static str {
         match self {
             Self::Pitch => "Pitch",
             Self::Filter => "Filter",
             Self::Amp => "Amp",
+            Self::Width => "PWM",
+            Self::Position => "Pos",
         }
     }
     pub fn next(&self) -> Self {
         match self {
             Self::Pitch => Self::Filter,
             Self::Filter => Self::Amp,
-            Self::Amp => Self::Pitch,
+            Self::Amp => Self::Width,
+            Self::Width => Self::Position,
+            Self::Position => Self::Pitch,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Held note a channel cuts when all its voices are busy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VoiceSteal {
+    Oldest,
+    Quietest,
+    Lowest,
+    Highest,
+    None,
+}
+
+impl VoiceSteal {
+    pub const ALL: [VoiceSteal; 5] = [
+        Self::Oldest,
+        Self::Quietest,
+        Self::Lowest,
+        Self::Highest,
+        Self::None,
+    ];
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Oldest => "Oldest",
+            Self::Quietest => "Quiet",
+            Self::Lowest => "Low",
+            Self::Highest => "High",
+            Self::None => "None",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DrawMode {
     Toggle,
     Draw,
@@ -200,7 +298,46 @@ impl DrawMode {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Note value of one grid step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StepResolution {
+    Eighth,
+    Sixteenth,
+    ThirtySecond,
+    EighthTriplet,
+    SixteenthTriplet,
+}
+
+impl StepResolution {
+    pub const ALL: [StepResolution; 5] = [
+        Self::Eighth,
+        Self::Sixteenth,
+        Self::ThirtySecond,
+        Self::EighthTriplet,
+        Self::SixteenthTriplet,
+    ];
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Eighth => "1/8",
+            Self::Sixteenth => "1/16",
+            Self::ThirtySecond => "1/32",
+            Self::EighthTriplet => "1/8T",
+            Self::SixteenthTriplet => "1/16T",
+        }
+    }
+    /// Steps per quarter note.
+    pub fn steps_per_beat(&self) -> u32 {
+        match self {
+            Self::Eighth => 2,
+            Self::Sixteenth => 4,
+            Self::ThirtySecond => 8,
+            Self::EighthTriplet => 3,
+            Self::SixteenthTriplet => 6,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Scale {
     Chromatic,
     Major,
@@ -228,24 +365,125 @@ impl Scale {
     }
 }
 
+/// Per-cell note settings, kept alongside the on/off grid.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StepParams {
+    /// MIDI velocity 1–127, scaled by the row volume.
+    pub velocity: u8,
+    /// Note length in steps: 1 releases on the next step, more ties over
+    /// the following steps (up to `MAX_NOTE_LENGTH`).
+    pub length: f32,
+    /// Chance in percent that the note plays each time its step comes round.
+    pub probability: u8,
+    /// Bass: hold into the next step's note and glide to it, 303-style.
+    /// Needs the mono bass. Only saved when set.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub slide: bool,
+}
+
+impl Default for StepParams {
+    fn default() -> Self {
+        Self {
+            velocity: 100,
+            length: 1.0,
+            probability: 100,
+            slide: false,
+        }
+    }
+}
+
+pub fn default_step_grid() -> Box<StepGrid> {
+    Box::new([[StepParams::default(); MAX_STEPS]; ROWS])
+}
+
+/// A stored pattern of one channel bank.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PatternSlot {
+    #[serde(with = "grid_format")]
+    pub cells: Grid,
+    #[serde(with = "step_params_format")]
+    pub params: Box<StepGrid>,
+    pub steps: usize,
+    pub resolution: StepResolution,
+}
+
+impl Default for PatternSlot {
+    fn default() -> Self {
+        Self {
+            cells: [[false; MAX_STEPS]; ROWS],
+            params: default_step_grid(),
+            steps: DEFAULT_STEPS,
+            resolution: StepResolution::Sixteenth,
+        }
+    }
+}
+
+/// One link of the song chain: a pattern slot played `repeats` bars in a row
+/// on every channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SongStep {
+    pub slot: usize,
+    pub repeats: u32,
+}
+
 // ── Main state ──
 
-#[derive(Clone)]
+/// Everything a Matrix project saves. Fields missing from a project file keep
+/// their defaults; transport position is session state and never saved.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct MatrixState {
-    // Grid matrices (one per mode, preserved when switching)
-    pub grid: [[bool; COLS]; ROWS],
-    pub drum_grid: [[bool; COLS]; ROWS],
-    pub bass_grid: [[bool; COLS]; ROWS],
+    // Grid matrices (one per mode, preserved when switching).
+    // Each holds the channel's selected pattern slot.
+    #[serde(with = "grid_format")]
+    pub grid: Grid,
+    #[serde(with = "grid_format")]
+    pub drum_grid: Grid,
+    #[serde(with = "grid_format")]
+    pub bass_grid: Grid,
+    // Velocity, length and probability of each cell, per mode
+    #[serde(with = "step_params_format")]
+    pub lead_step_params: Box<StepGrid>,
+    #[serde(with = "step_params_format")]
+    pub drum_step_params: Box<StepGrid>,
+    #[serde(with = "step_params_format")]
+    pub bass_step_params: Box<StepGrid>,
+    /// Cell shown in the step inspector, as (mode, row, col).
+    #[serde(skip)]
+    pub selected_step: Option<(ChannelMode, usize, usize)>,
+    /// Length and step resolution of each channel's selected pattern,
+    /// indexed by `ChannelMode::index()`. Different lengths make polymeters.
+    pub pattern_steps: [usize; 3],
+    pub resolution: [StepResolution; 3],
+
+    // Pattern banks, indexed [channel][slot]: always 3 × PATTERN_SLOTS.
+    // The selected slot's entry is stale while selected: its live copy is
+    // the channel grid above.
+    #[serde(deserialize_with = "deserialize_banks")]
+    pub banks: Vec<Vec<PatternSlot>>,
+    pub pattern_slot: [usize; 3],
+    /// Slot switches waiting for the end of the bar while playing.
+    #[serde(skip)]
+    pub pending_slot: [Option<usize>; 3],
+
+    // Song mode: chain of pattern slots played in order
+    pub song: Vec<SongStep>,
+    pub song_mode: bool,
+    /// Song step currently playing, reported by the sequencer.
+    #[serde(skip)]
+    pub song_playing: Option<usize>,
 
     // Channel mode
     pub mode: ChannelMode,
 
     // Transport
+    #[serde(skip)]
     pub is_playing: bool,
-    pub play_col: i32,     // -1 = stopped, 0..15 = active column
-    pub bpm: f32,          // 40.0 ..= 240.0
-    pub swing: f32,        // 0.0 ..= 100.0
-    pub elapsed_secs: f64, // accumulator for step advancement
+    #[serde(skip)]
+    pub play_col: i32, // -1 = stopped, 0..15 = column played by the sequencer
+    pub bpm: f32,      // 40.0 ..= 240.0
+    pub swing: f32,    // 0.0 ..= 100.0
 
     // Draw mode
     pub draw_mode: DrawMode,
@@ -255,8 +493,31 @@ pub struct MatrixState {
 
     // Oscillator
     pub osc_waveform: Waveform,
-    pub osc_pitch: f32,  // 0.0 ..= 100.0
-    pub osc_detune: f32, // 0.0 ..= 100.0 (cents)
+    pub osc_pitch: f32,    // 0.0 ..= 100.0 (50 = no offset, ±24 semitones)
+    pub osc_detune: f32,   // 0.0 ..= 100.0 (cents)
+    pub osc_unison: usize, // stacked copies when detuned: 3, 5 or 7
+    pub osc_spread: f32,   // 0.0 ..= 100.0 (stereo width)
+    pub osc_width: f32,    // 0.0 ..= 100.0 (pulse width, 50 = square)
+    /// Wavetable file played by the `Table` waveform; `None` is the built-in table.
+    pub osc_wavetable: Option<String>,
+    pub osc_position: f32, // 0.0 ..= 100.0 (wavetable frame)
+
+    // Oscillator 2
+    pub osc2_enabled: bool,
+    pub osc2_waveform: Waveform,
+    pub osc2_octave: i32,   // -3 ..= 3
+    pub osc2_semitone: i32, // -12 ..= 12
+    pub osc2_fine: f32,     // -100.0 ..= 100.0 (cents)
+    pub osc2_level: f32,    // 0.0 ..= 100.0
+    pub osc2_sync: bool,    // hard sync to oscillator 1
+    pub osc2_ring: bool,    // ring-modulate with oscillator 1 instead of mixing
+
+    // FM (lead voices switch to two-operator FM while enabled)
+    pub fm_enabled: bool,
+    pub fm_ratio: f32,   // 0.5 ..= 16.0 (modulator / note frequency)
+    pub fm_index: f32,   // 0.0 ..= 100.0 (peak index 0–10)
+    pub fm_decay: f32,   // 0.0 ..= 100.0 (index envelope decay)
+    pub fm_sustain: f32, // 0.0 ..= 100.0 (index envelope sustain)
 
     // ADSR
     pub env_attack: f32,  // 0.0 ..= 100.0
@@ -268,6 +529,11 @@ pub struct MatrixState {
     pub filter_type: FilterType,
     pub filter_cutoff: f32, // 0.0 ..= 100.0
     pub filter_reso: f32,
+    pub filter_env_amount: f32,  // -100.0 ..= 100.0 (bipolar, ±6 octaves)
+    pub filter_env_attack: f32,  // 0.0 ..= 100.0
+    pub filter_env_decay: f32,
+    pub filter_env_sustain: f32,
+    pub filter_env_release: f32,
 
     // LFO
     pub lfo_rate: f32,
@@ -287,6 +553,19 @@ pub struct MatrixState {
 
     // Bass preset
     pub bass_preset: BassPreset,
+    /// One legato bass voice: overlapping and sliding notes glide.
+    pub bass_mono: bool,
+    pub bass_glide: f32, // 0.0 ..= 100.0 (0–500 ms)
+
+    // Polyphony and voice stealing of the lead and bass channels
+    pub lead_voices: usize, // 1 ..= 64
+    pub lead_steal: VoiceSteal,
+    pub bass_voices: usize,
+    pub bass_steal: VoiceSteal,
+
+    /// MIDI out channel (0–15) of each Matrix channel (`ChannelMode::index`
+    /// order); `None` keeps the channel off the MIDI output.
+    pub midi_out_channels: [Option<u8>; 3],
 
     // Per-row mute & volume (independent per mode)
     pub lead_row_mute:   [bool; ROWS],
@@ -300,20 +579,49 @@ pub struct MatrixState {
 impl Default for MatrixState {
     fn default() -> Self {
         Self {
-            grid: [[false; COLS]; ROWS],
-            drum_grid: [[false; COLS]; ROWS],
-            bass_grid: [[false; COLS]; ROWS],
+            grid: [[false; MAX_STEPS]; ROWS],
+            drum_grid: [[false; MAX_STEPS]; ROWS],
+            bass_grid: [[false; MAX_STEPS]; ROWS],
+            lead_step_params: default_step_grid(),
+            drum_step_params: default_step_grid(),
+            bass_step_params: default_step_grid(),
+            selected_step: None,
+            pattern_steps: [DEFAULT_STEPS; 3],
+            resolution: [StepResolution::Sixteenth; 3],
+            banks: vec![vec![PatternSlot::default(); PATTERN_SLOTS]; 3],
+            pattern_slot: [0; 3],
+            pending_slot: [None; 3],
+            song: Vec::new(),
+            song_mode: false,
+            song_playing: None,
             mode: ChannelMode::Lead,
             is_playing: false,
             play_col: -1,
             bpm: 120.0,
             swing: 0.0,
-            elapsed_secs: 0.0,
             draw_mode: DrawMode::Toggle,
             scale: Scale::Chromatic,
             osc_waveform: Waveform::Saw,
             osc_pitch: 50.0,
             osc_detune: 0.0,
+            osc_unison: 5,
+            osc_spread: 50.0,
+            osc_width: 50.0,
+            osc_wavetable: None,
+            osc_position: 0.0,
+            osc2_enabled: false,
+            osc2_waveform: Waveform::Saw,
+            osc2_octave: 0,
+            osc2_semitone: 0,
+            osc2_fine: 0.0,
+            osc2_level: 50.0,
+            osc2_sync: false,
+            osc2_ring: false,
+            fm_enabled: false,
+            fm_ratio: 1.0,
+            fm_index: 20.0,
+            fm_decay: 25.0,
+            fm_sustain: 30.0,
             env_attack: 10.0,
             env_decay: 30.0,
             env_sustain: 70.0,
@@ -321,6 +629,11 @@ impl Default for MatrixState {
             filter_type: FilterType::LP,
             filter_cutoff: 75.0,
             filter_reso: 30.0,
+            filter_env_amount: 0.0,
+            filter_env_attack: 0.0,
+            filter_env_decay: 15.0,
+            filter_env_sustain: 0.0,
+            filter_env_release: 15.0,
             lfo_rate: 30.0,
             lfo_depth: 50.0,
             lfo_dest: LfoDest::Filter,
@@ -329,9 +642,16 @@ impl Default for MatrixState {
             fx_chorus: 15.0,
             drum_preset: DrumPreset::LinnDrum,
             drum_tune: 50.0,
-            drum_decay: 50.0,
+            drum_decay: 100.0,
             drum_color: 50.0,
             bass_preset: BassPreset::SubBass,
+            bass_mono: false,
+            bass_glide: 12.0,
+            lead_voices: 8,
+            lead_steal: VoiceSteal::Oldest,
+            bass_voices: 8,
+            bass_steal: VoiceSteal::Oldest,
+            midi_out_channels: [Some(0), Some(9), Some(1)],
             lead_row_mute: [false; ROWS],
             lead_row_volume: [1.0; ROWS],
             drum_row_mute: [false; ROWS],
@@ -344,7 +664,7 @@ impl Default for MatrixState {
 
 impl MatrixState {
     /// Return the active grid based on current mode.
-    pub fn active_grid(&self) -> &[[bool; COLS]; ROWS] {
+    pub fn active_grid(&self) -> &[[bool; MAX_STEPS]; ROWS] {
         match self.mode {
             ChannelMode::Lead => &self.grid,
             ChannelMode::Drummer => &self.drum_grid,
@@ -353,7 +673,7 @@ impl MatrixState {
     }
 
     /// Return mutable reference to the active grid.
-    pub fn active_grid_mut(&mut self) -> &mut [[bool; COLS]; ROWS] {
+    pub fn active_grid_mut(&mut self) -> &mut [[bool; MAX_STEPS]; ROWS] {
         match self.mode {
             ChannelMode::Lead => &mut self.grid,
             ChannelMode::Drummer => &mut self.drum_grid,
@@ -361,6 +681,133 @@ impl MatrixState {
         }
     }
 
+    /// Grid holding the selected pattern of a channel.
+    pub fn channel_grid(&self, mode: ChannelMode) -> &Grid {
+        match mode {
+            ChannelMode::Lead => &self.grid,
+            ChannelMode::Drummer => &self.drum_grid,
+            ChannelMode::Bass => &self.bass_grid,
+        }
+    }
+
+    pub fn channel_grid_mut(&mut self, mode: ChannelMode) -> &mut Grid {
+        match mode {
+            ChannelMode::Lead => &mut self.grid,
+            ChannelMode::Drummer => &mut self.drum_grid,
+            ChannelMode::Bass => &mut self.bass_grid,
+        }
+    }
+
+    /// Step parameters of a channel's selected pattern.
+    pub fn channel_step_params(&self, mode: ChannelMode) -> &StepGrid {
+        match mode {
+            ChannelMode::Lead => &self.lead_step_params,
+            ChannelMode::Drummer => &self.drum_step_params,
+            ChannelMode::Bass => &self.bass_step_params,
+        }
+    }
+
+    pub fn channel_step_params_mut(&mut self, mode: ChannelMode) -> &mut StepGrid {
+        match mode {
+            ChannelMode::Lead => &mut self.lead_step_params,
+            ChannelMode::Drummer => &mut self.drum_step_params,
+            ChannelMode::Bass => &mut self.bass_step_params,
+        }
+    }
+
+    pub fn active_step_params(&self) -> &StepGrid {
+        self.channel_step_params(self.mode)
+    }
+
+    pub fn active_step_params_mut(&mut self) -> &mut StepGrid {
+        self.channel_step_params_mut(self.mode)
+    }
+
+    /// A pattern slot, reading the live grid and timing for the selected slot.
+    pub fn slot_pattern(&self, mode: ChannelMode, slot: usize) -> PatternSlot {
+        let ch = mode.index();
+        if slot == self.pattern_slot[ch] {
+            PatternSlot {
+                cells: *self.channel_grid(mode),
+                params: Box::new(*self.channel_step_params(mode)),
+                steps: self.pattern_steps[ch],
+                resolution: self.resolution[ch],
+            }
+        } else {
+            self.banks[ch][slot].clone()
+        }
+    }
+
+    /// Step count of the current mode's pattern.
+    pub fn active_steps(&self) -> usize {
+        self.pattern_steps[self.mode.index()].clamp(1, MAX_STEPS)
+    }
+
+    /// Make `slot` the channel's edited and played pattern right away.
+    pub fn select_slot(&mut self, mode: ChannelMode, slot: usize) {
+        let ch = mode.index();
+        self.pending_slot[ch] = None;
+        let current = self.pattern_slot[ch];
+        if slot == current || slot >= PATTERN_SLOTS {
+            return;
+        }
+        self.banks[ch][current] = self.slot_pattern(mode, current);
+        let next = self.banks[ch][slot].clone();
+        *self.channel_grid_mut(mode) = next.cells;
+        *self.channel_step_params_mut(mode) = *next.params;
+        self.pattern_steps[ch] = next.steps;
+        self.resolution[ch] = next.resolution;
+        self.pattern_slot[ch] = slot;
+        if self.selected_step.is_some_and(|(m, _, _)| m == mode) {
+            self.selected_step = None;
+        }
+    }
+
+    /// Ask for a slot change: while a pattern loop is playing it waits for
+    /// the end of the bar, otherwise it happens at once.
+    pub fn request_slot(&mut self, mode: ChannelMode, slot: usize) {
+        let ch = mode.index();
+        if self.is_playing && !self.song_mode {
+            self.pending_slot[ch] = (slot != self.pattern_slot[ch]).then_some(slot);
+        } else {
+            self.select_slot(mode, slot);
+        }
+    }
+
+    /// Apply every pending slot switch (the bar has ended).
+    pub fn apply_pending_slots(&mut self) {
+        for mode in ChannelMode::ALL {
+            if let Some(slot) = self.pending_slot[mode.index()] {
+                self.select_slot(mode, slot);
+            }
+        }
+    }
+
+    /// Copy the channel's selected pattern into another slot.
+    pub fn copy_slot(&mut self, mode: ChannelMode, to: usize) {
+        let ch = mode.index();
+        if to != self.pattern_slot[ch] && to < PATTERN_SLOTS {
+            self.banks[ch][to] = self.slot_pattern(mode, self.pattern_slot[ch]);
+        }
+    }
+
+    /// Bring indices and lengths read from a project file back in range, so
+    /// a hand-edited or corrupt file cannot index past the banks or grids.
+    pub fn clamp_loaded(&mut self) {
+        for slot in &mut self.pattern_slot {
+            *slot = (*slot).min(PATTERN_SLOTS - 1);
+        }
+        for steps in &mut self.pattern_steps {
+            *steps = (*steps).clamp(1, MAX_STEPS);
+        }
+        for slot in self.banks.iter_mut().flatten() {
+            slot.steps = slot.steps.clamp(1, MAX_STEPS);
+        }
+        for step in &mut self.song {
+            step.slot = step.slot.min(PATTERN_SLOTS - 1);
+        }
+    }
+
     /// Row labels for the current mode.
     pub fn row_labels(&self) -> &[&'static str; ROWS] {
         match self.mode {
@@ -371,24 +818,34 @@ impl MatrixState {
     }
 
     pub fn clear_grid(&mut self) {
-        *self.active_grid_mut() = [[false; COLS]; ROWS];
+        self.clear_channel(self.mode);
+    }
+
+    /// Empty a channel's selected pattern and reset its step parameters.
+    pub fn clear_channel(&mut self, mode: ChannelMode) {
+        *self.channel_grid_mut(mode) = [[false; MAX_STEPS]; ROWS];
+        *self.channel_step_params_mut(mode) = *default_step_grid();
+        if self.selected_step.is_some_and(|(m, _, _)| m == mode) {
+            self.selected_step = None;
+        }
     }
 
     pub fn toggle_play(&mut self) {
         self.is_playing = !self.is_playing;
         if self.is_playing {
             self.play_col = 0;
-            self.elapsed_secs = 0.0;
         } else {
             self.play_col = -1;
         }
     }
 
+    /// Toggle the played steps of a row.
     pub fn toggle_row(&mut self, row: usize) {
+        let steps = self.active_steps();
         let grid = self.active_grid_mut();
-        let all_on = grid[row].iter().all(|&v| v);
-        for col in 0..COLS {
-            grid[row][col] = !all_on;
+        let all_on = grid[row][..steps].iter().all(|&v| v);
+        for cell in grid[row][..steps].iter_mut() {
+            *cell = !all_on;
         }
     }
 
@@ -472,6 +929,135 @@ impl MatrixState {
             ChannelMode::Bass => &mut self.bass_row_volume,
         }
     }
+
+    /// Parameter addressed by a MIDI learn key, with its range.
+    pub fn learn_param_mut(&mut self, key: &str) -> Option<(&mut f32, f32, f32)> {
+        let value = match key {
+            "matrix.bpm" => return Some((&mut self.bpm, 40.0, 240.0)),
+            "matrix.swing" => &mut self.swing,
+            "matrix.osc.pitch" => &mut self.osc_pitch,
+            "matrix.osc.detune" => &mut self.osc_detune,
+            "matrix.osc.spread" => &mut self.osc_spread,
+            "matrix.osc.width" => &mut self.osc_width,
+            "matrix.osc.position" => &mut self.osc_position,
+            "matrix.osc2.fine" => return Some((&mut self.osc2_fine, -100.0, 100.0)),
+            "matrix.osc2.level" => &mut self.osc2_level,
+            "matrix.fm.ratio" => return Some((&mut self.fm_ratio, 0.5, 16.0)),
+            "matrix.fm.index" => &mut self.fm_index,
+            "matrix.fm.decay" => &mut self.fm_decay,
+            "matrix.env.attack" => &mut self.env_attack,
+            "matrix.env.decay" => &mut self.env_decay,
+            "matrix.env.sustain" => &mut self.env_sustain,
+            "matrix.env.release" => &mut self.env_release,
+            "matrix.filter.cutoff" => &mut self.filter_cutoff,
+            "matrix.filter.resonance" => &mut self.filter_reso,
+            "matrix.filter.env_amount" => return Some((&mut self.filter_env_amount, -100.0, 100.0)),
+            "matrix.filter.env_attack" => &mut self.filter_env_attack,
+            "matrix.filter.env_decay" => &mut self.filter_env_decay,
+            "matrix.filter.env_sustain" => &mut self.filter_env_sustain,
+            "matrix.filter.env_release" => &mut self.filter_env_release,
+            "matrix.lfo.rate" => &mut self.lfo_rate,
+            "matrix.lfo.depth" => &mut self.lfo_depth,
+            "matrix.fx.reverb" => &mut self.fx_reverb,
+            "matrix.fx.delay" => &mut self.fx_delay,
+            "matrix.fx.chorus" => &mut self.fx_chorus,
+            "matrix.drums.tune" => &mut self.drum_tune,
+            "matrix.drums.decay" => &mut self.drum_decay,
+            "matrix.drums.color" => &mut self.drum_color,
+            "matrix.bass.glide" => &mut self.bass_glide,
+            _ => {
+                // matrix.<channel>.volume.<row>, rows counted from 1
+                let (channel, row) = key.strip_prefix("matrix.")?.split_once(".volume.")?;
+                let volumes = match channel {
+                    "lead" => &mut self.lead_row_volume,
+                    "drums" => &mut self.drum_row_volume,
+                    "bass" => &mut self.bass_row_volume,
+                    _ => return None,
+                };
+                let volume = volumes.get_mut(row.parse::<usize>().ok()?.checked_sub(1)?)?;
+                return Some((volume, 0.0, 1.0));
+            }
+        };
+        Some((value, 0.0, 100.0))
+    }
+}
+
+/// Pad or cut loaded banks to 3 channels of `PATTERN_SLOTS` slots.
+fn deserialize_banks<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Vec<Vec<PatternSlot>>, D::Error> {
+    let mut banks = Vec::<Vec<PatternSlot>>::deserialize(d)?;
+    banks.resize(3, Vec::new());
+    for bank in &mut banks {
+        bank.resize(PATTERN_SLOTS, PatternSlot::default());
+    }
+    Ok(banks)
+}
+
+/// Step parameters are saved sparsely: only cells that differ from the
+/// defaults, as `[row, col, params]` entries.
+mod step_params_format {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{default_step_grid, StepGrid, StepParams, MAX_STEPS, ROWS};
+
+    #[allow(clippy::borrowed_box)]
+    pub fn serialize<S: Serializer>(params: &Box<StepGrid>, s: S) -> Result<S::Ok, S::Error> {
+        let default = StepParams::default();
+        let mut cells = Vec::new();
+        for (row, cols) in params.iter().enumerate() {
+            for (col, p) in cols.iter().enumerate() {
+                if *p != default {
+                    cells.push((row, col, *p));
+                }
+            }
+        }
+        cells.serialize(s)
+    }
+
+    /// Entries outside the grid are ignored.
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Box<StepGrid>, D::Error> {
+        let cells = Vec::<(usize, usize, StepParams)>::deserialize(d)?;
+        let mut params = default_step_grid();
+        for (row, col, p) in cells {
+            if row < ROWS && col < MAX_STEPS {
+                params[row][col] = p;
+            }
+        }
+        Ok(params)
+    }
+}
+
+/// Grids are saved as one string per row, `x` for an active cell and `.`
+/// for an empty one, so project files stay small and readable. Rows are cut
+/// after the last active column (at least `DEFAULT_STEPS` wide).
+mod grid_format {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{Grid, DEFAULT_STEPS, MAX_STEPS, ROWS};
+
+    pub fn serialize<S: Serializer>(grid: &Grid, s: S) -> Result<S::Ok, S::Error> {
+        let width = grid
+            .iter()
+            .filter_map(|row| row.iter().rposition(|&on| on))
+            .map(|last| last + 1)
+            .fold(DEFAULT_STEPS, Ord::max);
+        let rows: Vec<String> = grid
+            .iter()
+            .map(|row| row[..width].iter().map(|&on| if on { 'x' } else { '.' }).collect())
+            .collect();
+        rows.serialize(s)
+    }
+
+    /// Missing rows or columns stay empty; extra ones are ignored.
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Grid, D::Error> {
+        let rows = Vec::<String>::deserialize(d)?;
+        let mut grid = [[false; MAX_STEPS]; ROWS];
+        for (cells, row) in grid.iter_mut().zip(&rows) {
+            for (cell, c) in cells.iter_mut().zip(row.chars()) {
+                *cell = c == 'x';
+            }
+        }
+        Ok(grid)
+    }
 }
 
 #[cfg(test)]
@@ -493,7 +1079,7 @@ mod tests {
     fn grid_starts_empty() {
         let s = MatrixState::default();
         for row in 0..ROWS {
-            for col in 0..COLS {
+            for col in 0..MAX_STEPS {
                 assert!(!s.grid[row][col]);
             }
         }
@@ -519,7 +1105,7 @@ mod tests {
         s.grid[15][15] = true;
         s.clear_grid();
         for row in 0..ROWS {
-            for col in 0..COLS {
+            for col in 0..MAX_STEPS {
                 assert!(!s.grid[row][col]);
             }
         }
@@ -530,7 +1116,9 @@ mod tests {
         let mut s = MatrixState::default();
         // Toggle row 3: all off -> all on
         s.toggle_row(3);
-        assert!(s.grid[3].iter().all(|&v| v));
+        assert!(s.grid[3][..DEFAULT_STEPS].iter().all(|&v| v));
+        // Steps past the pattern length are left alone
+        assert!(!s.grid[3][DEFAULT_STEPS]);
         // Toggle again: all on -> all off
         s.toggle_row(3);
         assert!(s.grid[3].iter().all(|&v| !v));
@@ -598,8 +1186,10 @@ mod tests {
 
     #[test]
     fn row_to_midi_major_scale() {
-        let mut s = MatrixState::default();
-        s.scale = Scale::Major;
+        let s = MatrixState {
+            scale: Scale::Major,
+            ..Default::default()
+        };
         // Bottom row: base + major[0] = 57 + 0 = 57
         assert_eq!(s.row_to_midi(15), 57);
         // Row 14: base + major[1] = 57 + 2 = 59
@@ -624,7 +1214,9 @@ mod tests {
     fn lfo_dest_cycle() {
         assert_eq!(LfoDest::Pitch.next(), LfoDest::Filter);
         assert_eq!(LfoDest::Filter.next(), LfoDest::Amp);
-        assert_eq!(LfoDest::Amp.next(), LfoDest::Pitch);
+        assert_eq!(LfoDest::Amp.next(), LfoDest::Width);
+        assert_eq!(LfoDest::Width.next(), LfoDest::Position);
+        assert_eq!(LfoDest::Position.next(), LfoDest::Pitch);
     }
 
     #[test]
@@ -693,8 +1285,10 @@ mod tests {
 
     #[test]
     fn drum_grid_independent_from_lead() {
-        let mut s = MatrixState::default();
-        s.mode = ChannelMode::Lead;
+        let mut s = MatrixState {
+            mode: ChannelMode::Lead,
+            ..Default::default()
+        };
         s.active_grid_mut()[3][5] = true;
         s.mode = ChannelMode::Drummer;
         s.active_grid_mut()[7][2] = true;
@@ -708,8 +1302,10 @@ mod tests {
 
     #[test]
     fn active_note_names_in_drum_mode() {
-        let mut s = MatrixState::default();
-        s.mode = ChannelMode::Drummer;
+        let mut s = MatrixState {
+            mode: ChannelMode::Drummer,
+            ..Default::default()
+        };
         s.drum_grid[14][0] = true; // Kick
         s.drum_grid[6][0] = true;  // Snare
         s.toggle_play();
@@ -747,7 +1343,7 @@ mod tests {
     fn bass_grid_starts_empty() {
         let s = MatrixState::default();
         for row in 0..ROWS {
-            for col in 0..COLS {
+            for col in 0..MAX_STEPS {
                 assert!(!s.bass_grid[row][col]);
             }
         }
@@ -770,8 +1366,10 @@ mod tests {
 
     #[test]
     fn row_labels_bass_mode() {
-        let mut s = MatrixState::default();
-        s.mode = ChannelMode::Bass;
+        let s = MatrixState {
+            mode: ChannelMode::Bass,
+            ..Default::default()
+        };
         assert_eq!(s.row_labels()[0], "C3");
         assert_eq!(s.row_labels()[15], "A1");
     }
@@ -810,14 +1408,18 @@ mod tests {
 
     #[test]
     fn drum_preset_dir_names() {
-        assert_eq!(DrumPreset::LinnDrum.dir_name(), "lm2");
-        assert_eq!(DrumPreset::TR505.dir_name(), "tr505");
-        assert_eq!(DrumPreset::CR78.dir_name(), "cr78");
+        assert_eq!(DrumPreset::LinnDrum.dir_name(), Some("lm2"));
+        assert_eq!(DrumPreset::TR505.dir_name(), Some("tr505"));
+        assert_eq!(DrumPreset::CR78.dir_name(), Some("cr78"));
+        for p in DrumPreset::SYNTH {
+            assert_eq!(p.dir_name(), None);
+        }
     }
 
     #[test]
     fn drum_preset_all_count() {
-        assert_eq!(DrumPreset::ALL.len(), 3);
+        assert_eq!(DrumPreset::SAMPLED.len(), 3);
+        assert_eq!(DrumPreset::SYNTH.len(), 6);
     }
 
     #[test]
@@ -825,4 +1427,248 @@ mod tests {
         let s = MatrixState::default();
         assert_eq!(s.drum_preset, DrumPreset::LinnDrum);
     }
+
+    #[test]
+    fn select_slot_keeps_each_pattern() {
+        let mut s = MatrixState::default();
+        s.drum_grid[0][0] = true;
+        s.select_slot(ChannelMode::Drummer, 3);
+        assert_eq!(s.pattern_slot[ChannelMode::Drummer.index()], 3);
+        assert!(!s.drum_grid[0][0]);
+        s.drum_grid[1][4] = true;
+        s.select_slot(ChannelMode::Drummer, 0);
+        assert!(s.drum_grid[0][0]);
+        assert!(s.slot_pattern(ChannelMode::Drummer, 3).cells[1][4]);
+        // Other channels keep their own slot.
+        assert_eq!(s.pattern_slot[ChannelMode::Lead.index()], 0);
+    }
+
+    #[test]
+    fn request_slot_waits_while_playing() {
+        let mut s = MatrixState {
+            is_playing: true,
+            ..MatrixState::default()
+        };
+        s.request_slot(ChannelMode::Bass, 2);
+        assert_eq!(s.pending_slot[ChannelMode::Bass.index()], Some(2));
+        assert_eq!(s.pattern_slot[ChannelMode::Bass.index()], 0);
+        s.apply_pending_slots();
+        assert_eq!(s.pattern_slot[ChannelMode::Bass.index()], 2);
+        assert_eq!(s.pending_slot, [None; 3]);
+
+        s.is_playing = false;
+        s.request_slot(ChannelMode::Bass, 5);
+        assert_eq!(s.pattern_slot[ChannelMode::Bass.index()], 5);
+    }
+
+    #[test]
+    fn copy_slot_duplicates_current_pattern() {
+        let mut s = MatrixState::default();
+        s.grid[2][2] = true;
+        s.pattern_steps[0] = 24;
+        s.copy_slot(ChannelMode::Lead, 7);
+        assert!(s.slot_pattern(ChannelMode::Lead, 7).cells[2][2]);
+        assert_eq!(s.slot_pattern(ChannelMode::Lead, 7).steps, 24);
+        assert!(s.grid[2][2]);
+    }
+
+    #[test]
+    fn grids_serialize_as_row_strings() {
+        let mut s = MatrixState::default();
+        s.grid[0][1] = true;
+        s.banks[1][4].cells[15][15] = true;
+        s.song.push(SongStep { slot: 4, repeats: 2 });
+        let json = serde_json::to_string(&s).unwrap();
+        assert!(json.contains("\".x..............\""));
+        let back: MatrixState = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.grid, s.grid);
+        assert_eq!(back.banks, s.banks);
+        assert_eq!(back.song, s.song);
+    }
+
+    #[test]
+    fn slots_keep_their_length_and_resolution() {
+        let mut s = MatrixState::default();
+        s.pattern_steps[1] = 12;
+        s.resolution[1] = StepResolution::SixteenthTriplet;
+        s.select_slot(ChannelMode::Drummer, 1);
+        assert_eq!(s.pattern_steps[1], DEFAULT_STEPS);
+        assert_eq!(s.resolution[1], StepResolution::Sixteenth);
+        s.select_slot(ChannelMode::Drummer, 0);
+        assert_eq!(s.pattern_steps[1], 12);
+        assert_eq!(s.resolution[1], StepResolution::SixteenthTriplet);
+    }
+
+    #[test]
+    fn long_grid_rows_serialize_to_last_active_step() {
+        let mut s = MatrixState::default();
+        s.grid[0][40] = true;
+        let json = serde_json::to_string(&s).unwrap();
+        assert!(json.contains(&format!("\"{}x\"", ".".repeat(40))));
+        let back: MatrixState = serde_json::from_str(&json).unwrap();
+        assert!(back.grid[0][40]);
+    }
+
+    #[test]
+    fn step_resolutions_divide_a_beat() {
+        let spb: Vec<u32> = StepResolution::ALL.iter().map(|r| r.steps_per_beat()).collect();
+        assert_eq!(spb, [2, 4, 8, 3, 6]);
+    }
+
+    #[test]
+    fn short_banks_are_padded_on_load() {
+        let back: MatrixState = serde_json::from_str(r#"{ "banks": [[{ "steps": 8 }]] }"#).unwrap();
+        assert_eq!(back.banks.len(), 3);
+        assert!(back.banks.iter().all(|bank| bank.len() == PATTERN_SLOTS));
+        assert_eq!(back.banks[0][0].steps, 8);
+        assert_eq!(back.banks[0][1].steps, DEFAULT_STEPS);
+    }
+
+    #[test]
+    fn step_params_follow_their_slot() {
+        let mut s = MatrixState::default();
+        s.bass_step_params[3][2].velocity = 40;
+        s.select_slot(ChannelMode::Bass, 1);
+        assert_eq!(s.bass_step_params[3][2], StepParams::default());
+        s.select_slot(ChannelMode::Bass, 0);
+        assert_eq!(s.bass_step_params[3][2].velocity, 40);
+    }
+
+    #[test]
+    fn step_params_serialize_sparsely() {
+        let mut s = MatrixState::default();
+        s.lead_step_params[1][9] = StepParams {
+            velocity: 127,
+            length: 2.5,
+            probability: 30,
+            slide: false,
+        };
+        let json = serde_json::to_string(&s).unwrap();
+        assert!(json.contains(r#""lead_step_params":[[1,9,{"velocity":127,"length":2.5,"probability":30}]]"#));
+        assert!(json.contains(r#""drum_step_params":[]"#));
+        let back: MatrixState = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.lead_step_params, s.lead_step_params);
+    }
+
+    #[test]
+    fn clear_grid_resets_step_params() {
+        let mut s = MatrixState::default();
+        s.grid[0][0] = true;
+        s.lead_step_params[0][0].probability = 10;
+        s.selected_step = Some((ChannelMode::Lead, 0, 0));
+        s.clear_grid();
+        assert_eq!(s.lead_step_params[0][0], StepParams::default());
+        assert_eq!(s.selected_step, None);
+    }
+
+    #[test]
+    fn learn_keys_reach_their_parameters() {
+        let mut s = MatrixState::default();
+        let (bpm, min, max) = s.learn_param_mut("matrix.bpm").unwrap();
+        *bpm = 90.0;
+        assert_eq!((min, max), (40.0, 240.0));
+        assert_eq!(s.bpm, 90.0);
+
+        *s.learn_param_mut("matrix.filter.cutoff").unwrap().0 = 12.0;
+        assert_eq!(s.filter_cutoff, 12.0);
+
+        let (volume, _, max) = s.learn_param_mut("matrix.drums.volume.16").unwrap();
+        *volume = 0.25;
+        assert_eq!(max, 1.0);
+        assert_eq!(s.drum_row_volume[15], 0.25);
+
+        assert!(s.learn_param_mut("matrix.drums.volume.0").is_none());
+        assert!(s.learn_param_mut("matrix.drums.volume.17").is_none());
+        assert!(s.learn_param_mut("synth.volume").is_none());
+    }
+
+    #[test]
+    fn bass_slides_round_trip() {
+        let mut s = MatrixState::default();
+        s.bass_step_params[0][3].slide = true;
+        let json = serde_json::to_string(&s).unwrap();
+        assert!(json.contains(r#""bass_step_params":[[0,3,{"velocity":100,"length":1.0,"probability":100,"slide":true}]]"#));
+        let back: MatrixState = serde_json::from_str(&json).unwrap();
+        assert!(back.bass_step_params[0][3].slide);
+        assert!(!back.bass_mono, "poly bass by default");
+    }
+
+    #[test]
+    fn voice_settings_round_trip() {
+        let s = MatrixState {
+            lead_voices: 16,
+            bass_steal: VoiceSteal::Lowest,
+            ..MatrixState::default()
+        };
+        let back: MatrixState = serde_json::from_str(&serde_json::to_string(&s).unwrap()).unwrap();
+        assert_eq!(back.lead_voices, 16);
+        assert_eq!(back.bass_steal, VoiceSteal::Lowest);
+        let old: MatrixState = serde_json::from_str("{}").unwrap();
+        assert_eq!((old.bass_voices, old.lead_steal), (8, VoiceSteal::Oldest));
+    }
+
+    #[test]
+    fn midi_out_channels_round_trip() {
+        let s = MatrixState {
+            midi_out_channels: [Some(3), None, Some(1)],
+            ..MatrixState::default()
+        };
+        let back: MatrixState = serde_json::from_str(&serde_json::to_string(&s).unwrap()).unwrap();
+        assert_eq!(back.midi_out_channels, [Some(3), None, Some(1)]);
+        let old: MatrixState = serde_json::from_str("{}").unwrap();
+        assert_eq!(old.midi_out_channels, [Some(0), Some(9), Some(1)], "same channels as MIDI export");
+    }
+
+    #[test]
+    fn wavetable_choice_round_trips() {
+        let s = MatrixState {
+            osc_waveform: Waveform::Table,
+            osc_wavetable: Some("harmonics".to_string()),
+            osc_position: 40.0,
+            ..MatrixState::default()
+        };
+        let back: MatrixState = serde_json::from_str(&serde_json::to_string(&s).unwrap()).unwrap();
+        assert_eq!(back.osc_waveform, Waveform::Table);
+        assert_eq!(back.osc_wavetable.as_deref(), Some("harmonics"));
+        assert_eq!(back.osc_position, 40.0);
+        let old: MatrixState = serde_json::from_str("{}").unwrap();
+        assert_eq!(old.osc_wavetable, None, "older projects play the built-in table");
+    }
+
+    #[test]
+    fn second_oscillator_round_trips() {
+        let s = MatrixState {
+            osc2_enabled: true,
+            osc2_waveform: Waveform::Square,
+            osc2_octave: -1,
+            osc2_semitone: 7,
+            osc2_sync: true,
+            ..MatrixState::default()
+        };
+        let back: MatrixState = serde_json::from_str(&serde_json::to_string(&s).unwrap()).unwrap();
+        assert!(back.osc2_enabled && back.osc2_sync && !back.osc2_ring);
+        assert_eq!(back.osc2_waveform, Waveform::Square);
+        assert_eq!((back.osc2_octave, back.osc2_semitone), (-1, 7));
+        let old: MatrixState = serde_json::from_str("{}").unwrap();
+        assert!(!old.osc2_enabled, "older projects keep a single oscillator");
+    }
+
+    #[test]
+    fn filter_envelope_round_trips() {
+        let s = MatrixState { filter_env_amount: -40.0, filter_env_decay: 60.0, ..MatrixState::default() };
+        let back: MatrixState = serde_json::from_str(&serde_json::to_string(&s).unwrap()).unwrap();
+        assert_eq!((back.filter_env_amount, back.filter_env_decay), (-40.0, 60.0));
+        let old: MatrixState = serde_json::from_str("{}").unwrap();
+        assert_eq!(old.filter_env_amount, 0.0, "older projects keep a static cutoff");
+    }
+
+    #[test]
+    fn fm_settings_round_trip() {
+        let s = MatrixState { fm_enabled: true, fm_ratio: 3.5, fm_index: 60.0, ..MatrixState::default() };
+        let back: MatrixState = serde_json::from_str(&serde_json::to_string(&s).unwrap()).unwrap();
+        assert!(back.fm_enabled);
+        assert_eq!((back.fm_ratio, back.fm_index), (3.5, 60.0));
+        let old: MatrixState = serde_json::from_str("{}").unwrap();
+        assert!(!old.fm_enabled, "older projects stay subtractive");
+    }
 }
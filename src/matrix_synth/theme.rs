@@ -94,6 +94,10 @@ impl Theme {
     pub const VOL_BAR_FILL: Color32 = Color32::from_rgb(100, 100, 160);
     pub const MUTED_ROW_OVERLAY: Color32 = Color32::from_rgba_premultiplied(0, 0, 0, 100);
 
+    // ── Step parameters ──
+    pub const STEP_MARK: Color32 = Color32::from_rgba_premultiplied(140, 140, 140, 140);
+    pub const TIE_LINE: Color32 = Color32::from_rgba_premultiplied(200, 200, 200, 200);
+
     // ── Shadows ──
     pub fn panel_shadow() -> Shadow {
         Shadow {
@@ -5,6 +5,7 @@ use super::history::History;
 use super::state::{ChannelMode, MatrixState};
 use super::theme::Theme;
 use super::widgets;
+use crate::midi_learn::learnable;
 
 pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState, history: &mut History) {
     ui.horizontal(|ui| {
@@ -91,7 +92,8 @@ pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState, history: &mut History) {
                 .strong()
                 .color(Theme::TEXT),
         );
-        ui.add(egui::Slider::new(&mut state.bpm, 40.0..=240.0).show_value(false));
+        let bpm = ui.add(egui::Slider::new(&mut state.bpm, 40.0..=240.0).show_value(false));
+        learnable(ui, &bpm, "matrix.bpm");
         ui.label(
             egui::RichText::new(format!("{}", state.bpm as i32))
                 .size(12.0)
@@ -104,7 +106,8 @@ pub fn draw(ui: &mut egui::Ui, state: &mut MatrixState, history: &mut History) {
         ui.painter().rect_filled(sep_rect, 0.0, Theme::BORDER);
 
         // Swing knob
-        widgets::knob::knob(ui, "Swing", &mut state.swing, 0.0, 100.0, 0.0, "%", 36.0);
+        let swing = widgets::knob::knob(ui, "Swing", &mut state.swing, 0.0, 100.0, 0.0, "%", 36.0);
+        learnable(ui, &swing.response, "matrix.swing");
 
         // Separator
         let (sep_rect, _) = ui.allocate_exact_size(egui::vec2(1.0, 28.0), egui::Sense::hover());
@@ -3,11 +3,12 @@ use eframe::egui::Stroke;
 
 use crate::matrix_synth::theme::Theme;
 
-pub fn hslider(ui: &mut egui::Ui, label: &str, value: &mut f32, min: f32, max: f32) {
+/// Returns the track's response.
+pub fn hslider(ui: &mut egui::Ui, label: &str, value: &mut f32, min: f32, max: f32) -> egui::Response {
     let desired_height = 14.0;
     let available_width = ui.available_width();
 
-    ui.horizontal(|ui| {
+    let track = ui.horizontal(|ui| {
         // Label
         ui.allocate_ui(egui::vec2(42.0, desired_height), |ui| {
             ui.with_layout(
@@ -93,7 +94,9 @@ pub fn hslider(ui: &mut egui::Ui, label: &str, value: &mut f32, min: f32, max: f
                     .color(Theme::TEXT_DIM),
             );
         });
+        response
     });
 
     ui.add_space(2.0);
+    track.inner
 }
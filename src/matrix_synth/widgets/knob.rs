@@ -6,6 +6,7 @@ use crate::matrix_synth::theme::Theme;
 #[allow(dead_code)]
 pub struct KnobResponse {
     pub changed: bool,
+    pub response: egui::Response,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -126,5 +127,6 @@ pub fn knob(
 
     KnobResponse {
         changed: response.dragged() || response.double_clicked(),
+        response,
     }
 }
@@ -1,10 +1,27 @@
-use std::sync::mpsc;
+use std::sync::{Arc, Mutex, mpsc};
 
-/// MIDI note events sent from the MIDI thread to the GUI thread.
+/// MIDI CC number of the modulation wheel.
+pub const CC_MOD_WHEEL: u8 = 1;
+/// MIDI CC number of the sustain (damper) pedal.
+pub const CC_SUSTAIN: u8 = 64;
+/// MIDI CC number of the sostenuto pedal.
+pub const CC_SOSTENUTO: u8 = 66;
+/// MIDI CC number of the All Notes Off channel mode message.
+pub const CC_ALL_NOTES_OFF: u8 = 123;
+/// Port name other software sees for the virtual output.
+pub const VIRTUAL_OUT_NAME: &str = "synthesis-out";
+
+/// MIDI events sent from the MIDI thread to the GUI thread.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NoteEvent {
     On { note: u8, velocity: u8 },
     Off { note: u8 },
+    ControlChange { controller: u8, value: u8 },
+    /// Pitch wheel position, -8192 (full down) to 8191 (full up).
+    PitchBend { value: i16 },
+    /// Channel aftertouch.
+    ChannelPressure { pressure: u8 },
+    ProgramChange { program: u8 },
     /// Force a note onto a specific voice (used by per-voice Test buttons).
     TestOn { voice_idx: usize, note: u8, velocity: u8 },
     /// Release a specific voice's test note.
@@ -14,28 +31,96 @@ pub enum NoteEvent {
 impl NoteEvent {
     /// Parse a raw MIDI message into a NoteEvent, if applicable.
     pub fn from_midi(data: &[u8]) -> Option<Self> {
-        if data.len() < 3 {
-            return None;
+        let status = *data.first()? & 0xF0;
+        // Program change and channel pressure carry one data byte
+        match status {
+            0xC0 => return data.get(1).map(|&program| NoteEvent::ProgramChange { program }),
+            0xD0 => return data.get(1).map(|&pressure| NoteEvent::ChannelPressure { pressure }),
+            _ if data.len() < 3 => return None,
+            _ => {}
         }
-        let status = data[0] & 0xF0;
-        let note = data[1];
-        let velocity = data[2];
+        let (data1, data2) = (data[1], data[2]);
 
         match status {
-            0x90 if velocity > 0 => Some(NoteEvent::On { note, velocity }),
-            0x90 => Some(NoteEvent::Off { note }), // velocity 0 = note off
-            0x80 => Some(NoteEvent::Off { note }),
+            0x90 if data2 > 0 => Some(NoteEvent::On { note: data1, velocity: data2 }),
+            0x90 => Some(NoteEvent::Off { note: data1 }), // velocity 0 = note off
+            0x80 => Some(NoteEvent::Off { note: data1 }),
+            0xB0 => Some(NoteEvent::ControlChange { controller: data1, value: data2 }),
+            0xE0 => Some(NoteEvent::PitchBend {
+                value: ((data2 as i16) << 7 | data1 as i16) - 8192,
+            }),
             _ => None,
         }
     }
+
+    /// Raw MIDI message for the event on a channel (0–15). Test events have none.
+    pub fn to_midi(self, channel: u8) -> Option<Vec<u8>> {
+        let ch = channel & 0x0F;
+        Some(match self {
+            NoteEvent::On { note, velocity } => vec![0x90 | ch, note, velocity],
+            NoteEvent::Off { note } => vec![0x80 | ch, note, 0],
+            NoteEvent::ControlChange { controller, value } => vec![0xB0 | ch, controller, value],
+            NoteEvent::PitchBend { value } => {
+                let value = (value.clamp(-8192, 8191) + 8192) as u16;
+                vec![0xE0 | ch, (value & 0x7F) as u8, (value >> 7) as u8]
+            }
+            NoteEvent::ChannelPressure { pressure } => vec![0xD0 | ch, pressure],
+            NoteEvent::ProgramChange { program } => vec![0xC0 | ch, program],
+            NoteEvent::TestOn { .. } | NoteEvent::TestOff { .. } => return None,
+        })
+    }
+
+    /// MIDI channel (0–15) of a channel voice message.
+    pub fn channel(data: &[u8]) -> Option<u8> {
+        let status = *data.first()?;
+        (0x80..0xF0).contains(&status).then_some(status & 0x0F)
+    }
+}
+
+/// Which MIDI channels the input listens to, and whether they share a sound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MidiChannels {
+    /// Every channel plays the same sound.
+    #[default]
+    Omni,
+    /// Only one channel (0–15) is heard.
+    Single(u8),
+    /// Every channel is heard and drives its own part.
+    Multi,
+}
+
+impl MidiChannels {
+    /// Omni, channels 1–16, then multitimbral, in menu order.
+    pub fn options() -> impl Iterator<Item = Self> {
+        std::iter::once(Self::Omni)
+            .chain((0..16).map(Self::Single))
+            .chain(std::iter::once(Self::Multi))
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            Self::Omni => "Omni".to_string(),
+            Self::Single(channel) => format!("Ch {}", channel + 1),
+            Self::Multi => "Multi".to_string(),
+        }
+    }
+
+    pub fn accepts(self, channel: u8) -> bool {
+        match self {
+            Self::Single(only) => channel == only,
+            Self::Omni | Self::Multi => true,
+        }
+    }
 }
 
-/// Manages a MIDI input connection and forwards note events via a channel.
+/// Manages a MIDI input connection and forwards note events, tagged with
+/// their MIDI channel, via a channel.
 pub struct MidiHandler {
     _connection: Option<midir::MidiInputConnection<()>>,
-    receiver: Option<mpsc::Receiver<NoteEvent>>,
+    receiver: Option<mpsc::Receiver<(u8, NoteEvent)>>,
     port_names: Vec<String>,
     selected_port: Option<usize>,
+    channels: MidiChannels,
 }
 
 impl MidiHandler {
@@ -46,6 +131,7 @@ impl MidiHandler {
             receiver: None,
             port_names,
             selected_port: None,
+            channels: MidiChannels::Omni,
         }
     }
 
@@ -77,6 +163,14 @@ impl MidiHandler {
         self._connection.is_some()
     }
 
+    pub fn channels(&self) -> MidiChannels {
+        self.channels
+    }
+
+    pub fn set_channels(&mut self, channels: MidiChannels) {
+        self.channels = channels;
+    }
+
     /// Connect to a MIDI input port by index.
     /// Optionally pass an egui::Context to request repaint on MIDI events.
     pub fn connect(&mut self, port_idx: usize, ctx: Option<eframe::egui::Context>) {
@@ -97,8 +191,10 @@ impl MidiHandler {
                 port,
                 "synthesis-input",
                 move |_timestamp, data, _| {
-                    if let Some(event) = NoteEvent::from_midi(data) {
-                        let _ = tx.send(event);
+                    if let Some(event) = NoteEvent::from_midi(data)
+                        && let Some(channel) = NoteEvent::channel(data)
+                    {
+                        let _ = tx.send((channel, event));
                         if let Some(ctx) = &ctx {
                             ctx.request_repaint();
                         }
@@ -122,9 +218,146 @@ impl MidiHandler {
         self.selected_port = None;
     }
 
-    /// Try to receive the next pending NoteEvent (non-blocking).
-    pub fn try_recv(&self) -> Option<NoteEvent> {
-        self.receiver.as_ref()?.try_recv().ok()
+    /// Try to receive the next pending NoteEvent and its MIDI channel
+    /// (non-blocking). Events on channels the setting excludes are dropped.
+    pub fn try_recv(&self) -> Option<(u8, NoteEvent)> {
+        let receiver = self.receiver.as_ref()?;
+        loop {
+            let (channel, event) = receiver.try_recv().ok()?;
+            if self.channels.accepts(channel) {
+                return Some((channel, event));
+            }
+        }
+    }
+}
+
+/// Sends messages to the MIDI output port. Clones share the connection, so
+/// a thread mirroring the sequencer keeps sending when the UI changes ports.
+#[derive(Clone, Default)]
+pub struct MidiSender {
+    connection: Arc<Mutex<Option<midir::MidiOutputConnection>>>,
+}
+
+impl MidiSender {
+    /// Send a raw message; dropped while no port is connected.
+    pub fn send(&self, message: &[u8]) {
+        if let Ok(mut connection) = self.connection.lock()
+            && let Some(connection) = connection.as_mut()
+        {
+            let _ = connection.send(message);
+        }
+    }
+
+    pub fn send_event(&self, channel: u8, event: NoteEvent) {
+        if let Some(message) = event.to_midi(channel) {
+            self.send(&message);
+        }
+    }
+
+    /// Silence whatever the port is still holding on a channel.
+    pub fn all_notes_off(&self, channel: u8) {
+        self.send_event(channel, NoteEvent::ControlChange { controller: CC_ALL_NOTES_OFF, value: 0 });
+    }
+}
+
+/// Manages a MIDI output connection to external gear: a hardware port or,
+/// on Linux and macOS, a virtual port other software connects to.
+pub struct MidiOutHandler {
+    sender: MidiSender,
+    port_names: Vec<String>,
+    /// Name of the connected port.
+    connected: Option<String>,
+}
+
+impl MidiOutHandler {
+    pub fn new() -> Self {
+        Self {
+            sender: MidiSender::default(),
+            port_names: Self::scan_ports(),
+            connected: None,
+        }
+    }
+
+    fn scan_ports() -> Vec<String> {
+        let Ok(midi_out) = midir::MidiOutput::new("synthesis-scan") else {
+            return Vec::new();
+        };
+        midi_out
+            .ports()
+            .iter()
+            .filter_map(|p| midi_out.port_name(p).ok())
+            .collect()
+    }
+
+    /// Re-scan available MIDI output ports.
+    pub fn refresh_ports(&mut self) {
+        self.port_names = Self::scan_ports();
+    }
+
+    pub fn port_names(&self) -> &[String] {
+        &self.port_names
+    }
+
+    /// Name of the connected port, if any.
+    pub fn connected_port(&self) -> Option<&str> {
+        self.connected.as_deref()
+    }
+
+    /// A handle sending to whichever port is connected.
+    pub fn sender(&self) -> MidiSender {
+        self.sender.clone()
+    }
+
+    pub fn send_event(&self, channel: u8, event: NoteEvent) {
+        self.sender.send_event(channel, event);
+    }
+
+    fn attach(&mut self, connection: Option<midir::MidiOutputConnection>, name: String) {
+        if let Some(connection) = connection
+            && let Ok(mut slot) = self.sender.connection.lock()
+        {
+            *slot = Some(connection);
+            self.connected = Some(name);
+        }
+    }
+
+    /// Connect to a MIDI output port by index.
+    pub fn connect(&mut self, port_idx: usize) {
+        self.disconnect();
+        let Ok(midi_out) = midir::MidiOutput::new("synthesis") else {
+            return;
+        };
+        let ports = midi_out.ports();
+        let Some(port) = ports.get(port_idx) else {
+            return;
+        };
+        let name = midi_out.port_name(port).unwrap_or_default();
+        self.attach(midi_out.connect(port, "synthesis-output").ok(), name);
+    }
+
+    /// Open a virtual output port named `VIRTUAL_OUT_NAME`.
+    #[cfg(unix)]
+    pub fn connect_virtual(&mut self) {
+        use midir::os::unix::VirtualOutput;
+
+        self.disconnect();
+        let Ok(midi_out) = midir::MidiOutput::new("synthesis") else {
+            return;
+        };
+        self.attach(midi_out.create_virtual(VIRTUAL_OUT_NAME).ok(), VIRTUAL_OUT_NAME.to_string());
+    }
+
+    /// Release every channel's notes, then close the port.
+    pub fn disconnect(&mut self) {
+        if self.connected.take().is_none() {
+            return;
+        }
+        for channel in 0..16 {
+            self.sender.all_notes_off(channel);
+        }
+        if let Ok(mut connection) = self.sender.connection.lock() {
+            *connection = None;
+        }
     }
 }
 
@@ -165,10 +398,77 @@ mod tests {
     }
 
     #[test]
-    fn parse_non_note_message() {
-        // Control change
-        let event = NoteEvent::from_midi(&[0xB0, 1, 64]);
-        assert_eq!(event, None);
+    fn parse_control_change() {
+        let event = NoteEvent::from_midi(&[0xB3, CC_MOD_WHEEL, 64]);
+        assert_eq!(event, Some(NoteEvent::ControlChange { controller: 1, value: 64 }));
+    }
+
+    #[test]
+    fn parse_pitch_bend() {
+        assert_eq!(NoteEvent::from_midi(&[0xE0, 0x00, 0x40]), Some(NoteEvent::PitchBend { value: 0 }));
+        assert_eq!(NoteEvent::from_midi(&[0xE0, 0x00, 0x00]), Some(NoteEvent::PitchBend { value: -8192 }));
+        assert_eq!(NoteEvent::from_midi(&[0xE0, 0x7F, 0x7F]), Some(NoteEvent::PitchBend { value: 8191 }));
+    }
+
+    #[test]
+    fn parse_two_byte_messages() {
+        assert_eq!(NoteEvent::from_midi(&[0xC1, 5]), Some(NoteEvent::ProgramChange { program: 5 }));
+        assert_eq!(NoteEvent::from_midi(&[0xD0, 90]), Some(NoteEvent::ChannelPressure { pressure: 90 }));
+        assert_eq!(NoteEvent::from_midi(&[0xC0]), None);
+    }
+
+    #[test]
+    fn parse_non_channel_message() {
+        // Polyphonic aftertouch and system messages are not handled
+        assert_eq!(NoteEvent::from_midi(&[0xA0, 60, 64]), None);
+        assert_eq!(NoteEvent::from_midi(&[0xF8]), None);
+    }
+
+    #[test]
+    fn parse_channel() {
+        assert_eq!(NoteEvent::channel(&[0x95, 72, 80]), Some(5));
+        assert_eq!(NoteEvent::channel(&[0xB0, 1, 64]), Some(0));
+        assert_eq!(NoteEvent::channel(&[0xEF, 0, 64]), Some(15));
+        assert_eq!(NoteEvent::channel(&[0xF8]), None);
+        assert_eq!(NoteEvent::channel(&[]), None);
+    }
+
+    #[test]
+    fn channel_setting_filters() {
+        assert!(MidiChannels::Omni.accepts(9));
+        assert!(MidiChannels::Multi.accepts(9));
+        assert!(MidiChannels::Single(9).accepts(9));
+        assert!(!MidiChannels::Single(0).accepts(9));
+        assert_eq!(MidiChannels::options().count(), 18);
+        assert_eq!(MidiChannels::Single(9).label(), "Ch 10");
+    }
+
+    #[test]
+    fn events_round_trip_through_raw_messages() {
+        let events = [
+            NoteEvent::On { note: 60, velocity: 100 },
+            NoteEvent::Off { note: 60 },
+            NoteEvent::ControlChange { controller: CC_SUSTAIN, value: 127 },
+            NoteEvent::PitchBend { value: -8192 },
+            NoteEvent::PitchBend { value: 8191 },
+            NoteEvent::ChannelPressure { pressure: 90 },
+            NoteEvent::ProgramChange { program: 5 },
+        ];
+        for event in events {
+            let message = event.to_midi(9).unwrap();
+            assert_eq!(NoteEvent::from_midi(&message), Some(event));
+            assert_eq!(NoteEvent::channel(&message), Some(9));
+        }
+        assert_eq!(NoteEvent::TestOff { voice_idx: 0 }.to_midi(0), None);
+    }
+
+    #[test]
+    fn midi_out_starts_disconnected() {
+        let mut out = MidiOutHandler::new();
+        assert_eq!(out.connected_port(), None);
+        // Sending and disconnecting without a port are no-ops
+        out.send_event(0, NoteEvent::On { note: 60, velocity: 100 });
+        out.disconnect();
     }
 
     #[test]
@@ -176,6 +476,7 @@ mod tests {
         let handler = MidiHandler::new();
         assert!(!handler.is_connected());
         assert_eq!(handler.selected_port(), None);
+        assert_eq!(handler.channels(), MidiChannels::Omni);
         assert_eq!(handler.try_recv(), None);
     }
 }
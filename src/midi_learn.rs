@@ -0,0 +1,296 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+/// Outline drawn around a control waiting for its CC.
+const LEARN_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 196, 0);
+
+/// A CC number assigned to a parameter key such as `matrix.filter.cutoff`
+/// or `synth.voice1.cutoff`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Binding {
+    pub cc: u8,
+    pub param: String,
+}
+
+/// The MIDI learn table. It lives in `~/.synthesis/midi_map.json` and can be
+/// edited by hand; keys no control knows about are ignored.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MidiMap {
+    pub bindings: Vec<Binding>,
+}
+
+impl MidiMap {
+    /// `~/.synthesis/midi_map.json`, next to the presets and projects.
+    pub fn path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(".synthesis")
+            .join("midi_map.json")
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Load the table, starting empty when there is no file yet. Unreadable
+    /// files are reported and also start empty.
+    pub fn load_or_default(path: &Path) -> Self {
+        match Self::load(path) {
+            Ok(map) => map,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(err) => {
+                eprintln!("failed to read {}: {err}", path.display());
+                Self::default()
+            }
+        }
+    }
+
+    /// Assign a CC to a parameter, replacing the parameter's previous CC.
+    /// One CC may drive several parameters.
+    pub fn bind(&mut self, cc: u8, param: &str) {
+        self.unbind(param);
+        self.bindings.push(Binding {
+            cc,
+            param: param.to_string(),
+        });
+    }
+
+    pub fn unbind(&mut self, param: &str) {
+        self.bindings.retain(|b| b.param != param);
+    }
+
+    pub fn cc_for(&self, param: &str) -> Option<u8> {
+        self.bindings.iter().find(|b| b.param == param).map(|b| b.cc)
+    }
+
+    /// Parameters driven by a CC.
+    pub fn params_for(&self, cc: u8) -> impl Iterator<Item = &str> {
+        self.bindings
+            .iter()
+            .filter(move |b| b.cc == cc)
+            .map(|b| b.param.as_str())
+    }
+}
+
+/// Learn state kept in egui memory, so panels only need a parameter key to
+/// offer MIDI Learn.
+#[derive(Clone, Default)]
+struct Learn {
+    map: MidiMap,
+    /// Where changes are saved; `None` keeps them in memory.
+    path: Option<PathBuf>,
+    /// Parameter waiting for the next CC.
+    armed: Option<String>,
+}
+
+impl Learn {
+    fn save(&self) {
+        if let Some(path) = &self.path
+            && let Err(err) = self.map.save(path)
+        {
+            eprintln!("failed to write {}: {err}", path.display());
+        }
+    }
+}
+
+fn with_learn<R>(ctx: &egui::Context, f: impl FnOnce(&mut Learn) -> R) -> R {
+    ctx.data_mut(|d| f(d.get_temp_mut_or_default::<Learn>(egui::Id::new("midi_learn"))))
+}
+
+/// Make a table available to `learnable` and `handle_cc`. Changes are
+/// written back to `path` when given.
+pub fn install(ctx: &egui::Context, map: MidiMap, path: Option<PathBuf>) {
+    with_learn(ctx, |learn| {
+        *learn = Learn {
+            map,
+            path,
+            armed: None,
+        }
+    });
+}
+
+/// Wait for the next CC to bind it to `param`.
+pub fn arm(ctx: &egui::Context, param: &str) {
+    with_learn(ctx, |learn| learn.armed = Some(param.to_string()));
+}
+
+/// Route an incoming CC. A parameter waiting for MIDI Learn is bound to it
+/// first. Returns the parameters the CC drives with the value scaled to
+/// 0.0–1.0; the caller looks them up and sets them.
+pub fn handle_cc(ctx: &egui::Context, cc: u8, value: u8) -> Vec<(String, f32)> {
+    with_learn(ctx, |learn| {
+        if let Some(param) = learn.armed.take() {
+            learn.map.bind(cc, &param);
+            learn.save();
+        }
+        let amount = value as f32 / 127.0;
+        learn
+            .map
+            .params_for(cc)
+            .map(|param| (param.to_string(), amount))
+            .collect()
+    })
+}
+
+/// Set a parameter found by key from a 0.0–1.0 CC amount.
+pub fn apply(target: Option<(&mut f32, f32, f32)>, amount: f32) {
+    if let Some((value, min, max)) = target {
+        *value = min + amount * (max - min);
+    }
+}
+
+/// Offer MIDI Learn in a control's right-click menu and outline the control
+/// while it waits for a CC.
+pub fn learnable(ui: &egui::Ui, response: &egui::Response, param: &str) {
+    let (armed, cc) = with_learn(ui.ctx(), |learn| {
+        (learn.armed.as_deref() == Some(param), learn.map.cc_for(param))
+    });
+    if armed {
+        ui.painter().rect_stroke(
+            response.rect.expand(1.0),
+            3.0,
+            egui::Stroke::new(1.5, LEARN_COLOR),
+            egui::StrokeKind::Outside,
+        );
+    }
+
+    response.context_menu(|ui| {
+        if armed {
+            ui.label("Move a controller\u{2026}");
+            if ui.button("Cancel").clicked() {
+                with_learn(ui.ctx(), |learn| learn.armed = None);
+                ui.close();
+            }
+        } else if ui.button("MIDI Learn").clicked() {
+            arm(ui.ctx(), param);
+            ui.close();
+        }
+        if let Some(cc) = cc
+            && ui.button(format!("Forget CC {cc}")).clicked()
+        {
+            with_learn(ui.ctx(), |learn| {
+                learn.map.unbind(param);
+                learn.save();
+            });
+            ui.close();
+        }
+    });
+}
+
+/// Window listing every binding with an editable CC number.
+pub fn map_window(ctx: &egui::Context, open: &mut bool) {
+    egui::Window::new("MIDI Map")
+        .open(open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            with_learn(ctx, |learn| {
+                if learn.map.bindings.is_empty() {
+                    ui.label("Right-click a knob or slider and choose MIDI Learn.");
+                }
+                let mut changed = false;
+                let mut remove = None;
+                egui::Grid::new("midi_map_grid").striped(true).show(ui, |ui| {
+                    for (i, binding) in learn.map.bindings.iter_mut().enumerate() {
+                        ui.label(&binding.param);
+                        changed |= ui
+                            .add(egui::DragValue::new(&mut binding.cc).range(0..=127).prefix("CC "))
+                            .changed();
+                        if ui.small_button("Remove").clicked() {
+                            remove = Some(i);
+                        }
+                        ui.end_row();
+                    }
+                });
+                if let Some(i) = remove {
+                    learn.map.bindings.remove(i);
+                    changed = true;
+                }
+                if changed {
+                    learn.save();
+                }
+                if let Some(path) = &learn.path {
+                    ui.label(
+                        egui::RichText::new(path.display().to_string())
+                            .size(9.0)
+                            .weak(),
+                    );
+                }
+            });
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn bind_replaces_previous_cc() {
+        let mut map = MidiMap::default();
+        map.bind(74, "matrix.filter.cutoff");
+        map.bind(71, "matrix.filter.cutoff");
+        map.bind(71, "matrix.filter.resonance");
+        assert_eq!(map.cc_for("matrix.filter.cutoff"), Some(71));
+        assert_eq!(map.bindings.len(), 2);
+        assert_eq!(
+            map.params_for(71).collect::<Vec<_>>(),
+            ["matrix.filter.cutoff", "matrix.filter.resonance"]
+        );
+
+        map.unbind("matrix.filter.cutoff");
+        assert_eq!(map.cc_for("matrix.filter.cutoff"), None);
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let path = env::temp_dir().join("synthesis_test_midi_map.json");
+        let mut map = MidiMap::default();
+        map.bind(7, "synth.volume");
+        map.save(&path).unwrap();
+        assert_eq!(MidiMap::load(&path).unwrap(), map);
+        let _ = fs::remove_file(&path);
+        assert_eq!(MidiMap::load_or_default(&path), MidiMap::default());
+    }
+
+    #[test]
+    fn hand_written_file_loads() {
+        let json = r#"{"bindings": [{"cc": 1, "param": "matrix.lfo.depth"}]}"#;
+        let map: MidiMap = serde_json::from_str(json).unwrap();
+        assert_eq!(map.cc_for("matrix.lfo.depth"), Some(1));
+    }
+
+    #[test]
+    fn learn_binds_next_cc() {
+        let ctx = egui::Context::default();
+        install(&ctx, MidiMap::default(), None);
+        assert!(handle_cc(&ctx, 74, 127).is_empty(), "unbound CC does nothing");
+
+        arm(&ctx, "matrix.filter.cutoff");
+        assert_eq!(handle_cc(&ctx, 74, 127), [("matrix.filter.cutoff".to_string(), 1.0)]);
+        let updates = handle_cc(&ctx, 74, 0);
+        assert_eq!(updates, [("matrix.filter.cutoff".to_string(), 0.0)]);
+        assert!(handle_cc(&ctx, 75, 64).is_empty(), "learning stops after one CC");
+    }
+
+    #[test]
+    fn apply_scales_to_range() {
+        let mut bpm = 120.0;
+        apply(Some((&mut bpm, 40.0, 240.0)), 0.5);
+        assert_eq!(bpm, 140.0);
+        apply(None, 1.0);
+    }
+}
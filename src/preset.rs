@@ -7,6 +7,7 @@ use serde::{Deserialize, Serialize};
 use crate::engine::effects::{EffectSlot, EffectsConfig};
 use crate::engine::filter::{FilterConfig, FilterType, LfoConfig, LfoTarget, LfoWaveform};
 use crate::engine::oscillator::{AdsrParams, Waveform};
+use crate::engine::voice::VoiceConfig;
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Preset {
@@ -43,6 +44,30 @@ impl Preset {
         serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
     }
 
+    /// Look up a preset by name: factory presets first, then user presets.
+    pub fn find(name: &str) -> Option<Preset> {
+        Self::factory_presets()
+            .into_iter()
+            .find(|p| p.name == name)
+            .or_else(|| Self::load(&Self::presets_dir().join(format!("{name}.json"))).ok())
+    }
+
+    /// Voice settings stored in the preset, applied to every voice.
+    pub fn voice_config(&self) -> VoiceConfig {
+        VoiceConfig {
+            waveform: self.waveform,
+            adsr: self.adsr,
+            filter_cfg: self.filter_cfg,
+            cutoff: self.cutoff,
+            resonance: self.resonance,
+            lfo_cfg: self.lfo_cfg,
+            lfo_rate: self.lfo_rate,
+            lfo_depth: self.lfo_depth,
+            level: 1.0,
+            ..VoiceConfig::default()
+        }
+    }
+
     pub fn presets_dir() -> PathBuf {
         dirs::home_dir()
             .unwrap_or_else(|| PathBuf::from("."))
@@ -293,6 +318,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn find_factory_preset_by_name() {
+        let preset = Preset::find("Deep Bass").unwrap();
+        assert_eq!(preset.waveform, Waveform::Saw);
+        assert!(Preset::find("No Such Preset").is_none());
+    }
+
+    #[test]
+    fn voice_config_copies_voice_settings() {
+        let preset = Preset::find("Sharp Lead").unwrap();
+        let cfg = preset.voice_config();
+        assert_eq!(cfg.waveform, Waveform::Square);
+        assert_eq!(cfg.cutoff, 5000.0);
+        assert!(cfg.lfo_cfg == preset.lfo_cfg);
+        assert_eq!(cfg.level, 1.0);
+    }
+
     #[test]
     fn presets_dir_exists_after_save() {
         let preset = Preset::factory_presets().into_iter().next().unwrap();
@@ -8,11 +8,19 @@ use eframe::egui::{self, Event, Key};
 use fundsp::snoop::Snoop;
 
 use crate::engine::effects::EffectsConfig;
-use crate::engine::voice::{VoiceAllocator, VoiceConfig};
-use crate::midi::{MidiHandler, NoteEvent};
+use crate::engine::voice::{
+    MAX_BEND_RANGE, NotePriority, StealPolicy, VoiceAllocator, VoiceConfig, VoiceMode,
+};
+use crate::midi::{MidiChannels, MidiHandler, MidiOutHandler, NoteEvent, VIRTUAL_OUT_NAME};
+use crate::midi_learn;
 
 use theme::SynthTheme;
 
+/// Narrowest a voice strip gets before the strips scroll sideways.
+const MIN_STRIP_WIDTH: f32 = 96.0;
+/// Most parts multitimbral MIDI can split the voices into, one per channel.
+const MAX_MULTI_PARTS: usize = 16;
+
 /// All synth parameters that the UI reads/writes.
 pub struct SynthParams<'a> {
     pub voice_configs: &'a mut [VoiceConfig],
@@ -23,12 +31,28 @@ pub struct SynthParams<'a> {
     pub delay_mix: &'a mut f32,
     pub reverb_mix: &'a mut f32,
     pub chorus_mix: &'a mut f32,
-    pub allocator: &'a VoiceAllocator,
+    /// Voice allocators, one per MIDI part.
+    pub parts: &'a [VoiceAllocator],
+    /// Requested voice count; applied after the frame.
+    pub polyphony: &'a mut usize,
+    /// Parts used when MIDI is multitimbral; applied after the frame.
+    pub multi_parts: &'a mut usize,
+    pub steal_policy: &'a mut StealPolicy,
+    pub voice_mode: &'a mut VoiceMode,
+    pub note_priority: &'a mut NotePriority,
+    /// Glide time in seconds for mono modes.
+    pub glide: &'a mut f32,
     pub snoop_left: &'a mut Option<Snoop>,
     pub snoop_right: &'a mut Option<Snoop>,
     pub midi: &'a mut MidiHandler,
+    pub midi_out: &'a mut MidiOutHandler,
+    /// MIDI out channel, 1–16.
+    pub midi_out_channel: &'a mut u8,
+    pub bend_range: &'a mut f32,
     pub playing: bool,
     pub preset_names: &'a [String],
+    /// Files in `Wavetable::wavetables_dir()`.
+    pub wavetable_names: &'a [String],
     pub current_preset: &'a mut Option<String>,
     pub save_name: &'a mut String,
 }
@@ -36,6 +60,7 @@ pub struct SynthParams<'a> {
 pub struct SynthUI {
     pressed_keys: HashSet<u8>,
     theme_applied: bool,
+    midi_map_open: bool,
 }
 
 impl SynthUI {
@@ -43,6 +68,7 @@ impl SynthUI {
         Self {
             pressed_keys: HashSet::new(),
             theme_applied: false,
+            midi_map_open: false,
         }
     }
 
@@ -101,25 +127,54 @@ impl SynthUI {
 
                 // Voice strips + master row
                 let strip_height = ui.available_height() - 200.0; // leave room for keyboard+pads+footer
-                let num_voices = params.voice_configs.len();
+                let voices: Vec<_> = params
+                    .parts
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(part, a)| a.voices.iter().map(move |v| (part, v)))
+                    .collect();
+                let multitimbral = params.parts.len() > 1;
+                let num_voices = params.voice_configs.len().min(voices.len());
                 let spacing = ui.spacing().item_spacing.x;
                 let master_width = 160.0;
-                let strip_width = (ui.available_width() - master_width - (num_voices as f32) * spacing) / num_voices as f32;
+                let strips_width = ui.available_width() - master_width - spacing;
+                let strip_width = ((strips_width - (num_voices as f32) * spacing)
+                    / num_voices as f32)
+                    .max(MIN_STRIP_WIDTH);
 
                 ui.horizontal(|ui| {
                     ui.set_height(strip_height);
 
-                    // 8 voice channel strips
-                    for i in 0..num_voices {
-                        let voice = &params.allocator.voices[i];
-                        ui.allocate_ui(egui::vec2(strip_width, strip_height), |ui| {
-                            // We need to split the borrow: voice_configs[i] + voice
-                            let (_, rest) = params.voice_configs.split_at_mut(i);
-                            let (cfg, _) = rest.split_first_mut().unwrap();
-                            let strip_events = panels::voice_strip::draw(ui, i, cfg, voice);
-                            events.extend(strip_events);
-                        });
-                    }
+                    // One channel strip per voice, scrolling when they don't fit
+                    ui.allocate_ui(egui::vec2(strips_width, strip_height), |ui| {
+                        egui::ScrollArea::horizontal()
+                            .id_salt("voice_strips")
+                            .auto_shrink([false, false])
+                            .show(ui, |ui| {
+                                ui.horizontal(|ui| {
+                                    for (i, (cfg, &(part, voice))) in params
+                                        .voice_configs
+                                        .iter_mut()
+                                        .zip(&voices)
+                                        .enumerate()
+                                    {
+                                        let part = multitimbral.then_some(part);
+                                        ui.allocate_ui(egui::vec2(strip_width, strip_height), |ui| {
+                                            let strip_events =
+                                                panels::voice_strip::draw(
+                                                    ui,
+                                                    i,
+                                                    part,
+                                                    cfg,
+                                                    voice,
+                                                    params.wavetable_names,
+                                                );
+                                            events.extend(strip_events);
+                                        });
+                                    }
+                                });
+                            });
+                    });
 
                     // Master strip
                     ui.allocate_ui(egui::vec2(master_width, strip_height), |ui| {
@@ -128,7 +183,16 @@ impl SynthUI {
                             params.amplitude,
                             params.snoop_left,
                             params.snoop_right,
-                            params.allocator,
+                            params.parts,
+                        );
+                        ui.add_space(4.0);
+                        panels::voicing::draw(
+                            ui,
+                            params.polyphony,
+                            params.steal_policy,
+                            params.voice_mode,
+                            params.note_priority,
+                            params.glide,
                         );
                         ui.add_space(4.0);
                         panels::effects::draw(
@@ -164,13 +228,15 @@ impl SynthUI {
                 // Footer
                 ui.horizontal(|ui| {
                     ui.label(
-                        egui::RichText::new("Keys: WXCV... (piano)  Space (panic)  Ctrl+1-4 (waveform all)")
+                        egui::RichText::new("Keys: WXCV... (piano)  Space (panic)  Ctrl+1-9 (waveform all)")
                             .color(SynthTheme::TEXT_DIM)
                             .size(9.0),
                     );
                 });
             });
 
+        midi_learn::map_window(ctx, &mut self.midi_map_open);
+
         events
     }
 
@@ -212,11 +278,41 @@ impl SynthUI {
 
                 // MIDI section on the right
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.small_button("MIDI Map").clicked() {
+                        self.midi_map_open = !self.midi_map_open;
+                    }
+                    let mut channels = params.midi.channels();
+                    if channels == MidiChannels::Multi {
+                        ui.add(
+                            egui::DragValue::new(params.multi_parts)
+                                .range(2..=MAX_MULTI_PARTS)
+                                .suffix(" parts"),
+                        )
+                        .on_hover_text("Channels 1 to N each play their own group of voices");
+                    }
+                    egui::ComboBox::from_id_salt("midi_channels")
+                        .selected_text(channels.label())
+                        .width(60.0)
+                        .show_ui(ui, |ui| {
+                            for option in MidiChannels::options() {
+                                ui.selectable_value(&mut channels, option, option.label());
+                            }
+                        });
+                    params.midi.set_channels(channels);
                     if params.midi.is_connected() {
                         if ui.small_button("Disconnect").clicked() {
                             params.midi.disconnect();
                         }
                         ui.label(egui::RichText::new("MIDI connected").color(SynthTheme::VU_GREEN).size(10.0));
+                        ui.add(
+                            egui::DragValue::new(params.bend_range)
+                                .range(0.0..=MAX_BEND_RANGE)
+                                .speed(0.1)
+                                .fixed_decimals(0)
+                                .suffix(" st"),
+                        )
+                        .on_hover_text("Pitch bend range");
+                        ui.label(egui::RichText::new("Bend \u{00b1}").color(SynthTheme::TEXT_DIM).size(10.0));
                     } else {
                         if ui.small_button("Refresh").clicked() {
                             params.midi.refresh_ports();
@@ -245,6 +341,45 @@ impl SynthUI {
                                 }
                             });
                     }
+
+                    // MIDI out: the keyboards also play an external synth
+                    ui.add_space(8.0);
+                    ui.add(
+                        egui::DragValue::new(params.midi_out_channel)
+                            .range(1..=16)
+                            .prefix("Ch "),
+                    )
+                    .on_hover_text("MIDI out channel");
+                    if let Some(name) = params.midi_out.connected_port() {
+                        let name = name.to_string();
+                        if ui.small_button("Close").clicked() {
+                            params.midi_out.disconnect();
+                        }
+                        ui.label(egui::RichText::new("Out").color(SynthTheme::VU_GREEN).size(10.0))
+                            .on_hover_text(name);
+                    } else {
+                        let port_names = params.midi_out.port_names().to_vec();
+                        egui::ComboBox::from_id_salt("midi_out_port")
+                            .selected_text("MIDI Out")
+                            .width(100.0)
+                            .show_ui(ui, |ui| {
+                                for (i, name) in port_names.iter().enumerate() {
+                                    if ui.selectable_label(false, name).clicked() {
+                                        params.midi_out.connect(i);
+                                    }
+                                }
+                                #[cfg(unix)]
+                                if ui
+                                    .selectable_label(false, format!("Virtual ({VIRTUAL_OUT_NAME})"))
+                                    .clicked()
+                                {
+                                    params.midi_out.connect_virtual();
+                                }
+                                if ui.button("Refresh").clicked() {
+                                    params.midi_out.refresh_ports();
+                                }
+                            });
+                    }
                 });
             });
         });
@@ -268,19 +403,16 @@ impl SynthUI {
                 if input.key_pressed(Key::ArrowDown) {
                     *params.amplitude = (*params.amplitude - 0.05).max(0.0);
                 }
-                // Ctrl+1-4: set waveform on ALL voices
+                // Ctrl+1-9: set waveform on ALL voices
                 use crate::engine::oscillator::Waveform;
-                let waveform_change = if input.key_pressed(Key::Num1) {
-                    Some(Waveform::Sine)
-                } else if input.key_pressed(Key::Num2) {
-                    Some(Waveform::Saw)
-                } else if input.key_pressed(Key::Num3) {
-                    Some(Waveform::Square)
-                } else if input.key_pressed(Key::Num4) {
-                    Some(Waveform::Triangle)
-                } else {
-                    None
-                };
+                let number_keys = [
+                    Key::Num1, Key::Num2, Key::Num3, Key::Num4,
+                    Key::Num5, Key::Num6, Key::Num7, Key::Num8, Key::Num9,
+                ];
+                let waveform_change = number_keys
+                    .iter()
+                    .position(|&k| input.key_pressed(k))
+                    .map(|i| Waveform::ALL[i]);
                 if let Some(wf) = waveform_change {
                     for vc in params.voice_configs.iter_mut() {
                         vc.waveform = wf;
@@ -306,7 +438,9 @@ impl SynthUI {
                 let new_target = match params.voice_configs[0].lfo_cfg.target {
                     LfoTarget::Frequency => LfoTarget::Cutoff,
                     LfoTarget::Cutoff => LfoTarget::Amplitude,
-                    LfoTarget::Amplitude => LfoTarget::Frequency,
+                    LfoTarget::Amplitude => LfoTarget::PulseWidth,
+                    LfoTarget::PulseWidth => LfoTarget::WavetablePosition,
+                    LfoTarget::WavetablePosition => LfoTarget::Frequency,
                 };
                 for vc in params.voice_configs.iter_mut() {
                     vc.lfo_cfg.target = new_target;
@@ -1,6 +1,7 @@
 use eframe::egui;
 
 use crate::engine::effects::{EffectSlot, EffectsConfig};
+use crate::midi_learn::learnable;
 use crate::synth_ui::theme::SynthTheme;
 use crate::synth_ui::widgets::hslider;
 
@@ -51,9 +52,12 @@ pub fn draw(
             );
         });
         if effects_cfg.delay_enabled {
-            hslider(ui, "Time", delay_time, 0.01, 2.0);
-            hslider(ui, "Fback", delay_feedback, 0.0, 0.99);
-            hslider(ui, "Mix", delay_mix, 0.0, 1.0);
+            let delay_time = hslider(ui, "Time", delay_time, 0.01, 2.0);
+            learnable(ui, &delay_time, "synth.delay.time");
+            let delay_feedback = hslider(ui, "Fback", delay_feedback, 0.0, 0.99);
+            learnable(ui, &delay_feedback, "synth.delay.feedback");
+            let delay_mix = hslider(ui, "Mix", delay_mix, 0.0, 1.0);
+            learnable(ui, &delay_mix, "synth.delay.mix");
         }
 
         ui.add_space(4.0);
@@ -73,9 +77,12 @@ pub fn draw(
             );
         });
         if effects_cfg.reverb_enabled {
-            hslider(ui, "Room", &mut effects_cfg.reverb_room_size, 1.0, 100.0);
-            hslider(ui, "Time", &mut effects_cfg.reverb_time, 0.1, 10.0);
-            hslider(ui, "Mix", reverb_mix, 0.0, 1.0);
+            let reverb_room = hslider(ui, "Room", &mut effects_cfg.reverb_room_size, 1.0, 100.0);
+            learnable(ui, &reverb_room, "synth.reverb.room");
+            let reverb_time = hslider(ui, "Time", &mut effects_cfg.reverb_time, 0.1, 10.0);
+            learnable(ui, &reverb_time, "synth.reverb.time");
+            let reverb_mix = hslider(ui, "Mix", reverb_mix, 0.0, 1.0);
+            learnable(ui, &reverb_mix, "synth.reverb.mix");
         }
 
         ui.add_space(4.0);
@@ -95,10 +102,14 @@ pub fn draw(
             );
         });
         if effects_cfg.chorus_enabled {
-            hslider(ui, "Sep", &mut effects_cfg.chorus_separation, 0.0, 1.0);
-            hslider(ui, "Var", &mut effects_cfg.chorus_variation, 0.0, 1.0);
-            hslider(ui, "ModFq", &mut effects_cfg.chorus_mod_freq, 0.1, 10.0);
-            hslider(ui, "Mix", chorus_mix, 0.0, 1.0);
+            let chorus_separation = hslider(ui, "Sep", &mut effects_cfg.chorus_separation, 0.0, 1.0);
+            learnable(ui, &chorus_separation, "synth.chorus.separation");
+            let chorus_variation = hslider(ui, "Var", &mut effects_cfg.chorus_variation, 0.0, 1.0);
+            learnable(ui, &chorus_variation, "synth.chorus.variation");
+            let chorus_mod_freq = hslider(ui, "ModFq", &mut effects_cfg.chorus_mod_freq, 0.1, 10.0);
+            learnable(ui, &chorus_mod_freq, "synth.chorus.mod_freq");
+            let chorus_mix = hslider(ui, "Mix", chorus_mix, 0.0, 1.0);
+            learnable(ui, &chorus_mix, "synth.chorus.mix");
         }
     });
 }
@@ -3,6 +3,7 @@ use fundsp::snoop::Snoop;
 
 use crate::engine::voice::VoiceAllocator;
 use crate::gui::oscilloscope;
+use crate::midi_learn::learnable;
 use crate::synth_ui::theme::SynthTheme;
 use crate::synth_ui::widgets::{level_meter, vslider};
 
@@ -15,12 +16,13 @@ pub fn draw(
     amplitude: &mut f32,
     snoop_left: &mut Option<Snoop>,
     snoop_right: &mut Option<Snoop>,
-    allocator: &VoiceAllocator,
+    parts: &[VoiceAllocator],
 ) {
     synth_panel(ui, "MASTER", |ui| {
         // Volume slider + VU meters
         ui.horizontal(|ui| {
-            vslider(ui, "Vol", amplitude, 0.0, 1.0);
+            let volume = vslider(ui, "Vol", amplitude, 0.0, 1.0);
+            learnable(ui, &volume, "synth.volume");
 
             // Compute RMS levels from snoops
             let level_l = compute_rms(snoop_left);
@@ -33,12 +35,18 @@ pub fn draw(
         ui.add_space(4.0);
 
         // Voice indicators
-        ui.label(egui::RichText::new("Voices").color(SynthTheme::TEXT_DIM).size(9.0));
+        let active: usize = parts.iter().map(VoiceAllocator::active_count).sum();
+        let total: usize = parts.iter().map(|p| p.voices.len()).sum();
+        ui.label(
+            egui::RichText::new(format!("Voices {active}/{total}"))
+                .color(SynthTheme::TEXT_DIM)
+                .size(9.0),
+        );
         ui.horizontal(|ui| {
-            for voice in &allocator.voices {
+            for voice in parts.iter().flat_map(|p| &p.voices) {
                 let color = if voice.note.is_some() {
                     SynthTheme::VU_GREEN
-                } else if voice.releasing {
+                } else if !voice.is_idle() {
                     SynthTheme::VU_YELLOW
                 } else {
                     SynthTheme::BORDER
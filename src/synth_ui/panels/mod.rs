@@ -7,6 +7,7 @@ pub mod master;
 pub mod oscillator;
 pub mod pads_panel;
 pub mod voice_strip;
+pub mod voicing;
 
 use eframe::egui::{self, RichText};
 
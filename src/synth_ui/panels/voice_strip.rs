@@ -1,9 +1,13 @@
+use std::sync::Arc;
+
 use eframe::egui::{self, vec2, Stroke};
 
 use crate::engine::filter::{FilterType, LfoTarget};
 use crate::engine::oscillator::Waveform;
-use crate::engine::voice::{Voice, VoiceConfig};
+use crate::engine::voice::{MAX_FILTER_ENV_OCTAVES, Voice, VoiceConfig, VoiceEngine};
+use crate::engine::wavetable::Wavetable;
 use crate::midi::NoteEvent;
+use crate::midi_learn::learnable;
 use crate::synth_ui::theme::SynthTheme;
 use crate::synth_ui::widgets::{hslider, knob, select_buttons};
 
@@ -12,22 +16,30 @@ const TEST_TRIGGER_FRAMES: u32 = 30;
 /// Test note: C4 (middle C).
 const TEST_NOTE: u8 = 60;
 
-/// Draw a compact per-voice channel strip.
+/// Draw a compact per-voice channel strip. `part` is the voice's MIDI part
+/// when the voices are split multitimbrally; `wavetables` lists the table
+/// files the voice can load.
 /// Returns note events for the test button.
 pub fn draw(
     ui: &mut egui::Ui,
     idx: usize,
+    part: Option<usize>,
     config: &mut VoiceConfig,
     voice: &Voice,
+    wavetables: &[String],
 ) -> Vec<NoteEvent> {
     let mut events = Vec::new();
+    let key = |name: &str| format!("synth.voice{}.{name}", idx + 1);
 
     SynthTheme::panel_frame().show(ui, |ui| {
         ui.set_min_width(ui.available_width());
         ui.vertical(|ui| {
-            // Header: "VOX N" + status LED
+            // Header: "VOX N" (+ "CH N" in multitimbral mode) + status LED
             ui.horizontal(|ui| {
-                let title = format!("VOX {}", idx + 1);
+                let title = match part {
+                    Some(part) => format!("VOX {} \u{00b7} CH {}", idx + 1, part + 1),
+                    None => format!("VOX {}", idx + 1),
+                };
                 ui.label(
                     egui::RichText::new(title)
                         .color(SynthTheme::ACCENT_LIGHT)
@@ -50,6 +62,28 @@ pub fn draw(
 
             ui.add_space(2.0);
 
+            // Engine: subtractive, or FM with the waveform below as carrier
+            select_buttons::select_buttons(
+                ui,
+                &mut config.engine,
+                &[(VoiceEngine::Subtractive, "Sub"), (VoiceEngine::Fm, "FM")],
+            );
+            if config.engine == VoiceEngine::Fm {
+                let ratio = hslider::hslider(ui, "Ratio", &mut config.fm.ratio, 0.5, 16.0);
+                learnable(ui, &ratio, &key("fm_ratio"));
+                let index = hslider::hslider(ui, "Index", &mut config.fm.index, 0.0, 10.0);
+                learnable(ui, &index, &key("fm_index"));
+                // Index envelope
+                ui.horizontal(|ui| {
+                    ui.spacing_mut().item_spacing.x = 1.0;
+                    let env = &mut config.fm.index_env;
+                    knob::mini_knob(ui, "iA", &mut env.attack, 0.001, 2.0);
+                    knob::mini_knob(ui, "iD", &mut env.decay, 0.001, 2.0);
+                    knob::mini_knob(ui, "iS", &mut env.sustain, 0.0, 1.0);
+                    knob::mini_knob(ui, "iR", &mut env.release, 0.001, 5.0);
+                });
+            }
+
             // Waveform selector (compact)
             select_buttons::select_buttons(
                 ui,
@@ -61,16 +95,105 @@ pub fn draw(
                     (Waveform::Triangle, "T"),
                 ],
             );
+            select_buttons::select_buttons(
+                ui,
+                &mut config.waveform,
+                &[
+                    (Waveform::Pulse, "P"),
+                    (Waveform::Supersaw, "SS"),
+                    (Waveform::Noise, "N"),
+                    (Waveform::PinkNoise, "Pk"),
+                    (Waveform::Wavetable, "WT"),
+                ],
+            );
+            if config.waveform == Waveform::Pulse {
+                let width = hslider::hslider(ui, "PW", &mut config.pulse_width, 0.05, 0.95);
+                learnable(ui, &width, &key("pulse_width"));
+            }
+            if config.waveform == Waveform::Wavetable {
+                let current = config.wavetable.as_ref().map_or_else(|| "Basic".to_string(), |t| t.name.clone());
+                egui::ComboBox::from_id_salt(("wavetable", idx))
+                    .selected_text(&current)
+                    .width(ui.available_width() - 8.0)
+                    .show_ui(ui, |ui| {
+                        if ui.selectable_label(config.wavetable.is_none(), "Basic").clicked() {
+                            config.wavetable = None;
+                        }
+                        for name in wavetables {
+                            if ui.selectable_label(&current == name, name).clicked() {
+                                match Wavetable::load(&Wavetable::path_for(name)) {
+                                    Ok(table) => config.wavetable = Some(Arc::new(table)),
+                                    Err(err) => eprintln!("failed to read wavetable {name}: {err}"),
+                                }
+                            }
+                        }
+                    });
+                let position = hslider::hslider(ui, "Pos", &mut config.wavetable_position, 0.0, 1.0);
+                learnable(ui, &position, &key("wavetable_position"));
+            }
+
+            ui.add_space(2.0);
+
+            // Oscillator 2
+            ui.horizontal(|ui| {
+                ui.spacing_mut().item_spacing.x = 1.0;
+                let (rect, resp) = ui.allocate_exact_size(vec2(16.0, 14.0), egui::Sense::click());
+                let painter = ui.painter_at(rect);
+                let (bg, text_c) = if config.osc2.enabled {
+                    (SynthTheme::ACCENT_DARK, SynthTheme::TEXT)
+                } else {
+                    (SynthTheme::KNOB_BG, SynthTheme::TEXT_DIM)
+                };
+                painter.rect_filled(rect, 3.0, bg);
+                painter.rect_stroke(rect, 3.0, Stroke::new(1.0, SynthTheme::BORDER), egui::StrokeKind::Inside);
+                painter.text(rect.center(), egui::Align2::CENTER_CENTER, "2", egui::FontId::proportional(8.0), text_c);
+                if resp.clicked() {
+                    config.osc2.enabled = !config.osc2.enabled;
+                }
+
+                egui::ComboBox::from_id_salt(("osc2_waveform", idx))
+                    .selected_text(config.osc2.waveform.to_string())
+                    .width(ui.available_width() - 8.0)
+                    .show_ui(ui, |ui| {
+                        for waveform in Waveform::ALL {
+                            ui.selectable_value(&mut config.osc2.waveform, waveform, waveform.to_string());
+                        }
+                    });
+            });
+            if config.osc2.enabled {
+                ui.horizontal(|ui| {
+                    ui.spacing_mut().item_spacing.x = 1.0;
+                    let mut octave = config.osc2.octave as f32;
+                    knob::mini_knob(ui, "Oct", &mut octave, -3.0, 3.0);
+                    config.osc2.octave = octave.round() as i32;
+                    let mut semitone = config.osc2.semitone as f32;
+                    knob::mini_knob(ui, "Semi", &mut semitone, -12.0, 12.0);
+                    config.osc2.semitone = semitone.round() as i32;
+                    let fine = knob::mini_knob(ui, "Fine", &mut config.osc2.fine, -100.0, 100.0);
+                    learnable(ui, &fine, &key("osc2_fine"));
+                    let level = knob::mini_knob(ui, "Lvl", &mut config.osc2.level, 0.0, 1.0);
+                    learnable(ui, &level, &key("osc2_level"));
+                });
+                ui.horizontal(|ui| {
+                    ui.spacing_mut().item_spacing.x = 1.0;
+                    select_buttons::select_buttons(ui, &mut config.osc2.sync, &[(false, "Free"), (true, "Sync")]);
+                    select_buttons::select_buttons(ui, &mut config.osc2.ring, &[(false, "Mix"), (true, "Ring")]);
+                });
+            }
 
             ui.add_space(4.0);
 
             // ADSR — 4 mini knobs
             ui.horizontal(|ui| {
                 ui.spacing_mut().item_spacing.x = 1.0;
-                knob::mini_knob(ui, "A", &mut config.adsr.attack, 0.001, 2.0);
-                knob::mini_knob(ui, "D", &mut config.adsr.decay, 0.001, 2.0);
-                knob::mini_knob(ui, "S", &mut config.adsr.sustain, 0.0, 1.0);
-                knob::mini_knob(ui, "R", &mut config.adsr.release, 0.001, 5.0);
+                let attack = knob::mini_knob(ui, "A", &mut config.adsr.attack, 0.001, 2.0);
+                learnable(ui, &attack, &key("attack"));
+                let decay = knob::mini_knob(ui, "D", &mut config.adsr.decay, 0.001, 2.0);
+                learnable(ui, &decay, &key("decay"));
+                let sustain = knob::mini_knob(ui, "S", &mut config.adsr.sustain, 0.0, 1.0);
+                learnable(ui, &sustain, &key("sustain"));
+                let release = knob::mini_knob(ui, "R", &mut config.adsr.release, 0.001, 5.0);
+                learnable(ui, &release, &key("release"));
             });
 
             ui.add_space(2.0);
@@ -102,8 +225,32 @@ pub fn draw(
                     ],
                 );
             });
-            hslider::hslider(ui, "Cut", &mut config.cutoff, 20.0, 20000.0);
-            hslider::hslider(ui, "Res", &mut config.resonance, 0.0, 1.0);
+            let cutoff = hslider::hslider(ui, "Cut", &mut config.cutoff, 20.0, 20000.0);
+            learnable(ui, &cutoff, &key("cutoff"));
+            let resonance = hslider::hslider(ui, "Res", &mut config.resonance, 0.0, 1.0);
+            learnable(ui, &resonance, &key("resonance"));
+
+            // Filter envelope: bipolar amount in octaves + ADSR
+            let env_amount = hslider::hslider(
+                ui,
+                "Env",
+                &mut config.filter_env_amount,
+                -MAX_FILTER_ENV_OCTAVES,
+                MAX_FILTER_ENV_OCTAVES,
+            );
+            learnable(ui, &env_amount, &key("filter_env_amount"));
+            ui.horizontal(|ui| {
+                ui.spacing_mut().item_spacing.x = 1.0;
+                let env = &mut config.filter_env;
+                let attack = knob::mini_knob(ui, "fA", &mut env.attack, 0.001, 2.0);
+                learnable(ui, &attack, &key("filter_attack"));
+                let decay = knob::mini_knob(ui, "fD", &mut env.decay, 0.001, 2.0);
+                learnable(ui, &decay, &key("filter_decay"));
+                let sustain = knob::mini_knob(ui, "fS", &mut env.sustain, 0.0, 1.0);
+                learnable(ui, &sustain, &key("filter_sustain"));
+                let release = knob::mini_knob(ui, "fR", &mut env.release, 0.001, 5.0);
+                learnable(ui, &release, &key("filter_release"));
+            });
 
             ui.add_space(2.0);
 
@@ -131,16 +278,21 @@ pub fn draw(
                         (LfoTarget::Frequency, "Fr"),
                         (LfoTarget::Cutoff, "Cu"),
                         (LfoTarget::Amplitude, "Am"),
+                        (LfoTarget::PulseWidth, "PW"),
+                        (LfoTarget::WavetablePosition, "WT"),
                     ],
                 );
             });
-            hslider::hslider(ui, "Rate", &mut config.lfo_rate, 0.1, 20.0);
-            hslider::hslider(ui, "Dep", &mut config.lfo_depth, 0.0, 1.0);
+            let lfo_rate = hslider::hslider(ui, "Rate", &mut config.lfo_rate, 0.1, 20.0);
+            learnable(ui, &lfo_rate, &key("lfo_rate"));
+            let lfo_depth = hslider::hslider(ui, "Dep", &mut config.lfo_depth, 0.0, 1.0);
+            learnable(ui, &lfo_depth, &key("lfo_depth"));
 
             ui.add_space(2.0);
 
             // Level
-            hslider::hslider(ui, "Level", &mut config.level, 0.0, 1.0);
+            let level = hslider::hslider(ui, "Level", &mut config.level, 0.0, 1.0);
+            learnable(ui, &level, &key("level"));
 
             ui.add_space(4.0);
 
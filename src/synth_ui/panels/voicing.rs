@@ -0,0 +1,64 @@
+use eframe::egui;
+
+use crate::engine::voice::{MAX_VOICES, NotePriority, StealPolicy, VoiceMode};
+use crate::midi_learn::learnable;
+use crate::synth_ui::theme::SynthTheme;
+use crate::synth_ui::widgets::{hslider, select_buttons};
+
+use super::synth_panel;
+
+/// Voice count, stealing policy, poly/mono voice mode, mono note priority
+/// and glide time.
+pub fn draw(
+    ui: &mut egui::Ui,
+    polyphony: &mut usize,
+    steal: &mut StealPolicy,
+    mode: &mut VoiceMode,
+    priority: &mut NotePriority,
+    glide: &mut f32,
+) {
+    synth_panel(ui, "VOICING", |ui| {
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Voices").color(SynthTheme::TEXT_DIM).size(10.0));
+            ui.add(egui::DragValue::new(polyphony).range(1..=MAX_VOICES));
+        });
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Steal").color(SynthTheme::TEXT_DIM).size(10.0));
+            egui::ComboBox::from_id_salt("steal_policy")
+                .selected_text(steal.label())
+                .width(80.0)
+                .show_ui(ui, |ui| {
+                    for policy in StealPolicy::ALL {
+                        ui.selectable_value(steal, policy, policy.label());
+                    }
+                });
+        });
+        ui.add_space(2.0);
+        select_buttons::select_buttons(
+            ui,
+            mode,
+            &[
+                (VoiceMode::Poly, "Poly"),
+                (VoiceMode::Mono, "Mono"),
+                (VoiceMode::Legato, "Leg"),
+            ],
+        );
+        ui.add_space(2.0);
+        ui.add_enabled_ui(mode.is_mono(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new("Prio").color(SynthTheme::TEXT_DIM).size(10.0));
+                select_buttons::select_buttons(
+                    ui,
+                    priority,
+                    &[
+                        (NotePriority::Last, "Last"),
+                        (NotePriority::Low, "Low"),
+                        (NotePriority::High, "High"),
+                    ],
+                );
+            });
+            let glide = hslider::hslider(ui, "Glide", glide, 0.0, 2.0);
+            learnable(ui, &glide, "synth.glide");
+        });
+    });
+}
@@ -13,7 +13,7 @@ pub fn hslider(ui: &mut Ui, label: &str, value: &mut f32, min: f32, max: f32) ->
     let track_width = (available - LABEL_WIDTH - VALUE_WIDTH - 12.0).max(60.0);
     let total_width = LABEL_WIDTH + track_width + VALUE_WIDTH + 12.0;
     let desired_size = vec2(total_width, 22.0);
-    let (rect, response) = ui.allocate_exact_size(desired_size, Sense::drag());
+    let (rect, response) = ui.allocate_exact_size(desired_size, Sense::click_and_drag());
 
     let painter = ui.painter_at(rect);
     let cy = rect.center().y;
@@ -12,7 +12,7 @@ const ARC_POINTS: usize = 50;
 #[allow(dead_code)]
 pub fn knob(ui: &mut Ui, label: &str, value: &mut f32, min: f32, max: f32, unit: &str) -> Response {
     let desired_size = vec2(58.0, 78.0);
-    let (rect, response) = ui.allocate_exact_size(desired_size, Sense::drag());
+    let (rect, response) = ui.allocate_exact_size(desired_size, Sense::click_and_drag());
 
     let center = pos2(rect.center().x, rect.top() + KNOB_RADIUS + 4.0);
     let painter = ui.painter_at(rect);
@@ -88,7 +88,7 @@ pub fn knob(ui: &mut Ui, label: &str, value: &mut f32, min: f32, max: f32, unit:
 pub fn mini_knob(ui: &mut Ui, label: &str, value: &mut f32, min: f32, max: f32) -> Response {
     let r = 16.0;
     let desired_size = vec2(36.0, 52.0);
-    let (rect, response) = ui.allocate_exact_size(desired_size, Sense::drag());
+    let (rect, response) = ui.allocate_exact_size(desired_size, Sense::click_and_drag());
 
     let center = pos2(rect.center().x, rect.top() + r + 2.0);
     let painter = ui.painter_at(rect);
@@ -9,7 +9,7 @@ const THUMB_H: f32 = 12.0;
 
 pub fn vslider(ui: &mut Ui, label: &str, value: &mut f32, min: f32, max: f32) -> Response {
     let desired_size = vec2(36.0, TRACK_HEIGHT + 30.0);
-    let (rect, response) = ui.allocate_exact_size(desired_size, Sense::drag());
+    let (rect, response) = ui.allocate_exact_size(desired_size, Sense::click_and_drag());
 
     let painter = ui.painter_at(rect);
     let track_x = rect.center().x;
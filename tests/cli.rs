@@ -24,7 +24,7 @@ fn help_flag_prints_usage() {
 #[test]
 fn invalid_waveform_is_rejected() {
     let output = synthesis_bin()
-        .args(["--waveform", "noise"])
+        .args(["--waveform", "wobble"])
         .output()
         .expect("failed to run synthesis");
 
@@ -35,3 +35,305 @@ fn invalid_waveform_is_rejected() {
         "expected error about invalid value, got: {stderr}"
     );
 }
+
+/// Peak absolute sample of a rendered WAV file, scaled to -1.0..=1.0.
+fn peak(path: &std::path::Path) -> f32 {
+    let mut reader = hound::WavReader::open(path).expect("rendered WAV should be readable");
+    let spec = reader.spec();
+    match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().map(|s| s.unwrap().abs()).fold(0.0, f32::max),
+        hound::SampleFormat::Int => {
+            let full_scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.unwrap().unsigned_abs() as f32 / full_scale)
+                .fold(0.0, f32::max)
+        }
+    }
+}
+
+#[test]
+fn render_writes_wav_file() {
+    let dir = std::env::temp_dir().join("synthesis_cli_render");
+    std::fs::create_dir_all(&dir).unwrap();
+
+    for (source, bit_depth) in [("tone", "16"), ("tone", "32"), ("poly", "24")] {
+        let path = dir.join(format!("{source}_{bit_depth}.wav"));
+        let output = synthesis_bin()
+            .args(["render", "--source", source, "--bit-depth", bit_depth])
+            .args(["--duration", "0.25", "--sample-rate", "22050", "--notes", "60,64,67"])
+            .arg("--output")
+            .arg(&path)
+            .output()
+            .expect("failed to run synthesis");
+
+        assert!(
+            output.status.success(),
+            "render failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let bytes = std::fs::read(&path).expect("render should create the WAV file");
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(&bytes[8..12], b"WAVE");
+        // 22050 Hz sample rate in the fmt chunk
+        assert_eq!(u32::from_le_bytes(bytes[24..28].try_into().unwrap()), 22050);
+        // Bits per sample in the fmt chunk
+        let bits = u16::from_le_bytes(bytes[34..36].try_into().unwrap());
+        assert_eq!(bits.to_string(), bit_depth);
+        assert!(peak(&path) > 0.01, "{source} render should not be silent");
+        let _ = std::fs::remove_file(&path);
+    }
+    let _ = std::fs::remove_dir(&dir);
+}
+
+#[test]
+fn render_plays_matrix_project() {
+    let dir = std::env::temp_dir().join("synthesis_cli_render_matrix");
+    std::fs::create_dir_all(&dir).unwrap();
+    let project = dir.join("riff.json");
+    let path = dir.join("riff.wav");
+    std::fs::write(
+        &project,
+        r#"{"version": 1, "state": {"bpm": 140.0, "grid": ["x...x...x...x..."]}}"#,
+    )
+    .unwrap();
+
+    let output = synthesis_bin()
+        .args(["render", "--source", "matrix", "--duration", "0.5", "--sample-rate", "22050"])
+        .arg("--project")
+        .arg(&project)
+        .arg("--output")
+        .arg(&path)
+        .output()
+        .expect("failed to run synthesis");
+
+    assert!(
+        output.status.success(),
+        "render failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(peak(&path) > 0.01, "matrix render should not be silent");
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn render_matrix_requires_project() {
+    let output = synthesis_bin()
+        .args(["render", "--source", "matrix", "--output", "unused.wav"])
+        .output()
+        .expect("failed to run synthesis");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--project"), "expected a missing --project error, got: {stderr}");
+}
+
+#[test]
+fn render_accepts_new_waveforms() {
+    for (source, waveform) in [("tone", "pink-noise"), ("poly", "supersaw"), ("poly", "pulse")] {
+        let path = std::env::temp_dir().join(format!("synthesis_cli_{source}_{waveform}.wav"));
+        let output = synthesis_bin()
+            .args(["render", "--source", source, "--waveform", waveform, "--duration", "0.1"])
+            .arg("--output")
+            .arg(&path)
+            .output()
+            .expect("failed to run synthesis");
+
+        assert!(
+            output.status.success(),
+            "render {waveform} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert!(path.exists(), "render should create the WAV file");
+        let _ = std::fs::remove_file(&path);
+    }
+}
+
+#[test]
+fn render_loads_wavetable_file() {
+    let dir = std::env::temp_dir().join("synthesis_cli_wavetable");
+    std::fs::create_dir_all(&dir).unwrap();
+    let table = dir.join("table.wav");
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&table, spec).unwrap();
+    for i in 0..2048 {
+        writer.write_sample(if i < 512 { i16::MAX / 2 } else { -i16::MAX / 2 }).unwrap();
+    }
+    writer.finalize().unwrap();
+
+    let path = dir.join("out.wav");
+    let output = synthesis_bin()
+        .args(["render", "--source", "poly", "--waveform", "wavetable", "--duration", "0.1"])
+        .arg("--wavetable")
+        .arg(&table)
+        .arg("--output")
+        .arg(&path)
+        .output()
+        .expect("failed to run synthesis");
+    assert!(
+        output.status.success(),
+        "render failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(path.exists(), "render should create the WAV file");
+
+    let output = synthesis_bin()
+        .args(["render", "--source", "poly", "--wavetable", "missing.wav", "--output"])
+        .arg(&path)
+        .output()
+        .expect("failed to run synthesis");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("failed to read wavetable"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn render_poly_voice_count_is_validated() {
+    let path = std::env::temp_dir().join("synthesis_cli_render_voices.wav");
+    let output = synthesis_bin()
+        .args(["render", "--source", "poly", "--voices", "1", "--steal", "none"])
+        .args(["--duration", "0.1", "--notes", "60,64"])
+        .arg("--output")
+        .arg(&path)
+        .output()
+        .expect("failed to run synthesis");
+    assert!(
+        output.status.success(),
+        "render failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let bytes = std::fs::read(&path).expect("render should create the WAV file");
+    assert!(bytes[44..].iter().any(|&b| b != 0), "poly notes should be audible");
+    let _ = std::fs::remove_file(&path);
+
+    let output = synthesis_bin()
+        .args(["render", "--source", "poly", "--voices", "65", "--output", "out.wav"])
+        .output()
+        .expect("failed to run synthesis");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("invalid value"), "got: {stderr}");
+}
+
+#[test]
+fn render_rejects_unknown_bit_depth() {
+    let output = synthesis_bin()
+        .args(["render", "--output", "out.wav", "--bit-depth", "8"])
+        .output()
+        .expect("failed to run synthesis");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("invalid value"), "got: {stderr}");
+}
+
+#[test]
+fn matrix_rejects_unreadable_project() {
+    let path = std::env::temp_dir().join("synthesis_cli_missing_project.json");
+    let _ = std::fs::remove_file(&path);
+    let output = synthesis_bin()
+        .arg("--matrix")
+        .arg(&path)
+        .output()
+        .expect("failed to run synthesis");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("failed to open"),
+        "expected error about the project file, got: {stderr}"
+    );
+}
+
+#[test]
+fn export_midi_writes_type_1_file() {
+    let dir = std::env::temp_dir().join("synthesis_cli_export_midi");
+    std::fs::create_dir_all(&dir).unwrap();
+    let project = dir.join("groove.json");
+    let output_path = dir.join("groove.mid");
+    std::fs::write(
+        &project,
+        r#"{"version": 1, "state": {"bpm": 100.0, "drum_grid": ["x...x...x...x..."]}}"#,
+    )
+    .unwrap();
+
+    let output = synthesis_bin()
+        .arg("export-midi")
+        .arg(&project)
+        .arg("--output")
+        .arg(&output_path)
+        .output()
+        .expect("failed to run synthesis");
+
+    assert!(
+        output.status.success(),
+        "export failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let bytes = std::fs::read(&output_path).expect("export should create the MIDI file");
+    assert_eq!(&bytes[0..4], b"MThd");
+    assert_eq!(&bytes[8..12], [0, 1, 0, 4], "type 1 with 4 tracks");
+    assert_eq!(bytes.windows(4).filter(|w| w == b"MTrk").count(), 4);
+}
+
+#[test]
+fn import_midi_round_trips_exported_project() {
+    let dir = std::env::temp_dir().join("synthesis_cli_import_midi");
+    std::fs::create_dir_all(&dir).unwrap();
+    let project = dir.join("beat.json");
+    let midi = dir.join("beat.mid");
+    let imported = dir.join("imported.json");
+    std::fs::write(
+        &project,
+        r#"{"version": 1, "state": {"bpm": 90.0, "drum_grid": ["..............x"]}}"#,
+    )
+    .unwrap();
+
+    let export = synthesis_bin()
+        .arg("export-midi")
+        .arg(&project)
+        .arg("-o")
+        .arg(&midi)
+        .output()
+        .expect("failed to run synthesis");
+    assert!(export.status.success());
+
+    let output = synthesis_bin()
+        .arg("import-midi")
+        .arg(&midi)
+        .arg("-o")
+        .arg(&imported)
+        .output()
+        .expect("failed to run synthesis");
+    assert!(
+        output.status.success(),
+        "import failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Imported 0 lead, 1 drum and 0 bass notes"), "got: {stdout}");
+    let json = std::fs::read_to_string(&imported).expect("import should write the project");
+    assert!(json.contains("\"bpm\": 90.0"));
+    assert!(json.contains("\"..............x.\""), "drum cell should survive: {json}");
+}
+
+#[test]
+fn import_midi_rejects_non_midi_file() {
+    let path = std::env::temp_dir().join("synthesis_cli_not_midi.mid");
+    std::fs::write(&path, b"not a midi file").unwrap();
+    let output = synthesis_bin()
+        .arg("import-midi")
+        .arg(&path)
+        .arg("-o")
+        .arg(std::env::temp_dir().join("synthesis_cli_not_midi.json"))
+        .output()
+        .expect("failed to run synthesis");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("failed to import"), "got: {stderr}");
+}